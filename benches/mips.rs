@@ -0,0 +1,62 @@
+use std::time::Instant;
+
+use risemu::emulator::Emulator;
+
+const ITERATIONS: u64 = 1_000_000;
+
+// a tight ALU/load loop, executed ITERATIONS times
+fn program() -> Vec<u8> {
+    vec![
+        0x17, 0x05, 0x00, 0x00, // auipc x10, 0
+        0x93, 0x02, 0x00, 0x00, // li x5, 0
+        0x37, 0x43, 0x0f, 0x00, // lui x6, 0xf4
+        0x13, 0x03, 0x03, 0x24, // addi x6, x6, 0x240
+        0x93, 0x82, 0x12, 0x00, // addi x5, x5, 1
+        0xb3, 0x83, 0x53, 0x00, // add x7, x7, x5
+        0x33, 0xc4, 0x53, 0x00, // xor x8, x7, x5
+        0x83, 0x24, 0x05, 0x00, // lw x9, 0(x10)
+        0xe3, 0x98, 0x62, 0xfe, // bne x5, x6, -16
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]
+}
+
+fn measure(decode_cache: bool, block_cache: bool, jit: bool) -> f64 {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(program());
+    emu.cpu.set_decode_cache(decode_cache);
+    emu.cpu.set_block_cache(block_cache);
+
+    #[cfg(feature = "jit")]
+    emu.cpu.set_jit_threshold(if jit { 16 } else { u64::MAX });
+    #[cfg(not(feature = "jit"))]
+    let _ = jit;
+
+    let start = Instant::now();
+    let _ = emu.cpu.run_for(u64::MAX);
+    let elapsed = start.elapsed().as_secs_f64();
+
+    assert_eq!(emu.cpu.xregs[5], ITERATIONS);
+    emu.cpu.instret as f64 / elapsed / 1e6
+}
+
+fn main() {
+    let baseline = measure(false, false, false);
+    let decoded = measure(true, false, false);
+    let blocks = measure(false, true, false);
+
+    println!("no caches:    {baseline:>8.2} MIPS");
+    println!(
+        "decode cache: {decoded:>8.2} MIPS ({:.2}x)",
+        decoded / baseline
+    );
+    println!(
+        "block cache:  {blocks:>8.2} MIPS ({:.2}x)",
+        blocks / baseline
+    );
+
+    #[cfg(feature = "jit")]
+    {
+        let jit = measure(false, true, true);
+        println!("jit:          {jit:>8.2} MIPS ({:.2}x)", jit / baseline);
+    }
+}
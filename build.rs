@@ -0,0 +1,13 @@
+fn main() {
+    #[cfg(feature = "header")]
+    {
+        let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+
+        cbindgen::generate(&crate_dir)
+            .expect("cannot generate the C header")
+            .write_to_file(format!("{crate_dir}/include/risemu.h"));
+    }
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+}
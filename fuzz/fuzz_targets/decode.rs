@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use risemu::fuzz::check_decode;
+
+fuzz_target!(|word: u32| check_decode(word));
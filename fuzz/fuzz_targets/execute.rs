@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use risemu::fuzz::Program;
+
+fuzz_target!(|program: Program| program.check());
@@ -0,0 +1,6 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use risemu::fuzz::Structured;
+
+fuzz_target!(|program: Structured| program.0.check());
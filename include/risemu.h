@@ -0,0 +1,114 @@
+#ifndef RISEMU_H
+#define RISEMU_H
+
+/* Generated by cbindgen from src/ffi.rs, do not edit. Regenerate with `cargo build --features header`. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum risemu_stop_kind {
+  RISEMU_STOP_KIND_LIMIT_REACHED,
+  RISEMU_STOP_KIND_BREAKPOINT,
+  RISEMU_STOP_KIND_WATCHPOINT,
+  RISEMU_STOP_KIND_CONDITION,
+  RISEMU_STOP_KIND_EXITED,
+  RISEMU_STOP_KIND_FAULT,
+  RISEMU_STOP_KIND_START_OF_HISTORY,
+  RISEMU_STOP_KIND_WAITING,
+  RISEMU_STOP_KIND_DIVERGED,
+} risemu_stop_kind;
+
+typedef struct risemu_emulator risemu_emulator;
+
+/**
+ * Why execution stopped. `value` holds the exit code, the trap value of a
+ * fault or the address that hit a watchpoint, while `cause` holds the
+ * exception code of a fault or the access kind of a watchpoint (0 for reads,
+ * 1 for writes).
+ */
+typedef struct risemu_stop {
+  enum risemu_stop_kind kind;
+  uint64_t pc;
+  uint64_t value;
+  uint32_t cause;
+} risemu_stop;
+
+typedef bool (*risemu_mmio_read)(void *context, uint64_t offset, uint32_t size, uint64_t *value);
+
+typedef bool (*risemu_mmio_write)(void *context, uint64_t offset, uint32_t size, uint64_t value);
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Creates an emulator with `ram_size` bytes of RAM and 32 or 64-bit
+ * registers, or returns NULL for any other width.
+ */
+struct risemu_emulator *risemu_create(size_t ram_size, uint32_t xlen);
+
+void risemu_destroy(struct risemu_emulator *emu);
+
+/**
+ * Copies a raw image to the start of RAM and points pc at it.
+ */
+bool risemu_load_image(struct risemu_emulator *emu, const uint8_t *data, size_t length);
+
+/**
+ * Loads an ELF executable, switching to the register width it was built for.
+ */
+bool risemu_load_elf(struct risemu_emulator *emu, const uint8_t *data, size_t length);
+
+struct risemu_stop risemu_step(struct risemu_emulator *emu);
+
+/**
+ * Runs at most `limit` instructions.
+ */
+struct risemu_stop risemu_run(struct risemu_emulator *emu, uint64_t limit);
+
+uint64_t risemu_get_register(const struct risemu_emulator *emu, uint32_t index);
+
+/**
+ * Returns false for indices past 31; writes to x0 are ignored.
+ */
+bool risemu_set_register(struct risemu_emulator *emu, uint32_t index, uint64_t value);
+
+uint64_t risemu_get_pc(const struct risemu_emulator *emu);
+
+void risemu_set_pc(struct risemu_emulator *emu, uint64_t pc);
+
+uint64_t risemu_get_instret(const struct risemu_emulator *emu);
+
+/**
+ * Reads physical memory, RAM or devices, failing if any byte is unmapped.
+ */
+bool risemu_read_memory(struct risemu_emulator *emu,
+                        uint64_t address,
+                        uint8_t *data,
+                        size_t length);
+
+bool risemu_write_memory(struct risemu_emulator *emu,
+                         uint64_t address,
+                         const uint8_t *data,
+                         size_t length);
+
+/**
+ * Maps `size` bytes at `base` to a device implemented by the callbacks,
+ * failing if the range overlaps RAM or another device. A NULL callback
+ * makes the corresponding accesses fault.
+ */
+bool risemu_map_mmio(struct risemu_emulator *emu,
+                     uint64_t base,
+                     uint64_t size,
+                     risemu_mmio_read read,
+                     risemu_mmio_write write,
+                     void *context);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* RISEMU_H */
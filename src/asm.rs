@@ -0,0 +1,1523 @@
+use std::{collections::HashMap, error::Error, fmt};
+
+use crate::{
+    bus::{Address, RAM_BASE},
+    cpu::Xlen,
+};
+
+/// An error on a line of the source, counted from 1.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+// assembles RV64 code to be loaded at the start of RAM
+pub fn assemble(source: &str) -> Result<Vec<u8>, AsmError> {
+    Assembler::new(RAM_BASE, Xlen::Rv64).assemble(source)
+}
+
+/// A two-pass assembler for the instructions the emulator executes: the base
+/// integer ISA with M, A, Zicsr, Zifencei, Zba, Zbb, Zbc and Zbs, along with
+/// the usual pseudo-instructions, labels (numeric ones included) and the
+/// %hi, %lo, %pcrel_hi and %pcrel_lo relocations.
+///
+/// All sections end up in a single flat image starting at `origin`. Branch
+/// and jump targets written as plain numbers are offsets, while those
+/// naming a symbol are addresses, as with GNU as and llvm-mc.
+pub struct Assembler {
+    origin: Address,
+    xlen: Xlen,
+}
+
+#[derive(Clone, Debug)]
+enum Statement {
+    Instruction {
+        mnemonic: String,
+        operands: Vec<String>,
+    },
+    Data {
+        width: usize,
+        values: Vec<String>,
+    },
+    Bytes(Vec<u8>),
+    Align(u64),
+    Space(u64),
+}
+
+struct Line {
+    number: usize,
+    address: Address,
+    statement: Statement,
+}
+
+// what the statements of a pass can see
+struct Context<'a> {
+    symbols: &'a HashMap<String, i64>,
+    locals: &'a HashMap<u64, Vec<Address>>,
+
+    // targets of the %pcrel_hi relocations seen so far, by the address of their AUIPC
+    pcrel: HashMap<Address, i64>,
+
+    // the first pass only needs sizes, so unknown symbols are taken as zero
+    sizing: bool,
+    xlen: Xlen,
+    pc: Address,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct Value {
+    value: i64,
+
+    // whether a symbol was involved, which makes branch targets addresses rather than offsets
+    symbolic: bool,
+
+    // the offset a %pcrel_hi was taken of, for the %pcrel_lo of the pair
+    pcrel: Option<i64>,
+}
+
+impl Assembler {
+    pub fn new(origin: Address, xlen: Xlen) -> Self {
+        Self { origin, xlen }
+    }
+
+    pub fn assemble(&self, source: &str) -> Result<Vec<u8>, AsmError> {
+        let mut symbols = HashMap::new();
+        let mut locals: HashMap<u64, Vec<Address>> = HashMap::new();
+        let mut lines = vec![];
+
+        // first pass: define symbols and lay out the statements
+        let mut address = self.origin;
+        for (index, text) in source.lines().enumerate() {
+            let number = index + 1;
+            let error = |message: String| AsmError {
+                line: number,
+                message,
+            };
+
+            let mut rest = strip_comment(text).trim();
+            while let Some((label, after)) = split_label(rest) {
+                if label.bytes().all(|byte| byte.is_ascii_digit()) {
+                    let label = label.parse().map_err(|_| error("bad label".into()))?;
+                    locals.entry(label).or_default().push(address);
+                } else if symbols.insert(label.to_string(), address as i64).is_some() {
+                    return Err(error(format!("`{label}` is already defined")));
+                }
+
+                rest = after.trim();
+            }
+
+            if rest.is_empty() {
+                continue;
+            }
+
+            let (name, arguments) = match rest.find(char::is_whitespace) {
+                Some(split) => (&rest[..split], rest[split..].trim()),
+                None => (rest, ""),
+            };
+            let name = name.to_ascii_lowercase();
+
+            let context = Context {
+                symbols: &symbols,
+                locals: &locals,
+                pcrel: HashMap::new(),
+                sizing: true,
+                xlen: self.xlen,
+                pc: address,
+            };
+
+            let statement = match name.as_str() {
+                ".equ" | ".set" => {
+                    let operands = split_operands(arguments);
+                    let [symbol, value] = operands.as_slice() else {
+                        return Err(error(format!("`{name}` takes a name and a value")));
+                    };
+
+                    let value = context.resolve(value).map_err(error)?;
+                    symbols.insert(symbol.clone(), value);
+                    continue;
+                }
+
+                _ => parse_statement(&name, arguments, &context).map_err(error)?,
+            };
+
+            let size = match &statement {
+                Statement::Instruction { mnemonic, operands } => {
+                    let mut context = context;
+                    4 * encode(mnemonic, operands, &mut context)
+                        .map_err(error)?
+                        .len() as u64
+                }
+                Statement::Data { width, values } => (width * values.len()) as u64,
+                Statement::Bytes(bytes) => bytes.len() as u64,
+                Statement::Align(alignment) => address.next_multiple_of(*alignment) - address,
+                Statement::Space(size) => *size,
+            };
+
+            lines.push(Line {
+                number,
+                address,
+                statement,
+            });
+            address += size;
+        }
+
+        // second pass: encode everything now that all symbols are known
+        let mut output = vec![];
+        let mut context = Context {
+            symbols: &symbols,
+            locals: &locals,
+            pcrel: HashMap::new(),
+            sizing: false,
+            xlen: self.xlen,
+            pc: self.origin,
+        };
+
+        for line in lines {
+            let error = |message: String| AsmError {
+                line: line.number,
+                message,
+            };
+            context.pc = line.address;
+
+            match line.statement {
+                Statement::Instruction { mnemonic, operands } => {
+                    for word in encode(&mnemonic, &operands, &mut context).map_err(error)? {
+                        output.extend(word.to_le_bytes());
+                    }
+                }
+
+                Statement::Data { width, values } => {
+                    for value in values {
+                        let value = context.resolve(&value).map_err(error)?;
+                        if width < 8 && !fits(value, width * 8) {
+                            return Err(error(format!("{value} does not fit in {width} bytes")));
+                        }
+
+                        output.extend(&value.to_le_bytes()[..width]);
+                    }
+                }
+
+                Statement::Bytes(bytes) => output.extend(bytes),
+
+                Statement::Align(alignment) => {
+                    let padding = line.address.next_multiple_of(alignment) - line.address;
+                    output.resize(output.len() + padding as usize, 0);
+                }
+
+                Statement::Space(size) => output.resize(output.len() + size as usize, 0),
+            }
+        }
+
+        Ok(output)
+    }
+}
+
+fn parse_statement(name: &str, arguments: &str, context: &Context) -> Result<Statement, String> {
+    let constant = |argument: &str| -> Result<u64, String> {
+        let value = context.resolve(argument)?;
+        u64::try_from(value).map_err(|_| format!("`{argument}` must not be negative"))
+    };
+
+    Ok(match name {
+        // a single flat image has no use for sections or symbol visibility
+        ".text" | ".data" | ".rodata" | ".bss" | ".section" | ".globl" | ".global" | ".option"
+        | ".type" | ".size" | ".file" => Statement::Bytes(vec![]),
+
+        ".byte" => data(1, arguments),
+        ".half" | ".short" | ".2byte" => data(2, arguments),
+        ".word" | ".long" | ".4byte" => data(4, arguments),
+        ".dword" | ".quad" | ".8byte" => data(8, arguments),
+
+        ".ascii" | ".asciz" | ".string" => {
+            let mut bytes = vec![];
+            for string in split_operands(arguments) {
+                bytes.extend(parse_string(&string)?);
+                if name != ".ascii" {
+                    bytes.push(0);
+                }
+            }
+
+            Statement::Bytes(bytes)
+        }
+
+        ".zero" | ".space" | ".skip" => Statement::Space(constant(arguments)?),
+
+        // .align is a power of two on RISC-V
+        ".align" | ".p2align" => match constant(arguments)? {
+            shift @ 0..=16 => Statement::Align(1 << shift),
+            _ => return Err("alignment too large".into()),
+        },
+        ".balign" => match constant(arguments)? {
+            alignment if alignment.is_power_of_two() => Statement::Align(alignment),
+            _ => return Err("alignment must be a power of two".into()),
+        },
+
+        _ if name.starts_with('.') => return Err(format!("unknown directive `{name}`")),
+
+        _ => Statement::Instruction {
+            mnemonic: name.to_string(),
+            operands: split_operands(arguments),
+        },
+    })
+}
+
+fn data(width: usize, arguments: &str) -> Statement {
+    Statement::Data {
+        width,
+        values: split_operands(arguments),
+    }
+}
+
+impl Context<'_> {
+    fn resolve(&self, text: &str) -> Result<i64, String> {
+        self.evaluate(text).map(|value| value.value)
+    }
+
+    fn evaluate(&self, text: &str) -> Result<Value, String> {
+        let tokens = tokenize(text)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            context: self,
+        };
+
+        let value = parser.expression()?;
+        if parser.position != tokens.len() {
+            return Err(format!(
+                "unexpected `{}` in `{text}`",
+                tokens[parser.position]
+            ));
+        }
+
+        Ok(value)
+    }
+
+    fn symbol(&self, name: &str) -> Result<i64, String> {
+        if let Some(&value) = self.symbols.get(name) {
+            return Ok(value);
+        }
+
+        // numeric labels are referred to as 1b or 1f, for the closest one before or after
+        let local = name
+            .strip_suffix('b')
+            .map(|label| (label, true))
+            .or_else(|| name.strip_suffix('f').map(|label| (label, false)))
+            .and_then(|(label, backward)| Some((label.parse::<u64>().ok()?, backward)));
+        if let Some((label, backward)) = local {
+            let definitions = self.locals.get(&label).map(Vec::as_slice).unwrap_or(&[]);
+            let found = match backward {
+                true => definitions
+                    .iter()
+                    .rev()
+                    .find(|&&address| address <= self.pc),
+                false => definitions.iter().find(|&&address| address > self.pc),
+            };
+
+            if let Some(&address) = found {
+                return Ok(address as i64);
+            }
+        }
+
+        match self.sizing {
+            true => Ok(0),
+            false => Err(format!("undefined symbol `{name}`")),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Token {
+    Number(i64),
+    Symbol(String),
+    Relocation(String),
+    Open,
+    Close,
+    Plus,
+    Minus,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Token::Number(value) => write!(f, "{value}"),
+            Token::Symbol(name) => write!(f, "{name}"),
+            Token::Relocation(name) => write!(f, "%{name}"),
+            Token::Open => write!(f, "("),
+            Token::Close => write!(f, ")"),
+            Token::Plus => write!(f, "+"),
+            Token::Minus => write!(f, "-"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let bytes = text.as_bytes();
+
+    let mut i = 0;
+    while i < bytes.len() {
+        let byte = bytes[i];
+        let word_end = |start: usize| {
+            start
+                + bytes[start..]
+                    .iter()
+                    .take_while(|&&byte| byte.is_ascii_alphanumeric() || b"_.$".contains(&byte))
+                    .count()
+        };
+
+        match byte {
+            b' ' | b'\t' => i += 1,
+            b'(' => {
+                tokens.push(Token::Open);
+                i += 1;
+            }
+            b')' => {
+                tokens.push(Token::Close);
+                i += 1;
+            }
+            b'+' => {
+                tokens.push(Token::Plus);
+                i += 1;
+            }
+            b'-' => {
+                tokens.push(Token::Minus);
+                i += 1;
+            }
+
+            b'%' => {
+                let end = word_end(i + 1);
+                tokens.push(Token::Relocation(text[i + 1..end].to_string()));
+                i = end;
+            }
+
+            b'\'' => {
+                let end = text[i + 1..]
+                    .find('\'')
+                    .map(|end| i + 1 + end)
+                    .ok_or("unterminated character")?;
+                let character = parse_string(&format!("\"{}\"", &text[i + 1..end]))?;
+                let [character] = character.as_slice() else {
+                    return Err("a character literal holds a single byte".into());
+                };
+
+                tokens.push(Token::Number(*character as i64));
+                i = end + 1;
+            }
+
+            b'0'..=b'9' => {
+                let end = word_end(i);
+                let word = &text[i..end];
+
+                // numeric label references like 1b and 1f
+                if word.ends_with(['b', 'f'])
+                    && word[..word.len() - 1].bytes().all(|b| b.is_ascii_digit())
+                {
+                    tokens.push(Token::Symbol(word.to_string()));
+                } else {
+                    tokens.push(Token::Number(parse_number(word)?));
+                }
+                i = end;
+            }
+
+            _ if byte.is_ascii_alphabetic() || b"_.$".contains(&byte) => {
+                let end = word_end(i);
+                tokens.push(Token::Symbol(text[i..end].to_string()));
+                i = end;
+            }
+
+            _ => return Err(format!("unexpected `{}`", byte as char)),
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn parse_number(word: &str) -> Result<i64, String> {
+    let lower = word.to_ascii_lowercase();
+    let (digits, radix) = if let Some(digits) = lower.strip_prefix("0x") {
+        (digits, 16)
+    } else if let Some(digits) = lower.strip_prefix("0b") {
+        (digits, 2)
+    } else {
+        (lower.as_str(), 10)
+    };
+
+    // large unsigned constants wrap around, so that 0xffffffffffffffff is -1
+    u64::from_str_radix(&digits.replace('_', ""), radix)
+        .map(|value| value as i64)
+        .map_err(|_| format!("bad number `{word}`"))
+}
+
+fn parse_string(text: &str) -> Result<Vec<u8>, String> {
+    let inner = text
+        .strip_prefix('"')
+        .and_then(|text| text.strip_suffix('"'))
+        .ok_or_else(|| format!("expected a string, found `{text}`"))?;
+
+    let mut bytes = vec![];
+    let mut chars = inner.chars();
+    while let Some(character) = chars.next() {
+        if character != '\\' {
+            let mut buffer = [0; 4];
+            bytes.extend(character.encode_utf8(&mut buffer).as_bytes());
+            continue;
+        }
+
+        bytes.push(match chars.next() {
+            Some('n') => b'\n',
+            Some('t') => b'\t',
+            Some('r') => b'\r',
+            Some('0') => 0,
+            Some('\\') => b'\\',
+            Some('"') => b'"',
+            Some('\'') => b'\'',
+
+            _ => return Err("bad escape sequence".into()),
+        });
+    }
+
+    Ok(bytes)
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    context: &'a Context<'a>,
+}
+
+impl Parser<'_> {
+    fn expression(&mut self) -> Result<Value, String> {
+        let mut value = self.term()?;
+
+        loop {
+            let sign = match self.tokens.get(self.position) {
+                Some(Token::Plus) => 1,
+                Some(Token::Minus) => -1,
+
+                _ => return Ok(value),
+            };
+            self.position += 1;
+
+            let rhs = self.term()?;
+            value = Value {
+                value: value.value.wrapping_add(sign * rhs.value),
+                symbolic: value.symbolic || rhs.symbolic,
+                pcrel: value.pcrel.or(rhs.pcrel),
+            };
+        }
+    }
+
+    fn term(&mut self) -> Result<Value, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or("missing operand")?
+            .clone();
+        self.position += 1;
+
+        match token {
+            Token::Number(value) => Ok(Value {
+                value,
+                ..Value::default()
+            }),
+
+            Token::Symbol(name) => Ok(Value {
+                value: self.context.symbol(&name)?,
+                symbolic: true,
+                pcrel: None,
+            }),
+
+            Token::Minus => {
+                let value = self.term()?;
+                Ok(Value {
+                    value: value.value.wrapping_neg(),
+                    ..value
+                })
+            }
+
+            Token::Open => {
+                let value = self.expression()?;
+                self.expect_close()?;
+                Ok(value)
+            }
+
+            Token::Relocation(name) => {
+                if self.tokens.get(self.position) != Some(&Token::Open) {
+                    return Err(format!("expected `(` after `%{name}`"));
+                }
+                self.position += 1;
+
+                let value = self.expression()?;
+                self.expect_close()?;
+
+                let context = self.context;
+                let offset = value.value.wrapping_sub(context.pc as i64);
+                let value = match name.as_str() {
+                    "hi" => hi(value.value),
+                    "lo" => lo(value.value),
+                    "pcrel_hi" => {
+                        return Ok(Value {
+                            value: hi(offset),
+                            symbolic: false,
+                            pcrel: Some(offset),
+                        })
+                    }
+
+                    // refers to the AUIPC holding the matching %pcrel_hi
+                    "pcrel_lo" => match context.pcrel.get(&(value.value as Address)) {
+                        Some(&offset) => lo(offset),
+                        None if context.sizing => 0,
+                        None => return Err("%pcrel_lo does not point at a %pcrel_hi".into()),
+                    },
+
+                    _ => return Err(format!("unknown relocation `%{name}`")),
+                };
+
+                Ok(Value {
+                    value,
+                    ..Value::default()
+                })
+            }
+
+            token => Err(format!("unexpected `{token}`")),
+        }
+    }
+
+    fn expect_close(&mut self) -> Result<(), String> {
+        match self.tokens.get(self.position) {
+            Some(Token::Close) => {
+                self.position += 1;
+                Ok(())
+            }
+
+            _ => Err("expected `)`".into()),
+        }
+    }
+}
+
+// the upper 20 bits, rounded so that adding the sign-extended lower 12 bits gives back the value
+fn hi(value: i64) -> i64 {
+    (value.wrapping_add(0x800) >> 12) & 0xFFFFF
+}
+
+fn lo(value: i64) -> i64 {
+    (value << 52) >> 52
+}
+
+fn fits(value: i64, bits: usize) -> bool {
+    let min = -(1i64 << (bits - 1));
+    let max = (1i64 << bits) - 1;
+
+    (min..=max).contains(&value)
+}
+
+fn fits_signed(value: i64, bits: u32) -> bool {
+    let bound = 1i64 << (bits - 1);
+    (-bound..bound).contains(&value)
+}
+
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (index, character) in line.char_indices() {
+        match character {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..index],
+            _ => {}
+        }
+    }
+
+    line
+}
+
+// a leading `label:`, unless the colon belongs to something else
+fn split_label(text: &str) -> Option<(&str, &str)> {
+    let colon = text.find(':')?;
+    let label = text[..colon].trim();
+
+    let valid = !label.is_empty()
+        && label
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || b"_.$".contains(&byte));
+
+    valid.then(|| (label, &text[colon + 1..]))
+}
+
+// splits on commas outside of parentheses and strings
+fn split_operands(text: &str) -> Vec<String> {
+    let mut operands = vec![];
+    let mut current = String::new();
+    let mut depth = 0;
+    let mut quoted = false;
+
+    for character in text.chars() {
+        match character {
+            '"' => quoted = !quoted,
+            '(' if !quoted => depth += 1,
+            ')' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                operands.push(current.trim().to_string());
+                current.clear();
+                continue;
+            }
+            _ => {}
+        }
+
+        current.push(character);
+    }
+
+    if !current.trim().is_empty() || !operands.is_empty() {
+        operands.push(current.trim().to_string());
+    }
+
+    operands
+}
+
+fn register(name: &str) -> Result<u32, String> {
+    const ABI: [&str; 32] = [
+        "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+        "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+        "t5", "t6",
+    ];
+
+    let name = name.trim().to_ascii_lowercase();
+    if name == "fp" {
+        return Ok(8);
+    }
+
+    if let Some(index) = ABI.iter().position(|&abi| abi == name) {
+        return Ok(index as u32);
+    }
+
+    name.strip_prefix('x')
+        .and_then(|digits| {
+            digits
+                .parse::<u32>()
+                .ok()
+                .filter(|n| n.to_string() == digits)
+        })
+        .filter(|&number| number < 32)
+        .ok_or_else(|| format!("`{name}` is not a register"))
+}
+
+fn csr(name: &str, context: &Context) -> Result<u32, String> {
+    const NAMES: [(&str, u32); 26] = [
+        ("sstatus", 0x100),
+        ("satp", 0x180),
+        ("mstatus", 0x300),
+        ("misa", 0x301),
+        ("mie", 0x304),
+        ("mtvec", 0x305),
+        ("mscratch", 0x340),
+        ("mepc", 0x341),
+        ("mcause", 0x342),
+        ("mtval", 0x343),
+        ("mip", 0x344),
+        ("mcycle", 0xB00),
+        ("minstret", 0xB02),
+        ("cycle", 0xC00),
+        ("time", 0xC01),
+        ("instret", 0xC02),
+        ("mcycleh", 0xB80),
+        ("cycleh", 0xC80),
+        ("mhartid", 0xF14),
+        ("vstart", 0x008),
+        ("vxsat", 0x009),
+        ("vxrm", 0x00A),
+        ("vcsr", 0x00F),
+        ("vl", 0xC20),
+        ("vtype", 0xC21),
+        ("vlenb", 0xC22),
+    ];
+
+    let lower = name.to_ascii_lowercase();
+    if let Some(&(_, number)) = NAMES.iter().find(|(csr, _)| *csr == lower) {
+        return Ok(number);
+    }
+
+    let indexed = |prefix: &str, count: u32| {
+        lower
+            .strip_prefix(prefix)
+            .and_then(|index| index.parse::<u32>().ok())
+            .filter(|&index| index < count)
+    };
+    if let Some(index) = indexed("pmpcfg", 16) {
+        return Ok(0x3A0 + index);
+    }
+    if let Some(index) = indexed("pmpaddr", 64) {
+        return Ok(0x3B0 + index);
+    }
+
+    match context.resolve(name)? {
+        number @ 0..=0xFFF => Ok(number as u32),
+        _ => Err(format!("`{name}` is not a CSR")),
+    }
+}
+
+fn r_type(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+fn i_type(imm: i64, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    ((imm as u32 & 0xFFF) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+fn s_type(imm: i64, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+    let imm = imm as u32;
+    ((imm >> 5 & 0x7F) << 25)
+        | (rs2 << 20)
+        | (rs1 << 15)
+        | (funct3 << 12)
+        | ((imm & 0x1F) << 7)
+        | 0x23
+}
+
+fn b_type(offset: i64, rs2: u32, rs1: u32, funct3: u32) -> u32 {
+    let offset = offset as u32;
+    ((offset >> 12 & 1) << 31)
+        | ((offset >> 5 & 0x3F) << 25)
+        | (rs2 << 20)
+        | (rs1 << 15)
+        | (funct3 << 12)
+        | ((offset >> 1 & 0xF) << 8)
+        | ((offset >> 11 & 1) << 7)
+        | 0x63
+}
+
+fn u_type(imm: i64, rd: u32, opcode: u32) -> u32 {
+    ((imm as u32 & 0xFFFFF) << 12) | (rd << 7) | opcode
+}
+
+fn j_type(offset: i64, rd: u32) -> u32 {
+    let offset = offset as u32;
+    ((offset >> 20 & 1) << 31)
+        | ((offset >> 1 & 0x3FF) << 21)
+        | ((offset >> 11 & 1) << 20)
+        | ((offset >> 12 & 0xFF) << 12)
+        | (rd << 7)
+        | 0x6F
+}
+
+// the instructions loading `value` into `rd`, the way LLVM does it
+fn load_immediate(rd: u32, value: i64, xlen: Xlen) -> Vec<u32> {
+    if xlen == Xlen::Rv32 || fits_signed(value, 32) {
+        let value = value as i32 as i64;
+        let (upper, lower) = (hi(value), lo(value));
+        let add = if xlen == Xlen::Rv64 { 0x1B } else { 0x13 };
+
+        return match (upper, lower) {
+            (0, _) => vec![i_type(lower, 0, 0b000, rd, 0x13)],
+            (_, 0) => vec![u_type(upper, rd, 0x37)],
+            _ => vec![u_type(upper, rd, 0x37), i_type(lower, rd, 0b000, rd, add)],
+        };
+    }
+
+    // build the upper bits, shift them into place and add the low twelve
+    let lower = lo(value);
+    let upper = value.wrapping_sub(lower) >> 12;
+    let shift = upper.trailing_zeros();
+
+    let mut words = load_immediate(rd, upper >> shift, xlen);
+    words.push(i_type(12 + shift as i64, rd, 0b001, rd, 0x13));
+    if lower != 0 {
+        words.push(i_type(lower, rd, 0b000, rd, 0x13));
+    }
+
+    words
+}
+
+fn encode(mnemonic: &str, operands: &[String], context: &mut Context) -> Result<Vec<u32>, String> {
+    let rv64 = context.xlen == Xlen::Rv64;
+    let pc = context.pc;
+
+    let count = |expected: usize| -> Result<(), String> {
+        match operands.len() == expected {
+            true => Ok(()),
+            false => Err(format!("`{mnemonic}` takes {expected} operands")),
+        }
+    };
+    let reg = |index: usize| register(&operands[index]);
+
+    // signed immediates, unless the first pass cannot know them yet
+    let imm = |index: usize, bits: u32, context: &Context| -> Result<i64, String> {
+        let value = context.resolve(&operands[index])?;
+        match fits_signed(value, bits) || context.sizing {
+            true => Ok(value),
+            false => Err(format!("{value} does not fit in {bits} bits")),
+        }
+    };
+
+    // jump targets, as offsets from the instruction
+    let target = |index: usize, bits: u32, context: &Context| -> Result<i64, String> {
+        let value = context.evaluate(&operands[index])?;
+        let offset = match value.symbolic {
+            true => value.value.wrapping_sub(pc as i64),
+            false => value.value,
+        };
+
+        if context.sizing {
+            return Ok(0);
+        }
+        if offset % 2 != 0 || !fits_signed(offset, bits) {
+            return Err(format!("cannot reach `{}`", operands[index]));
+        }
+
+        Ok(offset)
+    };
+
+    // `offset(base)`, the offset being optional
+    let memory = |index: usize, context: &Context| -> Result<(i64, u32), String> {
+        let operand = operands[index].trim();
+        let open = operand
+            .rfind('(')
+            .filter(|_| operand.ends_with(')'))
+            .ok_or_else(|| format!("expected `offset(register)`, found `{operand}`"))?;
+
+        let base = register(&operand[open + 1..operand.len() - 1])?;
+        let offset = match operand[..open].trim() {
+            "" => 0,
+            text => context.resolve(text)?,
+        };
+
+        match fits_signed(offset, 12) || context.sizing {
+            true => Ok((offset, base)),
+            false => Err(format!("{offset} does not fit in 12 bits")),
+        }
+    };
+
+    // shift amounts up to XLEN - 1, or 31 for the word forms
+    let shamt = |index: usize, word: bool, context: &Context| -> Result<i64, String> {
+        let limit = if word || !rv64 { 32 } else { 64 };
+        let value = context.resolve(&operands[index])?;
+
+        match (0..limit).contains(&value) {
+            true => Ok(value),
+            false => Err(format!("shift amount {value} out of range")),
+        }
+    };
+
+    let rv64_only = |words: Vec<u32>| match rv64 {
+        true => Ok(words),
+        false => Err(format!("`{mnemonic}` is only available on RV64")),
+    };
+
+    // the AUIPC of a PC-relative pair, remembering where it points for %pcrel_lo
+    let pcrel = |index: usize, context: &mut Context| -> Result<i64, String> {
+        let target = context.resolve(&operands[index])?;
+        let offset = target.wrapping_sub(pc as i64);
+        if !context.sizing && !fits_signed(offset, 32) {
+            return Err(format!("cannot reach `{}`", operands[index]));
+        }
+
+        context.pcrel.insert(pc, offset);
+        Ok(offset)
+    };
+
+    let r = |funct7: u32, funct3: u32, opcode: u32| -> Result<Vec<u32>, String> {
+        count(3)?;
+        Ok(vec![r_type(
+            funct7,
+            reg(2)?,
+            reg(1)?,
+            funct3,
+            reg(0)?,
+            opcode,
+        )])
+    };
+    let unary = |funct12: u32, funct3: u32, opcode: u32| -> Result<Vec<u32>, String> {
+        count(2)?;
+        Ok(vec![i_type(
+            funct12 as i64,
+            reg(1)?,
+            funct3,
+            reg(0)?,
+            opcode,
+        )])
+    };
+
+    // branch pseudo-instructions map onto the real ones with swapped or zero operands
+    let branch = |funct3: u32,
+                  rs1: u32,
+                  rs2: u32,
+                  index: usize,
+                  context: &Context|
+     -> Result<Vec<u32>, String> {
+        Ok(vec![b_type(target(index, 13, context)?, rs2, rs1, funct3)])
+    };
+
+    let words = match mnemonic {
+        "lui" | "auipc" => {
+            count(2)?;
+            let Value { value, pcrel, .. } = context.evaluate(&operands[1])?;
+            if !(0..=0xFFFFF).contains(&value) && !context.sizing {
+                return Err(format!("{value} does not fit in 20 bits"));
+            }
+
+            if let Some(offset) = pcrel.filter(|_| mnemonic == "auipc") {
+                context.pcrel.insert(pc, offset);
+            }
+
+            let opcode = if mnemonic == "lui" { 0x37 } else { 0x17 };
+            vec![u_type(value, reg(0)?, opcode)]
+        }
+
+        "jal" => match operands.len() {
+            1 => vec![j_type(target(0, 21, context)?, 1)],
+            _ => {
+                count(2)?;
+                vec![j_type(target(1, 21, context)?, reg(0)?)]
+            }
+        },
+
+        "jalr" => match operands.len() {
+            1 => {
+                let (offset, base) = match operands[0].contains('(') {
+                    true => memory(0, context)?,
+                    false => (0, reg(0)?),
+                };
+                vec![i_type(offset, base, 0b000, 1, 0x67)]
+            }
+            2 => {
+                let (offset, base) = memory(1, context)?;
+                vec![i_type(offset, base, 0b000, reg(0)?, 0x67)]
+            }
+            _ => {
+                count(3)?;
+                vec![i_type(imm(2, 12, context)?, reg(1)?, 0b000, reg(0)?, 0x67)]
+            }
+        },
+
+        "beq" | "bne" | "blt" | "bge" | "bltu" | "bgeu" => {
+            count(3)?;
+            let funct3 = match mnemonic {
+                "beq" => 0b000,
+                "bne" => 0b001,
+                "blt" => 0b100,
+                "bge" => 0b101,
+                "bltu" => 0b110,
+                _ => 0b111,
+            };
+
+            branch(funct3, reg(0)?, reg(1)?, 2, context)?
+        }
+
+        "bgt" | "ble" | "bgtu" | "bleu" => {
+            count(3)?;
+            let funct3 = match mnemonic {
+                "bgt" => 0b100,
+                "ble" => 0b101,
+                "bgtu" => 0b110,
+                _ => 0b111,
+            };
+
+            branch(funct3, reg(1)?, reg(0)?, 2, context)?
+        }
+
+        "beqz" | "bnez" | "bltz" | "bgez" | "blez" | "bgtz" => {
+            count(2)?;
+            let rs = reg(0)?;
+            match mnemonic {
+                "beqz" => branch(0b000, rs, 0, 1, context)?,
+                "bnez" => branch(0b001, rs, 0, 1, context)?,
+                "bltz" => branch(0b100, rs, 0, 1, context)?,
+                "bgez" => branch(0b101, rs, 0, 1, context)?,
+                "blez" => branch(0b101, 0, rs, 1, context)?,
+                _ => branch(0b100, 0, rs, 1, context)?,
+            }
+        }
+
+        "lb" | "lh" | "lw" | "ld" | "lbu" | "lhu" | "lwu" => {
+            count(2)?;
+            let funct3 = match mnemonic {
+                "lb" => 0b000,
+                "lh" => 0b001,
+                "lw" => 0b010,
+                "ld" => 0b011,
+                "lbu" => 0b100,
+                "lhu" => 0b101,
+                _ => 0b110,
+            };
+
+            let (offset, base) = memory(1, context)?;
+            let words = vec![i_type(offset, base, funct3, reg(0)?, 0x03)];
+            match mnemonic {
+                "ld" | "lwu" => rv64_only(words)?,
+                _ => words,
+            }
+        }
+
+        "sb" | "sh" | "sw" | "sd" => {
+            count(2)?;
+            let funct3 = match mnemonic {
+                "sb" => 0b000,
+                "sh" => 0b001,
+                "sw" => 0b010,
+                _ => 0b011,
+            };
+
+            let (offset, base) = memory(1, context)?;
+            let words = vec![s_type(offset, reg(0)?, base, funct3)];
+            match mnemonic {
+                "sd" => rv64_only(words)?,
+                _ => words,
+            }
+        }
+
+        "addi" | "slti" | "sltiu" | "xori" | "ori" | "andi" => {
+            count(3)?;
+            let funct3 = match mnemonic {
+                "addi" => 0b000,
+                "slti" => 0b010,
+                "sltiu" => 0b011,
+                "xori" => 0b100,
+                "ori" => 0b110,
+                _ => 0b111,
+            };
+
+            vec![i_type(imm(2, 12, context)?, reg(1)?, funct3, reg(0)?, 0x13)]
+        }
+
+        "addiw" => {
+            count(3)?;
+            rv64_only(vec![i_type(
+                imm(2, 12, context)?,
+                reg(1)?,
+                0b000,
+                reg(0)?,
+                0x1B,
+            )])?
+        }
+
+        "slli" | "srli" | "srai" | "rori" | "bclri" | "bexti" | "binvi" | "bseti" => {
+            count(3)?;
+            let (funct6, funct3) = match mnemonic {
+                "slli" => (0b000000, 0b001),
+                "srli" => (0b000000, 0b101),
+                "srai" => (0b010000, 0b101),
+                "rori" => (0b011000, 0b101),
+                "bclri" => (0b010010, 0b001),
+                "bexti" => (0b010010, 0b101),
+                "binvi" => (0b011010, 0b001),
+                _ => (0b001010, 0b001),
+            };
+
+            let shamt = shamt(2, false, context)?;
+            vec![i_type(
+                (funct6 << 6) | shamt,
+                reg(1)?,
+                funct3,
+                reg(0)?,
+                0x13,
+            )]
+        }
+
+        "slliw" | "srliw" | "sraiw" | "roriw" | "slli.uw" => {
+            count(3)?;
+            let (funct7, funct3) = match mnemonic {
+                "slliw" => (0b0000000, 0b001),
+                "srliw" => (0b0000000, 0b101),
+                "sraiw" => (0b0100000, 0b101),
+                "roriw" => (0b0110000, 0b101),
+                _ => (0b0000100, 0b001),
+            };
+
+            // slli.uw shifts by up to 63
+            let shamt = shamt(2, mnemonic != "slli.uw", context)?;
+            rv64_only(vec![i_type(
+                (funct7 << 5) | shamt,
+                reg(1)?,
+                funct3,
+                reg(0)?,
+                0x1B,
+            )])?
+        }
+
+        "add" => r(0b0000000, 0b000, 0x33)?,
+        "sub" => r(0b0100000, 0b000, 0x33)?,
+        "sll" => r(0b0000000, 0b001, 0x33)?,
+        "slt" => r(0b0000000, 0b010, 0x33)?,
+        "sltu" => r(0b0000000, 0b011, 0x33)?,
+        "xor" => r(0b0000000, 0b100, 0x33)?,
+        "srl" => r(0b0000000, 0b101, 0x33)?,
+        "sra" => r(0b0100000, 0b101, 0x33)?,
+        "or" => r(0b0000000, 0b110, 0x33)?,
+        "and" => r(0b0000000, 0b111, 0x33)?,
+
+        "mul" => r(0b0000001, 0b000, 0x33)?,
+        "mulh" => r(0b0000001, 0b001, 0x33)?,
+        "mulhsu" => r(0b0000001, 0b010, 0x33)?,
+        "mulhu" => r(0b0000001, 0b011, 0x33)?,
+        "div" => r(0b0000001, 0b100, 0x33)?,
+        "divu" => r(0b0000001, 0b101, 0x33)?,
+        "rem" => r(0b0000001, 0b110, 0x33)?,
+        "remu" => r(0b0000001, 0b111, 0x33)?,
+
+        "sh1add" => r(0b0010000, 0b010, 0x33)?,
+        "sh2add" => r(0b0010000, 0b100, 0x33)?,
+        "sh3add" => r(0b0010000, 0b110, 0x33)?,
+        "andn" => r(0b0100000, 0b111, 0x33)?,
+        "orn" => r(0b0100000, 0b110, 0x33)?,
+        "xnor" => r(0b0100000, 0b100, 0x33)?,
+        "min" => r(0b0000101, 0b100, 0x33)?,
+        "minu" => r(0b0000101, 0b101, 0x33)?,
+        "max" => r(0b0000101, 0b110, 0x33)?,
+        "maxu" => r(0b0000101, 0b111, 0x33)?,
+        "rol" => r(0b0110000, 0b001, 0x33)?,
+        "ror" => r(0b0110000, 0b101, 0x33)?,
+        "clmul" => r(0b0000101, 0b001, 0x33)?,
+        "clmulr" => r(0b0000101, 0b010, 0x33)?,
+        "clmulh" => r(0b0000101, 0b011, 0x33)?,
+        "bclr" => r(0b0100100, 0b001, 0x33)?,
+        "bext" => r(0b0100100, 0b101, 0x33)?,
+        "binv" => r(0b0110100, 0b001, 0x33)?,
+        "bset" => r(0b0010100, 0b001, 0x33)?,
+
+        "addw" => rv64_only(r(0b0000000, 0b000, 0x3B)?)?,
+        "subw" => rv64_only(r(0b0100000, 0b000, 0x3B)?)?,
+        "sllw" => rv64_only(r(0b0000000, 0b001, 0x3B)?)?,
+        "srlw" => rv64_only(r(0b0000000, 0b101, 0x3B)?)?,
+        "sraw" => rv64_only(r(0b0100000, 0b101, 0x3B)?)?,
+        "mulw" => rv64_only(r(0b0000001, 0b000, 0x3B)?)?,
+        "divw" => rv64_only(r(0b0000001, 0b100, 0x3B)?)?,
+        "divuw" => rv64_only(r(0b0000001, 0b101, 0x3B)?)?,
+        "remw" => rv64_only(r(0b0000001, 0b110, 0x3B)?)?,
+        "remuw" => rv64_only(r(0b0000001, 0b111, 0x3B)?)?,
+        "add.uw" => rv64_only(r(0b0000100, 0b000, 0x3B)?)?,
+        "sh1add.uw" => rv64_only(r(0b0010000, 0b010, 0x3B)?)?,
+        "sh2add.uw" => rv64_only(r(0b0010000, 0b100, 0x3B)?)?,
+        "sh3add.uw" => rv64_only(r(0b0010000, 0b110, 0x3B)?)?,
+        "rolw" => rv64_only(r(0b0110000, 0b001, 0x3B)?)?,
+        "rorw" => rv64_only(r(0b0110000, 0b101, 0x3B)?)?,
+
+        "clz" => unary(0x600, 0b001, 0x13)?,
+        "ctz" => unary(0x601, 0b001, 0x13)?,
+        "cpop" => unary(0x602, 0b001, 0x13)?,
+        "sext.b" => unary(0x604, 0b001, 0x13)?,
+        "sext.h" => unary(0x605, 0b001, 0x13)?,
+        "orc.b" => unary(0x287, 0b101, 0x13)?,
+        "rev8" if rv64 => unary(0x6B8, 0b101, 0x13)?,
+        "rev8" => unary(0x698, 0b101, 0x13)?,
+        "zext.h" if rv64 => unary(0x080, 0b100, 0x3B)?,
+        "zext.h" => unary(0x080, 0b100, 0x33)?,
+        "clzw" => rv64_only(unary(0x600, 0b001, 0x1B)?)?,
+        "ctzw" => rv64_only(unary(0x601, 0b001, 0x1B)?)?,
+        "cpopw" => rv64_only(unary(0x602, 0b001, 0x1B)?)?,
+
+        "fence" => {
+            let bits = |index: usize| -> Result<u32, String> {
+                operands[index].chars().try_fold(0, |bits, character| {
+                    Ok(bits
+                        | match character {
+                            'i' => 8,
+                            'o' => 4,
+                            'r' => 2,
+                            'w' => 1,
+                            _ => return Err(format!("bad fence operand `{}`", operands[index])),
+                        })
+                })
+            };
+
+            let (predecessor, successor) = match operands.len() {
+                0 => (0b1111, 0b1111),
+                _ => {
+                    count(2)?;
+                    (bits(0)?, bits(1)?)
+                }
+            };
+
+            vec![(predecessor << 24) | (successor << 20) | 0x0F]
+        }
+        "fence.tso" => vec![0x8330000F],
+        "fence.i" => vec![0x0000100F],
+
+        "ecall" => vec![0x00000073],
+        "ebreak" => vec![0x00100073],
+        "mret" => vec![0x30200073],
+        "wfi" => vec![0x10500073],
+
+        "csrrw" | "csrrs" | "csrrc" => {
+            count(3)?;
+            let funct3 = match mnemonic {
+                "csrrw" => 0b001,
+                "csrrs" => 0b010,
+                _ => 0b011,
+            };
+
+            vec![i_type(
+                csr(&operands[1], context)? as i64,
+                reg(2)?,
+                funct3,
+                reg(0)?,
+                0x73,
+            )]
+        }
+
+        "csrrwi" | "csrrsi" | "csrrci" => {
+            count(3)?;
+            let funct3 = match mnemonic {
+                "csrrwi" => 0b101,
+                "csrrsi" => 0b110,
+                _ => 0b111,
+            };
+
+            let value = context.resolve(&operands[2])?;
+            if !(0..32).contains(&value) {
+                return Err(format!("{value} does not fit in 5 bits"));
+            }
+
+            vec![i_type(
+                csr(&operands[1], context)? as i64,
+                value as u32,
+                funct3,
+                reg(0)?,
+                0x73,
+            )]
+        }
+
+        "csrr" => {
+            count(2)?;
+            vec![i_type(
+                csr(&operands[1], context)? as i64,
+                0,
+                0b010,
+                reg(0)?,
+                0x73,
+            )]
+        }
+
+        "csrw" | "csrs" | "csrc" => {
+            count(2)?;
+            let funct3 = match mnemonic {
+                "csrw" => 0b001,
+                "csrs" => 0b010,
+                _ => 0b011,
+            };
+
+            vec![i_type(
+                csr(&operands[0], context)? as i64,
+                reg(1)?,
+                funct3,
+                0,
+                0x73,
+            )]
+        }
+
+        "csrwi" | "csrsi" | "csrci" => {
+            count(2)?;
+            let funct3 = match mnemonic {
+                "csrwi" => 0b101,
+                "csrsi" => 0b110,
+                _ => 0b111,
+            };
+
+            let value = context.resolve(&operands[1])?;
+            if !(0..32).contains(&value) {
+                return Err(format!("{value} does not fit in 5 bits"));
+            }
+
+            vec![i_type(
+                csr(&operands[0], context)? as i64,
+                value as u32,
+                funct3,
+                0,
+                0x73,
+            )]
+        }
+
+        "nop" => {
+            count(0)?;
+            vec![0x00000013]
+        }
+
+        "li" => {
+            count(2)?;
+            // the length depends on the value, so symbols defined further down cannot be used
+            let known = Context {
+                pcrel: HashMap::new(),
+                sizing: false,
+                ..*context
+            };
+            let value = known
+                .resolve(&operands[1])
+                .map_err(|error| match context.sizing {
+                    true => format!("{error}, `li` needs a value known at this point"),
+                    false => error,
+                })?;
+
+            let value = match rv64 {
+                true => value,
+                false if fits(value, 32) => value as i32 as i64,
+                false => return Err(format!("{value} does not fit in 32 bits")),
+            };
+
+            load_immediate(reg(0)?, value, context.xlen)
+        }
+
+        "la" | "lla" => {
+            count(2)?;
+            let rd = reg(0)?;
+            let offset = pcrel(1, context)?;
+
+            vec![
+                u_type(hi(offset), rd, 0x17),
+                i_type(lo(offset), rd, 0b000, rd, 0x13),
+            ]
+        }
+
+        "call" | "tail" => {
+            count(1)?;
+            let (link, scratch) = if mnemonic == "call" { (1, 1) } else { (0, 6) };
+            let offset = pcrel(0, context)?;
+
+            vec![
+                u_type(hi(offset), scratch, 0x17),
+                i_type(lo(offset), scratch, 0b000, link, 0x67),
+            ]
+        }
+
+        "mv" => {
+            count(2)?;
+            vec![i_type(0, reg(1)?, 0b000, reg(0)?, 0x13)]
+        }
+        "not" => {
+            count(2)?;
+            vec![i_type(-1, reg(1)?, 0b100, reg(0)?, 0x13)]
+        }
+        "neg" => {
+            count(2)?;
+            vec![r_type(0b0100000, reg(1)?, 0, 0b000, reg(0)?, 0x33)]
+        }
+        "negw" => {
+            count(2)?;
+            rv64_only(vec![r_type(0b0100000, reg(1)?, 0, 0b000, reg(0)?, 0x3B)])?
+        }
+        "sext.w" => {
+            count(2)?;
+            rv64_only(vec![i_type(0, reg(1)?, 0b000, reg(0)?, 0x1B)])?
+        }
+        "zext.b" => {
+            count(2)?;
+            vec![i_type(0xFF, reg(1)?, 0b111, reg(0)?, 0x13)]
+        }
+        "zext.w" => {
+            count(2)?;
+            rv64_only(vec![r_type(0b0000100, 0, reg(1)?, 0b000, reg(0)?, 0x3B)])?
+        }
+        "seqz" => {
+            count(2)?;
+            vec![i_type(1, reg(1)?, 0b011, reg(0)?, 0x13)]
+        }
+        "snez" => {
+            count(2)?;
+            vec![r_type(0, reg(1)?, 0, 0b011, reg(0)?, 0x33)]
+        }
+        "sltz" => {
+            count(2)?;
+            vec![r_type(0, 0, reg(1)?, 0b010, reg(0)?, 0x33)]
+        }
+        "sgtz" => {
+            count(2)?;
+            vec![r_type(0, reg(1)?, 0, 0b010, reg(0)?, 0x33)]
+        }
+
+        "j" => {
+            count(1)?;
+            vec![j_type(target(0, 21, context)?, 0)]
+        }
+        "jr" => {
+            count(1)?;
+            vec![i_type(0, reg(0)?, 0b000, 0, 0x67)]
+        }
+        "ret" => {
+            count(0)?;
+            vec![i_type(0, 1, 0b000, 0, 0x67)]
+        }
+
+        _ => match atomic(mnemonic) {
+            Some((funct5, funct3, ordering)) => {
+                let base = |index: usize| -> Result<u32, String> {
+                    let operand = operands[index].trim();
+                    operand
+                        .strip_prefix('(')
+                        .and_then(|operand| operand.strip_suffix(')'))
+                        .map(register)
+                        .unwrap_or_else(|| Err(format!("expected `(register)`, found `{operand}`")))
+                };
+
+                let (rs2, rs1) = match funct5 {
+                    0b00010 => {
+                        count(2)?;
+                        (0, base(1)?)
+                    }
+                    _ => {
+                        count(3)?;
+                        (reg(1)?, base(2)?)
+                    }
+                };
+
+                let words = vec![r_type(
+                    (funct5 << 2) | ordering,
+                    rs2,
+                    rs1,
+                    funct3,
+                    reg(0)?,
+                    0x2F,
+                )];
+                match funct3 {
+                    0b011 => rv64_only(words)?,
+                    _ => words,
+                }
+            }
+
+            None => return Err(format!("unknown instruction `{mnemonic}`")),
+        },
+    };
+
+    Ok(words)
+}
+
+// funct5, width and the aq/rl bits of an atomic mnemonic like amoadd.w.aqrl
+fn atomic(mnemonic: &str) -> Option<(u32, u32, u32)> {
+    let mut parts = mnemonic.split('.');
+    let funct5 = match parts.next()? {
+        "lr" => 0b00010,
+        "sc" => 0b00011,
+        "amoswap" => 0b00001,
+        "amoadd" => 0b00000,
+        "amoxor" => 0b00100,
+        "amoand" => 0b01100,
+        "amoor" => 0b01000,
+        "amomin" => 0b10000,
+        "amomax" => 0b10100,
+        "amominu" => 0b11000,
+        "amomaxu" => 0b11100,
+
+        _ => return None,
+    };
+
+    let funct3 = match parts.next()? {
+        "w" => 0b010,
+        "d" => 0b011,
+
+        _ => return None,
+    };
+
+    let ordering = match parts.next() {
+        None => 0b00,
+        Some("rl") => 0b01,
+        Some("aq") => 0b10,
+        Some("aqrl") => 0b11,
+
+        _ => return None,
+    };
+
+    parts.next().is_none().then_some((funct5, funct3, ordering))
+}
@@ -0,0 +1,152 @@
+use crate::{
+    bus::{Address, Device, RAM_BASE},
+    cpu::CPU,
+    debug::WatchKind,
+    decoder::AmoOp,
+    exception::RVException,
+    pmp::Access,
+};
+
+// what the last LR loaded; a later SC to the same word succeeds if memory still holds `value`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Reservation {
+    pub(crate) address: Address,
+    pub(crate) value: u64,
+    pub(crate) double: bool,
+}
+
+impl CPU {
+    pub(crate) fn amo(
+        &mut self,
+        op: AmoOp,
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+        double: bool,
+    ) -> Result<(), RVException> {
+        let address = self.xlen().zext(self.xregs[rs1 as usize]);
+        let size = if double { 8 } else { 4 };
+
+        let (misaligned, access_fault) = match op {
+            AmoOp::Lr => (
+                RVException::LoadAddressMisaligned,
+                RVException::LoadAccessFault,
+            ),
+            _ => (
+                RVException::StoreAddressMisaligned,
+                RVException::StoreAccessFault,
+            ),
+        };
+
+        if !address.is_multiple_of(size as Address) {
+            return Err(self.fault(misaligned, address));
+        }
+
+        // atomics are only supported on RAM
+        if !self.bus.in_ram(address, size) {
+            return Err(self.fault(access_fault, address));
+        }
+
+        // AMOs and SC need both read and write permission
+        let permitted = match op {
+            AmoOp::Lr => self.permits(address, size, Access::Read),
+            _ => {
+                self.permits(address, size, Access::Read)
+                    && self.permits(address, size, Access::Write)
+            }
+        };
+        if !permitted {
+            return Err(self.fault(access_fault, address));
+        }
+
+        // the read is reported first, as it happens first
+        if self.debugger.is_watching() {
+            self.debugger.check_access(address, size, WatchKind::Read);
+            if op != AmoOp::Lr {
+                self.debugger.check_access(address, size, WatchKind::Write);
+            }
+        }
+
+        match op {
+            AmoOp::Lr => self.observe(address, size, Access::Read),
+            _ => self.observe(address, size, Access::Write),
+        }
+
+        let offset = address - RAM_BASE;
+        let rhs = self.xregs[rs2 as usize];
+
+        let value = match op {
+            AmoOp::Lr => {
+                let value = match double {
+                    true => self.bus.ram.read::<u64>(offset),
+                    false => self.bus.ram.read::<u32>(offset).map(u64::from),
+                }
+                .map_err(|ex| self.fault(ex, address))?;
+
+                self.reservation = Some(Reservation {
+                    address,
+                    value,
+                    double,
+                });
+                value
+            }
+
+            AmoOp::Sc => {
+                self.invalidate(address, size);
+
+                let succeeded = match self.reservation.take() {
+                    Some(reservation)
+                        if reservation.address == address && reservation.double == double =>
+                    {
+                        self.bus
+                            .ram
+                            .compare_exchange(offset, double, reservation.value, rhs)
+                    }
+                    _ => false,
+                };
+
+                !succeeded as u64
+            }
+
+            _ => {
+                self.invalidate(address, size);
+                self.bus
+                    .ram
+                    .fetch_update(offset, double, |old| combine(op, old, rhs, double))
+            }
+        };
+
+        self.xregs[rd as usize] = match double {
+            true => value,
+            false => self.xlen().sext(value as i32 as i64 as u64),
+        };
+
+        Ok(())
+    }
+}
+
+// the value an AMO stores, comparing 32-bit operands as such
+fn combine(op: AmoOp, old: u64, rhs: u64, double: bool) -> u64 {
+    let (signed_old, signed_rhs) = match double {
+        true => (old as i64, rhs as i64),
+        false => (old as i32 as i64, rhs as i32 as i64),
+    };
+    let (unsigned_old, unsigned_rhs) = match double {
+        true => (old, rhs),
+        false => (old as u32 as u64, rhs as u32 as u64),
+    };
+
+    match op {
+        AmoOp::Swap => rhs,
+        AmoOp::Add => old.wrapping_add(rhs),
+        AmoOp::Xor => old ^ rhs,
+        AmoOp::And => old & rhs,
+        AmoOp::Or => old | rhs,
+        AmoOp::Min => signed_old.min(signed_rhs) as u64,
+        AmoOp::Max => signed_old.max(signed_rhs) as u64,
+        AmoOp::Minu => unsigned_old.min(unsigned_rhs),
+        AmoOp::Maxu => unsigned_old.max(unsigned_rhs),
+
+        AmoOp::Lr | AmoOp::Sc => unreachable!("{op:?} is not an AMO"),
+    }
+}
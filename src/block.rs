@@ -0,0 +1,474 @@
+use std::{collections::HashMap, sync::Arc};
+
+#[cfg(feature = "jit")]
+use crate::jit::NativeBlock;
+use crate::{
+    bus::{Address, RAM_BASE},
+    cpu::{Xlen, CPU},
+    decoder::{AluOp, BranchOp, Instruction, LoadOp, StoreOp, PAGE_SHIFT, PAGE_SIZE},
+    exception::RVException,
+};
+
+// upper bound on the number of instructions translated into a single block
+pub const MAX_BLOCK_LENGTH: usize = 64;
+
+pub(crate) type Handler = fn(&mut CPU, &Op) -> Result<(), RVException>;
+
+/// A decoded instruction bound to the handler that executes it, with its
+/// operands pulled out of the instruction so that running it needs no
+/// further matching.
+#[derive(Clone, Copy)]
+pub struct Op {
+    pub instruction: Instruction,
+    pub(crate) handler: Handler,
+
+    pub(crate) rd: u8,
+    pub(crate) rs1: u8,
+    pub(crate) rs2: u8,
+    pub(crate) imm: u64,
+
+    // in bytes, two for the C extension
+    pub(crate) length: u64,
+}
+
+impl Op {
+    // runs through the generic interpreter
+    pub fn new(instruction: Instruction, length: u64) -> Self {
+        Self {
+            instruction,
+            handler: execute,
+            rd: 0,
+            rs1: 0,
+            rs2: 0,
+            imm: 0,
+            length,
+        }
+    }
+
+    // picks a dedicated handler for the common RV64 instructions, and the generic one otherwise
+    pub fn bind(instruction: Instruction, length: u64, xlen: Xlen) -> Self {
+        let generic = Self::new(instruction, length);
+        if xlen != Xlen::Rv64 {
+            return generic;
+        }
+
+        let (handler, rd, rs1, rs2, imm): (Handler, _, _, _, _) = match instruction {
+            Instruction::Lui { rd, imm } => (lui, rd, 0, 0, imm),
+            Instruction::Auipc { rd, imm } => (auipc, rd, 0, 0, imm),
+
+            Instruction::Branch {
+                op,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let handler: Handler = match op {
+                    BranchOp::Beq => beq,
+                    BranchOp::Bne => bne,
+                    BranchOp::Blt => blt,
+                    BranchOp::Bge => bge,
+                    BranchOp::Bltu => bltu,
+                    BranchOp::Bgeu => bgeu,
+                };
+                (handler, 0, rs1, rs2, offset)
+            }
+
+            Instruction::Load {
+                op,
+                rd,
+                rs1,
+                offset,
+            } => {
+                let handler: Handler = match op {
+                    LoadOp::Lb => lb,
+                    LoadOp::Lh => lh,
+                    LoadOp::Lw => lw,
+                    LoadOp::Ld => ld,
+                    LoadOp::Lbu => lbu,
+                    LoadOp::Lhu => lhu,
+                    LoadOp::Lwu => lwu,
+                };
+                (handler, rd, rs1, 0, offset)
+            }
+
+            Instruction::Store {
+                op,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let handler: Handler = match op {
+                    StoreOp::Sb => sb,
+                    StoreOp::Sh => sh,
+                    StoreOp::Sw => sw,
+                    StoreOp::Sd => sd,
+                };
+                (handler, 0, rs1, rs2, offset)
+            }
+
+            Instruction::OpImm { op, rd, rs1, imm } => {
+                let handler: Handler = match op {
+                    AluOp::Add => addi,
+                    AluOp::Slt => slti,
+                    AluOp::Sltu => sltiu,
+                    AluOp::Xor => xori,
+                    AluOp::Or => ori,
+                    AluOp::And => andi,
+                    AluOp::Sll => slli,
+                    AluOp::Srl => srli,
+                    AluOp::Sra => srai,
+
+                    _ => return generic,
+                };
+                (handler, rd, rs1, 0, imm)
+            }
+
+            Instruction::OpImm32 {
+                op: AluOp::Add,
+                rd,
+                rs1,
+                imm,
+            } => (addiw, rd, rs1, 0, imm),
+
+            Instruction::Op { op, rd, rs1, rs2 } => {
+                let handler: Handler = match op {
+                    AluOp::Add => add,
+                    AluOp::Sub => sub,
+                    AluOp::Slt => slt,
+                    AluOp::Sltu => sltu,
+                    AluOp::Xor => xor,
+                    AluOp::Or => or,
+                    AluOp::And => and,
+                    AluOp::Sll => sll,
+                    AluOp::Srl => srl,
+                    AluOp::Sra => sra,
+                    AluOp::Mul => mul,
+
+                    _ => return generic,
+                };
+                (handler, rd, rs1, rs2, 0)
+            }
+
+            Instruction::Op32 { op, rd, rs1, rs2 } => {
+                let handler: Handler = match op {
+                    AluOp::Add => addw,
+                    AluOp::Sub => subw,
+
+                    _ => return generic,
+                };
+                (handler, rd, rs1, rs2, 0)
+            }
+
+            _ => return generic,
+        };
+
+        Self {
+            instruction,
+            handler,
+            rd,
+            rs1,
+            rs2,
+            imm,
+            length,
+        }
+    }
+}
+
+fn execute(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+    cpu.execute(op.instruction, op.length)
+}
+
+fn lui(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+    cpu.xregs[op.rd as usize] = op.imm;
+    Ok(())
+}
+
+fn auipc(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+    cpu.xregs[op.rd as usize] = cpu.pc.wrapping_add(op.imm);
+    Ok(())
+}
+
+// the RV64 form of an ALU operation, whose second operand is a register or the immediate
+macro_rules! alu {
+    ($name:ident, $op:ident, register) => {
+        fn $name(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+            let (lhs, rhs) = (cpu.xregs[op.rs1 as usize], cpu.xregs[op.rs2 as usize]);
+            cpu.xregs[op.rd as usize] = CPU::alu(AluOp::$op, lhs, rhs);
+            Ok(())
+        }
+    };
+
+    ($name:ident, $op:ident, immediate) => {
+        fn $name(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+            cpu.xregs[op.rd as usize] = CPU::alu(AluOp::$op, cpu.xregs[op.rs1 as usize], op.imm);
+            Ok(())
+        }
+    };
+}
+
+alu!(addi, Add, immediate);
+alu!(slti, Slt, immediate);
+alu!(sltiu, Sltu, immediate);
+alu!(xori, Xor, immediate);
+alu!(ori, Or, immediate);
+alu!(andi, And, immediate);
+alu!(slli, Sll, immediate);
+alu!(srli, Srl, immediate);
+alu!(srai, Sra, immediate);
+
+alu!(add, Add, register);
+alu!(sub, Sub, register);
+alu!(slt, Slt, register);
+alu!(sltu, Sltu, register);
+alu!(xor, Xor, register);
+alu!(or, Or, register);
+alu!(and, And, register);
+alu!(sll, Sll, register);
+alu!(srl, Srl, register);
+alu!(sra, Sra, register);
+alu!(mul, Mul, register);
+
+fn addiw(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+    cpu.xregs[op.rd as usize] = CPU::alu32(AluOp::Add, cpu.xregs[op.rs1 as usize], op.imm);
+    Ok(())
+}
+
+fn addw(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+    let (lhs, rhs) = (cpu.xregs[op.rs1 as usize], cpu.xregs[op.rs2 as usize]);
+    cpu.xregs[op.rd as usize] = CPU::alu32(AluOp::Add, lhs, rhs);
+    Ok(())
+}
+
+fn subw(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+    let (lhs, rhs) = (cpu.xregs[op.rs1 as usize], cpu.xregs[op.rs2 as usize]);
+    cpu.xregs[op.rd as usize] = CPU::alu32(AluOp::Sub, lhs, rhs);
+    Ok(())
+}
+
+macro_rules! branch {
+    ($name:ident, |$lhs:ident, $rhs:ident| $taken:expr) => {
+        fn $name(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+            let ($lhs, $rhs) = (cpu.xregs[op.rs1 as usize], cpu.xregs[op.rs2 as usize]);
+            if $taken {
+                let target = cpu.jump_target(cpu.pc.wrapping_add(op.imm))?;
+                cpu.pc = target.wrapping_sub(op.length);
+            }
+            Ok(())
+        }
+    };
+}
+
+branch!(beq, |lhs, rhs| lhs == rhs);
+branch!(bne, |lhs, rhs| lhs != rhs);
+branch!(blt, |lhs, rhs| (lhs as i64) < (rhs as i64));
+branch!(bge, |lhs, rhs| (lhs as i64) >= (rhs as i64));
+branch!(bltu, |lhs, rhs| lhs < rhs);
+branch!(bgeu, |lhs, rhs| lhs >= rhs);
+
+macro_rules! load {
+    ($name:ident, $type:ty, $extended:ty) => {
+        fn $name(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+            let address = cpu.xregs[op.rs1 as usize].wrapping_add(op.imm);
+            cpu.xregs[op.rd as usize] = cpu.read::<$type>(address)? as $extended as u64;
+            Ok(())
+        }
+    };
+}
+
+load!(lb, i8, i64);
+load!(lh, i16, i64);
+load!(lw, i32, i64);
+load!(ld, u64, u64);
+load!(lbu, u8, u64);
+load!(lhu, u16, u64);
+load!(lwu, u32, u64);
+
+macro_rules! store {
+    ($name:ident, $type:ty) => {
+        fn $name(cpu: &mut CPU, op: &Op) -> Result<(), RVException> {
+            let address = cpu.xregs[op.rs1 as usize].wrapping_add(op.imm);
+            cpu.write::<$type>(address, cpu.xregs[op.rs2 as usize] as $type)
+        }
+    };
+}
+
+store!(sb, u8);
+store!(sh, u16);
+store!(sw, u32);
+store!(sd, u64);
+
+/// A straight-line run of decoded instructions ending at the first control
+/// transfer, system instruction or page boundary.
+pub struct Block {
+    pub start: Address,
+    pub ops: Arc<[Op]>,
+
+    // the last two distinct blocks execution continued into, so that hot
+    // paths dispatch without going through the lookup table
+    successors: [Option<usize>; 2],
+
+    #[cfg(feature = "jit")]
+    pub executions: u64,
+    #[cfg(feature = "jit")]
+    pub native: Option<NativeBlock>,
+}
+
+impl Block {
+    pub fn new(start: Address, ops: Vec<Op>) -> Self {
+        Self {
+            start,
+            ops: ops.into(),
+            successors: [None; 2],
+
+            #[cfg(feature = "jit")]
+            executions: 0,
+            #[cfg(feature = "jit")]
+            native: None,
+        }
+    }
+
+    pub fn ends_block(instruction: &Instruction) -> bool {
+        matches!(
+            instruction,
+            Instruction::Jal { .. }
+                | Instruction::Jalr { .. }
+                | Instruction::Branch { .. }
+                | Instruction::FenceI
+                | Instruction::Ecall
+                | Instruction::Ebreak
+                | Instruction::Mret
+                | Instruction::Wfi
+        )
+    }
+}
+
+#[derive(Default)]
+pub struct BlockCache {
+    blocks: Vec<Option<Block>>,
+    free: Vec<usize>,
+
+    lookup: HashMap<Address, usize>,
+    pages: Vec<Vec<usize>>,
+}
+
+impl BlockCache {
+    pub fn block(&self, index: usize) -> &Block {
+        self.blocks[index].as_ref().expect("stale block index")
+    }
+
+    pub fn block_mut(&mut self, index: usize) -> &mut Block {
+        self.blocks[index].as_mut().expect("stale block index")
+    }
+
+    // the block at `index`, unless it has been dropped since
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut Block> {
+        self.blocks[index].as_mut()
+    }
+
+    /// Finds the block starting at `pc`, first by following the chain out of
+    /// `previous` and then through the lookup table, linking the two on a hit.
+    pub fn find(&mut self, pc: Address, previous: Option<usize>) -> Option<usize> {
+        if let Some(previous) = previous {
+            for successor in self.block(previous).successors.into_iter().flatten() {
+                if matches!(&self.blocks[successor], Some(block) if block.start == pc) {
+                    return Some(successor);
+                }
+            }
+        }
+
+        let index = *self.lookup.get(&pc)?;
+        if let Some(previous) = previous {
+            self.chain(previous, index);
+        }
+
+        Some(index)
+    }
+
+    pub fn insert(&mut self, block: Block, previous: Option<usize>) -> usize {
+        let start = block.start;
+        let page = Self::page(start);
+
+        let index = match self.free.pop() {
+            Some(index) => {
+                self.blocks[index] = Some(block);
+                index
+            }
+
+            None => {
+                self.blocks.push(Some(block));
+                self.blocks.len() - 1
+            }
+        };
+
+        self.lookup.insert(start, index);
+        if let Some(page) = page {
+            if page >= self.pages.len() {
+                self.pages.resize_with(page + 1, Vec::new);
+            }
+
+            self.pages[page].push(index);
+        }
+
+        if let Some(previous) = previous {
+            self.chain(previous, index);
+        }
+
+        index
+    }
+
+    /// Drops every block living in a page touched by a `size`-byte access at
+    /// `address`, returning whether anything was dropped.
+    pub fn invalidate(&mut self, address: Address, size: usize) -> bool {
+        let mut dropped = false;
+
+        for address in [address, address.wrapping_add(size as u64 - 1)] {
+            let Some(page) = Self::page(address) else {
+                continue;
+            };
+
+            let Some(indices) = self.pages.get_mut(page) else {
+                continue;
+            };
+
+            for index in std::mem::take(indices) {
+                if let Some(block) = self.blocks[index].take() {
+                    self.lookup.remove(&block.start);
+                    self.free.push(index);
+                    dropped = true;
+                }
+            }
+        }
+
+        dropped
+    }
+
+    pub fn flush(&mut self) {
+        self.blocks.clear();
+        self.free.clear();
+        self.lookup.clear();
+        self.pages.clear();
+    }
+
+    fn chain(&mut self, from: usize, to: usize) {
+        if let Some(block) = self.blocks[from].as_mut() {
+            // keep the most recent successor first
+            if block.successors[0] != Some(to) {
+                block.successors[1] = block.successors[0];
+                block.successors[0] = Some(to);
+            }
+        }
+    }
+
+    fn page(address: Address) -> Option<usize> {
+        if address < RAM_BASE {
+            return None;
+        }
+
+        Some(((address - RAM_BASE) >> PAGE_SHIFT) as usize)
+    }
+
+    // saturates in the last page of the address space
+    pub fn page_end(address: Address) -> Address {
+        (address & !(PAGE_SIZE - 1)).saturating_add(PAGE_SIZE)
+    }
+}
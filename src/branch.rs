@@ -0,0 +1,642 @@
+use std::{collections::BTreeMap, fmt, io};
+
+use crate::{
+    bus::Address,
+    decoder::Instruction,
+    snapshot::{get, get_length, invalid, put},
+};
+
+// ra and t0, the registers the calling convention links through
+const LINKS: [u8; 2] = [1, 5];
+
+/// What a control transfer instruction does, as told by its opcode and the
+/// link registers it uses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BranchKind {
+    Conditional,
+    Jump,
+    Call,
+    IndirectJump,
+    IndirectCall,
+    Return,
+}
+
+// in the order of their discriminants
+const KINDS: [BranchKind; 6] = [
+    BranchKind::Conditional,
+    BranchKind::Jump,
+    BranchKind::Call,
+    BranchKind::IndirectJump,
+    BranchKind::IndirectCall,
+    BranchKind::Return,
+];
+
+impl BranchKind {
+    pub fn of(instruction: &Instruction) -> Option<Self> {
+        let link = |register: &u8| LINKS.contains(register);
+
+        Some(match instruction {
+            Instruction::Branch { .. } => BranchKind::Conditional,
+            Instruction::Jal { rd, .. } if link(rd) => BranchKind::Call,
+            Instruction::Jal { .. } => BranchKind::Jump,
+            Instruction::Jalr { rd, .. } if link(rd) => BranchKind::IndirectCall,
+            Instruction::Jalr { rs1, .. } if link(rs1) => BranchKind::Return,
+            Instruction::Jalr { .. } => BranchKind::IndirectJump,
+
+            _ => return None,
+        })
+    }
+
+    // whether the target comes from a register rather than the instruction
+    pub fn is_indirect(self) -> bool {
+        matches!(
+            self,
+            BranchKind::IndirectJump | BranchKind::IndirectCall | BranchKind::Return
+        )
+    }
+}
+
+impl fmt::Display for BranchKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            BranchKind::Conditional => "conditional",
+            BranchKind::Jump => "jump",
+            BranchKind::Call => "call",
+            BranchKind::IndirectJump => "indirect jump",
+            BranchKind::IndirectCall => "indirect call",
+            BranchKind::Return => "return",
+        })
+    }
+}
+
+/// The outcome of an executed control transfer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Branch {
+    pub pc: Address,
+    pub kind: BranchKind,
+    pub taken: bool,
+
+    // where execution continued, and the instruction right after it
+    pub target: Address,
+    pub fallthrough: Address,
+}
+
+/// What a predictor expects a control transfer to do. The target only
+/// matters for indirect ones, the others encode it in the instruction.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Prediction {
+    pub taken: bool,
+    pub target: Option<Address>,
+}
+
+impl Prediction {
+    pub fn is_correct(&self, branch: &Branch) -> bool {
+        self.taken == branch.taken
+            && (!branch.taken || !branch.kind.is_indirect() || self.target == Some(branch.target))
+    }
+}
+
+/// A branch predictor, asked about every control transfer before being told
+/// what it actually did.
+///
+/// Snapshots keep whatever `save_state` writes and hand it back to
+/// `load_state`, so predictors that keep nothing there start over cold when
+/// one is restored.
+pub trait Predictor: Send {
+    fn predict(&mut self, pc: Address, kind: BranchKind) -> Prediction;
+    fn update(&mut self, branch: &Branch, prediction: &Prediction);
+
+    fn save_state(&self, _out: &mut Vec<u8>) {}
+
+    fn load_state(&mut self, _input: &mut &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// the direction alone, where anything but a conditional branch is always taken
+fn direction(kind: BranchKind, taken: impl FnOnce() -> bool) -> Prediction {
+    Prediction {
+        taken: kind != BranchKind::Conditional || taken(),
+        target: None,
+    }
+}
+
+// moves a two-bit saturating counter towards the outcome
+fn train(counter: &mut u8, taken: bool) {
+    *counter = match taken {
+        true => (*counter + 1).min(3),
+        false => counter.saturating_sub(1),
+    };
+}
+
+fn table(entries: usize) -> Vec<u8> {
+    assert!(
+        entries.is_power_of_two(),
+        "{entries} entries is not a power of two"
+    );
+
+    // weakly not taken
+    vec![1; entries]
+}
+
+fn save_table(out: &mut Vec<u8>, counters: &[u8]) {
+    put(out, counters.len() as u64);
+    out.extend_from_slice(counters);
+}
+
+fn load_table(input: &mut &[u8], counters: &mut [u8]) -> io::Result<()> {
+    get_length(input, counters.len())?;
+    if input.len() < counters.len() || input[..counters.len()].iter().any(|&c| c > 3) {
+        return Err(invalid("malformed predictor state"));
+    }
+
+    counters.copy_from_slice(&input[..counters.len()]);
+    *input = &input[counters.len()..];
+    Ok(())
+}
+
+/// A table of two-bit counters indexed by the address of the branch.
+pub struct Bimodal {
+    counters: Vec<u8>,
+}
+
+impl Bimodal {
+    pub fn new(entries: usize) -> Self {
+        Self {
+            counters: table(entries),
+        }
+    }
+
+    fn index(&self, pc: Address) -> usize {
+        (pc >> 2) as usize & (self.counters.len() - 1)
+    }
+}
+
+impl Predictor for Bimodal {
+    fn predict(&mut self, pc: Address, kind: BranchKind) -> Prediction {
+        direction(kind, || self.counters[self.index(pc)] >= 2)
+    }
+
+    fn update(&mut self, branch: &Branch, _prediction: &Prediction) {
+        if branch.kind == BranchKind::Conditional {
+            let index = self.index(branch.pc);
+            train(&mut self.counters[index], branch.taken);
+        }
+    }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        save_table(out, &self.counters);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        load_table(input, &mut self.counters)
+    }
+}
+
+/// Two-bit counters indexed by the address of the branch XORed with the
+/// outcomes of the most recent conditional branches.
+pub struct Gshare {
+    counters: Vec<u8>,
+    history: u64,
+}
+
+impl Gshare {
+    pub fn new(entries: usize) -> Self {
+        Self {
+            counters: table(entries),
+            history: 0,
+        }
+    }
+
+    fn index(&self, pc: Address) -> usize {
+        ((pc >> 2) ^ self.history) as usize & (self.counters.len() - 1)
+    }
+}
+
+impl Predictor for Gshare {
+    fn predict(&mut self, pc: Address, kind: BranchKind) -> Prediction {
+        direction(kind, || self.counters[self.index(pc)] >= 2)
+    }
+
+    fn update(&mut self, branch: &Branch, _prediction: &Prediction) {
+        if branch.kind == BranchKind::Conditional {
+            let index = self.index(branch.pc);
+            train(&mut self.counters[index], branch.taken);
+            self.history = (self.history << 1) | branch.taken as u64;
+        }
+    }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        save_table(out, &self.counters);
+        put(out, self.history);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        load_table(input, &mut self.counters)?;
+        self.history = get(input)?;
+        Ok(())
+    }
+}
+
+// history lengths of the tagged tables, growing geometrically
+const TAGE_HISTORIES: [u32; 4] = [4, 8, 16, 32];
+const TAGE_INDEX_BITS: u32 = 10;
+const TAGE_TAG_BITS: u32 = 9;
+
+#[derive(Clone, Copy, Default)]
+struct TageEntry {
+    // None until allocated
+    tag: Option<u16>,
+
+    // three-bit signed counter, taken when not negative
+    counter: i8,
+
+    // whether the entry made better predictions than the shorter histories, up to 3
+    useful: u8,
+}
+
+/// A reduced TAGE: a bimodal base predictor backed by four tagged tables
+/// using ever longer global histories, where the longest matching one makes
+/// the prediction. Mispredictions allocate entries in longer tables unless
+/// all candidates have been useful lately.
+pub struct Tage {
+    base: Bimodal,
+    tables: Vec<Vec<TageEntry>>,
+    history: u64,
+}
+
+impl Default for Tage {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tage {
+    pub fn new() -> Self {
+        Self {
+            base: Bimodal::new(1 << 12),
+            tables: vec![vec![TageEntry::default(); 1 << TAGE_INDEX_BITS]; TAGE_HISTORIES.len()],
+            history: 0,
+        }
+    }
+
+    // the history of a table folded into `bits` bits
+    fn fold(&self, table: usize, bits: u32) -> u64 {
+        let length = TAGE_HISTORIES[table];
+        let mut history = self.history & ((1 << length) - 1);
+
+        let mut folded = 0;
+        while history != 0 {
+            folded ^= history & ((1 << bits) - 1);
+            history >>= bits;
+        }
+        folded
+    }
+
+    fn index(&self, table: usize, pc: Address) -> usize {
+        let pc = pc >> 2;
+        ((pc ^ (pc >> TAGE_INDEX_BITS) ^ self.fold(table, TAGE_INDEX_BITS)) as usize)
+            & ((1 << TAGE_INDEX_BITS) - 1)
+    }
+
+    fn tag(&self, table: usize, pc: Address) -> u16 {
+        let pc = pc >> 2;
+        ((pc ^ self.fold(table, TAGE_TAG_BITS) ^ (self.fold(table, TAGE_TAG_BITS - 1) << 1))
+            & ((1 << TAGE_TAG_BITS) - 1)) as u16
+    }
+
+    // the tables with a matching entry, longest history first
+    fn matches(&self, pc: Address) -> Vec<(usize, usize)> {
+        (0..self.tables.len())
+            .rev()
+            .map(|table| (table, self.index(table, pc)))
+            .filter(|&(table, index)| self.tables[table][index].tag == Some(self.tag(table, pc)))
+            .collect()
+    }
+
+    // the prediction of the longest matching table, and of the next one or the base predictor
+    fn directions(&self, pc: Address) -> (bool, bool) {
+        let base = self.base.counters[self.base.index(pc)] >= 2;
+        let mut directions = self
+            .matches(pc)
+            .into_iter()
+            .map(|(table, index)| self.tables[table][index].counter >= 0);
+
+        let provider = directions.next().unwrap_or(base);
+        (provider, directions.next().unwrap_or(base))
+    }
+}
+
+impl Predictor for Tage {
+    fn predict(&mut self, pc: Address, kind: BranchKind) -> Prediction {
+        direction(kind, || self.directions(pc).0)
+    }
+
+    fn update(&mut self, branch: &Branch, prediction: &Prediction) {
+        if branch.kind != BranchKind::Conditional {
+            return;
+        }
+
+        let (pc, taken) = (branch.pc, branch.taken);
+        let (predicted, alternative) = self.directions(pc);
+        let provider = self.matches(pc).first().copied();
+
+        match provider {
+            Some((table, index)) => {
+                let entry = &mut self.tables[table][index];
+                if predicted != alternative {
+                    entry.useful = match predicted == taken {
+                        true => (entry.useful + 1).min(3),
+                        false => entry.useful.saturating_sub(1),
+                    };
+                }
+
+                entry.counter = match taken {
+                    true => (entry.counter + 1).min(3),
+                    false => (entry.counter - 1).max(-4),
+                };
+            }
+            None => self.base.update(branch, prediction),
+        }
+
+        if predicted != taken {
+            let longer = provider.map_or(0, |(table, _)| table + 1);
+            let free = (longer..self.tables.len())
+                .find(|&table| self.tables[table][self.index(table, pc)].useful == 0);
+
+            match free {
+                Some(table) => {
+                    let index = self.index(table, pc);
+                    self.tables[table][index] = TageEntry {
+                        tag: Some(self.tag(table, pc)),
+                        counter: if taken { 0 } else { -1 },
+                        useful: 0,
+                    };
+                }
+                None => {
+                    for table in longer..self.tables.len() {
+                        let index = self.index(table, pc);
+                        let entry = &mut self.tables[table][index];
+                        entry.useful = entry.useful.saturating_sub(1);
+                    }
+                }
+            }
+        }
+
+        self.history = (self.history << 1) | taken as u64;
+    }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        self.base.save_state(out);
+        for entry in self.tables.iter().flatten() {
+            put(out, entry.tag.map_or(u64::MAX, u64::from));
+            put(out, entry.counter as u64);
+            put(out, entry.useful as u64);
+        }
+        put(out, self.history);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        self.base.load_state(input)?;
+        for entry in self.tables.iter_mut().flatten() {
+            let tag = get(input)?;
+            let counter = get(input)? as i64;
+            let useful = get(input)?;
+            if (tag >= 1 << TAGE_TAG_BITS && tag != u64::MAX)
+                || !(-4..=3).contains(&counter)
+                || useful > 3
+            {
+                return Err(invalid("malformed predictor state"));
+            }
+
+            *entry = TageEntry {
+                tag: (tag != u64::MAX).then_some(tag as u16),
+                counter: counter as i8,
+                useful: useful as u8,
+            };
+        }
+        self.history = get(input)?;
+        Ok(())
+    }
+}
+
+/// A return address stack in front of another predictor: calls push the
+/// address after them and returns pop their target, while everything else
+/// is left to the other predictor. The oldest entries are lost on overflow.
+pub struct ReturnStack {
+    stack: Vec<Address>,
+    depth: usize,
+    inner: Box<dyn Predictor>,
+}
+
+impl ReturnStack {
+    pub fn new(depth: usize, inner: impl Predictor + 'static) -> Self {
+        Self {
+            stack: vec![],
+            depth,
+            inner: Box::new(inner),
+        }
+    }
+}
+
+impl Predictor for ReturnStack {
+    fn predict(&mut self, pc: Address, kind: BranchKind) -> Prediction {
+        match kind {
+            BranchKind::Return => Prediction {
+                taken: true,
+                target: self.stack.last().copied(),
+            },
+            _ => self.inner.predict(pc, kind),
+        }
+    }
+
+    fn update(&mut self, branch: &Branch, prediction: &Prediction) {
+        match branch.kind {
+            BranchKind::Call | BranchKind::IndirectCall => {
+                if self.stack.len() == self.depth {
+                    self.stack.remove(0);
+                }
+                if self.depth > 0 {
+                    self.stack.push(branch.fallthrough);
+                }
+            }
+            BranchKind::Return => {
+                self.stack.pop();
+            }
+            _ => {}
+        }
+
+        self.inner.update(branch, prediction);
+    }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        put(out, self.stack.len() as u64);
+        for &address in &self.stack {
+            put(out, address);
+        }
+        self.inner.save_state(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        let length = get(input)?;
+        if length > self.depth as u64 {
+            return Err(invalid("return stack deeper than configured"));
+        }
+
+        self.stack = (0..length).map(|_| get(input)).collect::<io::Result<_>>()?;
+        self.inner.load_state(input)
+    }
+}
+
+/// How the executions of one static branch went.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BranchStats {
+    pub kind: BranchKind,
+    pub executions: u64,
+    pub taken: u64,
+    pub mispredictions: u64,
+}
+
+impl BranchStats {
+    // the share of correct predictions, one before the first execution
+    pub fn accuracy(&self) -> f64 {
+        match self.executions {
+            0 => 1.0,
+            executions => 1.0 - self.mispredictions as f64 / executions as f64,
+        }
+    }
+}
+
+/// A predictor fed with every control transfer a hart retires, along with
+/// statistics for each static branch.
+pub struct BranchPrediction {
+    predictor: Box<dyn Predictor>,
+    branches: BTreeMap<Address, BranchStats>,
+}
+
+impl BranchPrediction {
+    pub fn new(predictor: impl Predictor + 'static) -> Self {
+        Self {
+            predictor: Box::new(predictor),
+            branches: BTreeMap::new(),
+        }
+    }
+
+    // by address
+    pub fn branches(&self) -> &BTreeMap<Address, BranchStats> {
+        &self.branches
+    }
+
+    pub fn executions(&self) -> u64 {
+        self.branches.values().map(|stats| stats.executions).sum()
+    }
+
+    pub fn mispredictions(&self) -> u64 {
+        self.branches
+            .values()
+            .map(|stats| stats.mispredictions)
+            .sum()
+    }
+
+    // the share of correct predictions over all branches, one before the first
+    pub fn accuracy(&self) -> f64 {
+        match self.executions() {
+            0 => 1.0,
+            executions => 1.0 - self.mispredictions() as f64 / executions as f64,
+        }
+    }
+
+    // a branch to the next instruction cannot be told apart from one that was not taken, and costs the same
+    pub(crate) fn observe(
+        &mut self,
+        pc: Address,
+        instruction: &Instruction,
+        fallthrough: Address,
+        next: Address,
+    ) {
+        let Some(kind) = BranchKind::of(instruction) else {
+            return;
+        };
+
+        let branch = Branch {
+            pc,
+            kind,
+            taken: next != fallthrough,
+            target: next,
+            fallthrough,
+        };
+
+        let prediction = self.predictor.predict(pc, kind);
+        let correct = prediction.is_correct(&branch);
+        self.predictor.update(&branch, &prediction);
+
+        let stats = self.branches.entry(pc).or_insert(BranchStats {
+            kind,
+            executions: 0,
+            taken: 0,
+            mispredictions: 0,
+        });
+        stats.executions += 1;
+        stats.taken += branch.taken as u64;
+        stats.mispredictions += !correct as u64;
+    }
+
+    pub(crate) fn save_state(&self, out: &mut Vec<u8>) {
+        self.predictor.save_state(out);
+
+        put(out, self.branches.len() as u64);
+        for (&pc, stats) in &self.branches {
+            put(out, pc);
+            put(out, stats.kind as u64);
+            put(out, stats.executions);
+            put(out, stats.taken);
+            put(out, stats.mispredictions);
+        }
+    }
+
+    pub(crate) fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        self.predictor.load_state(input)?;
+
+        self.branches.clear();
+        for _ in 0..get(input)? {
+            let pc = get(input)?;
+            let kind = *KINDS
+                .get(get(input)? as usize)
+                .ok_or_else(|| invalid("unknown branch kind"))?;
+            let stats = BranchStats {
+                kind,
+                executions: get(input)?,
+                taken: get(input)?,
+                mispredictions: get(input)?,
+            };
+            self.branches.insert(pc, stats);
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for BranchPrediction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} branches, {} mispredicted, {:.2}% accuracy",
+            self.executions(),
+            self.mispredictions(),
+            100.0 * self.accuracy()
+        )?;
+
+        for (pc, stats) in &self.branches {
+            write!(
+                f,
+                "\n  {pc:#010x} {:<13} {:>10} executed {:>7.2}% taken {:>7.2}% accuracy",
+                stats.kind.to_string(),
+                stats.executions,
+                100.0 * stats.taken as f64 / stats.executions as f64,
+                100.0 * stats.accuracy()
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,4 +1,11 @@
-use crate::{dram::DRAM, exception::RVException};
+use std::{mem, sync::Arc};
+
+use crate::{
+    clint::{Clint, CLINT_BASE, CLINT_SIZE},
+    dram::DRAM,
+    exception::RVException,
+    mmio::{Mmio, MmioRegion},
+};
 
 pub type Address = u64;
 
@@ -11,23 +18,156 @@ pub trait Device {
 }
 
 pub struct Bus {
-    pub ram: DRAM,
+    // shared by every hart of a machine, so it never leaves the bus
+    pub(crate) ram: DRAM,
+
+    // only present on machines with several harts
+    pub clint: Option<Arc<Clint>>,
+
+    // devices provided by the embedder
+    pub mmio: Vec<MmioRegion>,
 }
 
 impl Bus {
+    pub fn new(ram: DRAM, clint: Option<Arc<Clint>>) -> Self {
+        Self {
+            ram,
+            clint,
+            mmio: vec![],
+        }
+    }
+
+    pub fn ram(&self) -> &DRAM {
+        &self.ram
+    }
+
+    // another bus mapping the same memory and devices
+    pub(crate) fn share(&self) -> Self {
+        Self {
+            ram: self.ram.share(),
+            clint: self.clint.clone(),
+            mmio: self.mmio.clone(),
+        }
+    }
+
+    // maps a device at `base`, unless the range is empty or overlaps anything already mapped
+    pub fn map(&mut self, base: Address, size: Address, device: Arc<dyn Mmio>) -> bool {
+        let Some(end) = base.checked_add(size).filter(|_| size != 0) else {
+            return false;
+        };
+
+        let overlaps = |start: Address, length: Address| base < start + length && start < end;
+        let taken = overlaps(RAM_BASE, self.ram.size() as Address)
+            || (self.clint.is_some() && overlaps(CLINT_BASE, CLINT_SIZE))
+            || self
+                .mmio
+                .iter()
+                .any(|region| overlaps(region.base, region.size));
+        if taken {
+            return false;
+        }
+
+        self.mmio.push(MmioRegion { base, size, device });
+        true
+    }
+
+    // accesses must lie entirely within RAM, the CLINT or a single region, anything else faults
     pub fn read<T: Sized>(&self, address: Address) -> Result<T, RVException> {
-        if address >= RAM_BASE && address < (RAM_BASE + self.ram.size() as Address) {
+        let size = mem::size_of::<T>();
+        if self.in_ram(address, size) {
             return self.ram.read::<T>(address - RAM_BASE);
         }
 
+        if let Some(clint) = self.clint(address, size) {
+            return clint.read::<T>(address - CLINT_BASE);
+        }
+
+        if let Some(region) = self.region(address, size) {
+            return region
+                .device
+                .read(address - region.base, size)
+                .map(from_bits)
+                .ok_or(RVException::LoadAccessFault);
+        }
+
         Err(RVException::LoadAccessFault)
     }
 
     pub fn write<T: Sized>(&mut self, address: Address, value: T) -> Result<(), RVException> {
-        if address >= RAM_BASE && address < (RAM_BASE + self.ram.size() as Address) {
+        let size = mem::size_of::<T>();
+        if self.in_ram(address, size) {
             return self.ram.write::<T>(address - RAM_BASE, value);
         }
 
+        if let Some(clint) = self.clint(address, size) {
+            return clint.write::<T>(address - CLINT_BASE, value);
+        }
+
+        if let Some(region) = self.region(address, size) {
+            return match region
+                .device
+                .write(address - region.base, size, to_bits(value))
+            {
+                true => Ok(()),
+                false => Err(RVException::StoreAccessFault),
+            };
+        }
+
         Err(RVException::StoreAccessFault)
     }
+
+    // whether `address` starts an access of `size` bytes that lies entirely in RAM
+    pub(crate) fn in_ram(&self, address: Address, size: usize) -> bool {
+        within(address, size, RAM_BASE, self.ram.size() as Address)
+    }
+
+    // whether anything maps the byte at `address`
+    pub(crate) fn mapped(&self, address: Address) -> bool {
+        self.in_ram(address, 1)
+            || self.clint(address, 1).is_some()
+            || self.region(address, 1).is_some()
+    }
+
+    fn clint(&self, address: Address, size: usize) -> Option<&Clint> {
+        self.clint
+            .as_deref()
+            .filter(|_| within(address, size, CLINT_BASE, CLINT_SIZE))
+    }
+
+    fn region(&self, address: Address, size: usize) -> Option<&MmioRegion> {
+        self.mmio
+            .iter()
+            .find(|region| region.contains(address, size))
+    }
+}
+
+// whether an access of `size` bytes at `address` lies entirely in the `length` bytes from `base`
+pub(crate) fn within(address: Address, size: usize, base: Address, length: Address) -> bool {
+    address >= base
+        && (address - base)
+            .checked_add(size as Address)
+            .is_some_and(|end| end <= length)
+}
+
+// the low bytes of a value of at most eight bytes, and back
+pub(crate) fn to_bits<T: Sized>(value: T) -> u64 {
+    unsafe {
+        match mem::size_of::<T>() {
+            1 => mem::transmute_copy::<T, u8>(&value) as u64,
+            2 => mem::transmute_copy::<T, u16>(&value) as u64,
+            4 => mem::transmute_copy::<T, u32>(&value) as u64,
+            _ => mem::transmute_copy::<T, u64>(&value),
+        }
+    }
+}
+
+pub(crate) fn from_bits<T: Sized>(value: u64) -> T {
+    unsafe {
+        match mem::size_of::<T>() {
+            1 => mem::transmute_copy(&(value as u8)),
+            2 => mem::transmute_copy(&(value as u16)),
+            4 => mem::transmute_copy(&(value as u32)),
+            _ => mem::transmute_copy(&value),
+        }
+    }
 }
@@ -0,0 +1,529 @@
+use std::{io, ops::Range};
+
+use crate::{
+    bus::Address,
+    snapshot::{get, get_length, invalid, put},
+};
+
+/// Which line of a full set makes room for a new one. Empty lines are
+/// always filled first.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum Replacement {
+    #[default]
+    Lru,
+
+    // tree pseudo-LRU, which needs a power-of-two number of ways
+    Plru,
+
+    // from a fixed seed, so runs are reproducible
+    Random,
+}
+
+/// What stores do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum WritePolicy {
+    // stores allocate lines and mark them dirty, dirty lines reach the next level when evicted
+    #[default]
+    WriteBack,
+
+    // stores go on to the next level right away and never allocate lines
+    WriteThrough,
+}
+
+/// Geometry and policies of a cache, where `latency` is the number of
+/// cycles a hit takes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CacheConfig {
+    pub size: usize,
+    pub ways: usize,
+    pub line: usize,
+    pub replacement: Replacement,
+    pub write: WritePolicy,
+    pub latency: u64,
+}
+
+impl CacheConfig {
+    // a write-back LRU cache
+    pub fn new(size: usize, ways: usize, line: usize, latency: u64) -> Self {
+        Self {
+            size,
+            ways,
+            line,
+            replacement: Replacement::default(),
+            write: WritePolicy::default(),
+            latency,
+        }
+    }
+
+    // whether lines and sets are powers of two, with at most 64 ways
+    pub fn supports(&self) -> bool {
+        let plru = self.replacement != Replacement::Plru || self.ways.is_power_of_two();
+
+        self.line.is_power_of_two()
+            && (1..=64).contains(&self.ways)
+            && plru
+            && self.size.is_multiple_of(self.ways * self.line)
+            && self.sets().is_power_of_two()
+    }
+
+    pub fn sets(&self) -> usize {
+        self.size / (self.ways * self.line)
+    }
+}
+
+/// What happened to the accesses of a cache.
+///
+/// Evictions count valid lines replaced, and write-backs the dirty ones
+/// among them.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub writebacks: u64,
+}
+
+impl CacheStats {
+    pub fn accesses(&self) -> u64 {
+        self.hits + self.misses
+    }
+
+    // zero before the first access
+    pub fn miss_rate(&self) -> f64 {
+        match self.accesses() {
+            0 => 0.0,
+            accesses => self.misses as f64 / accesses as f64,
+        }
+    }
+
+    fn save(&self, out: &mut Vec<u8>) {
+        for value in [self.hits, self.misses, self.evictions, self.writebacks] {
+            put(out, value);
+        }
+    }
+
+    fn load(input: &mut &[u8]) -> io::Result<Self> {
+        Ok(Self {
+            hits: get(input)?,
+            misses: get(input)?,
+            evictions: get(input)?,
+            writebacks: get(input)?,
+        })
+    }
+
+    fn add(&mut self, outcome: &Outcome) {
+        match outcome.hit {
+            true => self.hits += 1,
+            false => self.misses += 1,
+        }
+        self.evictions += outcome.evicted as u64;
+        self.writebacks += outcome.writeback.is_some() as u64;
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct Line {
+    tag: u64,
+    valid: bool,
+    dirty: bool,
+
+    // when it was last used, for LRU
+    used: u64,
+}
+
+struct Outcome {
+    hit: bool,
+    evicted: bool,
+
+    // address of a dirty line that was evicted
+    writeback: Option<Address>,
+}
+
+/// A set-associative cache, keeping only tags: data always lives in RAM.
+#[derive(Clone, Debug)]
+pub struct Cache {
+    config: CacheConfig,
+
+    // `ways` consecutive lines per set
+    lines: Vec<Line>,
+
+    // tree bits of each set for PLRU, pointing to the half to replace next
+    trees: Vec<u64>,
+
+    clock: u64,
+    seed: u64,
+    stats: CacheStats,
+}
+
+impl Cache {
+    pub fn new(config: CacheConfig) -> Self {
+        assert!(config.supports(), "unsupported cache geometry {config:?}");
+
+        Self {
+            config,
+            lines: vec![Line::default(); config.sets() * config.ways],
+            trees: vec![0; config.sets()],
+            clock: 0,
+            seed: 0x2545_F491_4F6C_DD1D,
+            stats: CacheStats::default(),
+        }
+    }
+
+    pub fn config(&self) -> CacheConfig {
+        self.config
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    // whether the line holding `address` is present
+    pub fn contains(&self, address: Address) -> bool {
+        let (set, tag) = self.locate(address);
+        self.set(set)
+            .iter()
+            .any(|line| line.valid && line.tag == tag)
+    }
+
+    // whether the line holding `address` is present and differs from RAM
+    pub fn is_dirty(&self, address: Address) -> bool {
+        let (set, tag) = self.locate(address);
+        self.set(set)
+            .iter()
+            .any(|line| line.valid && line.dirty && line.tag == tag)
+    }
+
+    // looks up the line holding `address`, bringing it in on a miss when `allocate` is set
+    fn access(&mut self, address: Address, dirty: bool, allocate: bool) -> Outcome {
+        let (set, tag) = self.locate(address);
+        self.clock += 1;
+
+        let hit = self
+            .set(set)
+            .iter()
+            .position(|line| line.valid && line.tag == tag);
+        let mut outcome = Outcome {
+            hit: hit.is_some(),
+            evicted: false,
+            writeback: None,
+        };
+
+        let way = match hit {
+            Some(way) => way,
+            None if allocate => {
+                let way = self.victim(set);
+                let line = self.lines[set * self.config.ways + way];
+
+                outcome.evicted = line.valid;
+                if line.valid && line.dirty {
+                    outcome.writeback = Some(self.address(set, line.tag));
+                }
+
+                self.lines[set * self.config.ways + way] = Line {
+                    tag,
+                    valid: true,
+                    dirty: false,
+                    used: 0,
+                };
+                way
+            }
+            None => {
+                self.stats.add(&outcome);
+                return outcome;
+            }
+        };
+
+        let line = &mut self.lines[set * self.config.ways + way];
+        line.dirty |= dirty;
+        line.used = self.clock;
+        self.touch(set, way);
+
+        self.stats.add(&outcome);
+        outcome
+    }
+
+    fn save_state(&self, out: &mut Vec<u8>) {
+        put(out, self.lines.len() as u64);
+        for line in &self.lines {
+            put(out, line.tag);
+            put(out, line.valid as u64 | (line.dirty as u64) << 1);
+            put(out, line.used);
+        }
+        for &tree in &self.trees {
+            put(out, tree);
+        }
+        put(out, self.clock);
+        put(out, self.seed);
+        self.stats.save(out);
+    }
+
+    fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        get_length(input, self.lines.len())?;
+        for line in self.lines.iter_mut() {
+            let tag = get(input)?;
+            let flags = get(input)?;
+            *line = Line {
+                tag,
+                valid: flags & 0x01 != 0,
+                dirty: flags & 0x02 != 0,
+                used: get(input)?,
+            };
+        }
+        for tree in self.trees.iter_mut() {
+            *tree = get(input)?;
+        }
+        self.clock = get(input)?;
+        self.seed = get(input)?;
+        self.stats = CacheStats::load(input)?;
+
+        Ok(())
+    }
+
+    fn locate(&self, address: Address) -> (usize, u64) {
+        let line = address / self.config.line as u64;
+        let sets = self.config.sets() as u64;
+
+        ((line % sets) as usize, line / sets)
+    }
+
+    fn address(&self, set: usize, tag: u64) -> Address {
+        (tag * self.config.sets() as u64 + set as u64) * self.config.line as u64
+    }
+
+    fn set(&self, set: usize) -> &[Line] {
+        let ways = self.config.ways;
+        &self.lines[set * ways..(set + 1) * ways]
+    }
+
+    fn victim(&mut self, set: usize) -> usize {
+        let ways = self.config.ways;
+        if let Some(way) = self.set(set).iter().position(|line| !line.valid) {
+            return way;
+        }
+
+        match self.config.replacement {
+            Replacement::Lru => self
+                .set(set)
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, line)| line.used)
+                .map_or(0, |(way, _)| way),
+
+            Replacement::Plru => {
+                let tree = self.trees[set];
+                let (mut node, mut way) = (0, 0);
+                for _ in 0..ways.trailing_zeros() {
+                    let bit = (tree >> node) as usize & 0x01;
+                    way = (way << 1) | bit;
+                    node = 2 * node + 1 + bit;
+                }
+                way
+            }
+
+            // xorshift64
+            Replacement::Random => {
+                self.seed ^= self.seed << 13;
+                self.seed ^= self.seed >> 7;
+                self.seed ^= self.seed << 17;
+                (self.seed % ways as u64) as usize
+            }
+        }
+    }
+
+    // points the tree bits on the path to `way` away from it
+    fn touch(&mut self, set: usize, way: usize) {
+        if self.config.replacement != Replacement::Plru {
+            return;
+        }
+
+        let levels = self.config.ways.trailing_zeros();
+        let tree = &mut self.trees[set];
+        let mut node = 0;
+        for level in (0..levels).rev() {
+            let bit = (way >> level) & 0x01;
+            match bit {
+                0 => *tree |= 1 << node,
+                _ => *tree &= !(1 << node),
+            }
+            node = 2 * node + 1 + bit;
+        }
+    }
+}
+
+/// A level of the cache hierarchy.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheLevel {
+    L1i,
+    L1d,
+    L2,
+}
+
+// where a level goes on a miss
+const MEMORY: usize = 3;
+
+struct Region {
+    name: String,
+    range: Range<Address>,
+    stats: [CacheStats; 3],
+}
+
+/// Split first-level instruction and data caches in front of a unified
+/// second level, each of which may be left out, simulated for accesses to
+/// RAM.
+///
+/// Every access reports how many cycles it takes beyond a first-level hit,
+/// which is what a timing model attached to the same hart stalls for.
+/// Stores through write-through caches are assumed to be buffered and cost
+/// no more than a hit.
+pub struct CacheHierarchy {
+    caches: [Option<Cache>; 3],
+
+    // cycles to reach RAM past the last level
+    memory_latency: u64,
+
+    regions: Vec<Region>,
+}
+
+impl CacheHierarchy {
+    pub fn new(
+        l1i: Option<CacheConfig>,
+        l1d: Option<CacheConfig>,
+        l2: Option<CacheConfig>,
+        memory_latency: u64,
+    ) -> Self {
+        Self {
+            caches: [l1i, l1d, l2].map(|config| config.map(Cache::new)),
+            memory_latency,
+            regions: vec![],
+        }
+    }
+
+    pub fn cache(&self, level: CacheLevel) -> Option<&Cache> {
+        self.caches[level as usize].as_ref()
+    }
+
+    // collects the statistics of accesses within `range` separately under `name`
+    pub fn add_region(&mut self, name: &str, range: Range<Address>) {
+        self.regions.push(Region {
+            name: name.to_string(),
+            range,
+            stats: Default::default(),
+        });
+    }
+
+    pub fn region_stats(&self, name: &str, level: CacheLevel) -> Option<CacheStats> {
+        self.regions
+            .iter()
+            .find(|region| region.name == name)
+            .map(|region| region.stats[level as usize])
+    }
+
+    pub(crate) fn save_state(&self, out: &mut Vec<u8>) {
+        for cache in &self.caches {
+            put(out, cache.is_some() as u64);
+            if let Some(cache) = cache {
+                cache.save_state(out);
+            }
+        }
+
+        put(out, self.regions.len() as u64);
+        for stats in self.regions.iter().flat_map(|region| &region.stats) {
+            stats.save(out);
+        }
+    }
+
+    pub(crate) fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        for cache in self.caches.iter_mut() {
+            if get(input)? != cache.is_some() as u64 {
+                return Err(invalid("model configured differently"));
+            }
+            if let Some(cache) = cache {
+                cache.load_state(input)?;
+            }
+        }
+
+        get_length(input, self.regions.len())?;
+        for stats in self.regions.iter_mut().flat_map(|region| &mut region.stats) {
+            *stats = CacheStats::load(input)?;
+        }
+
+        Ok(())
+    }
+
+    // each returns the cycles the access takes beyond a first-level hit
+    pub fn fetch(&mut self, address: Address, size: usize) -> u64 {
+        self.access(CacheLevel::L1i, address, size, false)
+    }
+
+    pub fn load(&mut self, address: Address, size: usize) -> u64 {
+        self.access(CacheLevel::L1d, address, size, false)
+    }
+
+    pub fn store(&mut self, address: Address, size: usize) -> u64 {
+        self.access(CacheLevel::L1d, address, size, true)
+    }
+
+    // every first-level line the access touches is looked up in turn
+    fn access(&mut self, level: CacheLevel, address: Address, size: usize, write: bool) -> u64 {
+        let first = level as usize;
+        let hit = self.caches[first]
+            .as_ref()
+            .map_or(0, |cache| cache.config.latency);
+        let line = [first, 2]
+            .into_iter()
+            .find_map(|level| self.caches[level].as_ref())
+            .map_or(u64::MAX, |cache| cache.config.line as u64);
+
+        let last = address.saturating_add(size.max(1) as u64 - 1);
+        let mut latency = 0;
+        let mut start = address;
+        while start <= last {
+            latency += self.lookup(first, start, write).saturating_sub(hit);
+
+            match (start | (line - 1)).checked_add(1) {
+                Some(next) => start = next,
+                None => break,
+            }
+        }
+
+        latency
+    }
+
+    fn lookup(&mut self, level: usize, address: Address, write: bool) -> u64 {
+        if level == MEMORY {
+            return self.memory_latency;
+        }
+
+        let next = match level {
+            2 => MEMORY,
+            _ => 2,
+        };
+        let Some(cache) = &mut self.caches[level] else {
+            return self.lookup(next, address, write);
+        };
+
+        let latency = cache.config.latency;
+        let through = write && cache.config.write == WritePolicy::WriteThrough;
+        let outcome = cache.access(address, write && !through, !through);
+
+        for region in &mut self.regions {
+            if region.range.contains(&address) {
+                region.stats[level].add(&outcome);
+            }
+        }
+
+        if let Some(victim) = outcome.writeback {
+            self.lookup(next, victim, true);
+        }
+
+        if through {
+            self.lookup(next, address, true);
+            return latency;
+        }
+
+        match outcome.hit {
+            true => latency,
+            false => latency + self.lookup(next, address, false),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+use std::{
+    mem,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use crate::{bus::Address, exception::RVException};
+
+pub const CLINT_BASE: Address = 0x0200_0000;
+pub const CLINT_SIZE: Address = 0x10000;
+
+/// Core-local interruptor, reduced to the msip registers used for
+/// inter-processor interrupts: writing 1 to the word at `4 * hartid` raises
+/// the machine software interrupt of that hart, and writing 0 clears it.
+pub struct Clint {
+    msip: Vec<AtomicU32>,
+}
+
+impl Clint {
+    pub fn new(harts: usize) -> Self {
+        Self {
+            msip: (0..harts).map(|_| AtomicU32::new(0)).collect(),
+        }
+    }
+
+    pub fn harts(&self) -> usize {
+        self.msip.len()
+    }
+
+    pub fn software_pending(&self, hartid: u64) -> bool {
+        self.msip
+            .get(hartid as usize)
+            .is_some_and(|msip| msip.load(Ordering::SeqCst) != 0)
+    }
+
+    pub fn set_software_pending(&self, hartid: u64, pending: bool) {
+        if let Some(msip) = self.msip.get(hartid as usize) {
+            msip.store(pending as u32, Ordering::SeqCst);
+        }
+    }
+
+    pub fn read<T: Sized>(&self, offset: Address) -> Result<T, RVException> {
+        let msip = self
+            .msip(offset, mem::size_of::<T>())
+            .ok_or(RVException::LoadAccessFault)?;
+        Ok(unsafe { mem::transmute_copy(&msip.load(Ordering::SeqCst)) })
+    }
+
+    pub fn write<T: Sized>(&self, offset: Address, value: T) -> Result<(), RVException> {
+        let msip = self
+            .msip(offset, mem::size_of::<T>())
+            .ok_or(RVException::StoreAccessFault)?;
+
+        // only the lowest bit is implemented
+        let value = unsafe { mem::transmute_copy::<T, u32>(&value) };
+        msip.store(value & 0x01, Ordering::SeqCst);
+
+        Ok(())
+    }
+
+    // the msip register accessed, which only supports whole aligned words
+    fn msip(&self, offset: Address, size: usize) -> Option<&AtomicU32> {
+        if size != 4 || !offset.is_multiple_of(4) {
+            return None;
+        }
+
+        self.msip.get((offset / 4) as usize)
+    }
+}
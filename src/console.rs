@@ -0,0 +1,50 @@
+use crate::{bus::Address, mmio::Mmio};
+
+// where QEMU's virt machine puts its first UART
+pub const CONSOLE_BASE: Address = 0x1000_0000;
+pub const CONSOLE_SIZE: Address = 0x100;
+
+// 16550 registers
+const THR: Address = 0;
+const LSR: Address = 5;
+
+// transmit holding register empty and transmitter idle
+const LSR_IDLE: u64 = 0x60;
+
+/// The transmit side of a 16550 UART: bytes written to THR are handed to the
+/// sink, and LSR always reports the transmitter as ready. The other registers
+/// read as zero and ignore writes.
+pub struct Console {
+    sink: Box<dyn Fn(u8) + Send + Sync>,
+}
+
+impl Console {
+    pub fn new(sink: impl Fn(u8) + Send + Sync + 'static) -> Self {
+        Self {
+            sink: Box::new(sink),
+        }
+    }
+}
+
+impl Mmio for Console {
+    fn read(&self, offset: Address, size: usize) -> Option<u64> {
+        match (offset, size) {
+            (LSR, 1) => Some(LSR_IDLE),
+            (_, 1) => Some(0),
+
+            _ => None,
+        }
+    }
+
+    fn write(&self, offset: Address, size: usize, value: u64) -> bool {
+        if size != 1 {
+            return false;
+        }
+
+        if offset == THR {
+            (self.sink)(value as u8);
+        }
+
+        true
+    }
+}
@@ -0,0 +1,548 @@
+use std::{
+    fmt,
+    io::{self, Read},
+};
+
+use crate::{
+    bus::Address,
+    emulator::{Emulator, StopReason},
+    exception::RVException,
+};
+
+// how many of the preceding events a divergence shows
+const CONTEXT: usize = 8;
+
+/// An instruction retired by the reference model, with everything it wrote.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Commit {
+    pub hart: u64,
+    pub privilege: u8,
+    pub pc: Address,
+    pub instruction: u32,
+    pub registers: Vec<(usize, u64)>,
+    pub csrs: Vec<(u16, u64)>,
+    pub stores: Vec<Store>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Store {
+    pub address: Address,
+    pub size: usize,
+    pub value: u64,
+}
+
+/// An exception taken by the reference model instead of retiring an instruction.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Trap {
+    pub hart: u64,
+
+    // None for interrupts and causes the emulator has no equivalent for
+    pub exception: Option<RVException>,
+    pub cause: String,
+    pub epc: Address,
+    pub tval: Option<u64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    Commit(Commit),
+    Trap(Trap),
+}
+
+/// A reference trace, as written by Spike with `--log-commits`.
+///
+/// Disassembly lines and writes to floating point and vector registers are
+/// ignored, so logs taken with `-l` or from programs using those extensions
+/// can be read as they are.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub events: Vec<Event>,
+}
+
+/// Where the emulator first disagreed with the reference trace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Divergence {
+    // index of the event in the trace
+    pub index: usize,
+    pub expected: Event,
+    pub differences: Vec<String>,
+
+    // the events leading up to the divergence, oldest first
+    pub recent: Vec<Event>,
+    pub xregs: [u64; 32],
+}
+
+impl Event {
+    pub fn hart(&self) -> u64 {
+        match self {
+            Event::Commit(commit) => commit.hart,
+            Event::Trap(trap) => trap.hart,
+        }
+    }
+
+    pub fn pc(&self) -> Address {
+        match self {
+            Event::Commit(commit) => commit.pc,
+            Event::Trap(trap) => trap.epc,
+        }
+    }
+}
+
+impl Trace {
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        Self::parse(&text)
+    }
+
+    pub fn parse(text: &str) -> io::Result<Self> {
+        let mut events = vec![];
+
+        for (index, line) in text.lines().enumerate() {
+            let invalid = |message: &str| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("line {}: {message}", index + 1),
+                )
+            };
+
+            // every line of interest starts with `core N:`
+            let Some(rest) = line.trim_start().strip_prefix("core") else {
+                continue;
+            };
+            let Some((hart, rest)) = rest.split_once(':') else {
+                continue;
+            };
+            let hart = hart.trim().parse().map_err(|_| invalid("bad hart"))?;
+            let tokens: Vec<&str> = rest.split_whitespace().collect();
+
+            match tokens.as_slice() {
+                ["exception", cause, "epc", epc] => events.push(Event::Trap(Trap {
+                    hart,
+                    exception: exception(cause.trim_end_matches(',')),
+                    cause: cause.trim_end_matches(',').to_string(),
+                    epc: hex(epc).ok_or_else(|| invalid("bad epc"))?,
+                    tval: None,
+                })),
+
+                ["interrupt", cause, "epc", epc] => events.push(Event::Trap(Trap {
+                    hart,
+                    exception: None,
+                    cause: cause.trim_end_matches(',').to_string(),
+                    epc: hex(epc).ok_or_else(|| invalid("bad epc"))?,
+                    tval: None,
+                })),
+
+                ["tval", tval] => match events.last_mut() {
+                    Some(Event::Trap(trap)) if trap.hart == hart => {
+                        trap.tval = Some(hex(tval).ok_or_else(|| invalid("bad tval"))?);
+                    }
+                    _ => return Err(invalid("tval without an exception")),
+                },
+
+                // commits start with the privilege level, disassembly with the pc
+                [privilege, pc, instruction, writes @ ..] if privilege.len() == 1 => {
+                    let privilege = privilege.parse().map_err(|_| invalid("bad privilege"))?;
+                    let pc = hex(pc).ok_or_else(|| invalid("bad pc"))?;
+                    let instruction = instruction
+                        .strip_prefix('(')
+                        .and_then(|instruction| instruction.strip_suffix(')'))
+                        .and_then(hex)
+                        .ok_or_else(|| invalid("bad instruction"))?;
+
+                    let mut commit = Commit {
+                        hart,
+                        privilege,
+                        pc,
+                        instruction: instruction as u32,
+                        registers: vec![],
+                        csrs: vec![],
+                        stores: vec![],
+                    };
+                    parse_writes(writes, &mut commit).map_err(|message| invalid(&message))?;
+
+                    events.push(Event::Commit(commit));
+                }
+
+                _ => {}
+            }
+        }
+
+        Ok(Self { events })
+    }
+
+    /// Runs the emulator in lockstep with the trace, stopping at the first
+    /// difference in pc, privilege, instruction, registers, CSRs or stored
+    /// memory, and returning the number of instructions compared otherwise.
+    ///
+    /// The emulator must start in the same state as the reference. Events
+    /// before its pc is first reached, such as Spike's boot ROM, are skipped,
+    /// but their register writes carry over. The emulator does not deliver
+    /// traps, so the comparison ends at the first exception, once its cause,
+    /// pc and tval have been checked.
+    pub fn cosimulate(&self, emu: &mut Emulator) -> Result<u64, Box<Divergence>> {
+        let hart = emu.cpu.hartid();
+        let events: Vec<(usize, &Event)> = self
+            .events
+            .iter()
+            .enumerate()
+            .filter(|(_, event)| event.hart() == hart)
+            .collect();
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        let Some(start) = events
+            .iter()
+            .position(|(_, event)| event.pc() == emu.cpu.pc)
+        else {
+            return Err(self.divergence(
+                emu,
+                &events,
+                0,
+                vec![format!("the trace never reaches pc {:#x}", emu.cpu.pc)],
+            ));
+        };
+
+        let xlen = emu.cpu.xlen();
+        for (_, event) in &events[..start] {
+            if let Event::Commit(commit) = event {
+                for &(register, value) in &commit.registers {
+                    emu.cpu.xregs[register] = xlen.sext(value);
+                }
+            }
+        }
+
+        let mut compared = 0;
+        for position in start..events.len() {
+            let differences = match events[position].1 {
+                Event::Commit(commit) => compare_commit(emu, commit),
+                Event::Trap(trap) => {
+                    let differences = compare_trap(emu, trap);
+                    if differences.is_empty() {
+                        return Ok(compared);
+                    }
+
+                    differences
+                }
+            };
+
+            if !differences.is_empty() {
+                return Err(self.divergence(emu, &events, position, differences));
+            }
+
+            compared += 1;
+        }
+
+        Ok(compared)
+    }
+
+    fn divergence(
+        &self,
+        emu: &Emulator,
+        events: &[(usize, &Event)],
+        position: usize,
+        differences: Vec<String>,
+    ) -> Box<Divergence> {
+        let (index, expected) = events[position];
+
+        Box::new(Divergence {
+            index,
+            expected: expected.clone(),
+            differences,
+            recent: events[position.saturating_sub(CONTEXT)..position]
+                .iter()
+                .map(|(_, event)| (*event).clone())
+                .collect(),
+            xregs: emu.cpu.xregs,
+        })
+    }
+}
+
+// the exception the emulator raises for the same cause, if any
+fn exception(cause: &str) -> Option<RVException> {
+    Some(match cause {
+        "trap_instruction_address_misaligned" => RVException::InstructionAddressMisaligned,
+        "trap_instruction_access_fault" => RVException::InstructionAccessFault,
+        "trap_illegal_instruction" => RVException::IllegalInstruction,
+        "trap_breakpoint" => RVException::Breakpoint,
+        "trap_load_address_misaligned" => RVException::LoadAddressMisaligned,
+        "trap_load_access_fault" => RVException::LoadAccessFault,
+        "trap_store_address_misaligned" => RVException::StoreAddressMisaligned,
+        "trap_store_access_fault" => RVException::StoreAccessFault,
+        "trap_user_ecall" | "trap_supervisor_ecall" | "trap_machine_ecall" => {
+            RVException::EnvironmentCall
+        }
+
+        _ => return None,
+    })
+}
+
+fn compare_commit(emu: &mut Emulator, commit: &Commit) -> Vec<String> {
+    let mut differences = vec![];
+
+    if emu.cpu.pc != commit.pc {
+        differences.push(format!(
+            "pc: expected {:#x}, found {:#x}",
+            commit.pc, emu.cpu.pc
+        ));
+        return differences;
+    }
+
+    if emu.cpu.privilege() as u8 != commit.privilege {
+        differences.push(format!(
+            "privilege: expected {}, found {}",
+            commit.privilege,
+            emu.cpu.privilege() as u8
+        ));
+    }
+
+    match fetch(emu, commit.pc) {
+        Ok(word) if word == commit.instruction => {}
+        Ok(word) => differences.push(format!(
+            "instruction: expected {:#010x}, found {word:#010x}",
+            commit.instruction
+        )),
+        Err(_) => differences.push(format!(
+            "instruction: expected {:#010x}, but it cannot be fetched",
+            commit.instruction
+        )),
+    }
+
+    if !differences.is_empty() {
+        return differences;
+    }
+
+    let before = emu.cpu.xregs;
+    let reason = emu.step();
+    if reason != StopReason::LimitReached {
+        differences.push(format!(
+            "stop: expected the instruction to retire, found {reason:?}"
+        ));
+        return differences;
+    }
+
+    let xlen = emu.cpu.xlen();
+    for (register, (&found, &previous)) in emu.cpu.xregs.iter().zip(&before).enumerate().skip(1) {
+        let written = commit
+            .registers
+            .iter()
+            .rfind(|&&(index, _)| index == register)
+            .map(|&(_, value)| value);
+
+        match written {
+            Some(value) if xlen.zext(found) != value => differences.push(format!(
+                "x{register}: expected {value:#x}, found {:#x}",
+                xlen.zext(found)
+            )),
+            None if found != previous => differences.push(format!(
+                "x{register}: expected {:#x} to be left alone, found {:#x}",
+                xlen.zext(previous),
+                xlen.zext(found)
+            )),
+
+            _ => {}
+        }
+    }
+
+    // CSRs the emulator lacks cannot have been read by the program without it trapping
+    for &(csr, value) in &commit.csrs {
+        match emu.cpu.read_csr(csr) {
+            Some(found) if found != value => differences.push(format!(
+                "CSR {csr:#x}: expected {value:#x}, found {found:#x}"
+            )),
+
+            _ => {}
+        }
+    }
+
+    for store in &commit.stores {
+        let mut data = [0; 8];
+        let data = &mut data[..store.size];
+        let expected = &store.value.to_le_bytes()[..store.size];
+
+        match emu.read_memory(store.address, data) {
+            Ok(()) if data == expected => {}
+            Ok(()) => differences.push(format!(
+                "memory at {:#x}: expected {expected:02x?}, found {data:02x?}",
+                store.address
+            )),
+            Err(_) => differences.push(format!(
+                "memory at {:#x}: expected {expected:02x?}, but it cannot be read",
+                store.address
+            )),
+        }
+    }
+
+    differences
+}
+
+// the instruction at `pc`, only 16 bits long when compressed, as the reference logs it
+fn fetch(emu: &mut Emulator, pc: Address) -> Result<u32, RVException> {
+    let mut low = [0; 2];
+    emu.read_memory(pc, &mut low)?;
+    let low = u16::from_le_bytes(low) as u32;
+    if low & 0x03 != 0x03 {
+        return Ok(low);
+    }
+
+    let mut high = [0; 2];
+    emu.read_memory(pc.wrapping_add(2), &mut high)?;
+    Ok((u16::from_le_bytes(high) as u32) << 16 | low)
+}
+
+fn compare_trap(emu: &mut Emulator, trap: &Trap) -> Vec<String> {
+    let Some(expected) = trap.exception else {
+        return vec![format!("the emulator cannot take `{}`", trap.cause)];
+    };
+
+    let pc = emu.cpu.pc;
+    let (exception, tval) = match emu.step() {
+        StopReason::Exited { .. } => (RVException::EnvironmentCall, 0),
+        StopReason::Breakpoint { .. } => (RVException::Breakpoint, pc),
+        StopReason::Fault {
+            exception, tval, ..
+        } => (exception, tval),
+
+        reason => return vec![format!("stop: expected {expected:?}, found {reason:?}")],
+    };
+
+    let mut differences = vec![];
+    if exception != expected {
+        differences.push(format!(
+            "exception: expected {expected:?}, found {exception:?}"
+        ));
+    }
+    if pc != trap.epc {
+        differences.push(format!("epc: expected {:#x}, found {pc:#x}", trap.epc));
+    }
+    if let Some(expected) = trap.tval.filter(|&expected| expected != tval) {
+        differences.push(format!("tval: expected {expected:#x}, found {tval:#x}"));
+    }
+
+    differences
+}
+
+// the register and memory writes following the instruction of a commit
+fn parse_writes(tokens: &[&str], commit: &mut Commit) -> Result<(), String> {
+    let mut tokens = tokens.iter().peekable();
+
+    while let Some(&token) = tokens.next() {
+        let mut value = || {
+            tokens
+                .next()
+                .and_then(|value| hex(value))
+                .ok_or_else(|| format!("missing value after `{token}`"))
+        };
+
+        if token == "mem" {
+            let address = value()?;
+
+            // loads only log the address
+            if let Some(data) = tokens.next_if(|value| value.starts_with("0x")) {
+                let size = (data.len() - 2) / 2;
+                if !matches!(size, 1 | 2 | 4 | 8) {
+                    return Err(format!("bad store value `{data}`"));
+                }
+
+                commit.stores.push(Store {
+                    address,
+                    size,
+                    value: hex(data).ok_or_else(|| format!("bad store value `{data}`"))?,
+                });
+            }
+        } else if let Some(register) = token
+            .strip_prefix('x')
+            .and_then(|index| index.parse::<usize>().ok())
+            .filter(|&index| index < 32)
+        {
+            commit.registers.push((register, value()?));
+        } else if let Some(csr) = token
+            .strip_prefix('c')
+            .and_then(|name| name.split('_').next())
+            .and_then(|number| number.parse::<u16>().ok())
+        {
+            commit.csrs.push((csr, value()?));
+        } else if token.starts_with(['f', 'v']) {
+            // floating point and vector registers have no counterpart to compare against
+            let _ = tokens.next_if(|value| value.starts_with("0x"));
+        }
+    }
+
+    Ok(())
+}
+
+fn hex(text: &str) -> Option<u64> {
+    u64::from_str_radix(text.strip_prefix("0x")?, 16).ok()
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Commit(commit) => {
+                write!(
+                    f,
+                    "core {:3}: {} {:#018x} ({:#010x})",
+                    commit.hart, commit.privilege, commit.pc, commit.instruction
+                )?;
+                for (register, value) in &commit.registers {
+                    write!(f, " x{register:<2} {value:#018x}")?;
+                }
+                for (csr, value) in &commit.csrs {
+                    write!(f, " c{csr} {value:#018x}")?;
+                }
+                for store in &commit.stores {
+                    write!(
+                        f,
+                        " mem {:#018x} {:#0width$x}",
+                        store.address,
+                        store.value,
+                        width = 2 + 2 * store.size
+                    )?;
+                }
+
+                Ok(())
+            }
+
+            Event::Trap(trap) => {
+                write!(
+                    f,
+                    "core {:3}: {}, epc {:#018x}",
+                    trap.hart, trap.cause, trap.epc
+                )?;
+                match trap.tval {
+                    Some(tval) => write!(f, ", tval {tval:#018x}"),
+                    None => Ok(()),
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "divergence at event {}: {}", self.index, self.expected)?;
+        for difference in &self.differences {
+            writeln!(f, "  {difference}")?;
+        }
+
+        if !self.recent.is_empty() {
+            writeln!(f, "preceded by:")?;
+            for event in &self.recent {
+                writeln!(f, "  {event}")?;
+            }
+        }
+
+        writeln!(f, "registers:")?;
+        for (index, row) in self.xregs.chunks(4).enumerate() {
+            let row: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(column, value)| format!("x{:<2} {value:#018x}", 4 * index + column))
+                .collect();
+            writeln!(f, "  {}", row.join("  "))?;
+        }
+
+        Ok(())
+    }
+}
@@ -1,610 +1,1213 @@
+use std::{
+    mem,
+    sync::atomic::{fence, Ordering},
+};
+
 use crate::{
-    bus::{Address, Bus, Device, RAM_BASE},
+    atomic::Reservation,
+    block::{Block, BlockCache, Op, MAX_BLOCK_LENGTH},
+    branch::BranchPrediction,
+    bus::{from_bits, to_bits, Address, Bus, Device, RAM_BASE},
+    cache::CacheHierarchy,
+    debug::{Debugger, WatchKind},
+    decoder::{
+        decode, decode_compressed, AluOp, BranchOp, DecodeCache, Instruction, LoadOp, StoreOp,
+    },
     exception::RVException,
+    pmp::{Access, Pmp},
+    profile::Profiler,
+    replay::{Event, EventLog},
+    timing::Pipeline,
+    vector::VectorUnit,
 };
 
+#[cfg(feature = "jit")]
+use crate::jit;
+
+/// Width of the integer registers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Xlen {
+    Rv32,
+    Rv64,
+}
+
+impl Xlen {
+    pub fn bits(self) -> u32 {
+        match self {
+            Xlen::Rv32 => 32,
+            Xlen::Rv64 => 64,
+        }
+    }
+
+    // brings a result to the register width, as held in the 64-bit register file
+    pub fn sext(self, value: u64) -> u64 {
+        match self {
+            Xlen::Rv32 => value as i32 as i64 as u64,
+            Xlen::Rv64 => value,
+        }
+    }
+
+    // the unsigned value of a register, as used for addresses
+    pub fn zext(self, value: u64) -> u64 {
+        match self {
+            Xlen::Rv32 => value as u32 as u64,
+            Xlen::Rv64 => value,
+        }
+    }
+}
+
+/// Privilege level a hart runs at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Privilege {
+    User = 0,
+    Supervisor = 1,
+    Machine = 3,
+}
+
+/// What loads and stores that are not naturally aligned do.
+///
+/// Emulated accesses are split into single bytes, each going to whichever
+/// device maps it. AMOs and LR/SC always trap, as they could not stay atomic.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum MisalignedPolicy {
+    Trap,
+    #[default]
+    Emulate,
+}
+
 pub struct CPU {
+    // in RV32 mode registers hold their value sign-extended to 64 bits
     pub xregs: [u64; 32],
     pub bus: Bus,
     pub pc: u64,
+
+    // number of retired instructions
+    pub instret: u64,
+
+    // what mcycle counts, one per instruction unless a timing model is attached
+    pub cycle: u64,
+
+    // trap value of the last raised exception, i.e. what mtval would hold
+    pub tval: u64,
+
+    pub debugger: Debugger,
+
+    pub vector: VectorUnit,
+
+    pub misaligned: MisalignedPolicy,
+
+    // accounts for every retired instruction when set, which keeps blocks from being compiled
+    pub timing: Option<Pipeline>,
+
+    // sees every fetch and data access to RAM when set, which also keeps blocks from being compiled
+    pub caches: Option<CacheHierarchy>,
+
+    // cycles the current instruction waited for the caches
+    memory_stall: u64,
+
+    // sees every retired branch and jump when set, which also keeps blocks from being compiled
+    pub branch_prediction: Option<BranchPrediction>,
+
+    // sees every retired instruction when set, which also keeps blocks from being compiled
+    pub profiler: Option<Profiler>,
+
+    xlen: Xlen,
+    privilege: Privilege,
+
+    // whether the C extension is enabled, which misa can turn off
+    compressed: bool,
+
+    // only the MPP and MPRV fields are kept
+    pub(crate) mstatus: u64,
+    pub(crate) mepc: u64,
+    pub(crate) pmp: Pmp,
+
+    pub(crate) hartid: u64,
+    pub(crate) reservation: Option<Reservation>,
+
+    // set by a WFI executed with no interrupt pending, until one is
+    pub(crate) waiting: bool,
+
+    // what devices and the CLINT answered, while recording or replaying
+    pub(crate) log: Option<EventLog>,
+
+    decode_cache: DecodeCache,
+    decode_cache_enabled: bool,
+
+    block_cache: BlockCache,
+    block_cache_enabled: bool,
+    pub(crate) block_invalidated: bool,
+
+    // number of executions after which a block is compiled to host code
+    #[cfg(feature = "jit")]
+    jit_threshold: u64,
 }
 
 impl CPU {
     pub fn new(bus: Bus) -> Self {
+        Self::with_xlen(bus, Xlen::Rv64)
+    }
+
+    pub fn with_xlen(bus: Bus, xlen: Xlen) -> Self {
         let mut xregs = [0x00; 32];
 
         // set the stack pointer to the end of RAM
         xregs[2] = RAM_BASE + (bus.ram.size() as u64);
 
-        Self {
+        let mut cpu = Self {
             xregs,
             bus,
             pc: 0x00,
+
+            instret: 0,
+            cycle: 0,
+            tval: 0,
+
+            debugger: Debugger::default(),
+
+            vector: VectorUnit::default(),
+
+            misaligned: MisalignedPolicy::default(),
+
+            timing: None,
+            caches: None,
+            memory_stall: 0,
+            branch_prediction: None,
+            profiler: None,
+
+            xlen: Xlen::Rv64,
+            privilege: Privilege::Machine,
+            compressed: true,
+
+            mstatus: 0,
+            mepc: 0,
+            pmp: Pmp::default(),
+
+            hartid: 0,
+            reservation: None,
+            waiting: false,
+            log: None,
+
+            decode_cache: DecodeCache::default(),
+            decode_cache_enabled: true,
+
+            block_cache: BlockCache::default(),
+            block_cache_enabled: true,
+            block_invalidated: false,
+
+            #[cfg(feature = "jit")]
+            jit_threshold: 16,
+        };
+
+        cpu.set_xlen(xlen);
+        cpu
+    }
+
+    pub fn xlen(&self) -> Xlen {
+        self.xlen
+    }
+
+    // switches the register width, truncating the registers to it
+    pub(crate) fn set_xlen(&mut self, xlen: Xlen) {
+        self.xlen = xlen;
+        self.pc = xlen.zext(self.pc);
+        for value in self.xregs.iter_mut() {
+            *value = xlen.sext(*value);
         }
+
+        // decoding depends on the register width
+        self.flush_caches();
     }
 
-    pub fn fetch_and_execute(&mut self) -> Result<(), RVException> {
-        let instruction = self.fetch()?;
-        self.execute(instruction)?;
-        self.pc += 4;
+    pub fn compressed(&self) -> bool {
+        self.compressed
+    }
 
-        self.xregs[0] = 0x00; // hardwire x0 to be zero
+    pub(crate) fn set_compressed(&mut self, enabled: bool) {
+        self.compressed = enabled;
 
-        Ok(())
+        // decoding depends on it too
+        self.flush_caches();
     }
 
-    fn read<T: Sized>(&self, address: Address) -> Result<T, RVException> {
-        self.bus.read::<T>(address)
+    pub fn privilege(&self) -> Privilege {
+        self.privilege
     }
 
-    fn write<T: Sized>(&mut self, address: Address, value: T) -> Result<(), RVException> {
-        self.bus.write::<T>(address, value)
+    pub fn set_privilege(&mut self, privilege: Privilege) {
+        self.privilege = privilege;
+
+        // translations were fetched with the permissions of the previous level
+        self.flush_caches();
+    }
+
+    pub fn pmp(&self) -> &Pmp {
+        &self.pmp
     }
 
-    fn fetch(&self) -> Result<u32, RVException> {
-        self.read::<u32>(self.pc)
+    pub fn fetch_and_execute(&mut self) -> Result<(), RVException> {
+        let (instruction, length) = self.fetch_decoded()?;
+        self.retire(&Op::new(instruction, length))
     }
 
-    fn execute(&mut self, instruction: u32) -> Result<(), RVException> {
-        let _instruction = instruction as u64; // extend it for convenience
+    // executes at most `count` instructions, dispatching whole blocks when the block cache is enabled
+    pub fn run_for(&mut self, count: u64) -> Result<(), RVException> {
+        let limit = self.instret.saturating_add(count);
+        let debugging = self.debugger.is_active();
 
-        let opcode = _instruction & 0x7F;
-        let funct3 = (_instruction & 0x00007000) >> 12;
-        let funct7 = (_instruction & 0xFE000000) >> 25;
+        if !self.block_cache_enabled {
+            while self.instret < limit && !self.waiting {
+                if debugging {
+                    self.check_breakpoint()?;
+                }
 
-        let dest = ((_instruction & 0xF80) >> 7) as usize;
-        let source1 = ((_instruction & 0x000F8000) >> 15) as usize;
-        let source2 = ((_instruction & 0x01F00000) >> 20) as usize;
+                self.fetch_and_execute()?;
+            }
 
-        match opcode {
-            // LUI
-            0b0110111 => {
-                self.xregs[dest] = (_instruction & 0xFFFFF000) as i32 as i64 as u64;
+            return Ok(());
+        }
+
+        let mut previous = None;
+        while self.instret < limit && !self.waiting {
+            // only RAM is translated, and neither misaligned targets nor instructions that may cross a page
+            if self.pc & 0x01 != 0
+                || !self.bus.in_ram(self.pc, 4)
+                || BlockCache::page_end(self.pc) - self.pc < 4
+            {
+                if debugging {
+                    self.check_breakpoint()?;
+                }
+
+                self.fetch_and_execute()?;
+                previous = None;
+                continue;
             }
 
-            // AUIPC
-            0b0010111 => {
-                self.xregs[dest] = self.pc + ((_instruction & 0xFFFFF000) as i32 as i64 as u64);
+            let index = match self.block_cache.find(self.pc, previous) {
+                Some(index) => index,
+                None => {
+                    let block = self.translate()?;
+                    self.block_cache.insert(block, previous)
+                }
+            };
+
+            // models and the debugger need every instruction to go through retire
+            let observed = debugging || self.instrumented();
+
+            self.block_invalidated = false;
+
+            #[cfg(not(feature = "jit"))]
+            let native = 0;
+            #[cfg(feature = "jit")]
+            let native = if observed {
+                0
+            } else {
+                self.run_native(index, limit)?
+            };
+
+            // a store in compiled code may have dropped the block
+            if self.block_invalidated {
+                previous = None;
+                continue;
             }
 
-            // JAL
-            0b1101111 => {
-                self.xregs[dest] = self.pc + 0x04;
+            let ops = self.block_cache.block(index).ops.clone();
+            for op in ops[native..].iter() {
+                if self.instret >= limit {
+                    return Ok(());
+                }
 
-                let offset = ((_instruction & 0x80000000) as i32 as i64 >> 11) as u64
-                    | (_instruction & 0xFF000)
-                    | ((_instruction >> 9) & 0x800)
-                    | ((_instruction >> 20) & 0x7FE);
+                if debugging {
+                    self.check_breakpoint()?;
+                }
 
-                self.pc = self.pc.wrapping_add(offset).wrapping_sub(0x04);
+                match observed {
+                    true => self.retire(op)?,
+                    false => self.retire_unobserved(op)?,
+                }
+
+                // the block itself may have just been overwritten
+                if self.block_invalidated {
+                    break;
+                }
             }
 
-            // JALR
-            0b1100111 => {
-                let tmp = self.pc.wrapping_add(0x04);
-                let offset = (_instruction as i32 as i64) >> 20;
-                let target = ((self.xregs[source1] as i64) + offset) & !0x01;
+            previous = (!self.block_invalidated).then_some(index);
+        }
+
+        Ok(())
+    }
+
+    pub fn hartid(&self) -> u64 {
+        self.hartid
+    }
+
+    pub fn interrupt_pending(&self) -> bool {
+        self.bus
+            .clint
+            .as_ref()
+            .is_some_and(|clint| clint.software_pending(self.hartid))
+    }
+
+    // whether the hart can run, ending a WFI once an interrupt is pending
+    pub(crate) fn wake(&mut self) -> bool {
+        if self.waiting && self.software_interrupt().unwrap_or(false) {
+            self.waiting = false;
+        }
+
+        !self.waiting
+    }
+
+    // the pending interrupt as the running hart sees it, which a recording keeps
+    pub(crate) fn software_interrupt(&mut self) -> Result<bool, RVException> {
+        let pending = self.interrupt_pending();
+        let Some(log) = &mut self.log else {
+            return Ok(pending);
+        };
+
+        match log.exchange(self.instret, Event::Msip { pending }) {
+            Some(Event::Msip { pending }) => Ok(pending),
+            _ => Err(RVException::LoadAccessFault),
+        }
+    }
+
+    // whether a replayed run went somewhere the recorded one did not
+    pub(crate) fn diverged(&self) -> bool {
+        self.log.as_ref().is_some_and(|log| log.diverged)
+    }
+
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache_enabled = enabled;
+        self.decode_cache.flush();
+    }
+
+    #[cfg(feature = "jit")]
+    pub fn set_jit_threshold(&mut self, threshold: u64) {
+        self.jit_threshold = threshold;
+    }
+
+    pub fn set_block_cache(&mut self, enabled: bool) {
+        self.block_cache_enabled = enabled;
+        self.block_cache.flush();
+    }
+
+    // must be called whenever memory is modified without going through the CPU
+    pub fn flush_caches(&mut self) {
+        self.decode_cache.flush();
+        self.block_cache.flush();
+        self.block_invalidated = true;
+    }
 
-                self.pc = (target as u64).wrapping_sub(0x04);
-                self.xregs[dest] = tmp;
+    fn retire(&mut self, op: &Op) -> Result<(), RVException> {
+        let instruction = op.instruction;
+        let pc = self.pc;
+        let fallthrough = pc.wrapping_add(op.length);
+        self.memory_stall = 0;
+        self.observe(pc, op.length as usize, Access::Execute);
+
+        if let Err(ex) = (op.handler)(self, op) {
+            self.debugger.pending = None;
+            return Err(ex);
+        }
+
+        self.pc = self.pc.wrapping_add(op.length);
+        self.instret += 1;
+        self.cycle += match &mut self.timing {
+            Some(pipeline) => {
+                pipeline.retire(&instruction, self.pc != fallthrough, self.memory_stall)
             }
+            None => 1,
+        };
 
-            // BRANCH
-            0b1100011 => {
-                let immediate = ((_instruction & 0x80000000) as i32 as i64 >> 19) as u64
-                    | ((_instruction & 0x80) << 4)
-                    | ((_instruction >> 20) & 0x7E0)
-                    | ((_instruction >> 7) & 0x1E);
+        if let Some(prediction) = &mut self.branch_prediction {
+            prediction.observe(pc, &instruction, fallthrough, self.pc);
+        }
 
-                match funct3 {
-                    // BEQ
-                    0b000 => {
-                        if self.xregs[source1] == self.xregs[source2] {
-                            self.pc = self.pc.wrapping_add(immediate).wrapping_sub(0x04);
-                        }
-                    }
+        if let Some(profiler) = &mut self.profiler {
+            profiler.observe(pc, &instruction, fallthrough, self.pc);
+        }
 
-                    // BNE
-                    0b001 => {
-                        if self.xregs[source1] != self.xregs[source2] {
-                            self.pc = self.pc.wrapping_add(immediate).wrapping_sub(0x04);
-                        }
-                    }
+        self.xregs[0] = 0x00; // hardwire x0 to be zero
 
-                    // BLT
-                    0b100 => {
-                        if (self.xregs[source1] as i64) < (self.xregs[source2] as i64) {
-                            self.pc = self.pc.wrapping_add(immediate).wrapping_sub(0x04);
-                        }
-                    }
+        // watchpoints fire once the accessing instruction has retired
+        if let Some(hit) = self.debugger.pending.take() {
+            self.debugger.last_hit = Some(hit);
+            return Err(self.fault(RVException::Breakpoint, hit.address));
+        }
 
-                    // BGE
-                    0b101 => {
-                        if (self.xregs[source1] as i64) >= (self.xregs[source2] as i64) {
-                            self.pc = self.pc.wrapping_add(immediate).wrapping_sub(0x04);
-                        }
-                    }
+        Ok(())
+    }
 
-                    // BLTU
-                    0b110 => {
-                        if self.xregs[source1] < self.xregs[source2] {
-                            self.pc = self.pc.wrapping_add(immediate).wrapping_sub(0x04);
-                        }
-                    }
+    // what retire does for instructions no model or debugger is watching
+    fn retire_unobserved(&mut self, op: &Op) -> Result<(), RVException> {
+        (op.handler)(self, op)?;
 
-                    // BGEU
-                    0b111 => {
-                        if self.xregs[source1] >= self.xregs[source2] {
-                            self.pc = self.pc.wrapping_add(immediate).wrapping_sub(0x04);
-                        }
-                    }
+        self.pc = self.pc.wrapping_add(op.length);
+        self.instret += 1;
+        self.cycle += 1;
+        self.xregs[0] = 0x00;
 
-                    _ => return Err(RVException::IllegalInstruction),
-                }
+        Ok(())
+    }
+
+    // whether a model must see every retired instruction
+    fn instrumented(&self) -> bool {
+        self.timing.is_some()
+            || self.caches.is_some()
+            || self.branch_prediction.is_some()
+            || self.profiler.is_some()
+    }
+
+    fn check_breakpoint(&mut self) -> Result<(), RVException> {
+        if self.debugger.should_break(self.pc) {
+            return Err(self.fault(RVException::Breakpoint, self.pc));
+        }
+
+        Ok(())
+    }
+
+    // runs the compiled prefix of a block, if any, returning how many instructions it retired
+    #[cfg(feature = "jit")]
+    fn run_native(&mut self, index: usize, limit: u64) -> Result<usize, RVException> {
+        let threshold = self.jit_threshold;
+        let xlen = self.xlen;
+        let block = self.block_cache.block_mut(index);
+
+        // compiled code implements RV64 semantics only, without C
+        block.executions += 1;
+        if block.executions == threshold.saturating_add(1)
+            && xlen == Xlen::Rv64
+            && block.ops.iter().all(|op| op.length == 4)
+        {
+            let instructions: Vec<Instruction> =
+                block.ops.iter().map(|op| op.instruction).collect();
+            block.native = jit::compile(block.start, &instructions);
+        }
+
+        let Some(native) = block
+            .native
+            .take_if(|native| self.instret + native.length as u64 <= limit)
+        else {
+            return Ok(0);
+        };
+
+        // a store the block makes may drop it, so it keeps its code only once done running
+        let cpu: *mut CPU = self;
+        let mut context = unsafe { jit::Context::new(cpu) };
+        let exit = unsafe { native.entry()(&mut context) };
+
+        if let Some(block) = self.block_cache.get_mut(index) {
+            block.native = Some(native);
+        }
+
+        self.pc = exit.pc;
+        self.instret += exit.retired;
+        self.cycle += exit.retired;
+
+        match context.fault() {
+            Some(ex) => Err(ex),
+            None => Ok(exit.retired as usize),
+        }
+    }
+
+    fn translate(&mut self) -> Result<Block, RVException> {
+        let ram_end = RAM_BASE + self.bus.ram.size() as Address;
+        let end = BlockCache::page_end(self.pc).min(ram_end);
+
+        let mut ops = vec![];
+        let mut pc = self.pc;
+        loop {
+            let (instruction, length) = match self.fetch(pc).and_then(|word| self.decode(word)) {
+                Ok(decoded) => decoded,
+
+                // faults are raised once execution actually reaches them
+                Err(_) if !ops.is_empty() => break,
+                Err(ex) => return Err(ex),
+            };
+
+            ops.push(Op::bind(instruction, length, self.xlen));
+            if Block::ends_block(&instruction) || ops.len() == MAX_BLOCK_LENGTH {
+                break;
             }
 
-            // LOAD
-            0b0000011 => {
-                let offset = ((_instruction as i32 as i64) >> 20) as u64;
-                let address = self.xregs[source1] + offset;
-
-                match funct3 {
-                    // LB
-                    0b000 => {
-                        let value = self.read::<i8>(address)?;
-                        self.xregs[dest] = value as i64 as u64;
-                    }
-
-                    // LH
-                    0b001 => {
-                        let value = self.read::<i16>(address)?;
-                        self.xregs[dest] = value as i64 as u64;
-                    }
-
-                    // LW
-                    0b010 => {
-                        let value = self.read::<i32>(address)?;
-                        self.xregs[dest] = value as i64 as u64;
-                    }
-
-                    // LD
-                    0b011 => {
-                        let value = self.read::<i64>(address)?;
-                        self.xregs[dest] = value as u64;
-                    }
-
-                    // LBU
-                    0b100 => {
-                        let value = self.read::<u8>(address)?;
-                        self.xregs[dest] = value as u64;
-                    }
-
-                    // LHU
-                    0b101 => {
-                        let value = self.read::<u16>(address)?;
-                        self.xregs[dest] = value as u64;
-                    }
-
-                    // LWU
-                    0b110 => {
-                        let value = self.read::<u32>(address)?;
-                        self.xregs[dest] = value as u64;
-                    }
-
-                    _ => return Err(RVException::IllegalInstruction),
+            // the next instruction must lie entirely in the same page, and in RAM
+            match pc.checked_add(length) {
+                Some(next) if next.checked_add(4).is_some_and(|next_end| next_end <= end) => {
+                    pc = next
                 }
+                _ => break,
             }
+        }
 
-            // STORE
-            0b0100011 => {
-                let offset = (((_instruction & 0xFE000000) as i32 as i64 >> 20) as u64)
-                    | ((_instruction >> 7) & 0x1F);
-                let address = self.xregs[source1] + offset;
+        Ok(Block::new(self.pc, ops))
+    }
 
-                match funct3 {
-                    // SB
-                    0b000 => {
-                        self.write::<u8>(address, self.xregs[source2] as u8)?;
-                    }
+    // records the trap value for an exception about to be raised
+    pub(crate) fn fault(&mut self, exception: RVException, tval: u64) -> RVException {
+        self.tval = tval;
+        exception
+    }
 
-                    // SH
-                    0b001 => {
-                        self.write::<u16>(address, self.xregs[source2] as u16)?;
-                    }
+    // the value a load brings to a register
+    pub(crate) fn load(&mut self, op: LoadOp, address: Address) -> Result<u64, RVException> {
+        Ok(match op {
+            LoadOp::Lb => self.read::<i8>(address)? as i64 as u64,
+            LoadOp::Lh => self.read::<i16>(address)? as i64 as u64,
+            LoadOp::Lw => self.read::<i32>(address)? as i64 as u64,
+            LoadOp::Ld => self.read::<i64>(address)? as u64,
+            LoadOp::Lbu => self.read::<u8>(address)? as u64,
+            LoadOp::Lhu => self.read::<u16>(address)? as u64,
+            LoadOp::Lwu => self.read::<u32>(address)? as u64,
+        })
+    }
 
-                    // SW
-                    0b010 => {
-                        self.write::<u32>(address, self.xregs[source2] as u32)?;
-                    }
+    pub(crate) fn store(
+        &mut self,
+        op: StoreOp,
+        address: Address,
+        value: u64,
+    ) -> Result<(), RVException> {
+        match op {
+            StoreOp::Sb => self.write::<u8>(address, value as u8),
+            StoreOp::Sh => self.write::<u16>(address, value as u16),
+            StoreOp::Sw => self.write::<u32>(address, value as u32),
+            StoreOp::Sd => self.write::<u64>(address, value),
+        }
+    }
 
-                    // SD
-                    0b011 => {
-                        self.write::<u64>(address, self.xregs[source2] as u64)?;
-                    }
+    pub(crate) fn read<T: Sized>(&mut self, address: Address) -> Result<T, RVException> {
+        let size = mem::size_of::<T>();
+        if self.debugger.is_watching() {
+            self.debugger.check_access(address, size, WatchKind::Read);
+        }
 
-                    _ => return Err(RVException::IllegalInstruction),
-                }
+        let aligned = address.is_multiple_of(size as Address);
+        if !aligned && self.misaligned == MisalignedPolicy::Trap {
+            return Err(self.fault(RVException::LoadAddressMisaligned, address));
+        }
+
+        if !self.permits(address, size, Access::Read) {
+            return Err(self.fault(RVException::LoadAccessFault, address));
+        }
+
+        self.observe(address, size, Access::Read);
+
+        if aligned {
+            return self
+                .bus_read::<T>(address)
+                .map_err(|ex| self.fault(ex, address));
+        }
+
+        let mut value = 0;
+        for byte in (0..size as Address).rev() {
+            let part = self
+                .bus_read::<u8>(address.wrapping_add(byte))
+                .map_err(|ex| self.fault(ex, address))?;
+            value = (value << 8) | part as u64;
+        }
+
+        Ok(from_bits(value))
+    }
+
+    pub(crate) fn write<T: Sized>(
+        &mut self,
+        address: Address,
+        value: T,
+    ) -> Result<(), RVException> {
+        let size = mem::size_of::<T>();
+        if self.debugger.is_watching() {
+            self.debugger.check_access(address, size, WatchKind::Write);
+        }
+
+        let aligned = address.is_multiple_of(size as Address);
+        if !aligned && self.misaligned == MisalignedPolicy::Trap {
+            return Err(self.fault(RVException::StoreAddressMisaligned, address));
+        }
+
+        if !self.permits(address, size, Access::Write) {
+            return Err(self.fault(RVException::StoreAccessFault, address));
+        }
+
+        self.observe(address, size, Access::Write);
+
+        self.invalidate(address, size);
+
+        if aligned {
+            return self
+                .bus_write::<T>(address, value)
+                .map_err(|ex| self.fault(ex, address));
+        }
+
+        // nothing is written unless every byte lands somewhere
+        if (0..size as Address).any(|byte| !self.bus.mapped(address.wrapping_add(byte))) {
+            return Err(self.fault(RVException::StoreAccessFault, address));
+        }
+
+        let value = to_bits(value);
+        for byte in 0..size as Address {
+            self.bus_write::<u8>(address.wrapping_add(byte), (value >> (8 * byte)) as u8)
+                .map_err(|ex| self.fault(ex, address))?;
+        }
+
+        Ok(())
+    }
+
+    // feeds an access to the cache simulation, which only covers RAM
+    pub(crate) fn observe(&mut self, address: Address, size: usize, access: Access) {
+        let Some(caches) = &mut self.caches else {
+            return;
+        };
+
+        if self.bus.in_ram(address, size) {
+            self.memory_stall += match access {
+                Access::Execute => caches.fetch(address, size),
+                Access::Read => caches.load(address, size),
+                Access::Write => caches.store(address, size),
+            };
+        }
+    }
+
+    // drops the translations of code about to be overwritten
+    pub(crate) fn invalidate(&mut self, address: Address, size: usize) {
+        self.decode_cache.invalidate(address, size);
+        if self.block_cache.invalidate(address, size) {
+            self.block_invalidated = true;
+        }
+    }
+
+    // whether PMP allows an access, made at the effective privilege for loads and stores
+    pub(crate) fn permits(&self, address: Address, size: usize, access: Access) -> bool {
+        let privilege = match access {
+            Access::Execute => self.privilege,
+            _ => self.data_privilege(),
+        };
+
+        self.pmp.check(address, size, access, privilege)
+    }
+
+    // fetches a whole instruction, which with C may only be the first 16 bits
+    fn fetch(&mut self, address: Address) -> Result<u32, RVException> {
+        let low = self.fetch_half(address)?;
+        if low & 0x03 != 0x03 && self.compressed {
+            return Ok(low as u32);
+        }
+
+        let high = self.fetch_half(address.wrapping_add(2))?;
+        Ok(((high as u32) << 16) | low as u32)
+    }
+
+    fn fetch_half(&mut self, address: Address) -> Result<u16, RVException> {
+        if !self.permits(address, 2, Access::Execute) {
+            return Err(self.fault(RVException::InstructionAccessFault, address));
+        }
+
+        self.bus_read::<u16>(address)
+            .map_err(|_| self.fault(RVException::InstructionAccessFault, address))
+    }
+
+    // accesses outside RAM reach devices and the CLINT, whose answers a recording keeps
+    fn bus_read<T: Sized>(&mut self, address: Address) -> Result<T, RVException> {
+        let size = mem::size_of::<T>();
+        let Some(log) = self
+            .log
+            .as_mut()
+            .filter(|_| !self.bus.in_ram(address, size))
+        else {
+            return self.bus.read::<T>(address);
+        };
+
+        // a replay never asks the devices, which may not even be there
+        let value = match log.is_replaying() {
+            true => None,
+            false => self.bus.read::<T>(address).ok().map(to_bits),
+        };
+        let event = Event::Read {
+            address,
+            size: size as u8,
+            value,
+        };
+
+        match log.exchange(self.instret, event) {
+            Some(Event::Read {
+                value: Some(value), ..
+            }) => Ok(from_bits(value)),
+            _ => Err(RVException::LoadAccessFault),
+        }
+    }
+
+    // stores still reach the devices during a replay, but the recorded outcome stands
+    fn bus_write<T: Sized>(&mut self, address: Address, value: T) -> Result<(), RVException> {
+        let size = mem::size_of::<T>();
+        let Some(log) = self
+            .log
+            .as_mut()
+            .filter(|_| !self.bus.in_ram(address, size))
+        else {
+            return self.bus.write::<T>(address, value);
+        };
+
+        let event = Event::Write {
+            address,
+            size: size as u8,
+            accepted: self.bus.write::<T>(address, value).is_ok(),
+        };
+
+        match log.exchange(self.instret, event) {
+            Some(Event::Write { accepted: true, .. }) => Ok(()),
+            _ => Err(RVException::StoreAccessFault),
+        }
+    }
+
+    // along with the length of the instruction
+    fn decode(&mut self, word: u32) -> Result<(Instruction, u64), RVException> {
+        let decoded = match word & 0x03 != 0x03 && self.compressed {
+            true => decode_compressed(word as u16, self.xlen).map(|instruction| (instruction, 2)),
+            false => decode(word, self.xlen).map(|instruction| (instruction, 4)),
+        };
+
+        decoded.map_err(|ex| self.fault(ex, word as u64))
+    }
+
+    fn fetch_decoded(&mut self) -> Result<(Instruction, u64), RVException> {
+        // devices may change what they return, so only instructions in RAM are cached
+        if !self.decode_cache_enabled || !self.bus.in_ram(self.pc, 2) {
+            let word = self.fetch(self.pc)?;
+            return self.decode(word);
+        }
+
+        if let Some(decoded) = self.decode_cache.get(self.pc) {
+            return Ok(decoded);
+        }
+
+        let word = self.fetch(self.pc)?;
+        let (instruction, length) = self.decode(word)?;
+        if self.bus.in_ram(self.pc, length as usize) {
+            self.decode_cache.insert(self.pc, instruction, length);
+        }
+
+        Ok((instruction, length))
+    }
+
+    // `length` is that of the encoding, four bytes or two for the C extension
+    pub(crate) fn execute(
+        &mut self,
+        instruction: Instruction,
+        length: u64,
+    ) -> Result<(), RVException> {
+        match instruction {
+            Instruction::Lui { rd, imm } => {
+                self.xregs[rd as usize] = imm;
+            }
+
+            Instruction::Auipc { rd, imm } => {
+                self.xregs[rd as usize] = self.xlen.sext(self.pc.wrapping_add(imm));
+            }
+
+            Instruction::Jal { rd, offset } => {
+                let target = self.jump_target(self.pc.wrapping_add(offset))?;
+
+                self.xregs[rd as usize] = self.xlen.sext(self.pc.wrapping_add(length));
+                self.pc = target.wrapping_sub(length);
+            }
+
+            Instruction::Jalr { rd, rs1, offset } => {
+                let tmp = self.xlen.sext(self.pc.wrapping_add(length));
+                let target =
+                    self.jump_target(self.xregs[rs1 as usize].wrapping_add(offset) & !0x01)?;
+
+                self.pc = target.wrapping_sub(length);
+                self.xregs[rd as usize] = tmp;
             }
 
-            // IMMEDIATE
-            0b0010011 => {
-                let immediate = ((_instruction as i32 as i64) >> 20) as u64;
-                let funct6 = funct7 >> 1;
-
-                match funct3 {
-                    // ADDI
-                    0b000 => {
-                        self.xregs[dest] = self.xregs[source1].wrapping_add(immediate);
-                    }
-
-                    // SLLI
-                    0b001 => {
-                        let shift = (_instruction >> 20) & 0x3F;
-                        self.xregs[dest] = self.xregs[source1] << shift;
-                    }
-
-                    // SLTI
-                    0b010 => {
-                        self.xregs[dest] = if (self.xregs[source1] as i64) < (immediate as i64) {
-                            1
-                        } else {
-                            0
-                        }
-                    }
-
-                    // SLTIU
-                    0b011 => {
-                        self.xregs[dest] = if self.xregs[source1] < immediate {
-                            1
-                        } else {
-                            0
-                        };
-                    }
-
-                    // XORI
-                    0b100 => {
-                        self.xregs[dest] = self.xregs[source1] ^ immediate;
-                    }
-
-                    // SRLI & SRAI
-                    0b101 => {
-                        let shift = (_instruction >> 20) & 0x3F;
-
-                        match funct6 {
-                            0x00 => {
-                                self.xregs[dest] = self.xregs[source1] >> shift;
-                            }
-
-                            0x10 => {
-                                self.xregs[dest] = ((self.xregs[source1] as i64) >> shift) as u64;
-                            }
-
-                            _ => return Err(RVException::IllegalInstruction),
-                        }
-                    }
-
-                    // ORI
-                    0b110 => {
-                        self.xregs[dest] = self.xregs[source1] | immediate;
-                    }
-
-                    // ANDI
-                    0b111 => {
-                        self.xregs[dest] = self.xregs[source1] & immediate;
-                    }
-
-                    _ => return Err(RVException::IllegalInstruction),
+            Instruction::Branch {
+                op,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let lhs = self.xregs[rs1 as usize];
+                let rhs = self.xregs[rs2 as usize];
+
+                let taken = match op {
+                    BranchOp::Beq => lhs == rhs,
+                    BranchOp::Bne => lhs != rhs,
+                    BranchOp::Blt => (lhs as i64) < (rhs as i64),
+                    BranchOp::Bge => (lhs as i64) >= (rhs as i64),
+                    BranchOp::Bltu => lhs < rhs,
+                    BranchOp::Bgeu => lhs >= rhs,
+                };
+
+                if taken {
+                    let target = self.jump_target(self.pc.wrapping_add(offset))?;
+                    self.pc = target.wrapping_sub(length);
                 }
             }
 
-            // IMMEDIATE32
-            0b0011011 => {
-                let immediate = ((_instruction as i32 as i64) >> 20) as u64;
-                let shift = (immediate & 0x1F) as u32;
+            Instruction::Load {
+                op,
+                rd,
+                rs1,
+                offset,
+            } => {
+                let address = self
+                    .xlen
+                    .zext(self.xregs[rs1 as usize].wrapping_add(offset));
+
+                self.xregs[rd as usize] = self.load(op, address)?;
+            }
 
-                match funct3 {
-                    // ADDIW
-                    0b000 => {
-                        self.xregs[dest] =
-                            (self.xregs[source1].wrapping_add(immediate)) as i32 as i64 as u64;
-                    }
+            Instruction::Store {
+                op,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let address = self
+                    .xlen
+                    .zext(self.xregs[rs1 as usize].wrapping_add(offset));
+                let value = self.xregs[rs2 as usize];
+
+                self.store(op, address, value)?;
+            }
 
-                    // SLLIW
-                    0b001 => {
-                        self.xregs[dest] = (self.xregs[source1] << shift) as i32 as i64 as u64;
-                    }
+            Instruction::OpImm { op, rd, rs1, imm } => {
+                self.xregs[rd as usize] = match self.xlen {
+                    Xlen::Rv32 => Self::alu32(op, self.xregs[rs1 as usize], imm),
+                    Xlen::Rv64 => Self::alu(op, self.xregs[rs1 as usize], imm),
+                };
+            }
 
-                    // SRLIW & SRAIW
-                    0b101 => match funct7 {
-                        0b000000 => {
-                            self.xregs[dest] =
-                                (self.xregs[source1] as u32 >> shift) as i32 as i64 as u64;
-                        }
+            Instruction::OpImm32 { op, rd, rs1, imm } => {
+                self.xregs[rd as usize] = Self::alu32(op, self.xregs[rs1 as usize], imm);
+            }
 
-                        0b100000 => {
-                            self.xregs[dest] = (self.xregs[source1] as i32 >> shift) as i64 as u64;
-                        }
+            Instruction::Op { op, rd, rs1, rs2 } => {
+                let (lhs, rhs) = (self.xregs[rs1 as usize], self.xregs[rs2 as usize]);
 
-                        _ => return Err(RVException::IllegalInstruction),
-                    },
+                self.xregs[rd as usize] = match self.xlen {
+                    Xlen::Rv32 => Self::alu32(op, lhs, rhs),
+                    Xlen::Rv64 => Self::alu(op, lhs, rhs),
+                };
+            }
 
-                    _ => return Err(RVException::IllegalInstruction),
-                }
+            Instruction::Op32 { op, rd, rs1, rs2 } => {
+                self.xregs[rd as usize] =
+                    Self::alu32(op, self.xregs[rs1 as usize], self.xregs[rs2 as usize]);
             }
 
-            // OPERATION
-            0b0110011 => {
-                match (funct3, funct7) {
-                    // ADD
-                    (0b000, 0b0000000) => {
-                        self.xregs[dest] = self.xregs[source1].wrapping_add(self.xregs[source2]);
-                    }
-
-                    // SUB
-                    (0b000, 0b0100000) => {
-                        self.xregs[dest] = self.xregs[source1].wrapping_sub(self.xregs[source2]);
-                    }
-
-                    // SLL
-                    (0b001, 0b0000000) => {
-                        self.xregs[dest] = self.xregs[source1] << (self.xregs[source2] & 0x3F);
-                    }
-
-                    // SLT
-                    (0b010, 0b0000000) => {
-                        self.xregs[dest] =
-                            if (self.xregs[source1] as i64) < (self.xregs[source2] as i64) {
-                                1
-                            } else {
-                                0
-                            }
-                    }
-
-                    // SLTU
-                    (0b011, 0b0000000) => {
-                        self.xregs[dest] = if self.xregs[source1] < self.xregs[source2] {
-                            1
-                        } else {
-                            0
-                        }
-                    }
-
-                    // XOR
-                    (0b100, 0b0000000) => {
-                        self.xregs[dest] = self.xregs[source1] ^ self.xregs[source2];
-                    }
-
-                    // SRL
-                    (0b101, 0b0000000) => {
-                        self.xregs[dest] = self.xregs[source1] >> (self.xregs[source2] & 0x3F);
-                    }
-
-                    // SRA
-                    (0b101, 0b0100000) => {
-                        self.xregs[dest] =
-                            ((self.xregs[source1] as i64) >> (self.xregs[source2] & 0x3F)) as u64;
-                    }
-
-                    // OR
-                    (0b110, 0b0000000) => {
-                        self.xregs[dest] = self.xregs[source1] | self.xregs[source2];
-                    }
-
-                    // AND
-                    (0b111, 0b0000000) => {
-                        self.xregs[dest] = self.xregs[source1] & self.xregs[source2];
-                    }
-
-                    // =============================================================================
-                    // RV64M
-
-                    // MUL
-                    (0b000, 0b0000001) => {
-                        self.xregs[dest] = ((self.xregs[source1] as i64)
-                            .wrapping_mul(self.xregs[source2] as i64))
-                            as u64;
-                    }
-
-                    // MULH
-                    (0b001, 0b0000001) => {
-                        self.xregs[dest] = ((self.xregs[source1] as i64 as i128)
-                            .wrapping_mul(self.xregs[source2] as i64 as i128)
-                            >> 64) as u64;
-                    }
-
-                    // MULHSU
-                    (0b010, 0b0000001) => {
-                        self.xregs[dest] = ((self.xregs[source1] as i64 as u128)
-                            .wrapping_mul(self.xregs[source2] as u128)
-                            >> 64) as u64;
-                    }
-
-                    // MULHU
-                    (0b011, 0b0000001) => {
-                        self.xregs[dest] = ((self.xregs[source1] as u128)
-                            .wrapping_mul(self.xregs[source2] as u128)
-                            >> 64) as u64;
-                    }
-
-                    // DIV
-                    (0b100, 0b0000001) => {
-                        let dividend = self.xregs[source1] as i64;
-                        let divisor = self.xregs[source2] as i64;
-
-                        self.xregs[dest] = if divisor == 0 {
-                            u64::MAX // division by zero
-                        } else if dividend == i64::MIN && divisor == -1 {
-                            dividend as u64 // overflow
-                        } else {
-                            dividend.wrapping_div(divisor) as u64
-                        }
-                    }
-
-                    // DIVU
-                    (0b101, 0b0000001) => {
-                        let dividend = self.xregs[source1];
-                        let divisor = self.xregs[source2];
-
-                        self.xregs[dest] = if divisor == 0 {
-                            u64::MAX // division by zero
-                        } else {
-                            dividend.wrapping_div(divisor)
-                        }
-                    }
-
-                    // REM
-                    (0b110, 0b0000001) => {
-                        let dividend = self.xregs[source1] as i64;
-                        let divisor = self.xregs[source2] as i64;
-
-                        self.xregs[dest] = if divisor == 0 {
-                            dividend as u64 // division by zero
-                        } else if dividend == i64::MIN && divisor == -1 {
-                            0 // overflow
-                        } else {
-                            dividend.wrapping_rem(divisor) as u64
-                        }
-                    }
-
-                    // REMU
-                    (0b111, 0b0000001) => {
-                        let dividend = self.xregs[source1];
-                        let divisor = self.xregs[source2];
-
-                        self.xregs[dest] = if divisor == 0 {
-                            dividend // division by zero
-                        } else {
-                            dividend.wrapping_rem(divisor)
-                        }
-                    }
-
-                    _ => return Err(RVException::IllegalInstruction),
-                }
+            Instruction::Fence => fence(Ordering::SeqCst),
+
+            Instruction::FenceI => {
+                self.flush_caches();
             }
 
-            // OPERATION32
-            0b111011 => match (funct3, funct7) {
-                // ADDW
-                (0b000, 0b0000000) => {
-                    self.xregs[dest] = (self.xregs[source1].wrapping_add(self.xregs[source2]))
-                        as i32 as i64 as u64;
-                }
+            Instruction::Mret => self.mret(length)?,
 
-                // SUBW
-                (0b000, 0b0100000) => {
-                    self.xregs[dest] =
-                        (self.xregs[source1].wrapping_sub(self.xregs[source2])) as i32 as u64;
-                }
+            Instruction::Ecall => return Err(self.fault(RVException::EnvironmentCall, 0)),
+            Instruction::Ebreak => return Err(self.fault(RVException::Breakpoint, self.pc)),
 
-                // SLLW
-                (0b001, 0b0000000) => {
-                    self.xregs[dest] =
-                        (self.xregs[source1] << (self.xregs[source2] & 0x3F)) as i32 as i64 as u64;
-                }
+            // only the harts of a machine can be woken up, elsewhere WFI does nothing
+            Instruction::Wfi => {
+                self.waiting = self.bus.clint.is_some() && !self.software_interrupt()?;
+            }
 
-                // SRLW
-                (0b101, 0b0000000) => {
-                    self.xregs[dest] = (self.xregs[source1] as u32 >> (self.xregs[source2] & 0x1F))
-                        as i32 as i64 as u64;
-                }
+            Instruction::Amo {
+                op,
+                rd,
+                rs1,
+                rs2,
+                double,
+            } => {
+                self.amo(op, rd, rs1, rs2, double)?;
+            }
 
-                // SRAW
-                (0b101, 0b0100000) => {
-                    self.xregs[dest] =
-                        (self.xregs[source1] as i32 >> (self.xregs[source2] & 0x1F)) as i64 as u64;
-                }
+            Instruction::Csr { op, rd, rs1, csr } => {
+                self.csr(op, rd, csr, self.xregs[rs1 as usize], rs1 != 0)?;
+            }
 
-                // =================================================================================
-                // RV64M
+            Instruction::CsrImm { op, rd, imm, csr } => {
+                self.csr(op, rd, csr, imm, imm != 0)?;
+            }
 
-                // MULW
-                (0b000, 0b0000001) => {
-                    self.xregs[dest] = ((self.xregs[source1] as i32)
-                        .wrapping_mul(self.xregs[source2] as i32))
-                        as i64 as u64;
-                }
+            Instruction::Vsetvl { rd, avl, vtype } => {
+                self.vsetvl(rd, avl, vtype);
+            }
+
+            Instruction::VectorLoad { .. } | Instruction::VectorStore { .. } => {
+                self.vector_transfer(instruction)?;
+            }
 
-                // DIVW
-                (0b100, 0b0000001) => {
-                    let dividend = self.xregs[source1] as i32;
-                    let divisor = self.xregs[source2] as i32;
-
-                    self.xregs[dest] = if divisor == 0 {
-                        u64::MAX // division by zero
-                    } else if dividend == i32::MIN && divisor == -1 {
-                        dividend as i64 as u64 // overflow
-                    } else {
-                        dividend.wrapping_div(divisor) as i64 as u64
-                    }
+            Instruction::Vector {
+                op,
+                vd,
+                vs2,
+                operand,
+                masked,
+            } => {
+                let result = self
+                    .vector
+                    .execute(op, vd, vs2, operand, masked, &self.xregs)
+                    .map_err(|ex| self.fault(ex, 0))?;
+
+                if let Some(value) = result {
+                    self.xregs[vd as usize] = self.xlen.sext(value);
                 }
+            }
+        }
 
-                // DIVUW
-                (0b101, 0b0000001) => {
-                    let dividend = self.xregs[source1] as u32;
-                    let divisor = self.xregs[source2] as u32;
+        Ok(())
+    }
 
-                    self.xregs[dest] = if divisor == 0 {
-                        u64::MAX // division by zero
-                    } else {
-                        dividend.wrapping_div(divisor) as i32 as i64 as u64
-                    }
+    // control transfers must land on two-byte boundaries with C, and four-byte ones without
+    pub(crate) fn jump_target(&mut self, target: u64) -> Result<u64, RVException> {
+        let target = self.xlen.zext(target);
+        if target & self.alignment_mask() != 0 {
+            return Err(self.fault(RVException::InstructionAddressMisaligned, target));
+        }
+
+        Ok(target)
+    }
+
+    // the address bits that must be clear in the address of an instruction
+    pub(crate) fn alignment_mask(&self) -> u64 {
+        match self.compressed {
+            true => 0x01,
+            false => 0x03,
+        }
+    }
+
+    #[inline]
+    pub(crate) fn alu(op: AluOp, lhs: u64, rhs: u64) -> u64 {
+        match op {
+            AluOp::Add => lhs.wrapping_add(rhs),
+            AluOp::Sub => lhs.wrapping_sub(rhs),
+            AluOp::Sll => lhs << (rhs & 0x3F),
+            AluOp::Slt => ((lhs as i64) < (rhs as i64)) as u64,
+            AluOp::Sltu => (lhs < rhs) as u64,
+            AluOp::Xor => lhs ^ rhs,
+            AluOp::Srl => lhs >> (rhs & 0x3F),
+            AluOp::Sra => ((lhs as i64) >> (rhs & 0x3F)) as u64,
+            AluOp::Or => lhs | rhs,
+            AluOp::And => lhs & rhs,
+
+            // =====================================================================================
+            // RV64M
+            AluOp::Mul => (lhs as i64).wrapping_mul(rhs as i64) as u64,
+            AluOp::Mulh => ((lhs as i64 as i128).wrapping_mul(rhs as i64 as i128) >> 64) as u64,
+            AluOp::Mulhsu => ((lhs as i64 as u128).wrapping_mul(rhs as u128) >> 64) as u64,
+            AluOp::Mulhu => ((lhs as u128).wrapping_mul(rhs as u128) >> 64) as u64,
+
+            AluOp::Div => {
+                let dividend = lhs as i64;
+                let divisor = rhs as i64;
+
+                if divisor == 0 {
+                    u64::MAX // division by zero
+                } else if dividend == i64::MIN && divisor == -1 {
+                    dividend as u64 // overflow
+                } else {
+                    dividend.wrapping_div(divisor) as u64
                 }
+            }
 
-                // REMW
-                (0b110, 0b0000001) => {
-                    let dividend = self.xregs[source1] as i32;
-                    let divisor = self.xregs[source2] as i32;
-
-                    self.xregs[dest] = if divisor == 0 {
-                        dividend as i64 as u64 // division by zero
-                    } else if dividend == i32::MIN && divisor == -1 {
-                        0 // overflow
-                    } else {
-                        dividend.wrapping_rem(divisor) as i64 as u64
-                    }
+            AluOp::Divu => {
+                if rhs == 0 {
+                    u64::MAX // division by zero
+                } else {
+                    lhs.wrapping_div(rhs)
                 }
+            }
+
+            AluOp::Rem => {
+                let dividend = lhs as i64;
+                let divisor = rhs as i64;
 
-                // REMUW
-                (0b111, 0b0000001) => {
-                    let dividend = self.xregs[source1] as i32;
-                    let divisor = self.xregs[source2] as i32;
+                if divisor == 0 {
+                    dividend as u64 // division by zero
+                } else if dividend == i64::MIN && divisor == -1 {
+                    0 // overflow
+                } else {
+                    dividend.wrapping_rem(divisor) as u64
+                }
+            }
 
-                    self.xregs[dest] = if divisor == 0 {
-                        dividend as i32 as i64 as u64 // division by zero
-                    } else {
-                        dividend.wrapping_rem(divisor) as i32 as i64 as u64
-                    }
+            AluOp::Remu => {
+                if rhs == 0 {
+                    lhs // division by zero
+                } else {
+                    lhs.wrapping_rem(rhs)
                 }
+            }
 
-                _ => return Err(RVException::IllegalInstruction),
-            },
+            // =====================================================================================
+            // Zba
+            AluOp::Sh1add => (lhs << 1).wrapping_add(rhs),
+            AluOp::Sh2add => (lhs << 2).wrapping_add(rhs),
+            AluOp::Sh3add => (lhs << 3).wrapping_add(rhs),
+            AluOp::AddUw => (lhs as u32 as u64).wrapping_add(rhs),
+            AluOp::Sh1addUw => ((lhs as u32 as u64) << 1).wrapping_add(rhs),
+            AluOp::Sh2addUw => ((lhs as u32 as u64) << 2).wrapping_add(rhs),
+            AluOp::Sh3addUw => ((lhs as u32 as u64) << 3).wrapping_add(rhs),
+            AluOp::SllUw => (lhs as u32 as u64) << (rhs & 0x3F),
+
+            // =====================================================================================
+            // Zbb
+            AluOp::Andn => lhs & !rhs,
+            AluOp::Orn => lhs | !rhs,
+            AluOp::Xnor => !(lhs ^ rhs),
+            AluOp::Clz => lhs.leading_zeros() as u64,
+            AluOp::Ctz => lhs.trailing_zeros() as u64,
+            AluOp::Cpop => lhs.count_ones() as u64,
+            AluOp::Max => (lhs as i64).max(rhs as i64) as u64,
+            AluOp::Maxu => lhs.max(rhs),
+            AluOp::Min => (lhs as i64).min(rhs as i64) as u64,
+            AluOp::Minu => lhs.min(rhs),
+            AluOp::SextB => lhs as i8 as i64 as u64,
+            AluOp::SextH => lhs as i16 as i64 as u64,
+            AluOp::ZextH => lhs as u16 as u64,
+            AluOp::Rol => lhs.rotate_left((rhs & 0x3F) as u32),
+            AluOp::Ror => lhs.rotate_right((rhs & 0x3F) as u32),
+            AluOp::OrcB => u64::from_le_bytes(lhs.to_le_bytes().map(|byte| match byte {
+                0 => 0x00,
+                _ => 0xFF,
+            })),
+            AluOp::Rev8 => lhs.swap_bytes(),
+
+            // =====================================================================================
+            // Zbc
+            AluOp::Clmul => clmul(lhs, rhs) as u64,
+            AluOp::Clmulh => (clmul(lhs, rhs) >> 64) as u64,
+            AluOp::Clmulr => (clmul(lhs, rhs) >> 63) as u64,
+
+            // =====================================================================================
+            // Zbs
+            AluOp::Bclr => lhs & !(1 << (rhs & 0x3F)),
+            AluOp::Bext => (lhs >> (rhs & 0x3F)) & 1,
+            AluOp::Binv => lhs ^ (1 << (rhs & 0x3F)),
+            AluOp::Bset => lhs | (1 << (rhs & 0x3F)),
+        }
+    }
 
-            // MEM-MISC
-            0b0001111 => {
-                match funct3 {
-                    // FENCE
-                    0b000 => {}
+    // the W instructions of RV64 and every operation of RV32, sign-extending the 32-bit result
+    #[inline]
+    pub(crate) fn alu32(op: AluOp, lhs: u64, rhs: u64) -> u64 {
+        let (lhs, rhs) = (lhs as u32, rhs as u32);
+
+        let result = match op {
+            AluOp::Add => lhs.wrapping_add(rhs),
+            AluOp::Sub => lhs.wrapping_sub(rhs),
+            AluOp::Sll => lhs << (rhs & 0x1F),
+            AluOp::Slt => ((lhs as i32) < (rhs as i32)) as u32,
+            AluOp::Sltu => (lhs < rhs) as u32,
+            AluOp::Xor => lhs ^ rhs,
+            AluOp::Srl => lhs >> (rhs & 0x1F),
+            AluOp::Sra => ((lhs as i32) >> (rhs & 0x1F)) as u32,
+            AluOp::Or => lhs | rhs,
+            AluOp::And => lhs & rhs,
+
+            // =====================================================================================
+            // RV32M and RV64M
+            AluOp::Mul => lhs.wrapping_mul(rhs),
+            AluOp::Mulh => ((lhs as i32 as i64 * rhs as i32 as i64) >> 32) as u32,
+            AluOp::Mulhsu => ((lhs as i32 as i64 * rhs as i64) >> 32) as u32,
+            AluOp::Mulhu => ((lhs as u64 * rhs as u64) >> 32) as u32,
+
+            AluOp::Div => {
+                let dividend = lhs as i32;
+                let divisor = rhs as i32;
+
+                if divisor == 0 {
+                    u32::MAX // division by zero
+                } else if dividend == i32::MIN && divisor == -1 {
+                    dividend as u32 // overflow
+                } else {
+                    dividend.wrapping_div(divisor) as u32
+                }
+            }
 
-                    _ => return Err(RVException::IllegalInstruction),
+            AluOp::Divu => {
+                if rhs == 0 {
+                    u32::MAX // division by zero
+                } else {
+                    lhs.wrapping_div(rhs)
                 }
             }
 
-            // SYSTEM
-            0b1110011 => {
-                match source2 {
-                    // ECALL
-                    0b000000000000 => return Err(RVException::EnvironmentCall),
+            AluOp::Rem => {
+                let dividend = lhs as i32;
+                let divisor = rhs as i32;
 
-                    // EBREAK
-                    0b000000000001 => return Err(RVException::Breakpoint),
+                if divisor == 0 {
+                    dividend as u32 // division by zero
+                } else if dividend == i32::MIN && divisor == -1 {
+                    0 // overflow
+                } else {
+                    dividend.wrapping_rem(divisor) as u32
+                }
+            }
 
-                    _ => return Err(RVException::IllegalInstruction),
+            AluOp::Remu => {
+                if rhs == 0 {
+                    lhs // division by zero
+                } else {
+                    lhs.wrapping_rem(rhs)
                 }
             }
 
-            _ => return Err(RVException::IllegalInstruction),
-        }
+            // =====================================================================================
+            // Zba
+            AluOp::Sh1add => (lhs << 1).wrapping_add(rhs),
+            AluOp::Sh2add => (lhs << 2).wrapping_add(rhs),
+            AluOp::Sh3add => (lhs << 3).wrapping_add(rhs),
+
+            // =====================================================================================
+            // Zbb
+            AluOp::Andn => lhs & !rhs,
+            AluOp::Orn => lhs | !rhs,
+            AluOp::Xnor => !(lhs ^ rhs),
+            AluOp::Clz => lhs.leading_zeros(),
+            AluOp::Ctz => lhs.trailing_zeros(),
+            AluOp::Cpop => lhs.count_ones(),
+            AluOp::Max => (lhs as i32).max(rhs as i32) as u32,
+            AluOp::Maxu => lhs.max(rhs),
+            AluOp::Min => (lhs as i32).min(rhs as i32) as u32,
+            AluOp::Minu => lhs.min(rhs),
+            AluOp::SextB => lhs as i8 as u32,
+            AluOp::SextH => lhs as i16 as u32,
+            AluOp::ZextH => lhs as u16 as u32,
+            AluOp::Rol => lhs.rotate_left(rhs & 0x1F),
+            AluOp::Ror => lhs.rotate_right(rhs & 0x1F),
+            AluOp::OrcB => u32::from_le_bytes(lhs.to_le_bytes().map(|byte| match byte {
+                0 => 0x00,
+                _ => 0xFF,
+            })),
+            AluOp::Rev8 => lhs.swap_bytes(),
+
+            // =====================================================================================
+            // Zbc
+            AluOp::Clmul => clmul(lhs as u64, rhs as u64) as u32,
+            AluOp::Clmulh => (clmul(lhs as u64, rhs as u64) >> 32) as u32,
+            AluOp::Clmulr => (clmul(lhs as u64, rhs as u64) >> 31) as u32,
+
+            // =====================================================================================
+            // Zbs
+            AluOp::Bclr => lhs & !(1 << (rhs & 0x1F)),
+            AluOp::Bext => (lhs >> (rhs & 0x1F)) & 1,
+            AluOp::Binv => lhs ^ (1 << (rhs & 0x1F)),
+            AluOp::Bset => lhs | (1 << (rhs & 0x1F)),
+
+            AluOp::AddUw | AluOp::Sh1addUw | AluOp::Sh2addUw | AluOp::Sh3addUw | AluOp::SllUw => {
+                unreachable!("{op:?} has no 32-bit form")
+            }
+        };
 
-        Ok(())
+        result as i32 as i64 as u64
     }
 }
+
+// carry-less product of two 64-bit values
+fn clmul(lhs: u64, rhs: u64) -> u128 {
+    (0..64)
+        .filter(|bit| (rhs >> bit) & 1 != 0)
+        .fold(0, |product, bit| product ^ ((lhs as u128) << bit))
+}
@@ -0,0 +1,263 @@
+use crate::{
+    cpu::{Privilege, Xlen, CPU},
+    decoder::CsrOp,
+    exception::RVException,
+};
+
+// machine information
+pub const MSTATUS: u16 = 0x300;
+pub const MISA: u16 = 0x301;
+pub const MEPC: u16 = 0x341;
+pub const MIP: u16 = 0x344;
+pub const MHARTID: u16 = 0xF14;
+
+// counters, where the high halves only exist on RV32
+pub const MCYCLE: u16 = 0xB00;
+pub const MCYCLEH: u16 = 0xB80;
+pub const CYCLE: u16 = 0xC00;
+pub const CYCLEH: u16 = 0xC80;
+
+// physical memory protection
+pub const PMPCFG0: u16 = 0x3A0;
+pub const PMPCFG15: u16 = 0x3AF;
+pub const PMPADDR0: u16 = 0x3B0;
+pub const PMPADDR63: u16 = 0x3EF;
+
+// mstatus fields
+pub const MSTATUS_MPP: u64 = 0b11 << 11;
+pub const MSTATUS_MPRV: u64 = 1 << 17;
+
+// RVV
+pub const VSTART: u16 = 0x008;
+pub const VXSAT: u16 = 0x009;
+pub const VXRM: u16 = 0x00A;
+pub const VCSR: u16 = 0x00F;
+pub const VL: u16 = 0xC20;
+pub const VTYPE: u16 = 0xC21;
+pub const VLENB: u16 = 0xC22;
+
+impl CPU {
+    // `writes` is false for csrrs/csrrc with x0 or a zero immediate, which must not write at all
+    pub(crate) fn csr(
+        &mut self,
+        op: CsrOp,
+        rd: u8,
+        csr: u16,
+        value: u64,
+        writes: bool,
+    ) -> Result<(), RVException> {
+        // bits 9:8 of the address hold the lowest privilege level with access
+        if (csr >> 8) & 0x03 > self.privilege() as u16 {
+            return Err(self.fault(RVException::IllegalInstruction, 0));
+        }
+
+        let Some(old) = self.read_csr(csr) else {
+            return Err(self.fault(RVException::IllegalInstruction, 0));
+        };
+
+        // the running hart sees interrupts through the recording, if there is one
+        let old = match csr {
+            MIP => (self.software_interrupt()? as u64) << 3,
+            _ => old,
+        };
+
+        if op == CsrOp::Rw || writes {
+            let new = match op {
+                CsrOp::Rw => value,
+                CsrOp::Rs => old | value,
+                CsrOp::Rc => old & !value,
+            };
+
+            if !self.write_csr(csr, new) {
+                return Err(self.fault(RVException::IllegalInstruction, 0));
+            }
+        }
+
+        self.xregs[rd as usize] = self.xlen().sext(old);
+        Ok(())
+    }
+
+    pub fn read_csr(&self, csr: u16) -> Option<u64> {
+        let vector = &self.vector;
+        let xlen = self.xlen();
+
+        Some(match csr {
+            // UXL and SXL read as 64 on RV64
+            MSTATUS => match xlen {
+                Xlen::Rv32 => self.mstatus,
+                Xlen::Rv64 => self.mstatus | (0b1010 << 32),
+            },
+            MISA => misa(xlen) | (self.compressed() as u64) << 2,
+
+            // bit 1 reads as zero while instructions are four-byte aligned
+            MEPC => self.mepc & !self.alignment_mask(),
+            MIP => (self.interrupt_pending() as u64) << 3,
+            MHARTID => self.hartid(),
+
+            MCYCLE | CYCLE => xlen.zext(self.cycle),
+            MCYCLEH | CYCLEH if xlen == Xlen::Rv32 => self.cycle >> 32,
+
+            // RV64 only has the even pmpcfg registers, each holding eight entries
+            PMPCFG0..=PMPCFG15 if xlen == Xlen::Rv32 || csr.is_multiple_of(2) => {
+                self.pmp.read_cfg((csr - PMPCFG0) as usize, xlen)
+            }
+            PMPADDR0..=PMPADDR63 => self.pmp.addr((csr - PMPADDR0) as usize),
+
+            VSTART => vector.vstart,
+            VXSAT => vector.vxsat as u64,
+            VXRM => vector.vxrm,
+            VCSR => (vector.vxrm << 1) | vector.vxsat as u64,
+            VL => vector.vl,
+            // vill is the most significant bit at any XLEN
+            VTYPE => match vector.vtype >> 63 {
+                0 => vector.vtype,
+                _ => 1 << (xlen.bits() - 1),
+            },
+            VLENB => vector.vlenb() as u64,
+
+            _ => return None,
+        })
+    }
+
+    // returns false for CSRs that do not exist or are read-only
+    pub fn write_csr(&mut self, csr: u16, value: u64) -> bool {
+        // the top two address bits being set marks a read-only CSR
+        if csr >> 10 == 0b11 {
+            return false;
+        }
+
+        // MXL and C are the only writable fields of misa, other values leave the width as it is
+        if csr == MISA {
+            // turning C off is ignored when the next instruction would be misaligned
+            let compressed = value & (1 << 2) != 0;
+            if !compressed && self.pc & 0x03 != 0 {
+                return true;
+            }
+            if compressed != self.compressed() {
+                self.set_compressed(compressed);
+            }
+
+            match (value >> (self.xlen().bits() - 2)) & 0x03 {
+                1 => self.set_xlen(Xlen::Rv32),
+                2 => self.set_xlen(Xlen::Rv64),
+
+                _ => {}
+            }
+
+            return true;
+        }
+
+        // the software interrupt bit is only set and cleared through the CLINT
+        if csr == MIP {
+            return true;
+        }
+
+        let xlen = self.xlen();
+        match csr {
+            // the reserved level 2 and supervisor mode, which is not implemented, leave MPP unchanged
+            MSTATUS => {
+                let mpp = match (value & MSTATUS_MPP) >> 11 {
+                    1 | 2 => self.mstatus & MSTATUS_MPP,
+                    _ => value & MSTATUS_MPP,
+                };
+
+                self.mstatus = mpp | (value & MSTATUS_MPRV);
+                return true;
+            }
+
+            MCYCLE => {
+                self.cycle = match xlen {
+                    Xlen::Rv32 => (self.cycle & !0xFFFF_FFFF) | (value & 0xFFFF_FFFF),
+                    Xlen::Rv64 => value,
+                };
+                return true;
+            }
+
+            MCYCLEH if xlen == Xlen::Rv32 => {
+                self.cycle = (self.cycle & 0xFFFF_FFFF) | (value << 32);
+                return true;
+            }
+
+            // instructions are at least two-byte aligned
+            MEPC => {
+                self.mepc = xlen.zext(value) & !0x01;
+                return true;
+            }
+
+            PMPCFG0..=PMPCFG15 if xlen == Xlen::Rv32 || csr.is_multiple_of(2) => {
+                self.pmp.write_cfg((csr - PMPCFG0) as usize, value, xlen);
+
+                // cached translations were fetched under the old permissions
+                self.flush_caches();
+                return true;
+            }
+
+            PMPADDR0..=PMPADDR63 => {
+                self.pmp.write_addr((csr - PMPADDR0) as usize, value, xlen);
+                self.flush_caches();
+                return true;
+            }
+
+            _ => {}
+        }
+
+        let vector = &mut self.vector;
+        match csr {
+            VSTART => vector.vstart = value & (vector.vlen() as u64 - 1),
+            VXSAT => vector.vxsat = value & 0x01 != 0,
+            VXRM => vector.vxrm = value & 0x03,
+            VCSR => {
+                vector.vxsat = value & 0x01 != 0;
+                vector.vxrm = (value >> 1) & 0x03;
+            }
+
+            _ => return false,
+        }
+
+        true
+    }
+
+    // returns to the privilege level in MPP, at mepc
+    pub(crate) fn mret(&mut self, length: u64) -> Result<(), RVException> {
+        if self.privilege() != Privilege::Machine {
+            return Err(self.fault(RVException::IllegalInstruction, 0));
+        }
+
+        let privilege = self.mpp();
+        self.mstatus &= !MSTATUS_MPP;
+        if privilege != Privilege::Machine {
+            self.mstatus &= !MSTATUS_MPRV;
+        }
+
+        self.pc = (self.mepc & !self.alignment_mask()).wrapping_sub(length);
+        self.set_privilege(privilege);
+
+        Ok(())
+    }
+
+    pub(crate) fn mpp(&self) -> Privilege {
+        match (self.mstatus & MSTATUS_MPP) >> 11 {
+            0 => Privilege::User,
+            1 => Privilege::Supervisor,
+            _ => Privilege::Machine,
+        }
+    }
+
+    // loads and stores from machine mode run at the level in MPP when MPRV is set
+    pub(crate) fn data_privilege(&self) -> Privilege {
+        match self.privilege() {
+            Privilege::Machine if self.mstatus & MSTATUS_MPRV != 0 => self.mpp(),
+            privilege => privilege,
+        }
+    }
+}
+
+// MXL and the A, I, M, U and V extension bits, leaving C to the hart
+fn misa(xlen: Xlen) -> u64 {
+    let extensions = 1 | (1 << 8) | (1 << 12) | (1 << 20) | (1 << 21);
+
+    match xlen {
+        Xlen::Rv32 => (1 << 30) | extensions,
+        Xlen::Rv64 => (2 << 62) | extensions,
+    }
+}
@@ -0,0 +1,118 @@
+use std::{collections::HashSet, ops::Range};
+
+use crate::bus::Address;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access,
+}
+
+impl WatchKind {
+    fn matches(self, access: WatchKind) -> bool {
+        self == WatchKind::Access || self == access
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub range: Range<Address>,
+    pub kind: WatchKind,
+}
+
+/// The access that triggered a watchpoint; `kind` is either `Read` or `Write`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchpointHit {
+    pub address: Address,
+    pub size: usize,
+    pub kind: WatchKind,
+}
+
+/// PC breakpoints and memory watchpoints, checked by the CPU only while at
+/// least one of them is set.
+///
+/// Breakpoints stop execution before the instruction at their address runs,
+/// while watchpoints stop it right after the instruction performing the
+/// matching access retires.
+#[derive(Default)]
+pub struct Debugger {
+    breakpoints: HashSet<Address>,
+    watchpoints: Vec<Watchpoint>,
+
+    // the breakpoint execution resumes from, which must not fire again
+    pub(crate) resume_from: Option<Address>,
+
+    pub(crate) pending: Option<WatchpointHit>,
+    pub(crate) last_hit: Option<WatchpointHit>,
+}
+
+impl Debugger {
+    // returns whether the breakpoint was not already set
+    pub fn add_breakpoint(&mut self, pc: Address) -> bool {
+        self.breakpoints.insert(pc)
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: Address) -> bool {
+        self.breakpoints.remove(&pc)
+    }
+
+    pub fn add_watchpoint(&mut self, range: Range<Address>, kind: WatchKind) {
+        self.watchpoints.push(Watchpoint { range, kind });
+    }
+
+    pub fn remove_watchpoint(&mut self, range: Range<Address>, kind: WatchKind) -> bool {
+        let count = self.watchpoints.len();
+        self.watchpoints
+            .retain(|watchpoint| watchpoint.range != range || watchpoint.kind != kind);
+
+        self.watchpoints.len() != count
+    }
+
+    pub fn clear(&mut self) {
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = &Address> {
+        self.breakpoints.iter()
+    }
+
+    pub fn watchpoints(&self) -> &[Watchpoint] {
+        &self.watchpoints
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.breakpoints.is_empty() || !self.watchpoints.is_empty()
+    }
+
+    pub(crate) fn is_watching(&self) -> bool {
+        !self.watchpoints.is_empty()
+    }
+
+    pub(crate) fn should_break(&mut self, pc: Address) -> bool {
+        if self.resume_from.take() == Some(pc) {
+            return false;
+        }
+
+        self.breakpoints.contains(&pc)
+    }
+
+    pub(crate) fn check_access(&mut self, address: Address, size: usize, access: WatchKind) {
+        let end = address.saturating_add(size as Address);
+
+        let hit = self.watchpoints.iter().any(|watchpoint| {
+            watchpoint.kind.matches(access)
+                && address < watchpoint.range.end
+                && watchpoint.range.start < end
+        });
+
+        if hit && self.pending.is_none() {
+            self.pending = Some(WatchpointHit {
+                address,
+                size,
+                kind: access,
+            });
+        }
+    }
+}
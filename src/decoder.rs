@@ -0,0 +1,1427 @@
+use crate::{
+    bus::{Address, RAM_BASE},
+    cpu::Xlen,
+    exception::RVException,
+};
+
+pub const PAGE_SHIFT: u64 = 12;
+pub const PAGE_SIZE: u64 = 1 << PAGE_SHIFT;
+
+// instructions start on any two-byte boundary with C
+const SLOTS_PER_PAGE: usize = (PAGE_SIZE / 2) as usize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BranchOp {
+    Beq,
+    Bne,
+    Blt,
+    Bge,
+    Bltu,
+    Bgeu,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LoadOp {
+    Lb,
+    Lh,
+    Lw,
+    Ld,
+    Lbu,
+    Lhu,
+    Lwu,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StoreOp {
+    Sb,
+    Sh,
+    Sw,
+    Sd,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AluOp {
+    Add,
+    Sub,
+    Sll,
+    Slt,
+    Sltu,
+    Xor,
+    Srl,
+    Sra,
+    Or,
+    And,
+
+    // RV64M
+    Mul,
+    Mulh,
+    Mulhsu,
+    Mulhu,
+    Div,
+    Divu,
+    Rem,
+    Remu,
+
+    // Zba
+    Sh1add,
+    Sh2add,
+    Sh3add,
+    AddUw,
+    Sh1addUw,
+    Sh2addUw,
+    Sh3addUw,
+    SllUw,
+
+    // Zbb
+    Andn,
+    Orn,
+    Xnor,
+    Clz,
+    Ctz,
+    Cpop,
+    Max,
+    Maxu,
+    Min,
+    Minu,
+    SextB,
+    SextH,
+    ZextH,
+    Rol,
+    Ror,
+    OrcB,
+    Rev8,
+
+    // Zbc
+    Clmul,
+    Clmulh,
+    Clmulr,
+
+    // Zbs
+    Bclr,
+    Bext,
+    Binv,
+    Bset,
+}
+
+// A extension, where LR and SC are the reservation pair and the rest are AMOs
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AmoOp {
+    Lr,
+    Sc,
+    Swap,
+    Add,
+    Xor,
+    And,
+    Or,
+    Min,
+    Max,
+    Minu,
+    Maxu,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsrOp {
+    Rw,
+    Rs,
+    Rc,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VectorOperand {
+    Vector(u8),
+    Scalar(u8),
+    Immediate(u64),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VectorAddressing {
+    UnitStride,
+    FaultOnlyFirst,
+    Mask,
+    WholeRegister,
+    Strided { rs2: u8 },
+    Indexed { vs2: u8, ordered: bool },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VectorOp {
+    // single-width integer
+    Add,
+    Sub,
+    Rsub,
+    Minu,
+    Min,
+    Maxu,
+    Max,
+    And,
+    Or,
+    Xor,
+    Sll,
+    Srl,
+    Sra,
+    Mul,
+    Mulh,
+    Mulhu,
+    Mulhsu,
+    Divu,
+    Div,
+    Remu,
+    Rem,
+    Madd,
+    Nmsub,
+    Macc,
+    Nmsac,
+    Adc,
+    Sbc,
+    Merge,
+    Mv,
+    Zext2,
+    Sext2,
+    Zext4,
+    Sext4,
+    Zext8,
+    Sext8,
+
+    // comparisons and carries, writing a mask
+    Mseq,
+    Msne,
+    Msltu,
+    Mslt,
+    Msleu,
+    Msle,
+    Msgtu,
+    Msgt,
+    Madc,
+    Msbc,
+
+    // widening
+    Waddu,
+    Wadd,
+    Wsubu,
+    Wsub,
+    WadduW,
+    WaddW,
+    WsubuW,
+    WsubW,
+    Wmulu,
+    Wmulsu,
+    Wmul,
+    Wmaccu,
+    Wmacc,
+    Wmaccus,
+    Wmaccsu,
+
+    // narrowing
+    Nsrl,
+    Nsra,
+
+    // fixed-point
+    Saddu,
+    Sadd,
+    Ssubu,
+    Ssub,
+    Aaddu,
+    Aadd,
+    Asubu,
+    Asub,
+    Smul,
+    Ssrl,
+    Ssra,
+    Nclipu,
+    Nclip,
+
+    // reductions
+    Redsum,
+    Redand,
+    Redor,
+    Redxor,
+    Redminu,
+    Redmin,
+    Redmaxu,
+    Redmax,
+    Wredsumu,
+    Wredsum,
+
+    // mask
+    Mandn,
+    Mand,
+    Mor,
+    Mxor,
+    Morn,
+    Mnand,
+    Mnor,
+    Mxnor,
+    Cpop,
+    First,
+    Msbf,
+    Msif,
+    Msof,
+    Iota,
+    Id,
+
+    // permutation
+    MvXS,
+    MvSX,
+    Slideup,
+    Slidedown,
+    Slide1up,
+    Slide1down,
+    Rgather,
+    Rgatherei16,
+    Compress,
+    MvNr(u8),
+}
+
+/// A pre-decoded instruction, with every field already extracted and every
+/// immediate already sign-extended.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Instruction {
+    Lui {
+        rd: u8,
+        imm: u64,
+    },
+    Auipc {
+        rd: u8,
+        imm: u64,
+    },
+    Jal {
+        rd: u8,
+        offset: u64,
+    },
+    Jalr {
+        rd: u8,
+        rs1: u8,
+        offset: u64,
+    },
+    Branch {
+        op: BranchOp,
+        rs1: u8,
+        rs2: u8,
+        offset: u64,
+    },
+    Load {
+        op: LoadOp,
+        rd: u8,
+        rs1: u8,
+        offset: u64,
+    },
+    Store {
+        op: StoreOp,
+        rs1: u8,
+        rs2: u8,
+        offset: u64,
+    },
+    OpImm {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        imm: u64,
+    },
+    OpImm32 {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        imm: u64,
+    },
+    Op {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    Op32 {
+        op: AluOp,
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+    },
+    Fence,
+    FenceI,
+    Ecall,
+    Ebreak,
+    Mret,
+    Wfi,
+
+    // A, with `double` selecting the 64-bit forms
+    Amo {
+        op: AmoOp,
+        rd: u8,
+        rs1: u8,
+        rs2: u8,
+        double: bool,
+    },
+
+    // Zicsr
+    Csr {
+        op: CsrOp,
+        rd: u8,
+        rs1: u8,
+        csr: u16,
+    },
+    CsrImm {
+        op: CsrOp,
+        rd: u8,
+        imm: u64,
+        csr: u16,
+    },
+
+    // RVV, where scalar results such as vmv.x.s and vcpop.m go to `vd`
+    Vsetvl {
+        rd: u8,
+        avl: VectorOperand,
+        vtype: VectorOperand,
+    },
+    VectorLoad {
+        vd: u8,
+        rs1: u8,
+        addressing: VectorAddressing,
+        eew: u8,
+        nf: u8,
+        masked: bool,
+    },
+    VectorStore {
+        vs3: u8,
+        rs1: u8,
+        addressing: VectorAddressing,
+        eew: u8,
+        nf: u8,
+        masked: bool,
+    },
+    Vector {
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        operand: VectorOperand,
+        masked: bool,
+    },
+}
+
+pub fn decode(instruction: u32, xlen: Xlen) -> Result<Instruction, RVException> {
+    let _instruction = instruction as u64; // extend it for convenience
+    let rv64 = xlen == Xlen::Rv64;
+
+    let opcode = _instruction & 0x7F;
+    let funct3 = (_instruction & 0x00007000) >> 12;
+    let funct7 = (_instruction & 0xFE000000) >> 25;
+
+    let rd = ((_instruction & 0xF80) >> 7) as u8;
+    let rs1 = ((_instruction & 0x000F8000) >> 15) as u8;
+    let rs2 = ((_instruction & 0x01F00000) >> 20) as u8;
+
+    let decoded = match opcode {
+        // LUI
+        0b0110111 => Instruction::Lui {
+            rd,
+            imm: (_instruction & 0xFFFFF000) as i32 as i64 as u64,
+        },
+
+        // AUIPC
+        0b0010111 => Instruction::Auipc {
+            rd,
+            imm: (_instruction & 0xFFFFF000) as i32 as i64 as u64,
+        },
+
+        // JAL
+        0b1101111 => Instruction::Jal {
+            rd,
+            offset: ((_instruction & 0x80000000) as i32 as i64 >> 11) as u64
+                | (_instruction & 0xFF000)
+                | ((_instruction >> 9) & 0x800)
+                | ((_instruction >> 20) & 0x7FE),
+        },
+
+        // JALR
+        0b1100111 => Instruction::Jalr {
+            rd,
+            rs1,
+            offset: ((_instruction as i32 as i64) >> 20) as u64,
+        },
+
+        // BRANCH
+        0b1100011 => {
+            let op = match funct3 {
+                0b000 => BranchOp::Beq,
+                0b001 => BranchOp::Bne,
+                0b100 => BranchOp::Blt,
+                0b101 => BranchOp::Bge,
+                0b110 => BranchOp::Bltu,
+                0b111 => BranchOp::Bgeu,
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::Branch {
+                op,
+                rs1,
+                rs2,
+                offset: ((_instruction & 0x80000000) as i32 as i64 >> 19) as u64
+                    | ((_instruction & 0x80) << 4)
+                    | ((_instruction >> 20) & 0x7E0)
+                    | ((_instruction >> 7) & 0x1E),
+            }
+        }
+
+        // LOAD
+        0b0000011 => {
+            let op = match funct3 {
+                0b000 => LoadOp::Lb,
+                0b001 => LoadOp::Lh,
+                0b010 => LoadOp::Lw,
+                0b011 if rv64 => LoadOp::Ld,
+                0b100 => LoadOp::Lbu,
+                0b101 => LoadOp::Lhu,
+                0b110 if rv64 => LoadOp::Lwu,
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::Load {
+                op,
+                rd,
+                rs1,
+                offset: ((_instruction as i32 as i64) >> 20) as u64,
+            }
+        }
+
+        // STORE
+        0b0100011 => {
+            let op = match funct3 {
+                0b000 => StoreOp::Sb,
+                0b001 => StoreOp::Sh,
+                0b010 => StoreOp::Sw,
+                0b011 if rv64 => StoreOp::Sd,
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::Store {
+                op,
+                rs1,
+                rs2,
+                offset: (((_instruction & 0xFE000000) as i32 as i64 >> 20) as u64)
+                    | ((_instruction >> 7) & 0x1F),
+            }
+        }
+
+        // IMMEDIATE
+        0b0010011 => {
+            let immediate = ((_instruction as i32 as i64) >> 20) as u64;
+            let funct12 = (_instruction >> 20) & 0xFFF;
+            let shift = (_instruction >> 20) & 0x3F;
+            let funct6 = funct7 >> 1;
+
+            // RV32 shift amounts only have five bits
+            if !rv64 && funct3 & 0b011 == 0b001 && shift & 0x20 != 0 {
+                return Err(RVException::IllegalInstruction);
+            }
+
+            let (op, imm) = match funct3 {
+                0b000 => (AluOp::Add, immediate),
+                0b001 => match (funct6, funct12) {
+                    (0b000000, _) => (AluOp::Sll, shift),
+
+                    // Zbb
+                    (_, 0b011000000000) => (AluOp::Clz, 0),
+                    (_, 0b011000000001) => (AluOp::Ctz, 0),
+                    (_, 0b011000000010) => (AluOp::Cpop, 0),
+                    (_, 0b011000000100) => (AluOp::SextB, 0),
+                    (_, 0b011000000101) => (AluOp::SextH, 0),
+
+                    // Zbs
+                    (0b010010, _) => (AluOp::Bclr, shift),
+                    (0b011010, _) => (AluOp::Binv, shift),
+                    (0b001010, _) => (AluOp::Bset, shift),
+
+                    _ => return Err(RVException::IllegalInstruction),
+                },
+                0b010 => (AluOp::Slt, immediate),
+                0b011 => (AluOp::Sltu, immediate),
+                0b100 => (AluOp::Xor, immediate),
+                0b101 => match (funct6, funct12) {
+                    (0b000000, _) => (AluOp::Srl, shift),
+                    (0b010000, _) => (AluOp::Sra, shift),
+
+                    // Zbb
+                    (0b011000, _) => (AluOp::Ror, shift),
+                    (_, 0b001010000111) => (AluOp::OrcB, 0),
+                    (_, 0b011010111000) => (AluOp::Rev8, 0),
+                    (_, 0b011010011000) if !rv64 => (AluOp::Rev8, 0),
+
+                    // Zbs
+                    (0b010010, _) => (AluOp::Bext, shift),
+
+                    _ => return Err(RVException::IllegalInstruction),
+                },
+                0b110 => (AluOp::Or, immediate),
+                0b111 => (AluOp::And, immediate),
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::OpImm { op, rd, rs1, imm }
+        }
+
+        // IMMEDIATE32
+        0b0011011 if rv64 => {
+            let immediate = ((_instruction as i32 as i64) >> 20) as u64;
+            let shift = immediate & 0x1F;
+
+            // Zba
+            if funct3 == 0b001 && funct7 >> 1 == 0b000010 {
+                return Ok(Instruction::OpImm {
+                    op: AluOp::SllUw,
+                    rd,
+                    rs1,
+                    imm: immediate & 0x3F,
+                });
+            }
+
+            let (op, imm) = match funct3 {
+                0b000 => (AluOp::Add, immediate),
+                0b001 => match (funct7, rs2) {
+                    (0b0000000, _) => (AluOp::Sll, shift),
+
+                    // Zbb
+                    (0b0110000, 0b00000) => (AluOp::Clz, 0),
+                    (0b0110000, 0b00001) => (AluOp::Ctz, 0),
+                    (0b0110000, 0b00010) => (AluOp::Cpop, 0),
+
+                    _ => return Err(RVException::IllegalInstruction),
+                },
+                0b101 => match funct7 {
+                    0b0000000 => (AluOp::Srl, shift),
+                    0b0100000 => (AluOp::Sra, shift),
+
+                    // Zbb
+                    0b0110000 => (AluOp::Ror, shift),
+
+                    _ => return Err(RVException::IllegalInstruction),
+                },
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::OpImm32 { op, rd, rs1, imm }
+        }
+
+        // OPERATION
+        0b0110011 => {
+            let op = match (funct3, funct7) {
+                (0b000, 0b0000000) => AluOp::Add,
+                (0b000, 0b0100000) => AluOp::Sub,
+                (0b001, 0b0000000) => AluOp::Sll,
+                (0b010, 0b0000000) => AluOp::Slt,
+                (0b011, 0b0000000) => AluOp::Sltu,
+                (0b100, 0b0000000) => AluOp::Xor,
+                (0b101, 0b0000000) => AluOp::Srl,
+                (0b101, 0b0100000) => AluOp::Sra,
+                (0b110, 0b0000000) => AluOp::Or,
+                (0b111, 0b0000000) => AluOp::And,
+
+                // RV64M
+                (0b000, 0b0000001) => AluOp::Mul,
+                (0b001, 0b0000001) => AluOp::Mulh,
+                (0b010, 0b0000001) => AluOp::Mulhsu,
+                (0b011, 0b0000001) => AluOp::Mulhu,
+                (0b100, 0b0000001) => AluOp::Div,
+                (0b101, 0b0000001) => AluOp::Divu,
+                (0b110, 0b0000001) => AluOp::Rem,
+                (0b111, 0b0000001) => AluOp::Remu,
+
+                // Zba
+                (0b010, 0b0010000) => AluOp::Sh1add,
+                (0b100, 0b0010000) => AluOp::Sh2add,
+                (0b110, 0b0010000) => AluOp::Sh3add,
+
+                // Zbb
+                (0b111, 0b0100000) => AluOp::Andn,
+                (0b110, 0b0100000) => AluOp::Orn,
+                (0b100, 0b0100000) => AluOp::Xnor,
+                (0b110, 0b0000101) => AluOp::Max,
+                (0b111, 0b0000101) => AluOp::Maxu,
+                (0b100, 0b0000101) => AluOp::Min,
+                (0b101, 0b0000101) => AluOp::Minu,
+                (0b001, 0b0110000) => AluOp::Rol,
+                (0b101, 0b0110000) => AluOp::Ror,
+                (0b100, 0b0000100) if !rv64 && rs2 == 0 => AluOp::ZextH,
+
+                // Zbc
+                (0b001, 0b0000101) => AluOp::Clmul,
+                (0b010, 0b0000101) => AluOp::Clmulr,
+                (0b011, 0b0000101) => AluOp::Clmulh,
+
+                // Zbs
+                (0b001, 0b0100100) => AluOp::Bclr,
+                (0b101, 0b0100100) => AluOp::Bext,
+                (0b001, 0b0110100) => AluOp::Binv,
+                (0b001, 0b0010100) => AluOp::Bset,
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::Op { op, rd, rs1, rs2 }
+        }
+
+        // OPERATION32
+        0b0111011 if rv64 => {
+            // Zba and zext.h are encoded here but operate on whole registers
+            let full = match (funct3, funct7) {
+                (0b000, 0b0000100) => Some(AluOp::AddUw),
+                (0b010, 0b0010000) => Some(AluOp::Sh1addUw),
+                (0b100, 0b0010000) => Some(AluOp::Sh2addUw),
+                (0b110, 0b0010000) => Some(AluOp::Sh3addUw),
+                (0b100, 0b0000100) if rs2 == 0 => Some(AluOp::ZextH),
+
+                _ => None,
+            };
+
+            if let Some(op) = full {
+                return Ok(Instruction::Op { op, rd, rs1, rs2 });
+            }
+
+            let op = match (funct3, funct7) {
+                (0b000, 0b0000000) => AluOp::Add,
+                (0b000, 0b0100000) => AluOp::Sub,
+                (0b001, 0b0000000) => AluOp::Sll,
+                (0b101, 0b0000000) => AluOp::Srl,
+                (0b101, 0b0100000) => AluOp::Sra,
+
+                // RV64M
+                (0b000, 0b0000001) => AluOp::Mul,
+                (0b100, 0b0000001) => AluOp::Div,
+                (0b101, 0b0000001) => AluOp::Divu,
+                (0b110, 0b0000001) => AluOp::Rem,
+                (0b111, 0b0000001) => AluOp::Remu,
+
+                // Zbb
+                (0b001, 0b0110000) => AluOp::Rol,
+                (0b101, 0b0110000) => AluOp::Ror,
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::Op32 { op, rd, rs1, rs2 }
+        }
+
+        // MEM-MISC
+        0b0001111 => match funct3 {
+            // FENCE
+            0b000 => Instruction::Fence,
+
+            // FENCE.I
+            0b001 => Instruction::FenceI,
+
+            _ => return Err(RVException::IllegalInstruction),
+        },
+
+        // AMO
+        0b0101111 => {
+            let double = match funct3 {
+                0b010 => false,
+                0b011 if rv64 => true,
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            // the low two bits of funct7 are the aq and rl ordering bits
+            let op = match funct7 >> 2 {
+                0b00010 if rs2 == 0 => AmoOp::Lr,
+                0b00011 => AmoOp::Sc,
+                0b00001 => AmoOp::Swap,
+                0b00000 => AmoOp::Add,
+                0b00100 => AmoOp::Xor,
+                0b01100 => AmoOp::And,
+                0b01000 => AmoOp::Or,
+                0b10000 => AmoOp::Min,
+                0b10100 => AmoOp::Max,
+                0b11000 => AmoOp::Minu,
+                0b11100 => AmoOp::Maxu,
+
+                _ => return Err(RVException::IllegalInstruction),
+            };
+
+            Instruction::Amo {
+                op,
+                rd,
+                rs1,
+                rs2,
+                double,
+            }
+        }
+
+        // SYSTEM
+        0b1110011 => {
+            let csr = (_instruction >> 20) as u16;
+            let op = match funct3 & 0b011 {
+                0b01 => Some(CsrOp::Rw),
+                0b10 => Some(CsrOp::Rs),
+                0b11 => Some(CsrOp::Rc),
+
+                _ => None,
+            };
+
+            match (funct3, op) {
+                (0b000, _) => match (rs2, rs1, rd, funct7) {
+                    // ECALL
+                    (0b00000, 0, 0, 0) => Instruction::Ecall,
+
+                    // EBREAK
+                    (0b00001, 0, 0, 0) => Instruction::Ebreak,
+
+                    // MRET
+                    (0b00010, 0, 0, 0b0011000) => Instruction::Mret,
+
+                    // WFI
+                    (0b00101, 0, 0, 0b0001000) => Instruction::Wfi,
+
+                    _ => return Err(RVException::IllegalInstruction),
+                },
+
+                // Zicsr
+                (0b001..=0b011, Some(op)) => Instruction::Csr { op, rd, rs1, csr },
+                (0b101..=0b111, Some(op)) => Instruction::CsrImm {
+                    op,
+                    rd,
+                    imm: rs1 as u64,
+                    csr,
+                },
+
+                _ => return Err(RVException::IllegalInstruction),
+            }
+        }
+
+        // LOAD-FP, only holding vector loads without F/D
+        0b0000111 => decode_vector_memory(instruction, false)?,
+
+        // STORE-FP, only holding vector stores without F/D
+        0b0100111 => decode_vector_memory(instruction, true)?,
+
+        // OP-V
+        0b1010111 => decode_vector(instruction)?,
+
+        _ => return Err(RVException::IllegalInstruction),
+    };
+
+    Ok(decoded)
+}
+
+/// Expands a 16-bit instruction of the C extension into the instruction it
+/// stands for, which only differs from it in being two bytes long.
+pub fn decode_compressed(instruction: u16, xlen: Xlen) -> Result<Instruction, RVException> {
+    let _instruction = instruction as u64; // extend it for convenience
+    let rv64 = xlen == Xlen::Rv64;
+
+    // bits `high` down to `low` of the instruction, moved up to bit `to`
+    let bits = |high: u32, low: u32, to: u32| {
+        ((_instruction >> low) & ((1 << (high - low + 1)) - 1)) << to
+    };
+    let sext = |value: u64, width: u32| (((value << (64 - width)) as i64) >> (64 - width)) as u64;
+
+    let quadrant = _instruction & 0x03;
+    let funct3 = (_instruction >> 13) & 0x07;
+
+    let rd = ((_instruction >> 7) & 0x1F) as u8;
+    let rs2 = ((_instruction >> 2) & 0x1F) as u8;
+
+    // the three-bit register fields only reach x8 to x15
+    let rs1_prime = 8 + ((_instruction >> 7) & 0x07) as u8;
+    let rs2_prime = 8 + ((_instruction >> 2) & 0x07) as u8;
+
+    let imm = sext(bits(12, 12, 5) | bits(6, 2, 0), 6);
+    let shamt = bits(12, 12, 5) | bits(6, 2, 0);
+
+    // RV32 shift amounts only have five bits
+    let shift = rv64 || shamt & 0x20 == 0;
+    let jump = sext(
+        bits(12, 12, 11)
+            | bits(11, 11, 4)
+            | bits(10, 9, 8)
+            | bits(8, 8, 10)
+            | bits(7, 7, 6)
+            | bits(6, 6, 7)
+            | bits(5, 3, 1)
+            | bits(2, 2, 5),
+        12,
+    );
+    let branch = sext(
+        bits(12, 12, 8) | bits(11, 10, 3) | bits(6, 5, 6) | bits(4, 3, 1) | bits(2, 2, 5),
+        9,
+    );
+
+    let decoded = match (quadrant, funct3) {
+        // C.ADDI4SPN, where a zero immediate is reserved, as is the all-zero instruction
+        (0b00, 0b000) => {
+            let imm = bits(12, 11, 4) | bits(10, 7, 6) | bits(6, 6, 2) | bits(5, 5, 3);
+            if imm == 0 {
+                return Err(RVException::IllegalInstruction);
+            }
+
+            Instruction::OpImm {
+                op: AluOp::Add,
+                rd: rs2_prime,
+                rs1: 2,
+                imm,
+            }
+        }
+
+        // C.LW
+        (0b00, 0b010) => Instruction::Load {
+            op: LoadOp::Lw,
+            rd: rs2_prime,
+            rs1: rs1_prime,
+            offset: bits(12, 10, 3) | bits(6, 6, 2) | bits(5, 5, 6),
+        },
+
+        // C.LD
+        (0b00, 0b011) if rv64 => Instruction::Load {
+            op: LoadOp::Ld,
+            rd: rs2_prime,
+            rs1: rs1_prime,
+            offset: bits(12, 10, 3) | bits(6, 5, 6),
+        },
+
+        // C.SW
+        (0b00, 0b110) => Instruction::Store {
+            op: StoreOp::Sw,
+            rs1: rs1_prime,
+            rs2: rs2_prime,
+            offset: bits(12, 10, 3) | bits(6, 6, 2) | bits(5, 5, 6),
+        },
+
+        // C.SD
+        (0b00, 0b111) if rv64 => Instruction::Store {
+            op: StoreOp::Sd,
+            rs1: rs1_prime,
+            rs2: rs2_prime,
+            offset: bits(12, 10, 3) | bits(6, 5, 6),
+        },
+
+        // C.ADDI, and C.NOP with x0
+        (0b01, 0b000) => Instruction::OpImm {
+            op: AluOp::Add,
+            rd,
+            rs1: rd,
+            imm,
+        },
+
+        // C.JAL on RV32, C.ADDIW on RV64
+        (0b01, 0b001) if !rv64 => Instruction::Jal {
+            rd: 1,
+            offset: jump,
+        },
+        (0b01, 0b001) if rd != 0 => Instruction::OpImm32 {
+            op: AluOp::Add,
+            rd,
+            rs1: rd,
+            imm,
+        },
+
+        // C.LI
+        (0b01, 0b010) => Instruction::OpImm {
+            op: AluOp::Add,
+            rd,
+            rs1: 0,
+            imm,
+        },
+
+        // C.ADDI16SP with sp, C.LUI otherwise, where zero immediates are reserved
+        (0b01, 0b011) if rd == 2 => {
+            let imm = sext(
+                bits(12, 12, 9) | bits(6, 6, 4) | bits(5, 5, 6) | bits(4, 3, 7) | bits(2, 2, 5),
+                10,
+            );
+            if imm == 0 {
+                return Err(RVException::IllegalInstruction);
+            }
+
+            Instruction::OpImm {
+                op: AluOp::Add,
+                rd: 2,
+                rs1: 2,
+                imm,
+            }
+        }
+        (0b01, 0b011) => {
+            let imm = sext(bits(12, 12, 17) | bits(6, 2, 12), 18);
+            if imm == 0 {
+                return Err(RVException::IllegalInstruction);
+            }
+
+            Instruction::Lui { rd, imm }
+        }
+
+        (0b01, 0b100) => {
+            let (rd, rs1) = (rs1_prime, rs1_prime);
+
+            match ((_instruction >> 10) & 0x03, (_instruction >> 5) & 0x03) {
+                (0b00 | 0b01, _) if !shift => return Err(RVException::IllegalInstruction),
+
+                // C.SRLI, C.SRAI and C.ANDI
+                (0b00, _) => Instruction::OpImm {
+                    op: AluOp::Srl,
+                    rd,
+                    rs1,
+                    imm: shamt,
+                },
+                (0b01, _) => Instruction::OpImm {
+                    op: AluOp::Sra,
+                    rd,
+                    rs1,
+                    imm: shamt,
+                },
+                (0b10, _) => Instruction::OpImm {
+                    op: AluOp::And,
+                    rd,
+                    rs1,
+                    imm,
+                },
+
+                // C.SUB, C.XOR, C.OR and C.AND, then C.SUBW and C.ADDW on RV64
+                (_, funct2) => {
+                    let rs2 = rs2_prime;
+                    match (_instruction >> 12) & 0x01 {
+                        0 => Instruction::Op {
+                            op: [AluOp::Sub, AluOp::Xor, AluOp::Or, AluOp::And][funct2 as usize],
+                            rd,
+                            rs1,
+                            rs2,
+                        },
+                        _ if rv64 && funct2 < 0b10 => Instruction::Op32 {
+                            op: [AluOp::Sub, AluOp::Add][funct2 as usize],
+                            rd,
+                            rs1,
+                            rs2,
+                        },
+
+                        _ => return Err(RVException::IllegalInstruction),
+                    }
+                }
+            }
+        }
+
+        // C.J
+        (0b01, 0b101) => Instruction::Jal {
+            rd: 0,
+            offset: jump,
+        },
+
+        // C.BEQZ and C.BNEZ
+        (0b01, 0b110 | 0b111) => Instruction::Branch {
+            op: match funct3 {
+                0b110 => BranchOp::Beq,
+                _ => BranchOp::Bne,
+            },
+            rs1: rs1_prime,
+            rs2: 0,
+            offset: branch,
+        },
+
+        // C.SLLI
+        (0b10, 0b000) if shift => Instruction::OpImm {
+            op: AluOp::Sll,
+            rd,
+            rs1: rd,
+            imm: shamt,
+        },
+
+        // C.LWSP and C.LDSP, where x0 is reserved
+        (0b10, 0b010) if rd != 0 => Instruction::Load {
+            op: LoadOp::Lw,
+            rd,
+            rs1: 2,
+            offset: bits(12, 12, 5) | bits(6, 4, 2) | bits(3, 2, 6),
+        },
+        (0b10, 0b011) if rv64 && rd != 0 => Instruction::Load {
+            op: LoadOp::Ld,
+            rd,
+            rs1: 2,
+            offset: bits(12, 12, 5) | bits(6, 5, 3) | bits(4, 2, 6),
+        },
+
+        (0b10, 0b100) => match ((_instruction >> 12) & 0x01, rd, rs2) {
+            // C.JR, where x0 is reserved, and C.MV
+            (0, 0, 0) => return Err(RVException::IllegalInstruction),
+            (0, _, 0) => Instruction::Jalr {
+                rd: 0,
+                rs1: rd,
+                offset: 0,
+            },
+            (0, _, _) => Instruction::Op {
+                op: AluOp::Add,
+                rd,
+                rs1: 0,
+                rs2,
+            },
+
+            // C.EBREAK, C.JALR and C.ADD
+            (_, 0, 0) => Instruction::Ebreak,
+            (_, _, 0) => Instruction::Jalr {
+                rd: 1,
+                rs1: rd,
+                offset: 0,
+            },
+            (_, _, _) => Instruction::Op {
+                op: AluOp::Add,
+                rd,
+                rs1: rd,
+                rs2,
+            },
+        },
+
+        // C.SWSP and C.SDSP
+        (0b10, 0b110) => Instruction::Store {
+            op: StoreOp::Sw,
+            rs1: 2,
+            rs2,
+            offset: bits(12, 9, 2) | bits(8, 7, 6),
+        },
+        (0b10, 0b111) if rv64 => Instruction::Store {
+            op: StoreOp::Sd,
+            rs1: 2,
+            rs2,
+            offset: bits(12, 10, 3) | bits(9, 7, 6),
+        },
+
+        // the floating point loads and stores, without F/D
+        _ => return Err(RVException::IllegalInstruction),
+    };
+
+    Ok(decoded)
+}
+
+// OP-V funct3 values, selecting the operand kind
+const OPIVV: u32 = 0b000;
+const OPMVV: u32 = 0b010;
+const OPIVI: u32 = 0b011;
+const OPIVX: u32 = 0b100;
+const OPMVX: u32 = 0b110;
+const OPCFG: u32 = 0b111;
+
+fn decode_vector(instruction: u32) -> Result<Instruction, RVException> {
+    let funct6 = instruction >> 26;
+    let funct3 = (instruction >> 12) & 0x07;
+    let masked = (instruction >> 25) & 0x01 == 0;
+    let vd = ((instruction >> 7) & 0x1F) as u8;
+    let vs1 = ((instruction >> 15) & 0x1F) as u8;
+    let vs2 = ((instruction >> 20) & 0x1F) as u8;
+
+    if funct3 == OPCFG {
+        let (avl, vtype) = match instruction >> 30 {
+            // VSETVLI
+            0b00 | 0b01 => (
+                VectorOperand::Scalar(vs1),
+                VectorOperand::Immediate(((instruction >> 20) & 0x7FF) as u64),
+            ),
+
+            // VSETIVLI
+            0b11 => (
+                VectorOperand::Immediate(vs1 as u64),
+                VectorOperand::Immediate(((instruction >> 20) & 0x3FF) as u64),
+            ),
+
+            // VSETVL
+            _ if instruction >> 25 == 0b1000000 => {
+                (VectorOperand::Scalar(vs1), VectorOperand::Scalar(vs2))
+            }
+
+            _ => return Err(RVException::IllegalInstruction),
+        };
+
+        return Ok(Instruction::Vsetvl { rd: vd, avl, vtype });
+    }
+
+    let operand = match funct3 {
+        OPIVV | OPMVV => VectorOperand::Vector(vs1),
+        OPIVX | OPMVX => VectorOperand::Scalar(vs1),
+        OPIVI => VectorOperand::Immediate((((vs1 as i8) << 3) >> 3) as i64 as u64),
+
+        // floating point
+        _ => return Err(RVException::IllegalInstruction),
+    };
+
+    let op = match (funct6, funct3) {
+        (0b000000, OPIVV | OPIVX | OPIVI) => VectorOp::Add,
+        (0b000010, OPIVV | OPIVX) => VectorOp::Sub,
+        (0b000011, OPIVX | OPIVI) => VectorOp::Rsub,
+        (0b000100, OPIVV | OPIVX) => VectorOp::Minu,
+        (0b000101, OPIVV | OPIVX) => VectorOp::Min,
+        (0b000110, OPIVV | OPIVX) => VectorOp::Maxu,
+        (0b000111, OPIVV | OPIVX) => VectorOp::Max,
+        (0b001001, OPIVV | OPIVX | OPIVI) => VectorOp::And,
+        (0b001010, OPIVV | OPIVX | OPIVI) => VectorOp::Or,
+        (0b001011, OPIVV | OPIVX | OPIVI) => VectorOp::Xor,
+        (0b001100, OPIVV | OPIVX | OPIVI) => VectorOp::Rgather,
+        (0b001110, OPIVV) => VectorOp::Rgatherei16,
+        (0b001110, OPIVX | OPIVI) => VectorOp::Slideup,
+        (0b001111, OPIVX | OPIVI) => VectorOp::Slidedown,
+        (0b010000, OPIVV | OPIVX | OPIVI) if masked => VectorOp::Adc,
+        (0b010001, OPIVV | OPIVX | OPIVI) => VectorOp::Madc,
+        (0b010010, OPIVV | OPIVX) if masked => VectorOp::Sbc,
+        (0b010011, OPIVV | OPIVX) => VectorOp::Msbc,
+        (0b010111, OPIVV | OPIVX | OPIVI) if masked => VectorOp::Merge,
+        (0b010111, OPIVV | OPIVX | OPIVI) if vs2 == 0 => VectorOp::Mv,
+        (0b011000, OPIVV | OPIVX | OPIVI) => VectorOp::Mseq,
+        (0b011001, OPIVV | OPIVX | OPIVI) => VectorOp::Msne,
+        (0b011010, OPIVV | OPIVX) => VectorOp::Msltu,
+        (0b011011, OPIVV | OPIVX) => VectorOp::Mslt,
+        (0b011100, OPIVV | OPIVX | OPIVI) => VectorOp::Msleu,
+        (0b011101, OPIVV | OPIVX | OPIVI) => VectorOp::Msle,
+        (0b011110, OPIVX | OPIVI) => VectorOp::Msgtu,
+        (0b011111, OPIVX | OPIVI) => VectorOp::Msgt,
+        (0b100000, OPIVV | OPIVX | OPIVI) => VectorOp::Saddu,
+        (0b100001, OPIVV | OPIVX | OPIVI) => VectorOp::Sadd,
+        (0b100010, OPIVV | OPIVX) => VectorOp::Ssubu,
+        (0b100011, OPIVV | OPIVX) => VectorOp::Ssub,
+        (0b100101, OPIVV | OPIVX | OPIVI) => VectorOp::Sll,
+        (0b100111, OPIVV | OPIVX) => VectorOp::Smul,
+        (0b100111, OPIVI) if !masked && matches!(vs1, 0 | 1 | 3 | 7) => VectorOp::MvNr(vs1 + 1),
+        (0b101000, OPIVV | OPIVX | OPIVI) => VectorOp::Srl,
+        (0b101001, OPIVV | OPIVX | OPIVI) => VectorOp::Sra,
+        (0b101010, OPIVV | OPIVX | OPIVI) => VectorOp::Ssrl,
+        (0b101011, OPIVV | OPIVX | OPIVI) => VectorOp::Ssra,
+        (0b101100, OPIVV | OPIVX | OPIVI) => VectorOp::Nsrl,
+        (0b101101, OPIVV | OPIVX | OPIVI) => VectorOp::Nsra,
+        (0b101110, OPIVV | OPIVX | OPIVI) => VectorOp::Nclipu,
+        (0b101111, OPIVV | OPIVX | OPIVI) => VectorOp::Nclip,
+        (0b110000, OPIVV) => VectorOp::Wredsumu,
+        (0b110001, OPIVV) => VectorOp::Wredsum,
+
+        (0b000000, OPMVV) => VectorOp::Redsum,
+        (0b000001, OPMVV) => VectorOp::Redand,
+        (0b000010, OPMVV) => VectorOp::Redor,
+        (0b000011, OPMVV) => VectorOp::Redxor,
+        (0b000100, OPMVV) => VectorOp::Redminu,
+        (0b000101, OPMVV) => VectorOp::Redmin,
+        (0b000110, OPMVV) => VectorOp::Redmaxu,
+        (0b000111, OPMVV) => VectorOp::Redmax,
+        (0b001000, OPMVV | OPMVX) => VectorOp::Aaddu,
+        (0b001001, OPMVV | OPMVX) => VectorOp::Aadd,
+        (0b001010, OPMVV | OPMVX) => VectorOp::Asubu,
+        (0b001011, OPMVV | OPMVX) => VectorOp::Asub,
+        (0b001110, OPMVX) => VectorOp::Slide1up,
+        (0b001111, OPMVX) => VectorOp::Slide1down,
+
+        // VWXUNARY0
+        (0b010000, OPMVV) => match vs1 {
+            0b00000 if !masked => VectorOp::MvXS,
+            0b10000 => VectorOp::Cpop,
+            0b10001 => VectorOp::First,
+
+            _ => return Err(RVException::IllegalInstruction),
+        },
+
+        // VRXUNARY0
+        (0b010000, OPMVX) if vs2 == 0 && !masked => VectorOp::MvSX,
+
+        // VXUNARY0
+        (0b010010, OPMVV) => match vs1 {
+            0b00010 => VectorOp::Zext8,
+            0b00011 => VectorOp::Sext8,
+            0b00100 => VectorOp::Zext4,
+            0b00101 => VectorOp::Sext4,
+            0b00110 => VectorOp::Zext2,
+            0b00111 => VectorOp::Sext2,
+
+            _ => return Err(RVException::IllegalInstruction),
+        },
+
+        // VMUNARY0
+        (0b010100, OPMVV) => match vs1 {
+            0b00001 => VectorOp::Msbf,
+            0b00010 => VectorOp::Msof,
+            0b00011 => VectorOp::Msif,
+            0b10000 => VectorOp::Iota,
+            0b10001 if vs2 == 0 => VectorOp::Id,
+
+            _ => return Err(RVException::IllegalInstruction),
+        },
+
+        (0b010111, OPMVV) if !masked => VectorOp::Compress,
+        (0b011000, OPMVV) if !masked => VectorOp::Mandn,
+        (0b011001, OPMVV) if !masked => VectorOp::Mand,
+        (0b011010, OPMVV) if !masked => VectorOp::Mor,
+        (0b011011, OPMVV) if !masked => VectorOp::Mxor,
+        (0b011100, OPMVV) if !masked => VectorOp::Morn,
+        (0b011101, OPMVV) if !masked => VectorOp::Mnand,
+        (0b011110, OPMVV) if !masked => VectorOp::Mnor,
+        (0b011111, OPMVV) if !masked => VectorOp::Mxnor,
+        (0b100000, OPMVV | OPMVX) => VectorOp::Divu,
+        (0b100001, OPMVV | OPMVX) => VectorOp::Div,
+        (0b100010, OPMVV | OPMVX) => VectorOp::Remu,
+        (0b100011, OPMVV | OPMVX) => VectorOp::Rem,
+        (0b100100, OPMVV | OPMVX) => VectorOp::Mulhu,
+        (0b100101, OPMVV | OPMVX) => VectorOp::Mul,
+        (0b100110, OPMVV | OPMVX) => VectorOp::Mulhsu,
+        (0b100111, OPMVV | OPMVX) => VectorOp::Mulh,
+        (0b101001, OPMVV | OPMVX) => VectorOp::Madd,
+        (0b101011, OPMVV | OPMVX) => VectorOp::Nmsub,
+        (0b101101, OPMVV | OPMVX) => VectorOp::Macc,
+        (0b101111, OPMVV | OPMVX) => VectorOp::Nmsac,
+        (0b110000, OPMVV | OPMVX) => VectorOp::Waddu,
+        (0b110001, OPMVV | OPMVX) => VectorOp::Wadd,
+        (0b110010, OPMVV | OPMVX) => VectorOp::Wsubu,
+        (0b110011, OPMVV | OPMVX) => VectorOp::Wsub,
+        (0b110100, OPMVV | OPMVX) => VectorOp::WadduW,
+        (0b110101, OPMVV | OPMVX) => VectorOp::WaddW,
+        (0b110110, OPMVV | OPMVX) => VectorOp::WsubuW,
+        (0b110111, OPMVV | OPMVX) => VectorOp::WsubW,
+        (0b111000, OPMVV | OPMVX) => VectorOp::Wmulu,
+        (0b111010, OPMVV | OPMVX) => VectorOp::Wmulsu,
+        (0b111011, OPMVV | OPMVX) => VectorOp::Wmul,
+        (0b111100, OPMVV | OPMVX) => VectorOp::Wmaccu,
+        (0b111101, OPMVV | OPMVX) => VectorOp::Wmacc,
+        (0b111110, OPMVX) => VectorOp::Wmaccus,
+        (0b111111, OPMVV | OPMVX) => VectorOp::Wmaccsu,
+
+        _ => return Err(RVException::IllegalInstruction),
+    };
+
+    Ok(Instruction::Vector {
+        op,
+        vd,
+        vs2,
+        operand,
+        masked,
+    })
+}
+
+fn decode_vector_memory(instruction: u32, store: bool) -> Result<Instruction, RVException> {
+    let nf = ((instruction >> 29) + 1) as u8;
+    let mew = (instruction >> 28) & 0x01;
+    let mop = (instruction >> 26) & 0x03;
+    let masked = (instruction >> 25) & 0x01 == 0;
+    let rs2 = ((instruction >> 20) & 0x1F) as u8;
+    let rs1 = ((instruction >> 15) & 0x1F) as u8;
+    let vd = ((instruction >> 7) & 0x1F) as u8;
+
+    // in bits
+    let eew = match (instruction >> 12) & 0x07 {
+        0b000 => 8,
+        0b101 => 16,
+        0b110 => 32,
+        0b111 => 64,
+
+        // scalar floating point
+        _ => return Err(RVException::IllegalInstruction),
+    };
+
+    if mew != 0 {
+        return Err(RVException::IllegalInstruction);
+    }
+
+    let addressing = match (mop, rs2) {
+        (0b00, 0b00000) => VectorAddressing::UnitStride,
+        (0b00, 0b01000) if !masked && matches!(nf, 1 | 2 | 4 | 8) => {
+            VectorAddressing::WholeRegister
+        }
+        (0b00, 0b01011) if !masked && nf == 1 && eew == 8 => VectorAddressing::Mask,
+        (0b00, 0b10000) if !store => VectorAddressing::FaultOnlyFirst,
+        (0b01, _) => VectorAddressing::Indexed {
+            vs2: rs2,
+            ordered: false,
+        },
+        (0b10, _) => VectorAddressing::Strided { rs2 },
+        (0b11, _) => VectorAddressing::Indexed {
+            vs2: rs2,
+            ordered: true,
+        },
+
+        _ => return Err(RVException::IllegalInstruction),
+    };
+
+    // whole register stores only exist with 8-bit elements
+    if store && addressing == VectorAddressing::WholeRegister && eew != 8 {
+        return Err(RVException::IllegalInstruction);
+    }
+
+    Ok(if store {
+        Instruction::VectorStore {
+            vs3: vd,
+            rs1,
+            addressing,
+            eew,
+            nf,
+            masked,
+        }
+    } else {
+        Instruction::VectorLoad {
+            vd,
+            rs1,
+            addressing,
+            eew,
+            nf,
+            masked,
+        }
+    })
+}
+
+// along with the length of each instruction
+type DecodedPage = [Option<(Instruction, u8)>; SLOTS_PER_PAGE];
+
+/// Caches decoded instructions per physical RAM page, so that hot code skips
+/// both the bus fetch and the decoding step.
+///
+/// A page is dropped as a whole whenever it is written to, which keeps
+/// self-modifying code correct without having to track individual slots.
+#[derive(Default)]
+pub struct DecodeCache {
+    pages: Vec<Option<Box<DecodedPage>>>,
+}
+
+impl DecodeCache {
+    pub fn get(&self, address: Address) -> Option<(Instruction, u64)> {
+        let (page, slot) = Self::locate(address)?;
+
+        match self.pages.get(page) {
+            Some(Some(page)) => {
+                page[slot].map(|(instruction, length)| (instruction, length as u64))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn insert(&mut self, address: Address, instruction: Instruction, length: u64) {
+        let Some((page, slot)) = Self::locate(address) else {
+            return;
+        };
+
+        if page >= self.pages.len() {
+            self.pages.resize_with(page + 1, || None);
+        }
+
+        self.pages[page].get_or_insert_with(|| Box::new([None; SLOTS_PER_PAGE]))[slot] =
+            Some((instruction, length as u8));
+    }
+
+    /// Drops every page touched by a `size`-byte access at `address`, along
+    /// with the previous one when it may hold an instruction running into it.
+    pub fn invalidate(&mut self, address: Address, size: usize) {
+        for address in [
+            address.wrapping_sub(2),
+            address.wrapping_add(size as u64 - 1),
+        ] {
+            if let Some((page, _)) = Self::locate(address & !0x01) {
+                if let Some(entry) = self.pages.get_mut(page) {
+                    *entry = None;
+                }
+            }
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.pages.clear();
+    }
+
+    // aligned instructions at or above RAM_BASE, the CPU only inserting those in RAM
+    fn locate(address: Address) -> Option<(usize, usize)> {
+        if address < RAM_BASE || address & 0x01 != 0 {
+            return None;
+        }
+
+        let offset = address - RAM_BASE;
+        Some((
+            (offset >> PAGE_SHIFT) as usize,
+            ((offset & (PAGE_SIZE - 1)) >> 1) as usize,
+        ))
+    }
+}
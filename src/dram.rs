@@ -1,113 +1,263 @@
-use std::mem;
+use std::{
+    mem,
+    sync::{
+        atomic::{AtomicU16, AtomicU32, AtomicU64, AtomicU8, Ordering},
+        Arc,
+    },
+};
 
 use crate::{
     bus::{Address, Device},
     exception::RVException,
 };
 
+/// RAM, possibly shared by all the harts of a machine.
+///
+/// Naturally aligned accesses are single-copy atomic and ordered like
+/// acquire loads and release stores, which together with a full fence for
+/// FENCE is enough for RVWMO. The host reads and writes it through the same
+/// atomics, a word at a time where it can, so it may do so while harts run.
 pub struct DRAM {
-    memory: Vec<u8>,
+    // whole words, so that aligned guest accesses are aligned on the host too
+    words: Arc<[AtomicU64]>,
+    size: usize,
 }
 
 impl DRAM {
     pub fn new(size: usize) -> Self {
         Self {
-            memory: vec![0x00; size],
+            words: (0..size.div_ceil(8)).map(|_| AtomicU64::new(0)).collect(),
+            size,
+        }
+    }
+
+    // another handle to the same memory
+    pub(crate) fn share(&self) -> Self {
+        Self {
+            words: self.words.clone(),
+            size: self.size,
         }
     }
 
     pub fn initialize(&mut self, data: Vec<u8>) {
-        self.memory.splice(..data.len(), data.iter().cloned());
+        self.write_bytes(0, &data);
     }
 
-    // write routines
-    fn write08(&mut self, address: Address, value: u8) {
-        let index = address as usize;
-        self.memory[index] = value;
+    /// Copies the bytes at `offset` into `data`, panicking if they run past
+    /// the end of memory.
+    pub fn read_bytes(&self, offset: usize, data: &mut [u8]) {
+        assert!(
+            self.contains(offset as Address, data.len()),
+            "outside of RAM"
+        );
+
+        let mut done = 0;
+        while done < data.len() {
+            let address = (offset + done) as Address;
+            let size = self.chunk(address, data.len() - done);
+
+            let value = self.load(address, size).to_le_bytes();
+            data[done..done + size].copy_from_slice(&value[..size]);
+            done += size;
+        }
     }
 
-    fn write16(&mut self, address: Address, value: u16) {
-        let index = address as usize;
-        self.memory[index + 0] = ((value >> 0) & 0xff) as u8;
-        self.memory[index + 1] = ((value >> 8) & 0xff) as u8;
+    /// Copies `data` to `offset`, panicking if it runs past the end of memory.
+    pub fn write_bytes(&self, offset: usize, data: &[u8]) {
+        assert!(
+            self.contains(offset as Address, data.len()),
+            "outside of RAM"
+        );
+
+        let mut done = 0;
+        while done < data.len() {
+            let address = (offset + done) as Address;
+            let size = self.chunk(address, data.len() - done);
+
+            let mut value = [0; 8];
+            value[..size].copy_from_slice(&data[done..done + size]);
+            self.store(address, size, u64::from_le_bytes(value));
+            done += size;
+        }
     }
 
-    fn write32(&mut self, address: Address, value: u32) {
-        let index = address as usize;
-        self.memory[index + 0] = ((value >> 0) & 0xff) as u8;
-        self.memory[index + 1] = ((value >> 8) & 0xff) as u8;
-        self.memory[index + 2] = ((value >> 16) & 0xff) as u8;
-        self.memory[index + 3] = ((value >> 24) & 0xff) as u8;
+    // a copy of the whole memory
+    pub fn contents(&self) -> Vec<u8> {
+        let mut data = vec![0; self.size];
+        self.read_bytes(0, &mut data);
+        data
     }
 
-    fn write64(&mut self, address: Address, value: u64) {
-        let index = address as usize;
-        self.memory[index + 0] = ((value >> 0) & 0xff) as u8;
-        self.memory[index + 1] = ((value >> 8) & 0xff) as u8;
-        self.memory[index + 2] = ((value >> 16) & 0xff) as u8;
-        self.memory[index + 3] = ((value >> 24) & 0xff) as u8;
-        self.memory[index + 4] = ((value >> 32) & 0xff) as u8;
-        self.memory[index + 5] = ((value >> 40) & 0xff) as u8;
-        self.memory[index + 6] = ((value >> 48) & 0xff) as u8;
-        self.memory[index + 7] = ((value >> 56) & 0xff) as u8;
+    // atomically replaces the aligned word at `address` with `f(old)`, returning `old`
+    pub(crate) fn fetch_update(
+        &self,
+        address: Address,
+        double: bool,
+        f: impl Fn(u64) -> u64,
+    ) -> u64 {
+        let pointer = self.pointer(address);
+
+        unsafe {
+            if double {
+                let word = AtomicU64::from_ptr(pointer.cast());
+                let old = word
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |old| {
+                        Some(f(u64::from_le(old)).to_le())
+                    })
+                    .unwrap();
+
+                u64::from_le(old)
+            } else {
+                let word = AtomicU32::from_ptr(pointer.cast());
+                let old = word
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |old| {
+                        Some((f(u32::from_le(old) as u64) as u32).to_le())
+                    })
+                    .unwrap();
+
+                u32::from_le(old) as u64
+            }
+        }
     }
 
-    // read routines
-    fn read08(&self, address: Address) -> u8 {
-        let index = address as usize;
-        self.memory[index]
+    // atomically stores `new` at the aligned word at `address` if it still holds `current`
+    pub(crate) fn compare_exchange(
+        &self,
+        address: Address,
+        double: bool,
+        current: u64,
+        new: u64,
+    ) -> bool {
+        let pointer = self.pointer(address);
+
+        unsafe {
+            if double {
+                AtomicU64::from_ptr(pointer.cast())
+                    .compare_exchange(
+                        current.to_le(),
+                        new.to_le(),
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    )
+                    .is_ok()
+            } else {
+                AtomicU32::from_ptr(pointer.cast())
+                    .compare_exchange(
+                        (current as u32).to_le(),
+                        (new as u32).to_le(),
+                        Ordering::SeqCst,
+                        Ordering::SeqCst,
+                    )
+                    .is_ok()
+            }
+        }
     }
 
-    fn read16(&self, address: Address) -> u16 {
-        let index = address as usize;
-        (self.memory[index] as u16) | ((self.memory[index + 1] as u16) << 8)
+    pub(crate) fn base(&self) -> *mut u8 {
+        self.words.as_ptr() as *mut u8
     }
 
-    fn read32(&self, address: Address) -> u32 {
-        let index = address as usize;
-        (self.memory[index] as u32)
-            | ((self.memory[index + 1] as u32) << 8)
-            | ((self.memory[index + 2] as u32) << 16)
-            | ((self.memory[index + 3] as u32) << 24)
+    fn pointer(&self, address: Address) -> *mut u8 {
+        unsafe { self.base().add(address as usize) }
     }
 
-    fn read64(&self, address: Address) -> u64 {
-        let index = address as usize;
-        (self.memory[index] as u64)
-            | ((self.memory[index + 1] as u64) << 8)
-            | ((self.memory[index + 2] as u64) << 16)
-            | ((self.memory[index + 3] as u64) << 24)
-            | ((self.memory[index + 4] as u64) << 32)
-            | ((self.memory[index + 5] as u64) << 40)
-            | ((self.memory[index + 6] as u64) << 48)
-            | ((self.memory[index + 7] as u64) << 56)
+    // whole words where aligned, single bytes elsewhere
+    fn chunk(&self, address: Address, left: usize) -> usize {
+        match address.is_multiple_of(8) && left >= 8 {
+            true => 8,
+            false => 1,
+        }
+    }
+
+    fn contains(&self, address: Address, size: usize) -> bool {
+        address
+            .checked_add(size as Address)
+            .is_some_and(|end| end <= self.size as Address)
+    }
+
+    // single-copy atomic when naturally aligned, byte by byte otherwise
+    fn load(&self, address: Address, size: usize) -> u64 {
+        if !address.is_multiple_of(size as Address) {
+            return (0..size as Address)
+                .rev()
+                .fold(0, |value, byte| (value << 8) | self.load(address + byte, 1));
+        }
+
+        let pointer = self.pointer(address);
+        unsafe {
+            match size {
+                1 => AtomicU8::from_ptr(pointer).load(Ordering::Acquire) as u64,
+                2 => {
+                    u16::from_le(AtomicU16::from_ptr(pointer.cast()).load(Ordering::Acquire)) as u64
+                }
+                4 => {
+                    u32::from_le(AtomicU32::from_ptr(pointer.cast()).load(Ordering::Acquire)) as u64
+                }
+                _ => u64::from_le(AtomicU64::from_ptr(pointer.cast()).load(Ordering::Acquire)),
+            }
+        }
+    }
+
+    fn store(&self, address: Address, size: usize, value: u64) {
+        if !address.is_multiple_of(size as Address) {
+            for byte in 0..size as Address {
+                self.store(address + byte, 1, value >> (8 * byte));
+            }
+            return;
+        }
+
+        let pointer = self.pointer(address);
+        unsafe {
+            match size {
+                1 => AtomicU8::from_ptr(pointer).store(value as u8, Ordering::Release),
+                2 => AtomicU16::from_ptr(pointer.cast())
+                    .store((value as u16).to_le(), Ordering::Release),
+                4 => AtomicU32::from_ptr(pointer.cast())
+                    .store((value as u32).to_le(), Ordering::Release),
+                _ => AtomicU64::from_ptr(pointer.cast()).store(value.to_le(), Ordering::Release),
+            }
+        }
     }
 }
 
 impl Device for DRAM {
     fn size(&self) -> usize {
-        self.memory.len()
+        self.size
     }
 
     fn read<T: Sized>(&self, address: Address) -> Result<T, RVException> {
-        match 8 * mem::size_of::<T>() {
-            08 => Ok(unsafe { mem::transmute_copy(&self.read08(address)) }),
-            16 => Ok(unsafe { mem::transmute_copy(&self.read16(address)) }),
-            32 => Ok(unsafe { mem::transmute_copy(&self.read32(address)) }),
-            64 => Ok(unsafe { mem::transmute_copy(&self.read64(address)) }),
-
-            _ => Err(RVException::LoadAccessFault),
+        let size = mem::size_of::<T>();
+        if !matches!(size, 1 | 2 | 4 | 8) || !self.contains(address, size) {
+            return Err(RVException::LoadAccessFault);
         }
+
+        let value = self.load(address, size);
+        Ok(unsafe {
+            match size {
+                1 => mem::transmute_copy(&(value as u8)),
+                2 => mem::transmute_copy(&(value as u16)),
+                4 => mem::transmute_copy(&(value as u32)),
+                _ => mem::transmute_copy(&value),
+            }
+        })
     }
 
     fn write<T: Sized>(&mut self, address: Address, value: T) -> Result<(), RVException> {
-        match 8 * mem::size_of::<T>() {
-            08 => Ok(self.write08(address, unsafe { mem::transmute_copy(&value) })),
-            16 => Ok(self.write16(address, unsafe { mem::transmute_copy(&value) })),
-            32 => Ok(self.write32(address, unsafe { mem::transmute_copy(&value) })),
-            64 => Ok(self.write64(address, unsafe { mem::transmute_copy(&value) })),
-
-            _ => Err(RVException::StoreAccessFault),
+        let size = mem::size_of::<T>();
+        if !matches!(size, 1 | 2 | 4 | 8) || !self.contains(address, size) {
+            return Err(RVException::StoreAccessFault);
         }
+
+        let value = unsafe {
+            match size {
+                1 => mem::transmute_copy::<T, u8>(&value) as u64,
+                2 => mem::transmute_copy::<T, u16>(&value) as u64,
+                4 => mem::transmute_copy::<T, u32>(&value) as u64,
+                _ => mem::transmute_copy::<T, u64>(&value),
+            }
+        };
+        self.store(address, size, value);
+
+        Ok(())
     }
 }
@@ -0,0 +1,290 @@
+use std::io;
+
+use crate::{bus::Address, cpu::Xlen};
+
+// e_machine of RISC-V
+const EM_RISCV: u16 = 0xF3;
+
+// e_type of executables, and of position independent ones
+const ET_EXEC: u16 = 2;
+const ET_DYN: u16 = 3;
+
+// p_type of loadable segments
+const PT_LOAD: u32 = 1;
+
+// sh_type of symbol tables
+const SHT_SYMTAB: u32 = 2;
+
+// st_type of symbols naming code, labels being untyped
+const STT_NOTYPE: u8 = 0;
+const STT_FUNC: u8 = 2;
+
+// st_shndx of undefined and absolute symbols
+const SHN_UNDEF: u16 = 0;
+const SHN_ABS: u16 = 0xFFF1;
+
+/// The loadable parts of a little-endian RISC-V ELF executable.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Elf {
+    pub xlen: Xlen,
+    pub entry: Address,
+    pub segments: Vec<Segment>,
+
+    // sorted by address, and empty for stripped files
+    pub symbols: Vec<Symbol>,
+}
+
+/// A PT_LOAD segment, to be placed at its physical address. Memory past the
+/// end of `data` up to `size` bytes is zeroed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Segment {
+    pub address: Address,
+    pub size: u64,
+    pub data: Vec<u8>,
+}
+
+/// A function or label from the symbol table. A size of zero is unknown, in
+/// which case the symbol extends up to the next one.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Symbol {
+    pub name: String,
+    pub address: Address,
+    pub size: u64,
+}
+
+impl Elf {
+    pub fn parse(data: &[u8]) -> io::Result<Self> {
+        if data.get(..4) != Some(b"\x7FELF") {
+            return Err(invalid("not an ELF file"));
+        }
+
+        let xlen = match data.get(4) {
+            Some(1) => Xlen::Rv32,
+            Some(2) => Xlen::Rv64,
+
+            _ => return Err(invalid("unsupported ELF class")),
+        };
+
+        if data.get(5) != Some(&1) {
+            return Err(invalid("not a little-endian ELF file"));
+        }
+
+        let reader = Reader { data, xlen };
+        if !matches!(reader.u16(0x10)?, ET_EXEC | ET_DYN) {
+            return Err(invalid("not an executable"));
+        }
+        if reader.u16(0x12)? != EM_RISCV {
+            return Err(invalid("not a RISC-V executable"));
+        }
+
+        // the header fields after e_entry are shifted by the width of addresses
+        let word = reader.word_size();
+        let entry = reader.word(0x18)?;
+        let phoff = reader.word(0x18 + word)?;
+        let phentsize = reader.u16(0x1E + 3 * word)? as u64;
+        let phnum = reader.u16(0x20 + 3 * word)? as u64;
+
+        let mut segments = vec![];
+        for index in 0..phnum {
+            let header = add(phoff, index * phentsize)?;
+            if reader.u32(header)? != PT_LOAD {
+                continue;
+            }
+
+            // ELF64 moves p_flags right after p_type
+            let (offset, paddr, filesz, memsz) = match xlen {
+                Xlen::Rv32 => (
+                    reader.word(header + 4)?,
+                    reader.word(header + 12)?,
+                    reader.word(header + 16)?,
+                    reader.word(header + 20)?,
+                ),
+                Xlen::Rv64 => (
+                    reader.word(header + 8)?,
+                    reader.word(header + 24)?,
+                    reader.word(header + 32)?,
+                    reader.word(header + 40)?,
+                ),
+            };
+
+            if filesz > memsz {
+                return Err(invalid("segment larger in the file than in memory"));
+            }
+
+            segments.push(Segment {
+                address: paddr,
+                size: memsz,
+                data: reader.bytes(offset, filesz)?.to_vec(),
+            });
+        }
+
+        Ok(Self {
+            xlen,
+            entry,
+            segments,
+            symbols: symbols(&reader)?,
+        })
+    }
+
+    // the symbol `address` falls in, if any
+    pub fn symbol(&self, address: Address) -> Option<&Symbol> {
+        lookup(&self.symbols, address)
+    }
+}
+
+pub(crate) fn lookup(symbols: &[Symbol], address: Address) -> Option<&Symbol> {
+    let index = symbols.partition_point(|symbol| symbol.address <= address);
+    let symbol = symbols.get(index.checked_sub(1)?)?;
+
+    match symbol.size {
+        0 => Some(symbol),
+        size => (address - symbol.address < size).then_some(symbol),
+    }
+}
+
+// the defined functions and labels of every symbol table
+fn symbols(reader: &Reader) -> io::Result<Vec<Symbol>> {
+    let word = reader.word_size();
+    let shoff = reader.word(0x18 + 2 * word)?;
+    let shentsize = reader.u16(0x22 + 3 * word)? as u64;
+    let shnum = reader.u16(0x24 + 3 * word)? as u64;
+    if shoff == 0 {
+        return Ok(vec![]);
+    }
+
+    // every offset below stays within the tables checked here
+    reader.bytes(shoff, shnum * shentsize)?;
+
+    // ELF64 widens sh_flags, which moves every field after it
+    let section = |index: u64| -> io::Result<(u32, u64, u64, u32)> {
+        let header = add(shoff, index * shentsize)?;
+        let (offset, size, link) = match reader.xlen {
+            Xlen::Rv32 => (header + 16, header + 20, header + 24),
+            Xlen::Rv64 => (header + 24, header + 32, header + 40),
+        };
+        Ok((
+            reader.u32(header + 4)?,
+            reader.word(offset)?,
+            reader.word(size)?,
+            reader.u32(link)?,
+        ))
+    };
+
+    let mut symbols = vec![];
+    for index in 0..shnum {
+        let (kind, offset, size, link) = section(index)?;
+        if kind != SHT_SYMTAB {
+            continue;
+        }
+        let (_, strings, _, _) = section(link as u64)?;
+        reader.bytes(offset, size)?;
+
+        // ELF64 moves st_value and st_size after st_info, st_other and st_shndx
+        let entry = match reader.xlen {
+            Xlen::Rv32 => 16,
+            Xlen::Rv64 => 24,
+        };
+        for symbol in (offset..add(offset, size)?).step_by(entry) {
+            let (info, shndx, value, size) = match reader.xlen {
+                Xlen::Rv32 => (
+                    reader.bytes(symbol + 12, 1)?[0],
+                    reader.u16(symbol + 14)?,
+                    reader.word(symbol + 4)?,
+                    reader.word(symbol + 8)?,
+                ),
+                Xlen::Rv64 => (
+                    reader.bytes(symbol + 4, 1)?[0],
+                    reader.u16(symbol + 6)?,
+                    reader.word(symbol + 8)?,
+                    reader.word(symbol + 16)?,
+                ),
+            };
+
+            if !matches!(info & 0x0F, STT_NOTYPE | STT_FUNC) || matches!(shndx, SHN_UNDEF | SHN_ABS)
+            {
+                continue;
+            }
+
+            // mapping symbols such as $x mark code and data rather than naming anything
+            let name = reader.string(add(strings, reader.u32(symbol)? as u64)?)?;
+            if name.is_empty() || name.starts_with('$') {
+                continue;
+            }
+
+            symbols.push(Symbol {
+                name,
+                address: value,
+                size,
+            });
+        }
+    }
+
+    symbols.sort_by_key(|symbol| symbol.address);
+    Ok(symbols)
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    xlen: Xlen,
+}
+
+impl Reader<'_> {
+    fn word_size(&self) -> u64 {
+        self.xlen.bits() as u64 / 8
+    }
+
+    fn bytes(&self, offset: u64, length: u64) -> io::Result<&[u8]> {
+        usize::try_from(offset)
+            .ok()
+            .zip(usize::try_from(length).ok())
+            .and_then(|(offset, length)| self.data.get(offset..offset.checked_add(length)?))
+            .ok_or_else(|| invalid("truncated ELF file"))
+    }
+
+    // up to the terminating NUL
+    fn string(&self, offset: u64) -> io::Result<String> {
+        let rest = usize::try_from(offset)
+            .ok()
+            .and_then(|offset| self.data.get(offset..))
+            .ok_or_else(|| invalid("truncated ELF file"))?;
+        let end = rest
+            .iter()
+            .position(|&byte| byte == 0)
+            .ok_or_else(|| invalid("unterminated string"))?;
+
+        Ok(String::from_utf8_lossy(&rest[..end]).into_owned())
+    }
+
+    fn u16(&self, offset: u64) -> io::Result<u16> {
+        Ok(u16::from_le_bytes(
+            self.bytes(offset, 2)?.try_into().unwrap(),
+        ))
+    }
+
+    fn u32(&self, offset: u64) -> io::Result<u32> {
+        Ok(u32::from_le_bytes(
+            self.bytes(offset, 4)?.try_into().unwrap(),
+        ))
+    }
+
+    // a field as wide as addresses
+    fn word(&self, offset: u64) -> io::Result<u64> {
+        match self.xlen {
+            Xlen::Rv32 => self.u32(offset).map(u64::from),
+            Xlen::Rv64 => Ok(u64::from_le_bytes(
+                self.bytes(offset, 8)?.try_into().unwrap(),
+            )),
+        }
+    }
+}
+
+// offsets read from the file can be anything, so sums of them may overflow
+fn add(offset: u64, delta: u64) -> io::Result<u64> {
+    offset
+        .checked_add(delta)
+        .ok_or_else(|| invalid("truncated ELF file"))
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
@@ -1,31 +1,485 @@
+use std::{io, mem, ops::Range, sync::Arc};
+
 use crate::{
-    bus::{Bus, RAM_BASE},
-    cpu::CPU,
+    bus::{Address, Bus, Device, RAM_BASE},
+    cpu::{Xlen, CPU},
+    debug::WatchKind,
     dram::DRAM,
+    elf::Elf,
     exception::RVException,
+    mmio::Mmio,
+    replay::{EventLog, Recording},
+    reverse::History,
+    snapshot::Snapshot,
 };
 
+// a7 value of the exit system call, as in the Linux ABI
+const SYS_EXIT: u64 = 93;
+
+/// Why a call to one of the execution control methods returned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    // the requested number of instructions was executed
+    LimitReached,
+
+    // execution reached a breakpoint or the requested pc, or hit an EBREAK
+    Breakpoint {
+        pc: Address,
+    },
+
+    // an access matched a watchpoint; pc is the instruction after the access
+    Watchpoint {
+        pc: Address,
+        address: Address,
+        kind: WatchKind,
+    },
+
+    // the predicate given to run_until_with held
+    Condition,
+
+    // the guest issued the exit system call
+    Exited {
+        code: u64,
+    },
+
+    // an exception the emulator cannot handle was raised
+    Fault {
+        exception: RVException,
+        pc: Address,
+        tval: u64,
+    },
+
+    // reverse execution ran out of recorded history
+    StartOfHistory,
+
+    // the hart waits for an interrupt that no other hart can raise anymore
+    Waiting {
+        pc: Address,
+    },
+
+    // a replayed run asked a device or the CLINT something the recorded one did not, or
+    // re-executing history for reverse execution did not retrace it
+    Diverged {
+        pc: Address,
+    },
+}
+
 pub struct Emulator {
     pub cpu: CPU,
+
+    // checkpoints for reverse execution, when enabled
+    pub history: Option<History>,
 }
 
 impl Emulator {
     pub fn new(ram_size: usize) -> Self {
-        let bus = Bus {
-            ram: DRAM::new(ram_size),
-        };
+        Self::with_xlen(ram_size, Xlen::Rv64)
+    }
 
-        Self { cpu: CPU::new(bus) }
+    pub fn with_xlen(ram_size: usize, xlen: Xlen) -> Self {
+        let bus = Bus::new(DRAM::new(ram_size), None);
+
+        Self {
+            cpu: CPU::with_xlen(bus, xlen),
+            history: None,
+        }
     }
 
     pub fn init_ram(&mut self, data: Vec<u8>) {
         self.cpu.bus.ram.initialize(data);
+        self.cpu.flush_caches();
         self.cpu.pc = RAM_BASE;
     }
 
+    // loads the segments of an executable and jumps to its entry point, in the width it was built for
+    pub fn load_elf(&mut self, data: &[u8]) -> io::Result<()> {
+        let elf = Elf::parse(data)?;
+
+        let ram_end = RAM_BASE + self.cpu.bus.ram.size() as Address;
+        let outside = elf.segments.iter().any(|segment| {
+            segment.address < RAM_BASE
+                || segment
+                    .address
+                    .checked_add(segment.size)
+                    .is_none_or(|end| end > ram_end)
+        });
+        if outside {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "segment outside of RAM",
+            ));
+        }
+
+        let ram = &self.cpu.bus.ram;
+        for segment in &elf.segments {
+            let start = (segment.address - RAM_BASE) as usize;
+            let bss = segment.size as usize - segment.data.len();
+
+            ram.write_bytes(start, &segment.data);
+            ram.write_bytes(start + segment.data.len(), &vec![0; bss]);
+        }
+
+        self.cpu.set_xlen(elf.xlen);
+        self.cpu.flush_caches();
+        self.cpu.pc = elf.entry;
+
+        Ok(())
+    }
+
+    // maps a device next to RAM, failing if the range is already taken
+    pub fn map_mmio(&mut self, base: Address, size: Address, device: Arc<dyn Mmio>) -> bool {
+        let mapped = self.cpu.bus.map(base, size, device);
+        self.cpu.flush_caches();
+        mapped
+    }
+
+    // reads physical memory through the bus, bypassing PMP, watchpoints and the misaligned policy
+    pub fn read_memory(&mut self, address: Address, data: &mut [u8]) -> Result<(), RVException> {
+        for (offset, byte) in data.iter_mut().enumerate() {
+            *byte = self
+                .cpu
+                .bus
+                .read::<u8>(address.wrapping_add(offset as Address))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_memory(&mut self, address: Address, data: &[u8]) -> Result<(), RVException> {
+        self.cpu.invalidate(address, data.len());
+        for (offset, &byte) in data.iter().enumerate() {
+            self.cpu
+                .bus
+                .write::<u8>(address.wrapping_add(offset as Address), byte)?;
+        }
+
+        Ok(())
+    }
+
     pub fn run(&mut self) -> Result<(), RVException> {
         loop {
-            self.cpu.fetch_and_execute()?;
+            self.cpu.run_for(u64::MAX)?;
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot::capture(self)
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) -> io::Result<()> {
+        snapshot.restore(self)
+    }
+
+    // logs what devices and the CLINT answer from here on, until finish_recording
+    pub fn start_recording(&mut self) {
+        self.cpu.log = Some(EventLog::recording());
+    }
+
+    pub fn finish_recording(&mut self) -> Option<Recording> {
+        if self.cpu.log.as_ref().is_none_or(EventLog::is_replaying) {
+            return None;
+        }
+
+        let entries = self.cpu.log.take().unwrap().into_entries();
+        Some(Recording {
+            events: vec![entries],
+            schedule: vec![],
+        })
+    }
+
+    // answers every access to a device or the CLINT from a recording from here on, without asking them
+    pub fn start_replay(&mut self, recording: &Recording) {
+        let entries = recording.events.first().cloned().unwrap_or_default();
+        self.cpu.log = Some(EventLog::replaying(entries));
+    }
+
+    // stops replaying, returning whether every recorded event was replayed exactly
+    pub fn finish_replay(&mut self) -> bool {
+        self.cpu.log.take().is_some_and(|log| log.is_complete())
+    }
+
+    // starts recording a checkpoint every `interval` instructions, which makes reverse execution possible
+    pub fn enable_reverse(&mut self, interval: u64) {
+        let snapshot = self.snapshot();
+        let mut history = History::new(interval);
+        history.record(snapshot.clone());
+        history.leave(snapshot);
+
+        self.history = Some(history);
+    }
+
+    pub fn disable_reverse(&mut self) {
+        self.history = None;
+    }
+
+    // goes back to the state before the last retired instruction
+    pub fn reverse_step(&mut self) -> StopReason {
+        self.resume();
+
+        let reason = match self.cpu.instret.checked_sub(1) {
+            Some(target) => match self.travel_to(target) {
+                Ok(()) => StopReason::LimitReached,
+                Err(reason) => reason,
+            },
+            None => StopReason::StartOfHistory,
+        };
+
+        self.leave();
+        reason
+    }
+
+    // goes back to the most recent point where a breakpoint or watchpoint stopped execution
+    pub fn reverse_continue(&mut self) -> StopReason {
+        self.resume();
+        let reason = self.find_previous_hit();
+        self.leave();
+
+        reason
+    }
+
+    fn find_previous_hit(&mut self) -> StopReason {
+        let current = self.cpu.instret;
+        let Some(mut index) = self
+            .history
+            .as_ref()
+            .and_then(|history| history.before(current))
+        else {
+            return StopReason::StartOfHistory;
+        };
+
+        let mut end = current;
+        loop {
+            let checkpoint = self.checkpoint(index);
+            let start = checkpoint.instret;
+
+            self.rewind(&checkpoint);
+            self.cpu.debugger.resume_from = None;
+
+            // the current state itself does not count as a hit
+            let hit = match self.replay_hits(end) {
+                Ok(hits) => hits.into_iter().rfind(|&(instret, _)| instret < current),
+                Err(reason) => return reason,
+            };
+
+            if let Some((instret, reason)) = hit {
+                self.rewind(&checkpoint);
+
+                return match self.replay(instret) {
+                    Ok(()) => reason,
+                    Err(reason) => reason,
+                };
+            }
+
+            if index == 0 {
+                self.rewind(&checkpoint);
+                return StopReason::StartOfHistory;
+            }
+
+            index -= 1;
+            end = start;
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, pc: Address) -> bool {
+        self.cpu.debugger.add_breakpoint(pc)
+    }
+
+    pub fn remove_breakpoint(&mut self, pc: Address) -> bool {
+        self.cpu.debugger.remove_breakpoint(pc)
+    }
+
+    pub fn add_watchpoint(&mut self, range: Range<Address>, kind: WatchKind) {
+        self.cpu.debugger.add_watchpoint(range, kind);
+    }
+
+    pub fn remove_watchpoint(&mut self, range: Range<Address>, kind: WatchKind) -> bool {
+        self.cpu.debugger.remove_watchpoint(range, kind)
+    }
+
+    pub fn step(&mut self) -> StopReason {
+        self.run_for(1)
+    }
+
+    pub fn run_for(&mut self, count: u64) -> StopReason {
+        // a breakpoint at the current pc has already been reported
+        self.cpu.debugger.resume_from = Some(self.cpu.pc);
+        self.resume();
+
+        let reason = match self.advance(count) {
+            Ok(()) => StopReason::LimitReached,
+            Err(ex) => self.stop_reason(ex),
+        };
+
+        self.leave();
+        reason
+    }
+
+    // runs until the instruction at `pc` is about to be executed, always executing at least one instruction
+    pub fn run_until(&mut self, pc: Address) -> StopReason {
+        let temporary = self.add_breakpoint(pc);
+        let reason = self.run_for(u64::MAX);
+
+        if temporary {
+            self.remove_breakpoint(pc);
+        }
+
+        reason
+    }
+
+    // runs until `predicate` holds after an instruction, always executing at least one instruction
+    pub fn run_until_with<F: FnMut(&CPU) -> bool>(&mut self, mut predicate: F) -> StopReason {
+        self.cpu.debugger.resume_from = Some(self.cpu.pc);
+        self.resume();
+
+        let reason = loop {
+            if let Err(ex) = self.advance(1) {
+                break self.stop_reason(ex);
+            }
+
+            if predicate(&self.cpu) {
+                break StopReason::Condition;
+            }
+        };
+
+        self.leave();
+        reason
+    }
+
+    // the host may have changed anything since execution last stopped
+    fn resume(&mut self) {
+        if let Some(mut history) = self.history.take() {
+            history.resume(self.snapshot());
+            self.history = Some(history);
+        }
+    }
+
+    fn leave(&mut self) {
+        if let Some(mut history) = self.history.take() {
+            history.leave(self.snapshot());
+            self.history = Some(history);
+        }
+    }
+
+    // runs the CPU, stopping to record checkpoints as they become due
+    fn advance(&mut self, count: u64) -> Result<(), RVException> {
+        let limit = self.cpu.instret.saturating_add(count);
+
+        loop {
+            let Some(due) = self.history.as_ref().map(History::next_due) else {
+                return self.cpu.run_for(limit - self.cpu.instret);
+            };
+
+            if due <= self.cpu.instret {
+                let snapshot = self.snapshot();
+                if let Some(history) = &mut self.history {
+                    history.record(snapshot);
+                }
+                continue;
+            }
+
+            self.cpu.run_for(limit.min(due) - self.cpu.instret)?;
+
+            if self.cpu.instret >= limit {
+                return Ok(());
+            }
+        }
+    }
+
+    fn checkpoint(&self, index: usize) -> Snapshot {
+        self.history
+            .as_ref()
+            .expect("reverse execution is disabled")
+            .checkpoints()[index]
+            .clone()
+    }
+
+    // restores the state right after `target` instructions retired, if it is still recorded
+    fn travel_to(&mut self, target: u64) -> Result<(), StopReason> {
+        let Some(index) = self
+            .history
+            .as_ref()
+            .and_then(|history| history.before(target))
+        else {
+            return Err(StopReason::StartOfHistory);
+        };
+
+        let checkpoint = self.checkpoint(index);
+        self.rewind(&checkpoint);
+        self.replay(target)
+    }
+
+    // checkpoints were taken from this machine, so they only fail to restore into reconfigured models
+    fn rewind(&mut self, checkpoint: &Snapshot) {
+        if let Err(error) = checkpoint.restore(self) {
+            panic!("cannot travel back: {error}");
+        }
+    }
+
+    // re-executes recorded history up to `target`, ignoring breakpoints and watchpoints
+    fn replay(&mut self, target: u64) -> Result<(), StopReason> {
+        let debugger = mem::take(&mut self.cpu.debugger);
+        let result = self.cpu.run_for(target - self.cpu.instret);
+        self.cpu.debugger = debugger;
+
+        match result {
+            Ok(()) if self.cpu.instret == target => Ok(()),
+            _ => Err(StopReason::Diverged { pc: self.cpu.pc }),
         }
     }
+
+    // re-executes recorded history up to `end`, collecting where each breakpoint or watchpoint stopped it
+    fn replay_hits(&mut self, end: u64) -> Result<Vec<(u64, StopReason)>, StopReason> {
+        let mut hits = vec![];
+
+        while self.cpu.instret < end {
+            let Err(ex) = self.cpu.run_for(end - self.cpu.instret) else {
+                break;
+            };
+
+            match self.stop_reason(ex) {
+                reason @ (StopReason::Breakpoint { .. } | StopReason::Watchpoint { .. }) => {
+                    hits.push((self.cpu.instret, reason));
+                }
+
+                _ => return Err(StopReason::Diverged { pc: self.cpu.pc }),
+            }
+
+            self.cpu.debugger.resume_from = Some(self.cpu.pc);
+        }
+
+        match self.cpu.instret == end {
+            true => Ok(hits),
+            false => Err(StopReason::Diverged { pc: self.cpu.pc }),
+        }
+    }
+
+    fn stop_reason(&mut self, exception: RVException) -> StopReason {
+        stop_reason(&mut self.cpu, exception)
+    }
+}
+
+pub(crate) fn stop_reason(cpu: &mut CPU, exception: RVException) -> StopReason {
+    match exception {
+        _ if cpu.diverged() => StopReason::Diverged { pc: cpu.pc },
+
+        RVException::EnvironmentCall if cpu.xregs[17] == SYS_EXIT => StopReason::Exited {
+            code: cpu.xregs[10],
+        },
+
+        RVException::Breakpoint => match cpu.debugger.last_hit.take() {
+            Some(hit) => StopReason::Watchpoint {
+                pc: cpu.pc,
+                address: hit.address,
+                kind: hit.kind,
+            },
+
+            None => StopReason::Breakpoint { pc: cpu.pc },
+        },
+
+        _ => StopReason::Fault {
+            exception,
+            pc: cpu.pc,
+            tval: cpu.tval,
+        },
+    }
 }
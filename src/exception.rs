@@ -1,10 +1,34 @@
-#[derive(Debug)]
+use crate::cpu::Privilege;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum RVException {
+    InstructionAddressMisaligned,
+    InstructionAccessFault,
     StoreAccessFault,
     LoadAccessFault,
 
+    LoadAddressMisaligned,
+    StoreAddressMisaligned,
+
     IllegalInstruction,
 
     EnvironmentCall,
     Breakpoint,
 }
+
+impl RVException {
+    // the mcause exception code, environment calls being numbered after the level they come from
+    pub fn code(self, privilege: Privilege) -> u64 {
+        match self {
+            RVException::InstructionAddressMisaligned => 0,
+            RVException::InstructionAccessFault => 1,
+            RVException::IllegalInstruction => 2,
+            RVException::Breakpoint => 3,
+            RVException::LoadAddressMisaligned => 4,
+            RVException::LoadAccessFault => 5,
+            RVException::StoreAddressMisaligned => 6,
+            RVException::StoreAccessFault => 7,
+            RVException::EnvironmentCall => 8 + privilege as u64,
+        }
+    }
+}
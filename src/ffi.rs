@@ -0,0 +1,261 @@
+//! The C interface, declared in `include/risemu.h`.
+//!
+//! Every function taking an emulator expects a pointer returned by
+//! `risemu_create` and not yet destroyed, and buffers must be valid for the
+//! given length. An emulator may be used from any thread, one at a time.
+
+#![allow(clippy::missing_safety_doc)]
+
+use std::{ffi::c_void, ptr, slice, sync::Arc};
+
+use crate::{
+    bus::{Address, Device},
+    cpu::Xlen,
+    debug::WatchKind,
+    emulator::{Emulator, StopReason},
+    mmio::Mmio,
+};
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopKind {
+    LimitReached,
+    Breakpoint,
+    Watchpoint,
+    Condition,
+    Exited,
+    Fault,
+    StartOfHistory,
+    Waiting,
+    Diverged,
+}
+
+/// Why execution stopped. `value` holds the exit code, the trap value of a
+/// fault or the address that hit a watchpoint, while `cause` holds the
+/// exception code of a fault or the access kind of a watchpoint (0 for reads,
+/// 1 for writes).
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stop {
+    pub kind: StopKind,
+    pub pc: u64,
+    pub value: u64,
+    pub cause: u32,
+}
+
+// reads store the value through the last argument; returning false raises an access fault
+pub type MmioRead = Option<
+    unsafe extern "C" fn(context: *mut c_void, offset: u64, size: u32, value: *mut u64) -> bool,
+>;
+pub type MmioWrite =
+    Option<unsafe extern "C" fn(context: *mut c_void, offset: u64, size: u32, value: u64) -> bool>;
+
+struct Callbacks {
+    read: MmioRead,
+    write: MmioWrite,
+    context: *mut c_void,
+}
+
+// synchronising the context is up to the embedder
+unsafe impl Send for Callbacks {}
+unsafe impl Sync for Callbacks {}
+
+impl Mmio for Callbacks {
+    fn read(&self, offset: Address, size: usize) -> Option<u64> {
+        let mut value = 0;
+        let read = self.read?;
+
+        unsafe { read(self.context, offset, size as u32, &mut value) }.then_some(value)
+    }
+
+    fn write(&self, offset: Address, size: usize, value: u64) -> bool {
+        self.write
+            .is_some_and(|write| unsafe { write(self.context, offset, size as u32, value) })
+    }
+}
+
+/// Creates an emulator with `ram_size` bytes of RAM and 32 or 64-bit
+/// registers, or returns NULL for any other width.
+#[no_mangle]
+pub extern "C" fn risemu_create(ram_size: usize, xlen: u32) -> *mut Emulator {
+    let xlen = match xlen {
+        32 => Xlen::Rv32,
+        64 => Xlen::Rv64,
+
+        _ => return ptr::null_mut(),
+    };
+
+    Box::into_raw(Box::new(Emulator::with_xlen(ram_size, xlen)))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn risemu_destroy(emu: *mut Emulator) {
+    if !emu.is_null() {
+        drop(Box::from_raw(emu));
+    }
+}
+
+/// Copies a raw image to the start of RAM and points pc at it.
+#[no_mangle]
+pub unsafe extern "C" fn risemu_load_image(
+    emu: *mut Emulator,
+    data: *const u8,
+    length: usize,
+) -> bool {
+    let emu = &mut *emu;
+    if length > emu.cpu.bus.ram.size() {
+        return false;
+    }
+
+    emu.init_ram(bytes(data, length).to_vec());
+    true
+}
+
+/// Loads an ELF executable, switching to the register width it was built for.
+#[no_mangle]
+pub unsafe extern "C" fn risemu_load_elf(
+    emu: *mut Emulator,
+    data: *const u8,
+    length: usize,
+) -> bool {
+    (*emu).load_elf(bytes(data, length)).is_ok()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn risemu_step(emu: *mut Emulator) -> Stop {
+    stop(&mut *emu, (*emu).step())
+}
+
+/// Runs at most `limit` instructions.
+#[no_mangle]
+pub unsafe extern "C" fn risemu_run(emu: *mut Emulator, limit: u64) -> Stop {
+    stop(&mut *emu, (*emu).run_for(limit))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn risemu_get_register(emu: *const Emulator, index: u32) -> u64 {
+    let emu = &*emu;
+    emu.cpu
+        .xregs
+        .get(index as usize)
+        .map_or(0, |&value| emu.cpu.xlen().zext(value))
+}
+
+/// Returns false for indices past 31; writes to x0 are ignored.
+#[no_mangle]
+pub unsafe extern "C" fn risemu_set_register(emu: *mut Emulator, index: u32, value: u64) -> bool {
+    let cpu = &mut (*emu).cpu;
+    if index >= 32 {
+        return false;
+    }
+
+    if index != 0 {
+        cpu.xregs[index as usize] = cpu.xlen().sext(value);
+    }
+    true
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn risemu_get_pc(emu: *const Emulator) -> u64 {
+    (*emu).cpu.pc
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn risemu_set_pc(emu: *mut Emulator, pc: u64) {
+    let cpu = &mut (*emu).cpu;
+    cpu.pc = cpu.xlen().zext(pc);
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn risemu_get_instret(emu: *const Emulator) -> u64 {
+    (*emu).cpu.instret
+}
+
+/// Reads physical memory, RAM or devices, failing if any byte is unmapped.
+#[no_mangle]
+pub unsafe extern "C" fn risemu_read_memory(
+    emu: *mut Emulator,
+    address: u64,
+    data: *mut u8,
+    length: usize,
+) -> bool {
+    if length == 0 {
+        return true;
+    }
+
+    (*emu)
+        .read_memory(address, slice::from_raw_parts_mut(data, length))
+        .is_ok()
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn risemu_write_memory(
+    emu: *mut Emulator,
+    address: u64,
+    data: *const u8,
+    length: usize,
+) -> bool {
+    (*emu).write_memory(address, bytes(data, length)).is_ok()
+}
+
+/// Maps `size` bytes at `base` to a device implemented by the callbacks,
+/// failing if the range overlaps RAM or another device. A NULL callback
+/// makes the corresponding accesses fault.
+#[no_mangle]
+pub unsafe extern "C" fn risemu_map_mmio(
+    emu: *mut Emulator,
+    base: u64,
+    size: u64,
+    read: MmioRead,
+    write: MmioWrite,
+    context: *mut c_void,
+) -> bool {
+    let device = Callbacks {
+        read,
+        write,
+        context,
+    };
+
+    (*emu).map_mmio(base, size, Arc::new(device))
+}
+
+// a NULL pointer is fine for empty buffers
+unsafe fn bytes<'a>(data: *const u8, length: usize) -> &'a [u8] {
+    match length {
+        0 => &[],
+        _ => slice::from_raw_parts(data, length),
+    }
+}
+
+pub(crate) fn stop(emu: &mut Emulator, reason: StopReason) -> Stop {
+    let privilege = emu.cpu.privilege();
+    let (kind, pc, value, cause) = match reason {
+        StopReason::LimitReached => (StopKind::LimitReached, emu.cpu.pc, 0, 0),
+        StopReason::Breakpoint { pc } => (StopKind::Breakpoint, pc, 0, 0),
+        StopReason::Watchpoint { pc, address, kind } => {
+            let kind = match kind {
+                WatchKind::Read => 0,
+                _ => 1,
+            };
+
+            (StopKind::Watchpoint, pc, address, kind)
+        }
+        StopReason::Condition => (StopKind::Condition, emu.cpu.pc, 0, 0),
+        StopReason::Exited { code } => (StopKind::Exited, emu.cpu.pc, code, 0),
+        StopReason::Fault {
+            exception,
+            pc,
+            tval,
+        } => (StopKind::Fault, pc, tval, exception.code(privilege) as u32),
+        StopReason::StartOfHistory => (StopKind::StartOfHistory, emu.cpu.pc, 0, 0),
+        StopReason::Waiting { pc } => (StopKind::Waiting, pc, 0, 0),
+        StopReason::Diverged { pc } => (StopKind::Diverged, pc, 0, 0),
+    };
+
+    Stop {
+        kind,
+        pc,
+        value,
+        cause,
+    }
+}
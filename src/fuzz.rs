@@ -0,0 +1,438 @@
+use arbitrary::{Arbitrary, Result, Unstructured};
+
+use crate::{
+    bus::{Address, RAM_BASE},
+    cpu::Xlen,
+    decoder::{decode, decode_compressed},
+    emulator::{Emulator, StopReason},
+    exception::RVException,
+};
+
+pub const RAM_SIZE: usize = 0x4000;
+
+// more than the code could hold, so that loops and faults are exercised but every run is bounded
+const MAX_INSTRUCTIONS: usize = 256;
+const STEP_LIMIT: u64 = 4096;
+
+/// An initial register file and instruction stream, run from the start of
+/// RAM by [`Program::check`].
+///
+/// The [`Arbitrary`] implementation takes instruction words as they come,
+/// while [`Structured`] mostly produces valid encodings.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Program {
+    pub xlen: Xlen,
+    pub xregs: [u64; 32],
+    pub instructions: Vec<u32>,
+}
+
+/// A [`Program`] made of mostly valid instructions, with memory accesses
+/// based on registers pointing into RAM and close to its end.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Structured(pub Program);
+
+impl<'a> Arbitrary<'a> for Program {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let xlen = if u.arbitrary()? {
+            Xlen::Rv32
+        } else {
+            Xlen::Rv64
+        };
+        let xregs = u.arbitrary()?;
+
+        let mut instructions = vec![];
+        while instructions.len() < MAX_INSTRUCTIONS && !u.is_empty() {
+            instructions.push(u.arbitrary()?);
+        }
+
+        Ok(Self {
+            xlen,
+            xregs,
+            instructions,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Structured {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let xlen = if u.ratio(1, 4)? {
+            Xlen::Rv32
+        } else {
+            Xlen::Rv64
+        };
+
+        let mut xregs = [0; 32];
+        for value in xregs.iter_mut() {
+            *value = interesting(u)?;
+        }
+        for &register in &POINTERS {
+            let offset = u.int_in_range(0..=RAM_SIZE as u64 - 1)?;
+            xregs[register as usize] = match u.ratio(1, 3)? {
+                // close enough to the end for an access to cross it
+                true => RAM_BASE + RAM_SIZE as u64 - (offset & 0x0F),
+                false => RAM_BASE + offset,
+            };
+        }
+
+        let mut instructions = vec![];
+        while instructions.len() < MAX_INSTRUCTIONS && !u.is_empty() {
+            instructions.push(instruction(u, xlen)?);
+        }
+
+        Ok(Self(Program {
+            xlen,
+            xregs,
+            instructions,
+        }))
+    }
+}
+
+impl Program {
+    /// Runs the program, skipping over the instructions that trap, and
+    /// panics if an invariant breaks: x0 must read zero, registers must stay
+    /// sign-extended on RV32, the pc aligned and the instruction count within
+    /// the limit, and the decode cache, block cache and JIT must not change
+    /// the outcome.
+    pub fn check(&self) {
+        let reference = self.run(false);
+        let cached = self.run(true);
+
+        assert_eq!(reference.stops, cached.stops, "{self:x?}");
+        assert_eq!(reference.xregs, cached.xregs, "{self:x?}");
+        assert_eq!(reference.pc, cached.pc, "{self:x?}");
+        assert_eq!(reference.instret, cached.instret, "{self:x?}");
+        assert!(reference.memory == cached.memory, "{self:x?}");
+    }
+
+    fn run(&self, cached: bool) -> Outcome {
+        let mut emu = Emulator::with_xlen(RAM_SIZE, self.xlen);
+        emu.cpu.set_decode_cache(cached);
+        emu.cpu.set_block_cache(cached);
+
+        #[cfg(feature = "jit")]
+        emu.cpu.set_jit_threshold(if cached { 0 } else { u64::MAX });
+
+        emu.init_ram(
+            self.instructions
+                .iter()
+                .flat_map(|word| word.to_le_bytes())
+                .collect(),
+        );
+        for (register, &value) in self.xregs.iter().enumerate().skip(1) {
+            emu.cpu.xregs[register] = self.xlen.sext(value);
+        }
+
+        // faults do not retire, so they count towards the limit separately
+        let mut stops = vec![];
+        while emu.cpu.instret < STEP_LIMIT && (stops.len() as u64) < STEP_LIMIT {
+            let reason = emu.run_for(STEP_LIMIT - emu.cpu.instret);
+            self.check_state(&emu);
+
+            match reason {
+                StopReason::Fault { .. } | StopReason::Breakpoint { .. } => {
+                    stops.push(reason);
+                    emu.cpu.pc = emu.cpu.pc.wrapping_add(4);
+                }
+
+                StopReason::LimitReached => {
+                    assert_eq!(emu.cpu.instret, STEP_LIMIT, "{self:x?}");
+                    break;
+                }
+
+                _ => {
+                    stops.push(reason);
+                    break;
+                }
+            }
+        }
+
+        Outcome {
+            stops,
+            xregs: emu.cpu.xregs,
+            pc: emu.cpu.pc,
+            instret: emu.cpu.instret,
+            memory: emu.cpu.bus.ram.contents(),
+        }
+    }
+
+    fn check_state(&self, emu: &Emulator) {
+        let cpu = &emu.cpu;
+
+        assert_eq!(cpu.xregs[0], 0, "x0 was written: {self:x?}");
+        assert!(
+            cpu.pc & cpu.alignment_mask() == 0,
+            "misaligned pc {:#x}: {self:x?}",
+            cpu.pc
+        );
+        assert!(cpu.instret <= STEP_LIMIT, "{self:x?}");
+
+        // misa may have changed the width since the start
+        for (register, &value) in cpu.xregs.iter().enumerate() {
+            assert_eq!(value, cpu.xlen().sext(value), "x{register}: {self:x?}");
+        }
+    }
+}
+
+struct Outcome {
+    stops: Vec<StopReason>,
+    xregs: [u64; 32],
+    pc: Address,
+    instret: u64,
+    memory: Vec<u8>,
+}
+
+// decoding any word at either width returns, and only fails with an illegal instruction, as
+// does decoding its lower half as a compressed one
+pub fn check_decode(word: u32) {
+    for xlen in [Xlen::Rv32, Xlen::Rv64] {
+        if let Err(exception) = decode(word, xlen) {
+            assert_eq!(exception, RVException::IllegalInstruction, "{word:#010x}");
+        }
+
+        if let Err(exception) = decode_compressed(word as u16, xlen) {
+            assert_eq!(exception, RVException::IllegalInstruction, "{word:#010x}");
+        }
+    }
+}
+
+// registers the structured generator bases memory accesses on
+const POINTERS: [u32; 3] = [8, 9, 18];
+
+// funct7, funct3 and opcode of every register-register operation
+const REGISTER_OPS: [(u32, u32, u32); 53] = [
+    (0b0000000, 0b000, 0x33),
+    (0b0100000, 0b000, 0x33),
+    (0b0000000, 0b001, 0x33),
+    (0b0000000, 0b010, 0x33),
+    (0b0000000, 0b011, 0x33),
+    (0b0000000, 0b100, 0x33),
+    (0b0000000, 0b101, 0x33),
+    (0b0100000, 0b101, 0x33),
+    (0b0000000, 0b110, 0x33),
+    (0b0000000, 0b111, 0x33),
+    (0b0000001, 0b000, 0x33),
+    (0b0000001, 0b001, 0x33),
+    (0b0000001, 0b010, 0x33),
+    (0b0000001, 0b011, 0x33),
+    (0b0000001, 0b100, 0x33),
+    (0b0000001, 0b101, 0x33),
+    (0b0000001, 0b110, 0x33),
+    (0b0000001, 0b111, 0x33),
+    (0b0010000, 0b010, 0x33),
+    (0b0010000, 0b100, 0x33),
+    (0b0010000, 0b110, 0x33),
+    (0b0100000, 0b111, 0x33),
+    (0b0100000, 0b110, 0x33),
+    (0b0100000, 0b100, 0x33),
+    (0b0000101, 0b100, 0x33),
+    (0b0000101, 0b101, 0x33),
+    (0b0000101, 0b110, 0x33),
+    (0b0000101, 0b111, 0x33),
+    (0b0110000, 0b001, 0x33),
+    (0b0110000, 0b101, 0x33),
+    (0b0000101, 0b001, 0x33),
+    (0b0000101, 0b010, 0x33),
+    (0b0000101, 0b011, 0x33),
+    (0b0100100, 0b001, 0x33),
+    (0b0100100, 0b101, 0x33),
+    (0b0110100, 0b001, 0x33),
+    (0b0010100, 0b001, 0x33),
+    (0b0000000, 0b000, 0x3B),
+    (0b0100000, 0b000, 0x3B),
+    (0b0000000, 0b001, 0x3B),
+    (0b0000000, 0b101, 0x3B),
+    (0b0100000, 0b101, 0x3B),
+    (0b0000001, 0b000, 0x3B),
+    (0b0000001, 0b100, 0x3B),
+    (0b0000001, 0b101, 0x3B),
+    (0b0000001, 0b110, 0x3B),
+    (0b0000001, 0b111, 0x3B),
+    (0b0000100, 0b000, 0x3B),
+    (0b0010000, 0b010, 0x3B),
+    (0b0010000, 0b100, 0x3B),
+    (0b0010000, 0b110, 0x3B),
+    (0b0110000, 0b001, 0x3B),
+    (0b0110000, 0b101, 0x3B),
+];
+
+// the upper immediate bits and funct3 of the shifts by an immediate, and of the Zbb unary operations
+const SHIFTS: [(u32, u32); 8] = [
+    (0b000000, 0b001),
+    (0b000000, 0b101),
+    (0b010000, 0b101),
+    (0b011000, 0b101),
+    (0b010010, 0b001),
+    (0b010010, 0b101),
+    (0b011010, 0b001),
+    (0b001010, 0b001),
+];
+const UNARY: [(u32, u32); 9] = [
+    (0x600, 0b001),
+    (0x601, 0b001),
+    (0x602, 0b001),
+    (0x604, 0b001),
+    (0x605, 0b001),
+    (0x287, 0b101),
+    (0x6B8, 0b101),
+    (0x698, 0b101),
+    (0x080, 0b100),
+];
+
+const CSRS: [u32; 12] = [
+    0x300, 0x301, 0x341, 0x344, 0xF14, 0x3A0, 0x3A1, 0x3B0, 0x3B1, 0x008, 0xC20, 0xC22,
+];
+
+// funct5 of the AMOs, LR and SC
+const AMOS: [u32; 11] = [
+    0b00010, 0b00011, 0b00001, 0b00000, 0b00100, 0b01100, 0b01000, 0b10000, 0b10100, 0b11000,
+    0b11100,
+];
+
+fn interesting(u: &mut Unstructured) -> Result<u64> {
+    Ok(match u.int_in_range(0..=5)? {
+        0 => 0,
+        1 => u.int_in_range(0..=16)?,
+        2 => (-(u.int_in_range(1..=16)? as i64)) as u64,
+        3 => *u.choose(&[
+            i64::MIN as u64,
+            i64::MAX as u64,
+            i32::MIN as u64,
+            i32::MAX as u64,
+            u32::MAX as u64,
+            1 << 31,
+        ])?,
+        _ => u.arbitrary()?,
+    })
+}
+
+fn register(u: &mut Unstructured) -> Result<u32> {
+    u.int_in_range(0..=31)
+}
+
+fn base(u: &mut Unstructured) -> Result<u32> {
+    match u.ratio(7, 8)? {
+        true => Ok(*u.choose(&POINTERS)?),
+        false => register(u),
+    }
+}
+
+// a small offset, mostly a multiple of four so that control flow stays in the program
+fn offset(u: &mut Unstructured, bits: u32) -> Result<i64> {
+    let bound = 1i64 << (bits - 1);
+    match u.ratio(15, 16)? {
+        true => Ok(4 * u.int_in_range(-16..=16)?),
+        false => u.int_in_range(-bound..=bound - 1),
+    }
+}
+
+fn instruction(u: &mut Unstructured, xlen: Xlen) -> Result<u32> {
+    let rd = register(u)?;
+    let rs1 = register(u)?;
+    let rs2 = register(u)?;
+    let shamt = u.int_in_range(0..=xlen.bits() - 1)?;
+
+    let i_type = |imm: i64, rs1: u32, funct3: u32, rd: u32, opcode: u32| {
+        ((imm as u32 & 0xFFF) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+    };
+    let r_type = |funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32| {
+        (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+    };
+
+    Ok(match u.int_in_range(0..=15)? {
+        0..=3 => {
+            let &(funct7, funct3, opcode) = u.choose(&REGISTER_OPS)?;
+            r_type(funct7, rs2, rs1, funct3, rd, opcode)
+        }
+
+        4 | 5 => {
+            let funct3 = *u.choose(&[0b000, 0b010, 0b011, 0b100, 0b110, 0b111])?;
+            let opcode = if u.ratio(1, 4)? { 0x1B } else { 0x13 };
+            i_type(offset(u, 12)?, rs1, funct3, rd, opcode)
+        }
+
+        6 => {
+            let &(funct6, funct3) = u.choose(&SHIFTS)?;
+            i_type(((funct6 << 6) | shamt) as i64, rs1, funct3, rd, 0x13)
+        }
+
+        7 => {
+            let &(funct12, funct3) = u.choose(&UNARY)?;
+            let opcode = *u.choose(&[0x13, 0x1B, 0x33, 0x3B])?;
+            i_type(funct12 as i64, rs1, funct3, rd, opcode)
+        }
+
+        // loads and stores
+        8 | 9 => {
+            let imm = offset(u, 12)?;
+            match u.arbitrary()? {
+                true => i_type(imm, base(u)?, u.int_in_range(0..=6)?, rd, 0x03),
+                false => {
+                    let imm = imm as u32;
+                    ((imm >> 5 & 0x7F) << 25)
+                        | (rs2 << 20)
+                        | (base(u)? << 15)
+                        | (u.int_in_range(0..=3)? << 12)
+                        | ((imm & 0x1F) << 7)
+                        | 0x23
+                }
+            }
+        }
+
+        // branches and jumps
+        10 => {
+            let offset = offset(u, 13)? as u32;
+            let funct3 = *u.choose(&[0b000, 0b001, 0b100, 0b101, 0b110, 0b111])?;
+            ((offset >> 12 & 1) << 31)
+                | ((offset >> 5 & 0x3F) << 25)
+                | (rs2 << 20)
+                | (rs1 << 15)
+                | (funct3 << 12)
+                | ((offset >> 1 & 0xF) << 8)
+                | ((offset >> 11 & 1) << 7)
+                | 0x63
+        }
+        11 => match u.arbitrary()? {
+            true => {
+                let offset = offset(u, 21)? as u32;
+                ((offset >> 20 & 1) << 31)
+                    | ((offset >> 1 & 0x3FF) << 21)
+                    | ((offset >> 11 & 1) << 20)
+                    | ((offset >> 12 & 0xFF) << 12)
+                    | (rd << 7)
+                    | 0x6F
+            }
+            false => i_type(offset(u, 12)?, base(u)?, 0b000, rd, 0x67),
+        },
+
+        12 => {
+            let opcode = *u.choose(&[0x37, 0x17])?;
+            (u.int_in_range(0..=0xFFFFF)? << 12) | (rd << 7) | opcode
+        }
+
+        13 => {
+            let csr = *u.choose(&CSRS)?;
+            let funct3 = *u.choose(&[0b001, 0b010, 0b011, 0b101, 0b110, 0b111])?;
+            i_type(csr as i64, rs1, funct3, rd, 0x73)
+        }
+
+        14 => {
+            let funct5 = *u.choose(&AMOS)?;
+            let funct3 = *u.choose(&[0b010, 0b011])?;
+            let ordering = u.int_in_range(0..=3)?;
+            r_type((funct5 << 2) | ordering, rs2, base(u)?, funct3, rd, 0x2F)
+        }
+
+        _ => match u.int_in_range(0..=7)? {
+            0 => 0x00000073, // ecall
+            1 => 0x00100073, // ebreak
+            2 => 0x30200073, // mret
+            3 => 0x10500073, // wfi
+            4 => 0x0FF0000F, // fence
+            5 => 0x0000100F, // fence.i
+            6 => {
+                (u.int_in_range(0..=0x7FF)? << 20) | (rs1 << 15) | (0b111 << 12) | (rd << 7) | 0x57
+            } // vsetvli
+            _ => u.arbitrary()?,
+        },
+    })
+}
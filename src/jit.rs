@@ -0,0 +1,588 @@
+#[cfg(not(all(target_arch = "x86_64", target_os = "linux")))]
+compile_error!("the `jit` feature is only supported on x86-64 Linux");
+
+use std::ptr;
+
+use crate::{
+    bus::{Address, Device, RAM_BASE},
+    cpu::CPU,
+    decoder::{AluOp, BranchOp, Instruction, LoadOp, StoreOp},
+    exception::RVException,
+    pmp::Access,
+};
+
+// compiled code returns the next pc along with how many instructions it retired
+pub type Entry = unsafe extern "sysv64" fn(context: *mut Context) -> Exit;
+
+#[repr(C)]
+pub struct Exit {
+    pub pc: u64,
+    pub retired: u64,
+}
+
+/// What compiled code works on: the guest registers, RAM for loads to read
+/// directly and the hart for everything else.
+#[repr(C)]
+pub struct Context {
+    xregs: *mut u64,
+    ram: *const u8,
+
+    // loads from offsets below this read RAM directly; zero while PMP may deny them
+    ram_limit: u64,
+
+    cpu: *mut CPU,
+    fault: Option<RVException>,
+}
+
+// field offsets, as compiled code reaches them from rbx
+const XREGS: u8 = 0;
+const RAM: u8 = 8;
+const RAM_LIMIT: u8 = 16;
+
+impl Context {
+    // `cpu` must outlive the context, and be left alone for as long as compiled code runs on it
+    pub(crate) unsafe fn new(cpu: *mut CPU) -> Self {
+        let (ram, size, readable) = unsafe {
+            let ram = (*cpu).bus.ram();
+            let readable = (*cpu).permits(RAM_BASE, ram.size(), Access::Read);
+            (ram.base(), ram.size() as u64, readable)
+        };
+
+        Self {
+            xregs: unsafe { (*cpu).xregs.as_mut_ptr() },
+            ram,
+            ram_limit: match readable {
+                true => size.saturating_sub(7),
+                false => 0,
+            },
+            cpu,
+            fault: None,
+        }
+    }
+
+    // the exception compiled code stopped at, if any
+    pub(crate) fn fault(&self) -> Option<RVException> {
+        self.fault
+    }
+}
+
+/// Host code for the longest prefix of a block made only of instructions the
+/// JIT knows how to emit. Anything touching the system falls back to the
+/// interpreter, which resumes right after the instructions compiled code
+/// retired. Loads read RAM directly when they can, while every other access,
+/// and every store so that the code it overwrites is dropped, goes through
+/// the hart.
+pub struct NativeBlock {
+    code: *mut u8,
+    size: usize,
+
+    // number of guest instructions covered
+    pub length: usize,
+}
+
+impl NativeBlock {
+    pub fn entry(&self) -> Entry {
+        unsafe { std::mem::transmute::<*mut u8, Entry>(self.code) }
+    }
+}
+
+// the code is owned exclusively and never written once compiled
+unsafe impl Send for NativeBlock {}
+
+impl Drop for NativeBlock {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.code as *mut libc::c_void, self.size);
+        }
+    }
+}
+
+pub fn compile(start: Address, instructions: &[Instruction]) -> Option<NativeBlock> {
+    let mut emitter = Emitter::default();
+    emitter.prologue();
+
+    let mut pc = start;
+    let mut length = 0;
+    let mut terminated = false;
+    for instruction in instructions {
+        let emitted = emitter.code.len();
+        match emitter.instruction(length, pc, instruction) {
+            Some(ends) => {
+                length += 1;
+                if ends {
+                    terminated = true;
+                    break;
+                }
+            }
+
+            // drop whatever was emitted before the instruction turned out not to compile
+            None => {
+                emitter.code.truncate(emitted);
+                break;
+            }
+        }
+
+        pc += 4;
+    }
+
+    if length == 0 {
+        return None;
+    }
+
+    if !terminated {
+        emitter.mov_rax_imm(pc);
+        emitter.exit(length);
+    }
+
+    emitter.finish(length)
+}
+
+// the status of an access made on behalf of compiled code
+const DONE: u64 = 0;
+const FAULT: u64 = 1;
+
+// the access went through, but dropped translated code
+const DROPPED: u64 = 2;
+
+#[repr(C)]
+struct Outcome {
+    value: u64,
+    status: u64,
+}
+
+// in declaration order, as compiled code passes `op as u32`
+const LOADS: [LoadOp; 7] = [
+    LoadOp::Lb,
+    LoadOp::Lh,
+    LoadOp::Lw,
+    LoadOp::Ld,
+    LoadOp::Lbu,
+    LoadOp::Lhu,
+    LoadOp::Lwu,
+];
+const STORES: [StoreOp; 4] = [StoreOp::Sb, StoreOp::Sh, StoreOp::Sw, StoreOp::Sd];
+
+unsafe extern "sysv64" fn load(context: *mut Context, address: u64, op: u32) -> Outcome {
+    let context = unsafe { &mut *context };
+    let cpu = unsafe { &mut *context.cpu };
+
+    match cpu.load(LOADS[op as usize], address) {
+        Ok(value) => Outcome {
+            value,
+            status: DONE,
+        },
+
+        Err(ex) => {
+            context.fault = Some(ex);
+            Outcome {
+                value: 0,
+                status: FAULT,
+            }
+        }
+    }
+}
+
+unsafe extern "sysv64" fn store(
+    context: *mut Context,
+    address: u64,
+    value: u64,
+    op: u32,
+) -> Outcome {
+    let context = unsafe { &mut *context };
+    let cpu = unsafe { &mut *context.cpu };
+
+    let status = match cpu.store(STORES[op as usize], address, value) {
+        Ok(()) if cpu.block_invalidated => DROPPED,
+        Ok(()) => DONE,
+
+        Err(ex) => {
+            context.fault = Some(ex);
+            FAULT
+        }
+    };
+
+    Outcome { value: 0, status }
+}
+
+// general purpose registers, numbered as in the x86 encoding
+const RAX: u8 = 0;
+const RCX: u8 = 1;
+const RDX: u8 = 2;
+
+#[derive(Default)]
+struct Emitter {
+    code: Vec<u8>,
+}
+
+impl Emitter {
+    // rbx holds the context throughout, and rdi the guest registers between calls
+    fn prologue(&mut self) {
+        self.bytes(&[0x53]); // push rbx
+        self.bytes(&[0x48, 0x89, 0xFB]); // mov rbx, rdi
+        self.reload_xregs();
+    }
+
+    // emits the instruction after `index` others, returning whether it ends the block or
+    // `None` when it cannot be compiled
+    fn instruction(
+        &mut self,
+        index: usize,
+        pc: Address,
+        instruction: &Instruction,
+    ) -> Option<bool> {
+        match *instruction {
+            Instruction::Lui { rd, imm } => {
+                self.mov_rax_imm(imm);
+                self.store(rd, RAX);
+            }
+
+            Instruction::Auipc { rd, imm } => {
+                self.mov_rax_imm(pc.wrapping_add(imm));
+                self.store(rd, RAX);
+            }
+
+            // misaligned targets raise an exception, which only the interpreter can do
+            Instruction::Jal { offset, .. } | Instruction::Branch { offset, .. }
+                if pc.wrapping_add(offset) & 0x03 != 0 =>
+            {
+                return None;
+            }
+
+            Instruction::Jal { rd, offset } => {
+                self.mov_rax_imm(pc.wrapping_add(4));
+                self.store(rd, RAX);
+                self.mov_rax_imm(pc.wrapping_add(offset));
+                self.exit(index + 1);
+
+                return Some(true);
+            }
+
+            Instruction::Jalr { rd, rs1, offset } => {
+                self.address(rs1, offset);
+                self.bytes(&[0x48, 0x83, 0xE0, 0xFE]); // and rax, -2
+
+                // the interpreter takes the jump whenever the target is not aligned
+                self.bytes(&[0xA8, 0x02]); // test al, 2
+                let aligned = self.jump(&[0x0F, 0x84]); // jz
+                self.mov_rax_imm(pc);
+                self.exit(index);
+                self.land(aligned);
+
+                self.mov_rcx_imm(pc.wrapping_add(4));
+                self.store(rd, RCX);
+                self.exit(index + 1);
+
+                return Some(true);
+            }
+
+            Instruction::Branch {
+                op,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                let cmov = match op {
+                    BranchOp::Beq => 0x44,
+                    BranchOp::Bne => 0x45,
+                    BranchOp::Blt => 0x4C,
+                    BranchOp::Bge => 0x4D,
+                    BranchOp::Bltu => 0x42,
+                    BranchOp::Bgeu => 0x43,
+                };
+
+                self.load(RAX, rs1);
+                self.load(RCX, rs2);
+                self.bytes(&[0x48, 0x39, 0xC8]); // cmp rax, rcx
+
+                // mov does not touch the flags
+                self.mov_rax_imm(pc.wrapping_add(4));
+                self.mov_rcx_imm(pc.wrapping_add(offset));
+                self.bytes(&[0x48, 0x0F, cmov, 0xC1]); // cmovcc rax, rcx
+                self.exit(index + 1);
+
+                return Some(true);
+            }
+
+            Instruction::Load {
+                op,
+                rd,
+                rs1,
+                offset,
+            } => {
+                let size = match op {
+                    LoadOp::Lb | LoadOp::Lbu => 1,
+                    LoadOp::Lh | LoadOp::Lhu => 2,
+                    LoadOp::Lw | LoadOp::Lwu => 4,
+                    LoadOp::Ld => 8,
+                };
+
+                self.address(rs1, offset);
+                self.bytes(&[0x48, 0x89, 0xC1]); // mov rcx, rax
+                self.bytes(&[0x48, 0xBA]); // mov rdx, RAM_BASE
+                self.bytes(&RAM_BASE.to_le_bytes());
+                self.bytes(&[0x48, 0x29, 0xD1]); // sub rcx, rdx
+
+                // misaligned loads and anything past the end of RAM take the slow path
+                let mut slow = vec![];
+                if size > 1 {
+                    self.bytes(&[0xA8, size - 1]); // test al, size - 1
+                    slow.push(self.jump(&[0x0F, 0x85])); // jnz
+                }
+                self.bytes(&[0x48, 0x3B, 0x4B, RAM_LIMIT]); // cmp rcx, [rbx + RAM_LIMIT]
+                slow.push(self.jump(&[0x0F, 0x83])); // jae
+
+                self.bytes(&[0x48, 0x8B, 0x53, RAM]); // mov rdx, [rbx + RAM]
+                match op {
+                    LoadOp::Lb => self.bytes(&[0x48, 0x0F, 0xBE, 0x04, 0x0A]), // movsx rax, byte [rdx + rcx]
+                    LoadOp::Lbu => self.bytes(&[0x0F, 0xB6, 0x04, 0x0A]), // movzx eax, byte [rdx + rcx]
+                    LoadOp::Lh => self.bytes(&[0x48, 0x0F, 0xBF, 0x04, 0x0A]), // movsx rax, word [rdx + rcx]
+                    LoadOp::Lhu => self.bytes(&[0x0F, 0xB7, 0x04, 0x0A]), // movzx eax, word [rdx + rcx]
+                    LoadOp::Lw => self.bytes(&[0x48, 0x63, 0x04, 0x0A]),  // movsxd rax, [rdx + rcx]
+                    LoadOp::Lwu => self.bytes(&[0x8B, 0x04, 0x0A]),       // mov eax, [rdx + rcx]
+                    LoadOp::Ld => self.bytes(&[0x48, 0x8B, 0x04, 0x0A]),  // mov rax, [rdx + rcx]
+                }
+                let done = self.jump(&[0xE9]); // jmp
+
+                for jump in slow {
+                    self.land(jump);
+                }
+                self.bytes(&[0x48, 0x89, 0xC6]); // mov rsi, rax
+                self.mov_edx_imm(op as u32);
+                self.call(load as *const () as u64);
+                self.check(index, pc);
+
+                self.land(done);
+                self.store(rd, RAX);
+            }
+
+            Instruction::Store {
+                op,
+                rs1,
+                rs2,
+                offset,
+            } => {
+                self.address(rs1, offset);
+                self.bytes(&[0x48, 0x89, 0xC6]); // mov rsi, rax
+                self.load(RDX, rs2);
+                self.bytes(&[0xB9]); // mov ecx, op
+                self.bytes(&(op as u32).to_le_bytes());
+                self.call(store as *const () as u64);
+                self.check(index, pc);
+            }
+
+            Instruction::OpImm { op, rd, rs1, imm } => {
+                self.load(RAX, rs1);
+                match op {
+                    AluOp::Sll => self.bytes(&[0x48, 0xC1, 0xE0, imm as u8]),
+                    AluOp::Srl => self.bytes(&[0x48, 0xC1, 0xE8, imm as u8]),
+                    AluOp::Sra => self.bytes(&[0x48, 0xC1, 0xF8, imm as u8]),
+
+                    _ => {
+                        self.mov_rcx_imm(imm);
+                        self.alu(op)?;
+                    }
+                }
+                self.store(rd, RAX);
+            }
+
+            Instruction::Op { op, rd, rs1, rs2 } => {
+                self.load(RAX, rs1);
+                self.load(RCX, rs2);
+                self.alu(op)?;
+                self.store(rd, RAX);
+            }
+
+            Instruction::OpImm32 { op, rd, rs1, imm } => {
+                self.load(RAX, rs1);
+                match op {
+                    AluOp::Add => {
+                        self.mov_rcx_imm(imm);
+                        self.bytes(&[0x01, 0xC8]); // add eax, ecx
+                    }
+
+                    AluOp::Sll => self.bytes(&[0xC1, 0xE0, imm as u8]),
+                    AluOp::Srl => self.bytes(&[0xC1, 0xE8, imm as u8]),
+                    AluOp::Sra => self.bytes(&[0xC1, 0xF8, imm as u8]),
+
+                    _ => return None,
+                }
+                self.bytes(&[0x48, 0x63, 0xC0]); // movsxd rax, eax
+                self.store(rd, RAX);
+            }
+
+            Instruction::Op32 { op, rd, rs1, rs2 } => {
+                self.load(RAX, rs1);
+                self.load(RCX, rs2);
+                match op {
+                    AluOp::Add => self.bytes(&[0x01, 0xC8]), // add eax, ecx
+                    AluOp::Sub => self.bytes(&[0x29, 0xC8]), // sub eax, ecx
+                    AluOp::Mul => self.bytes(&[0x0F, 0xAF, 0xC1]), // imul eax, ecx
+
+                    _ => return None,
+                }
+                self.bytes(&[0x48, 0x63, 0xC0]); // movsxd rax, eax
+                self.store(rd, RAX);
+            }
+
+            // other harts may observe the accesses on either side
+            Instruction::Fence => self.bytes(&[0x0F, 0xAE, 0xF0]), // mfence
+
+            _ => return None,
+        }
+
+        Some(false)
+    }
+
+    // rax = rax <op> rcx
+    fn alu(&mut self, op: AluOp) -> Option<()> {
+        match op {
+            AluOp::Add => self.bytes(&[0x48, 0x01, 0xC8]),
+            AluOp::Sub => self.bytes(&[0x48, 0x29, 0xC8]),
+            AluOp::Xor => self.bytes(&[0x48, 0x31, 0xC8]),
+            AluOp::Or => self.bytes(&[0x48, 0x09, 0xC8]),
+            AluOp::And => self.bytes(&[0x48, 0x21, 0xC8]),
+            AluOp::Mul => self.bytes(&[0x48, 0x0F, 0xAF, 0xC1]),
+
+            // x86 masks the shift amount in cl to six bits, just like RV64
+            AluOp::Sll => self.bytes(&[0x48, 0xD3, 0xE0]),
+            AluOp::Srl => self.bytes(&[0x48, 0xD3, 0xE8]),
+            AluOp::Sra => self.bytes(&[0x48, 0xD3, 0xF8]),
+
+            AluOp::Slt | AluOp::Sltu => {
+                let set = if op == AluOp::Slt { 0x9C } else { 0x92 };
+
+                self.bytes(&[0x48, 0x39, 0xC8]); // cmp rax, rcx
+                self.bytes(&[0x0F, set, 0xC0]); // setl/setb al
+                self.bytes(&[0x0F, 0xB6, 0xC0]); // movzx eax, al
+            }
+
+            _ => return None,
+        }
+
+        Some(())
+    }
+
+    // rax = xregs[rs1] + offset
+    fn address(&mut self, rs1: u8, offset: u64) {
+        self.load(RAX, rs1);
+        self.mov_rcx_imm(offset);
+        self.bytes(&[0x48, 0x01, 0xC8]); // add rax, rcx
+    }
+
+    // calls a helper with the context as its first argument, rdi no longer holding the registers
+    fn call(&mut self, helper: u64) {
+        self.bytes(&[0x48, 0x89, 0xDF]); // mov rdi, rbx
+        self.mov_rax_imm(helper);
+        self.bytes(&[0xFF, 0xD0]); // call rax
+    }
+
+    // leaves on the status a helper returned in rdx: at the instruction that faulted, or right
+    // after the one that dropped translated code, which may include the block itself
+    fn check(&mut self, index: usize, pc: Address) {
+        self.bytes(&[0x48, 0x85, 0xD2]); // test rdx, rdx
+        let done = self.jump(&[0x0F, 0x84]); // jz
+
+        self.bytes(&[0x48, 0x83, 0xFA, DROPPED as u8]); // cmp rdx, DROPPED
+        let dropped = self.jump(&[0x0F, 0x84]); // je
+        self.mov_rax_imm(pc);
+        self.exit(index);
+
+        self.land(dropped);
+        self.mov_rax_imm(pc.wrapping_add(4));
+        self.exit(index + 1);
+
+        self.land(done);
+        self.reload_xregs();
+    }
+
+    fn reload_xregs(&mut self) {
+        self.bytes(&[0x48, 0x8B, 0x7B, XREGS]); // mov rdi, [rbx + XREGS]
+    }
+
+    // returns with the next pc in rax, and `retired` in rdx
+    fn exit(&mut self, retired: usize) {
+        self.mov_edx_imm(retired as u32);
+        self.bytes(&[0x5B]); // pop rbx
+        self.bytes(&[0xC3]); // ret
+    }
+
+    // emits a jump with a 32-bit displacement, returning where it ends for `land` to patch it
+    fn jump(&mut self, opcode: &[u8]) -> usize {
+        self.bytes(opcode);
+        self.bytes(&[0; 4]);
+        self.code.len()
+    }
+
+    // makes a jump land on whatever gets emitted next
+    fn land(&mut self, jump: usize) {
+        let displacement = (self.code.len() - jump) as u32;
+        self.code[jump - 4..jump].copy_from_slice(&displacement.to_le_bytes());
+    }
+
+    // mov reg, [rdi + 8 * xreg]
+    fn load(&mut self, reg: u8, xreg: u8) {
+        self.bytes(&[0x48, 0x8B, 0x87 | (reg << 3)]);
+        self.bytes(&(8 * xreg as u32).to_le_bytes());
+    }
+
+    // mov [rdi + 8 * xreg], reg
+    fn store(&mut self, xreg: u8, reg: u8) {
+        // x0 is hardwired to zero
+        if xreg == 0 {
+            return;
+        }
+
+        self.bytes(&[0x48, 0x89, 0x87 | (reg << 3)]);
+        self.bytes(&(8 * xreg as u32).to_le_bytes());
+    }
+
+    fn mov_rax_imm(&mut self, imm: u64) {
+        self.bytes(&[0x48, 0xB8]);
+        self.bytes(&imm.to_le_bytes());
+    }
+
+    fn mov_rcx_imm(&mut self, imm: u64) {
+        self.bytes(&[0x48, 0xB9]);
+        self.bytes(&imm.to_le_bytes());
+    }
+
+    fn mov_edx_imm(&mut self, imm: u32) {
+        self.bytes(&[0xBA]);
+        self.bytes(&imm.to_le_bytes());
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.code.extend_from_slice(bytes);
+    }
+
+    fn finish(self, length: usize) -> Option<NativeBlock> {
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let size = self.code.len().div_ceil(page) * page;
+
+        unsafe {
+            let code = libc::mmap(
+                ptr::null_mut(),
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            );
+            if code == libc::MAP_FAILED {
+                return None;
+            }
+
+            ptr::copy_nonoverlapping(self.code.as_ptr(), code as *mut u8, self.code.len());
+            if libc::mprotect(code, size, libc::PROT_READ | libc::PROT_EXEC) != 0 {
+                libc::munmap(code, size);
+                return None;
+            }
+
+            Some(NativeBlock {
+                code: code as *mut u8,
+                size,
+                length,
+            })
+        }
+    }
+}
@@ -1,5 +1,34 @@
+pub mod asm;
+pub mod atomic;
+pub mod block;
+pub mod branch;
 pub mod bus;
+pub mod cache;
+pub mod clint;
+pub mod console;
+pub mod cosim;
 pub mod cpu;
+pub mod csr;
+pub mod debug;
+pub mod decoder;
 pub mod dram;
+pub mod elf;
 pub mod emulator;
 pub mod exception;
+pub mod ffi;
+#[cfg(feature = "fuzzing")]
+pub mod fuzz;
+#[cfg(feature = "jit")]
+pub mod jit;
+pub mod mmio;
+pub mod pmp;
+pub mod profile;
+pub mod replay;
+pub mod reverse;
+pub mod smp;
+pub mod snapshot;
+pub mod testing;
+pub mod timing;
+pub mod vector;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,26 @@
+use std::sync::Arc;
+
+use crate::bus::{within, Address};
+
+/// A memory-mapped device implemented outside the emulator.
+///
+/// Accesses are 1, 2, 4 or 8 bytes wide and `offset` is relative to the
+/// start of the region. Returning `None` or `false` raises an access fault.
+pub trait Mmio: Send + Sync {
+    fn read(&self, offset: Address, size: usize) -> Option<u64>;
+    fn write(&self, offset: Address, size: usize, value: u64) -> bool;
+}
+
+#[derive(Clone)]
+pub struct MmioRegion {
+    pub base: Address,
+    pub size: Address,
+    pub device: Arc<dyn Mmio>,
+}
+
+impl MmioRegion {
+    // whether an access of `size` bytes at `address` lies entirely in the region
+    pub fn contains(&self, address: Address, size: usize) -> bool {
+        within(address, size, self.base, self.size)
+    }
+}
@@ -0,0 +1,198 @@
+use crate::{bus::Address, cpu::Privilege, cpu::Xlen};
+
+pub const PMP_ENTRIES: usize = 64;
+
+// pmpcfg bits
+const R: u8 = 1 << 0;
+const W: u8 = 1 << 1;
+const X: u8 = 1 << 2;
+const A: u8 = 0b11 << 3;
+const L: u8 = 1 << 7;
+
+// address matching modes, in the A field
+const OFF: u8 = 0;
+const TOR: u8 = 1;
+const NA4: u8 = 2;
+const NAPOT: u8 = 3;
+
+// pmpaddr holds bits 55:2 of the address on RV64
+const ADDRESS_MASK: u64 = (1 << 54) - 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+/// Physical memory protection unit with all 64 entries implemented, at a
+/// granularity of 4 bytes.
+///
+/// Entries are checked in order and the first one covering any byte of an
+/// access decides: the access must lie entirely within it and be permitted
+/// by it, which binds machine mode too. Past that, machine mode is only bound
+/// by locked entries, and other modes are denied whatever no entry covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Pmp {
+    cfg: [u8; PMP_ENTRIES],
+    addr: [u64; PMP_ENTRIES],
+
+    // no enabled entry means there is nothing to walk
+    configured: bool,
+}
+
+impl Default for Pmp {
+    fn default() -> Self {
+        Self {
+            cfg: [0; PMP_ENTRIES],
+            addr: [0; PMP_ENTRIES],
+            configured: false,
+        }
+    }
+}
+
+impl Pmp {
+    pub fn cfg(&self, index: usize) -> u8 {
+        self.cfg[index]
+    }
+
+    pub fn addr(&self, index: usize) -> u64 {
+        self.addr[index]
+    }
+
+    // rebuilds a unit from saved entries, whether locked or not
+    pub(crate) fn from_entries(cfg: [u8; PMP_ENTRIES], addr: [u64; PMP_ENTRIES]) -> Self {
+        Self {
+            configured: cfg.iter().any(|&cfg| mode(cfg) != OFF),
+            cfg,
+            addr,
+        }
+    }
+
+    pub fn check(
+        &self,
+        address: Address,
+        size: usize,
+        access: Access,
+        privilege: Privilege,
+    ) -> bool {
+        if !self.configured {
+            return privilege == Privilege::Machine;
+        }
+
+        let start = address as u128;
+        let end = start + size as u128;
+
+        for index in 0..PMP_ENTRIES {
+            let Some((low, high)) = self.range(index) else {
+                continue;
+            };
+
+            if end <= low || high <= start {
+                continue;
+            }
+
+            // partially covered accesses fail whatever the permissions
+            if start < low || high < end {
+                return false;
+            }
+
+            let cfg = self.cfg[index];
+            if privilege == Privilege::Machine && cfg & L == 0 {
+                return true;
+            }
+
+            let permission = match access {
+                Access::Read => R,
+                Access::Write => W,
+                Access::Execute => X,
+            };
+
+            return cfg & permission != 0;
+        }
+
+        privilege == Privilege::Machine
+    }
+
+    // pmpcfgN packs the configuration of entries 4N and up, four of them on RV32 and eight on RV64
+    pub(crate) fn read_cfg(&self, register: usize, xlen: Xlen) -> u64 {
+        let count = xlen.bits() as usize / 8;
+
+        (0..count).rev().fold(0, |value, entry| {
+            (value << 8) | self.cfg[4 * register + entry] as u64
+        })
+    }
+
+    pub(crate) fn write_cfg(&mut self, register: usize, value: u64, xlen: Xlen) {
+        let count = xlen.bits() as usize / 8;
+
+        for entry in 0..count {
+            let index = 4 * register + entry;
+            if self.cfg[index] & L != 0 {
+                continue;
+            }
+
+            // the reserved bits read as zero, and so does W without R
+            let mut cfg = (value >> (8 * entry)) as u8 & (R | W | X | A | L);
+            if cfg & R == 0 {
+                cfg &= !W;
+            }
+
+            self.cfg[index] = cfg;
+        }
+
+        self.configured = self.cfg.iter().any(|&cfg| mode(cfg) != OFF);
+    }
+
+    pub(crate) fn write_addr(&mut self, index: usize, value: u64, xlen: Xlen) {
+        // a locked TOR entry also locks the address below it
+        let locked = self.cfg[index] & L != 0
+            || self
+                .cfg
+                .get(index + 1)
+                .is_some_and(|&next| next & L != 0 && mode(next) == TOR);
+
+        if !locked {
+            self.addr[index] = match xlen {
+                Xlen::Rv32 => value as u32 as u64,
+                Xlen::Rv64 => value & ADDRESS_MASK,
+            };
+        }
+    }
+
+    // the byte range matched by an entry, if it is enabled
+    fn range(&self, index: usize) -> Option<(u128, u128)> {
+        let addr = self.addr[index] as u128;
+
+        match mode(self.cfg[index]) {
+            TOR => {
+                let low = match index {
+                    0 => 0,
+                    _ => (self.addr[index - 1] as u128) << 2,
+                };
+
+                Some((low, addr << 2))
+            }
+
+            NA4 => Some((addr << 2, (addr << 2) + 4)),
+
+            NAPOT => {
+                let ones = self.addr[index].trailing_ones();
+                let base = (addr & !((1 << ones) - 1)) << 2;
+
+                Some((base, base + (8 << ones)))
+            }
+
+            _ => None,
+        }
+    }
+}
+
+fn mode(cfg: u8) -> u8 {
+    match (cfg & A) >> 3 {
+        0 => OFF,
+        1 => TOR,
+        2 => NA4,
+        _ => NAPOT,
+    }
+}
@@ -0,0 +1,311 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt, io,
+};
+
+use crate::{
+    branch::BranchKind,
+    bus::Address,
+    decoder::Instruction,
+    elf::{self, Symbol},
+    snapshot::{get, invalid, put},
+};
+
+/// Which retired instructions a profiler records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Sampling {
+    // every one of them
+    Exact,
+
+    // one out of every so many
+    Every(u64),
+}
+
+/// The samples that landed in one function, directly or in its callees.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FunctionProfile {
+    pub name: String,
+    pub exclusive: u64,
+    pub inclusive: u64,
+}
+
+struct Frame {
+    node: usize,
+    return_address: Address,
+}
+
+/// Where a hart spends its instructions, by pc and by call stack, named
+/// after the symbols of the executable it runs.
+///
+/// Calls and returns are told apart by the link registers they use, as for
+/// branch prediction. A return to an address no call was made from is
+/// ignored, and one to a caller further up unwinds everything in between.
+pub struct Profiler {
+    sampling: Sampling,
+    symbols: Vec<Symbol>,
+    retired: u64,
+
+    // the call sites of every stack seen, as a tree rooted at 0
+    nodes: Vec<(usize, Address)>,
+    children: HashMap<(usize, Address), usize>,
+
+    frames: Vec<Frame>,
+
+    // by stack and pc
+    samples: HashMap<(usize, Address), u64>,
+}
+
+impl Profiler {
+    pub fn new(sampling: Sampling, symbols: Vec<Symbol>) -> Self {
+        assert!(sampling != Sampling::Every(0), "sampling period of zero");
+
+        let mut symbols = symbols;
+        symbols.sort_by_key(|symbol| symbol.address);
+
+        Self {
+            sampling,
+            symbols,
+            retired: 0,
+            nodes: vec![(0, 0)],
+            children: HashMap::new(),
+            frames: vec![],
+            samples: HashMap::new(),
+        }
+    }
+
+    pub fn samples(&self) -> u64 {
+        self.samples.values().sum()
+    }
+
+    // samples by pc
+    pub fn counts(&self) -> BTreeMap<Address, u64> {
+        let mut counts = BTreeMap::new();
+        for (&(_, pc), &count) in &self.samples {
+            *counts.entry(pc).or_default() += count;
+        }
+        counts
+    }
+
+    // the current call sites, outermost first
+    pub fn stack(&self) -> Vec<Address> {
+        self.call_sites(self.node())
+    }
+
+    // the most samples first
+    pub fn functions(&self) -> Vec<FunctionProfile> {
+        let mut functions: HashMap<String, FunctionProfile> = HashMap::new();
+        for (names, count) in self.stacks() {
+            let leaf = names.last().unwrap().clone();
+
+            let mut seen: Vec<&String> = vec![];
+            for name in &names {
+                // recursion counts once
+                if seen.contains(&name) {
+                    continue;
+                }
+                seen.push(name);
+
+                let function = functions
+                    .entry(name.clone())
+                    .or_insert_with(|| FunctionProfile {
+                        name: name.clone(),
+                        exclusive: 0,
+                        inclusive: 0,
+                    });
+                function.inclusive += count;
+                function.exclusive += count * (*name == leaf) as u64;
+            }
+        }
+
+        let mut functions: Vec<FunctionProfile> = functions.into_values().collect();
+        functions.sort_by(|a, b| {
+            (b.exclusive, b.inclusive)
+                .cmp(&(a.exclusive, a.inclusive))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+        functions
+    }
+
+    // one line per stack with its function names joined by semicolons, as flame graph tools take them
+    pub fn write_folded(&self, out: &mut impl io::Write) -> io::Result<()> {
+        let mut folded: BTreeMap<String, u64> = BTreeMap::new();
+        for (names, count) in self.stacks() {
+            *folded.entry(names.join(";")).or_default() += count;
+        }
+
+        for (stack, count) in folded {
+            writeln!(out, "{stack} {count}")?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn observe(
+        &mut self,
+        pc: Address,
+        instruction: &Instruction,
+        fallthrough: Address,
+        next: Address,
+    ) {
+        self.retired += 1;
+        let sampled = match self.sampling {
+            Sampling::Exact => true,
+            Sampling::Every(period) => self.retired.is_multiple_of(period),
+        };
+        if sampled {
+            *self.samples.entry((self.node(), pc)).or_default() += 1;
+        }
+
+        match BranchKind::of(instruction) {
+            Some(BranchKind::Call | BranchKind::IndirectCall) => {
+                let parent = self.node();
+                let next_node = self.nodes.len();
+                let node = *self.children.entry((parent, pc)).or_insert(next_node);
+                if node == next_node {
+                    self.nodes.push((parent, pc));
+                }
+
+                self.frames.push(Frame {
+                    node,
+                    return_address: fallthrough,
+                });
+            }
+
+            Some(BranchKind::Return) => {
+                if let Some(depth) = self
+                    .frames
+                    .iter()
+                    .rposition(|frame| frame.return_address == next)
+                {
+                    self.frames.truncate(depth);
+                }
+            }
+
+            _ => {}
+        }
+    }
+
+    pub(crate) fn save_state(&self, out: &mut Vec<u8>) {
+        put(out, self.retired);
+
+        put(out, self.nodes.len() as u64);
+        for &(parent, site) in &self.nodes {
+            put(out, parent as u64);
+            put(out, site);
+        }
+
+        put(out, self.frames.len() as u64);
+        for frame in &self.frames {
+            put(out, frame.node as u64);
+            put(out, frame.return_address);
+        }
+
+        // sorted, so that equal profiles save the same
+        let samples: BTreeMap<_, _> = self.samples.iter().collect();
+        put(out, samples.len() as u64);
+        for (&(node, pc), &count) in samples {
+            put(out, node as u64);
+            put(out, pc);
+            put(out, count);
+        }
+    }
+
+    pub(crate) fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        let retired = get(input)?;
+
+        // every node but the root hangs off an earlier one
+        let mut nodes = vec![];
+        for index in 0..get(input)? {
+            let parent = get(input)?;
+            let site = get(input)?;
+            if (index == 0 && (parent, site) != (0, 0)) || (index > 0 && parent >= index) {
+                return Err(invalid("malformed profile"));
+            }
+            nodes.push((parent as usize, site));
+        }
+        if nodes.is_empty() {
+            return Err(invalid("malformed profile"));
+        }
+        let node = |value: u64| match value < nodes.len() as u64 {
+            true => Ok(value as usize),
+            false => Err(invalid("malformed profile")),
+        };
+
+        let mut frames = vec![];
+        for _ in 0..get(input)? {
+            frames.push(Frame {
+                node: node(get(input)?)?,
+                return_address: get(input)?,
+            });
+        }
+
+        let mut samples = HashMap::new();
+        for _ in 0..get(input)? {
+            let key = (node(get(input)?)?, get(input)?);
+            samples.insert(key, get(input)?);
+        }
+
+        self.children = (1..nodes.len())
+            .map(|index| (nodes[index], index))
+            .collect();
+        self.retired = retired;
+        self.nodes = nodes;
+        self.frames = frames;
+        self.samples = samples;
+
+        Ok(())
+    }
+
+    fn node(&self) -> usize {
+        self.frames.last().map_or(0, |frame| frame.node)
+    }
+
+    fn call_sites(&self, mut node: usize) -> Vec<Address> {
+        let mut sites = vec![];
+        while node != 0 {
+            let (parent, site) = self.nodes[node];
+            sites.push(site);
+            node = parent;
+        }
+
+        sites.reverse();
+        sites
+    }
+
+    // the function names of every sampled stack, outermost first
+    fn stacks(&self) -> impl Iterator<Item = (Vec<String>, u64)> + '_ {
+        self.samples.iter().map(|(&(node, pc), &count)| {
+            let mut sites = self.call_sites(node);
+            sites.push(pc);
+            (sites.into_iter().map(|pc| self.name(pc)).collect(), count)
+        })
+    }
+
+    fn name(&self, pc: Address) -> String {
+        match elf::lookup(&self.symbols, pc) {
+            Some(symbol) => symbol.name.clone(),
+            None => format!("{pc:#x}"),
+        }
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let samples = self.samples();
+        write!(f, "{samples} samples")?;
+
+        let share = |count: u64| 100.0 * count as f64 / samples as f64;
+        for function in self.functions() {
+            write!(
+                f,
+                "\n  {:>10} {:>7.2}% self {:>10} {:>7.2}% total  {}",
+                function.exclusive,
+                share(function.exclusive),
+                function.inclusive,
+                share(function.inclusive),
+                function.name
+            )?;
+        }
+
+        Ok(())
+    }
+}
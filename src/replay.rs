@@ -0,0 +1,261 @@
+use std::io::{self, Read, Write};
+
+use crate::{
+    bus::Address,
+    snapshot::{get, invalid, put},
+};
+
+const MAGIC: &[u8; 8] = b"RISEMURR";
+pub const VERSION: u32 = 1;
+
+/// An input from outside a hart, which a run cannot reproduce by itself.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Event {
+    // what a device or the CLINT returned to a load or fetch, `None` when it faulted
+    Read {
+        address: Address,
+        size: u8,
+        value: Option<u64>,
+    },
+
+    // whether a device or the CLINT accepted a store
+    Write {
+        address: Address,
+        size: u8,
+        accepted: bool,
+    },
+
+    // the software interrupt pending at the hart, whenever it looked
+    Msip {
+        pending: bool,
+    },
+}
+
+impl Event {
+    // what the hart asked, leaving out the answer
+    fn question(&self) -> (u64, Address, u8) {
+        match *self {
+            Event::Read { address, size, .. } => (READ, address, size),
+            Event::Write { address, size, .. } => (WRITE, address, size),
+            Event::Msip { .. } => (MSIP, 0, 0),
+        }
+    }
+}
+
+/// An event, along with the number of instructions the hart had retired.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub instret: u64,
+    pub event: Event,
+}
+
+/// Everything a run took from outside its harts, which is enough to repeat
+/// it exactly from the same starting state, such as a snapshot taken when
+/// recording started.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Recording {
+    // what each hart observed, by hart id
+    pub events: Vec<Vec<Entry>>,
+
+    // the hart taking each turn of a threaded run, in order
+    pub schedule: Vec<u64>,
+}
+
+// event tags
+const READ: u64 = 0;
+const READ_FAULT: u64 = 1;
+const WRITE: u64 = 2;
+const MSIP: u64 = 3;
+
+impl Recording {
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut out = vec![];
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&VERSION.to_le_bytes());
+
+        put(&mut out, self.events.len() as u64);
+        for entries in &self.events {
+            put(&mut out, entries.len() as u64);
+            for entry in entries {
+                put(&mut out, entry.instret);
+                match entry.event {
+                    Event::Read {
+                        address,
+                        size,
+                        value,
+                    } => {
+                        put(&mut out, if value.is_some() { READ } else { READ_FAULT });
+                        put(&mut out, address);
+                        put(&mut out, size as u64);
+                        put(&mut out, value.unwrap_or(0));
+                    }
+                    Event::Write {
+                        address,
+                        size,
+                        accepted,
+                    } => {
+                        put(&mut out, WRITE);
+                        put(&mut out, address);
+                        put(&mut out, size as u64);
+                        put(&mut out, accepted as u64);
+                    }
+                    Event::Msip { pending } => {
+                        put(&mut out, MSIP);
+                        put(&mut out, pending as u64);
+                    }
+                }
+            }
+        }
+
+        put(&mut out, self.schedule.len() as u64);
+        for &hartid in &self.schedule {
+            put(&mut out, hartid);
+        }
+
+        writer.write_all(&out)
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut header = [0u8; 12];
+        reader.read_exact(&mut header)?;
+        if &header[..8] != MAGIC {
+            return Err(invalid("not a recording"));
+        }
+
+        let version = u32::from_le_bytes(header[8..].try_into().unwrap());
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported recording version {version}")));
+        }
+
+        let mut data = vec![];
+        reader.read_to_end(&mut data)?;
+        let input = &mut data.as_slice();
+
+        // every count is checked against what is left, so that none can allocate too much
+        let count = |input: &mut &[u8], width: usize| {
+            let count = get(input)?;
+            match count <= (input.len() / width) as u64 {
+                true => Ok(count as usize),
+                false => Err(invalid("truncated recording")),
+            }
+        };
+
+        let mut events = vec![];
+        for _ in 0..count(input, 8)? {
+            let mut entries = Vec::with_capacity(count(input, 24)?);
+            for _ in 0..entries.capacity() {
+                let instret = get(input)?;
+                let event = match get(input)? {
+                    tag @ (READ | READ_FAULT) => {
+                        let address = get(input)?;
+                        let size = size(get(input)?)?;
+                        let value = get(input)?;
+                        Event::Read {
+                            address,
+                            size,
+                            value: (tag == READ).then_some(value),
+                        }
+                    }
+                    WRITE => Event::Write {
+                        address: get(input)?,
+                        size: size(get(input)?)?,
+                        accepted: get(input)? != 0,
+                    },
+                    MSIP => Event::Msip {
+                        pending: get(input)? != 0,
+                    },
+
+                    _ => return Err(invalid("unknown event")),
+                };
+
+                entries.push(Entry { instret, event });
+            }
+            events.push(entries);
+        }
+
+        let schedule = (0..count(input, 8)?)
+            .map(|_| get(input))
+            .collect::<io::Result<_>>()?;
+
+        if !input.is_empty() {
+            return Err(invalid("trailing data"));
+        }
+
+        Ok(Self { events, schedule })
+    }
+}
+
+fn size(size: u64) -> io::Result<u8> {
+    match size {
+        1 | 2 | 4 | 8 => Ok(size as u8),
+        _ => Err(invalid("unsupported access size")),
+    }
+}
+
+/// The events of a hart, either being recorded or replayed.
+pub(crate) struct EventLog {
+    replaying: bool,
+    entries: Vec<Entry>,
+    next: usize,
+
+    // whether the replayed run asked for something the recorded one did not
+    pub(crate) diverged: bool,
+}
+
+impl EventLog {
+    pub(crate) fn recording() -> Self {
+        Self {
+            replaying: false,
+            entries: vec![],
+            next: 0,
+            diverged: false,
+        }
+    }
+
+    pub(crate) fn replaying(entries: Vec<Entry>) -> Self {
+        Self {
+            replaying: true,
+            entries,
+            next: 0,
+            diverged: false,
+        }
+    }
+
+    pub(crate) fn is_replaying(&self) -> bool {
+        self.replaying
+    }
+
+    // whether a replay handed back every recorded event and nothing else
+    pub(crate) fn is_complete(&self) -> bool {
+        self.replaying && !self.diverged && self.next == self.entries.len()
+    }
+
+    pub(crate) fn into_entries(self) -> Vec<Entry> {
+        self.entries
+    }
+
+    // keeps the event when recording, and when replaying hands back the recorded one
+    // instead, `None` once the run went somewhere the recorded one did not
+    pub(crate) fn exchange(&mut self, instret: u64, event: Event) -> Option<Event> {
+        if !self.replaying {
+            self.entries.push(Entry { instret, event });
+            return Some(event);
+        }
+
+        let recorded = self
+            .entries
+            .get(self.next)
+            .filter(|entry| entry.instret == instret && entry.event.question() == event.question());
+
+        match recorded {
+            Some(entry) if !self.diverged => {
+                self.next += 1;
+                Some(entry.event)
+            }
+            _ => {
+                self.diverged = true;
+                None
+            }
+        }
+    }
+}
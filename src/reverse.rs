@@ -0,0 +1,73 @@
+use crate::snapshot::Snapshot;
+
+/// Periodic checkpoints of a running machine, taken every `interval` retired
+/// instructions. Any earlier point of the execution can be reached again by
+/// restoring the closest preceding checkpoint and re-executing from there.
+///
+/// Whatever the host changes while the machine is stopped cannot be
+/// re-executed, so execution resuming from anywhere else than where it was
+/// left gets a checkpoint of its own.
+pub struct History {
+    interval: u64,
+    checkpoints: Vec<Snapshot>,
+
+    // the state the machine was left in by the last run or reverse travel
+    left: Option<Snapshot>,
+}
+
+impl History {
+    pub fn new(interval: u64) -> Self {
+        Self {
+            interval: interval.max(1),
+            checkpoints: vec![],
+            left: None,
+        }
+    }
+
+    pub fn interval(&self) -> u64 {
+        self.interval
+    }
+
+    pub fn checkpoints(&self) -> &[Snapshot] {
+        &self.checkpoints
+    }
+
+    // instret at which the next checkpoint is due
+    pub(crate) fn next_due(&self) -> u64 {
+        match self.checkpoints.last() {
+            Some(last) => last.instret + self.interval,
+            None => 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self, snapshot: Snapshot) {
+        // execution after travelling back retraces what was already recorded
+        if self.next_due() <= snapshot.instret {
+            self.checkpoints.push(snapshot);
+        }
+    }
+
+    pub(crate) fn leave(&mut self, snapshot: Snapshot) {
+        self.left = Some(snapshot);
+    }
+
+    // checkpoints the state execution resumes from if the host changed it, which also changes what follows
+    pub(crate) fn resume(&mut self, snapshot: Snapshot) {
+        if self.left.as_ref() == Some(&snapshot) {
+            return;
+        }
+
+        let kept = self
+            .checkpoints
+            .partition_point(|checkpoint| checkpoint.instret < snapshot.instret);
+        self.checkpoints.truncate(kept);
+        self.checkpoints.push(snapshot);
+    }
+
+    // the latest checkpoint taken at or before `instret`
+    pub(crate) fn before(&self, instret: u64) -> Option<usize> {
+        self.checkpoints
+            .partition_point(|checkpoint| checkpoint.instret <= instret)
+            .checked_sub(1)
+    }
+}
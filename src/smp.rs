@@ -0,0 +1,361 @@
+use std::{
+    mem,
+    sync::{Arc, Mutex},
+    thread,
+};
+
+use crate::{
+    bus::{Bus, RAM_BASE},
+    clint::Clint,
+    cpu::CPU,
+    dram::DRAM,
+    emulator::{stop_reason, StopReason},
+    replay::{EventLog, Recording},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum HartState {
+    Running,
+    Waiting,
+    Stopped,
+}
+
+/// Several harts sharing RAM and a CLINT, with hart ids counting from zero.
+///
+/// `run_for` interleaves the harts round-robin on the calling thread, each
+/// running `quantum` instructions at a time, so that runs are deterministic.
+/// `run_threaded` gives every hart its own host thread instead.
+///
+/// While recording, threaded runs let one hart take a turn at a time, in
+/// whichever order the threads get to it, and keep that order so that a
+/// replay with the same quantum can take the same turns on a single thread.
+pub struct Machine {
+    harts: Vec<CPU>,
+    clint: Arc<Clint>,
+    quantum: u64,
+
+    // the hart of every turn of threaded runs, and the next one to replay
+    schedule: Vec<u64>,
+    turn: usize,
+}
+
+impl Machine {
+    pub fn new(harts: usize, ram_size: usize) -> Self {
+        let clint = Arc::new(Clint::new(harts));
+        let bus = Bus::new(DRAM::new(ram_size), Some(clint.clone()));
+
+        let harts = (0..harts)
+            .map(|hartid| {
+                let mut cpu = CPU::new(bus.share());
+                cpu.hartid = hartid as u64;
+                cpu
+            })
+            .collect();
+
+        Self {
+            harts,
+            clint,
+            quantum: 100,
+            schedule: vec![],
+            turn: 0,
+        }
+    }
+
+    pub fn init_ram(&mut self, data: Vec<u8>) {
+        self.harts[0].bus.ram.initialize(data);
+
+        for hart in self.harts.iter_mut() {
+            hart.flush_caches();
+            hart.pc = RAM_BASE;
+        }
+    }
+
+    // harts are only handed out one at a time mutably, as they share memory
+    pub fn harts(&self) -> &[CPU] {
+        &self.harts
+    }
+
+    pub fn hart_mut(&mut self, hartid: usize) -> &mut CPU {
+        &mut self.harts[hartid]
+    }
+
+    pub fn clint(&self) -> &Clint {
+        &self.clint
+    }
+
+    pub fn quantum(&self) -> u64 {
+        self.quantum
+    }
+
+    pub fn set_quantum(&mut self, quantum: u64) {
+        self.quantum = quantum.max(1);
+    }
+
+    // logs what devices and the CLINT answer every hart from here on, until finish_recording
+    pub fn start_recording(&mut self) {
+        for hart in self.harts.iter_mut() {
+            hart.log = Some(EventLog::recording());
+        }
+
+        self.schedule.clear();
+        self.turn = 0;
+    }
+
+    pub fn finish_recording(&mut self) -> Option<Recording> {
+        if !self.recording() {
+            return None;
+        }
+
+        let events = self
+            .harts
+            .iter_mut()
+            .map(|hart| hart.log.take().map_or(vec![], EventLog::into_entries))
+            .collect();
+
+        Some(Recording {
+            events,
+            schedule: mem::take(&mut self.schedule),
+        })
+    }
+
+    // answers every access to a device or the CLINT from a recording from here on, without asking them
+    pub fn start_replay(&mut self, recording: &Recording) {
+        for (hartid, hart) in self.harts.iter_mut().enumerate() {
+            let entries = recording.events.get(hartid).cloned().unwrap_or_default();
+            hart.log = Some(EventLog::replaying(entries));
+        }
+
+        self.schedule = recording.schedule.clone();
+        self.turn = 0;
+    }
+
+    // stops replaying, returning whether every recorded event and turn was replayed exactly
+    pub fn finish_replay(&mut self) -> bool {
+        let complete = self.turn == self.schedule.len()
+            && self
+                .harts
+                .iter_mut()
+                .all(|hart| hart.log.take().is_some_and(|log| log.is_complete()));
+
+        for hart in self.harts.iter_mut() {
+            hart.log = None;
+        }
+        self.schedule.clear();
+        self.turn = 0;
+
+        complete
+    }
+
+    fn recording(&self) -> bool {
+        self.harts[0]
+            .log
+            .as_ref()
+            .is_some_and(|log| !log.is_replaying())
+    }
+
+    fn replaying(&self) -> bool {
+        self.harts[0]
+            .log
+            .as_ref()
+            .is_some_and(EventLog::is_replaying)
+    }
+
+    // runs every hart for at most `count` instructions, returning why each of them stopped
+    pub fn run_for(&mut self, count: u64) -> Vec<StopReason> {
+        let mut budgets = vec![count; self.harts.len()];
+        let mut stops = vec![None; self.harts.len()];
+
+        for hart in self.harts.iter_mut() {
+            hart.debugger.resume_from = Some(hart.pc);
+        }
+
+        while stops.iter().any(Option::is_none) {
+            let mut progress = false;
+
+            for (hartid, hart) in self.harts.iter_mut().enumerate() {
+                if stops[hartid].is_some() {
+                    continue;
+                }
+
+                if !hart.wake() {
+                    if hart.diverged() {
+                        stops[hartid] = Some(StopReason::Diverged { pc: hart.pc });
+                    }
+                    continue;
+                }
+
+                let start = hart.instret;
+                let result = hart.run_for(self.quantum.min(budgets[hartid]));
+                budgets[hartid] -= hart.instret - start;
+                progress = true;
+
+                stops[hartid] = match result {
+                    Err(ex) => Some(stop_reason(hart, ex)),
+                    Ok(()) if budgets[hartid] == 0 => Some(StopReason::LimitReached),
+                    Ok(()) => None,
+                };
+            }
+
+            // whoever is left waits for an interrupt nobody can raise anymore
+            if !progress {
+                for (hartid, hart) in self.harts.iter().enumerate() {
+                    stops[hartid].get_or_insert(StopReason::Waiting { pc: hart.pc });
+                }
+            }
+        }
+
+        stops.into_iter().flatten().collect()
+    }
+
+    // like run_for, but with a host thread per hart, so the interleaving is not deterministic
+    pub fn run_threaded(&mut self, count: u64) -> Vec<StopReason> {
+        if self.replaying() {
+            return self.replay_threaded(count);
+        }
+
+        let states = Mutex::new(vec![HartState::Running; self.harts.len()]);
+        let schedule = self
+            .recording()
+            .then(|| Mutex::new(mem::take(&mut self.schedule)));
+        let (clint, quantum) = (&self.clint, self.quantum);
+
+        let stops = thread::scope(|scope| {
+            let threads: Vec<_> = self
+                .harts
+                .iter_mut()
+                .enumerate()
+                .map(|(hartid, hart)| {
+                    let (states, schedule) = (&states, schedule.as_ref());
+                    scope.spawn(move || {
+                        run_hart(hart, hartid, count, quantum, clint, states, schedule)
+                    })
+                })
+                .collect();
+
+            threads
+                .into_iter()
+                .map(|thread| thread.join().expect("hart thread panicked"))
+                .collect()
+        });
+
+        if let Some(schedule) = schedule {
+            self.schedule = schedule.into_inner().unwrap();
+        }
+        stops
+    }
+
+    // takes the recorded turns on the calling thread, in the recorded order
+    fn replay_threaded(&mut self, count: u64) -> Vec<StopReason> {
+        let states = Mutex::new(vec![HartState::Running; self.harts.len()]);
+        let mut stops = vec![None; self.harts.len()];
+
+        let limits: Vec<u64> = self
+            .harts
+            .iter_mut()
+            .map(|hart| {
+                hart.debugger.resume_from = Some(hart.pc);
+                hart.instret.saturating_add(count)
+            })
+            .collect();
+
+        while stops.iter().any(Option::is_none) {
+            let Some(&hartid) = self.schedule.get(self.turn) else {
+                break;
+            };
+
+            // a turn of a hart that is not there or has already stopped belongs to another run
+            let hartid = hartid as usize;
+            if stops.get(hartid).is_none_or(Option::is_some) {
+                break;
+            }
+
+            self.turn += 1;
+            stops[hartid] = take_turn(
+                &mut self.harts[hartid],
+                hartid,
+                limits[hartid],
+                self.quantum,
+                &self.clint,
+                &states,
+            );
+        }
+
+        // as do harts left running when the turns run out
+        self.harts
+            .iter()
+            .zip(stops)
+            .map(|(hart, stop)| stop.unwrap_or(StopReason::Diverged { pc: hart.pc }))
+            .collect()
+    }
+}
+
+fn run_hart(
+    hart: &mut CPU,
+    hartid: usize,
+    count: u64,
+    quantum: u64,
+    clint: &Clint,
+    states: &Mutex<Vec<HartState>>,
+    schedule: Option<&Mutex<Vec<u64>>>,
+) -> StopReason {
+    hart.debugger.resume_from = Some(hart.pc);
+    let limit = hart.instret.saturating_add(count);
+
+    loop {
+        // while recording, a turn only starts once the previous one is over
+        let turn = schedule.map(|schedule| {
+            let mut turns = schedule.lock().unwrap();
+            turns.push(hartid as u64);
+            turns
+        });
+
+        if let Some(reason) = take_turn(hart, hartid, limit, quantum, clint, states) {
+            return reason;
+        }
+
+        drop(turn);
+        if hart.waiting {
+            thread::yield_now();
+        }
+    }
+}
+
+// runs a quantum, or looks for an interrupt while waiting, returning why the hart stopped if it did
+fn take_turn(
+    hart: &mut CPU,
+    hartid: usize,
+    limit: u64,
+    quantum: u64,
+    clint: &Clint,
+    states: &Mutex<Vec<HartState>>,
+) -> Option<StopReason> {
+    let reason = if hart.waiting {
+        let mut states = states.lock().unwrap();
+        if hart.wake() {
+            states[hartid] = HartState::Running;
+            return None;
+        }
+        states[hartid] = HartState::Waiting;
+
+        // interrupts are only raised by running harts, and waiting ones only resume under the lock
+        let stuck = states.iter().enumerate().all(|(id, state)| match state {
+            HartState::Running => false,
+            HartState::Waiting => !clint.software_pending(id as u64),
+            HartState::Stopped => true,
+        });
+
+        match hart.diverged() {
+            true => StopReason::Diverged { pc: hart.pc },
+            false if stuck => StopReason::Waiting { pc: hart.pc },
+            false => return None,
+        }
+    } else if hart.instret >= limit {
+        StopReason::LimitReached
+    } else {
+        let result = hart.run_for(quantum.min(limit - hart.instret));
+        stop_reason(hart, result.err()?)
+    };
+
+    states.lock().unwrap()[hartid] = HartState::Stopped;
+    Some(reason)
+}
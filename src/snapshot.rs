@@ -0,0 +1,688 @@
+use std::{
+    io::{self, Read, Write},
+    sync::Arc,
+};
+
+use crate::{
+    atomic::Reservation,
+    bus::RAM_BASE,
+    clint::Clint,
+    cpu::{MisalignedPolicy, Privilege, Xlen, CPU},
+    dram::DRAM,
+    emulator::Emulator,
+    pmp::{Pmp, PMP_ENTRIES},
+    vector::{AgnosticPolicy, VectorUnit},
+};
+
+const MAGIC: &[u8; 8] = b"RISEMUSS";
+pub const VERSION: u32 = 7;
+
+const PAGE_SIZE: usize = 4096;
+
+// page encodings
+const RAW: u8 = 0;
+const RLE: u8 = 1;
+
+/// The complete architectural state of a machine.
+///
+/// RAM is stored sparsely: all-zero pages are omitted, and the remaining ones
+/// are run-length encoded whenever that makes them smaller.
+///
+/// The state of the models attached to the hart goes along with it, and is
+/// restored into the models the target emulator has, which must be
+/// configured the same way. Models missing on either side are left alone.
+/// A snapshot that does not fit the target emulator is rejected before
+/// anything in it changes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Snapshot {
+    pub xlen: Xlen,
+    pub compressed: bool,
+    pub xregs: [u64; 32],
+    pub pc: u64,
+    pub instret: u64,
+    pub cycle: u64,
+    pub tval: u64,
+
+    pub privilege: Privilege,
+    pub mstatus: u64,
+    pub mepc: u64,
+    pub pmp: Pmp,
+    pub misaligned: MisalignedPolicy,
+
+    // an LR waiting for its SC, and a WFI for an interrupt
+    reservation: Option<Reservation>,
+    pub waiting: bool,
+
+    // the pending software interrupts of every hart on the CLINT, if there is one
+    pub msip: Option<Vec<bool>>,
+
+    pub vector: VectorUnit,
+
+    models: Models,
+
+    pub ram_size: usize,
+    pages: Vec<(u32, Page)>,
+}
+
+// the state of each model, as it saves it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Models {
+    timing: Option<Vec<u8>>,
+    caches: Option<Vec<u8>>,
+    branch_prediction: Option<Vec<u8>>,
+    profiler: Option<Vec<u8>>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Page {
+    Raw(Vec<u8>),
+    Rle(Vec<u8>),
+}
+
+impl Snapshot {
+    pub fn capture(emu: &Emulator) -> Self {
+        let memory = emu.cpu.bus.ram.contents();
+
+        let pages = memory
+            .chunks(PAGE_SIZE)
+            .enumerate()
+            .filter(|(_, page)| page.iter().any(|&byte| byte != 0))
+            .map(|(index, page)| {
+                let encoded = rle_encode(page);
+                let page = if encoded.len() < page.len() {
+                    Page::Rle(encoded)
+                } else {
+                    Page::Raw(page.to_vec())
+                };
+
+                (index as u32, page)
+            })
+            .collect();
+
+        Self {
+            xlen: emu.cpu.xlen(),
+            compressed: emu.cpu.compressed(),
+            xregs: emu.cpu.xregs,
+            pc: emu.cpu.pc,
+            instret: emu.cpu.instret,
+            cycle: emu.cpu.cycle,
+            tval: emu.cpu.tval,
+
+            privilege: emu.cpu.privilege(),
+            mstatus: emu.cpu.mstatus,
+            mepc: emu.cpu.mepc,
+            pmp: emu.cpu.pmp.clone(),
+            misaligned: emu.cpu.misaligned,
+
+            reservation: emu.cpu.reservation,
+            waiting: emu.cpu.waiting,
+
+            msip: emu.cpu.bus.clint.as_ref().map(|clint| {
+                (0..clint.harts() as u64)
+                    .map(|hartid| clint.software_pending(hartid))
+                    .collect()
+            }),
+
+            vector: emu.cpu.vector.clone(),
+
+            models: Models::save(&emu.cpu),
+
+            ram_size: memory.len(),
+            pages,
+        }
+    }
+
+    pub fn restore(&self, emu: &mut Emulator) -> io::Result<()> {
+        check_ram_size(self.ram_size as u64)?;
+
+        let mut memory = vec![];
+        memory
+            .try_reserve_exact(self.ram_size)
+            .map_err(|_| invalid("RAM too large to allocate"))?;
+        memory.resize(self.ram_size, 0);
+
+        for (index, page) in &self.pages {
+            check_page(*index, page, self.ram_size)?;
+
+            let start = *index as usize * PAGE_SIZE;
+            let end = (start + PAGE_SIZE).min(memory.len());
+
+            match page {
+                Page::Raw(data) => memory[start..end].copy_from_slice(data),
+                Page::Rle(data) => rle_decode(data, &mut memory[start..end]),
+            }
+        }
+
+        // the models go first, as only they can still turn the snapshot down
+        let models = Models::save(&emu.cpu);
+        if let Err(error) = self.models.load(&mut emu.cpu) {
+            models
+                .load(&mut emu.cpu)
+                .expect("a model takes back its own state");
+
+            return Err(error);
+        }
+
+        let mut ram = DRAM::new(self.ram_size);
+        ram.initialize(memory);
+        emu.cpu.bus.ram = ram;
+        emu.cpu.set_xlen(self.xlen);
+        emu.cpu.set_compressed(self.compressed);
+        emu.cpu.xregs = self.xregs;
+        emu.cpu.pc = self.pc;
+        emu.cpu.instret = self.instret;
+        emu.cpu.cycle = self.cycle;
+        emu.cpu.tval = self.tval;
+        emu.cpu.set_privilege(self.privilege);
+        emu.cpu.mstatus = self.mstatus;
+        emu.cpu.mepc = self.mepc;
+        emu.cpu.pmp = self.pmp.clone();
+        emu.cpu.misaligned = self.misaligned;
+        emu.cpu.reservation = self.reservation;
+        emu.cpu.waiting = self.waiting;
+        emu.cpu.vector = self.vector.clone();
+
+        // other harts share the CLINT, and see the restored interrupts too
+        if let Some(msip) = &self.msip {
+            let clint = match &emu.cpu.bus.clint {
+                Some(clint) if clint.harts() == msip.len() => clint.clone(),
+                _ => {
+                    let clint = Arc::new(Clint::new(msip.len()));
+                    emu.cpu.bus.clint = Some(clint.clone());
+                    clint
+                }
+            };
+
+            for (hartid, &pending) in msip.iter().enumerate() {
+                clint.set_software_pending(hartid as u64, pending);
+            }
+        }
+
+        emu.cpu.flush_caches();
+
+        Ok(())
+    }
+
+    pub fn write_to<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(MAGIC)?;
+        writer.write_all(&VERSION.to_le_bytes())?;
+
+        writer.write_all(&[self.xlen.bits() as u8, self.compressed as u8])?;
+
+        for value in self.xregs {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(&self.pc.to_le_bytes())?;
+        writer.write_all(&self.instret.to_le_bytes())?;
+        writer.write_all(&self.cycle.to_le_bytes())?;
+        writer.write_all(&self.tval.to_le_bytes())?;
+
+        writer.write_all(&[self.privilege as u8])?;
+        writer.write_all(&self.mstatus.to_le_bytes())?;
+        writer.write_all(&self.mepc.to_le_bytes())?;
+        for index in 0..PMP_ENTRIES {
+            writer.write_all(&[self.pmp.cfg(index)])?;
+        }
+        for index in 0..PMP_ENTRIES {
+            writer.write_all(&self.pmp.addr(index).to_le_bytes())?;
+        }
+        writer.write_all(&[self.misaligned as u8])?;
+
+        match self.reservation {
+            Some(reservation) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&reservation.address.to_le_bytes())?;
+                writer.write_all(&reservation.value.to_le_bytes())?;
+                writer.write_all(&[reservation.double as u8])?;
+            }
+            None => writer.write_all(&[0])?,
+        }
+        writer.write_all(&[self.waiting as u8])?;
+
+        let msip = self.msip.as_ref().map(|msip| {
+            msip.iter()
+                .map(|&pending| pending as u8)
+                .collect::<Vec<u8>>()
+        });
+        write_optional(&mut writer, msip.as_deref())?;
+
+        let vector = &self.vector;
+        writer.write_all(&(vector.vlen() as u32).to_le_bytes())?;
+        writer.write_all(&(vector.elen() as u32).to_le_bytes())?;
+        writer.write_all(&[vector.agnostic as u8, vector.vxsat as u8])?;
+        for value in [vector.vl, vector.vtype, vector.vstart, vector.vxrm] {
+            writer.write_all(&value.to_le_bytes())?;
+        }
+        writer.write_all(vector.registers())?;
+
+        let models = &self.models;
+        for state in [
+            &models.timing,
+            &models.caches,
+            &models.branch_prediction,
+            &models.profiler,
+        ] {
+            write_optional(&mut writer, state.as_deref())?;
+        }
+
+        writer.write_all(&(self.ram_size as u64).to_le_bytes())?;
+        writer.write_all(&(self.pages.len() as u32).to_le_bytes())?;
+        for (index, page) in &self.pages {
+            let (encoding, data) = match page {
+                Page::Raw(data) => (RAW, data),
+                Page::Rle(data) => (RLE, data),
+            };
+
+            writer.write_all(&index.to_le_bytes())?;
+            writer.write_all(&[encoding])?;
+            writer.write_all(&(data.len() as u32).to_le_bytes())?;
+            writer.write_all(data)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn read_from<R: Read>(mut reader: R) -> io::Result<Self> {
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a snapshot"));
+        }
+
+        let version = read_u32(&mut reader)?;
+        if version != VERSION {
+            return Err(invalid(&format!("unsupported snapshot version {version}")));
+        }
+
+        let mut width = [0u8];
+        reader.read_exact(&mut width)?;
+        let xlen = match width[0] {
+            32 => Xlen::Rv32,
+            64 => Xlen::Rv64,
+
+            _ => return Err(invalid("unsupported register width")),
+        };
+        let compressed = read_u8(&mut reader)? != 0;
+
+        let mut xregs = [0u64; 32];
+        for value in xregs.iter_mut() {
+            *value = read_u64(&mut reader)?;
+        }
+        let pc = read_u64(&mut reader)?;
+        let instret = read_u64(&mut reader)?;
+        let cycle = read_u64(&mut reader)?;
+        let tval = read_u64(&mut reader)?;
+
+        let mut level = [0u8];
+        reader.read_exact(&mut level)?;
+        let privilege = match level[0] {
+            0 => Privilege::User,
+            1 => Privilege::Supervisor,
+            3 => Privilege::Machine,
+
+            _ => return Err(invalid("unsupported privilege level")),
+        };
+        let mstatus = read_u64(&mut reader)?;
+        let mepc = read_u64(&mut reader)?;
+
+        let mut cfg = [0u8; PMP_ENTRIES];
+        reader.read_exact(&mut cfg)?;
+        let mut addr = [0u64; PMP_ENTRIES];
+        for value in addr.iter_mut() {
+            *value = read_u64(&mut reader)?;
+        }
+        let pmp = Pmp::from_entries(cfg, addr);
+
+        let misaligned = match read_u8(&mut reader)? {
+            0 => MisalignedPolicy::Trap,
+            1 => MisalignedPolicy::Emulate,
+
+            _ => return Err(invalid("unsupported misaligned policy")),
+        };
+
+        let reservation = match read_u8(&mut reader)? {
+            0 => None,
+            1 => Some(Reservation {
+                address: read_u64(&mut reader)?,
+                value: read_u64(&mut reader)?,
+                double: read_u8(&mut reader)? != 0,
+            }),
+
+            _ => return Err(invalid("malformed reservation")),
+        };
+        let waiting = read_u8(&mut reader)? != 0;
+
+        let msip = read_optional(&mut reader)?
+            .map(|msip| msip.into_iter().map(|pending| pending != 0).collect());
+
+        let vlen = read_u32(&mut reader)? as usize;
+        let elen = read_u32(&mut reader)? as usize;
+        if !VectorUnit::supports(vlen, elen) {
+            return Err(invalid("unsupported vector configuration"));
+        }
+
+        let mut vector = VectorUnit::new(vlen, elen);
+        let mut flags = [0u8; 2];
+        reader.read_exact(&mut flags)?;
+        vector.agnostic = match flags[0] {
+            0 => AgnosticPolicy::Undisturbed,
+            1 => AgnosticPolicy::Ones,
+
+            _ => return Err(invalid("malformed vector state")),
+        };
+        vector.vxsat = flags[1] != 0;
+        vector.vl = read_u64(&mut reader)?;
+        vector.vtype = read_u64(&mut reader)?;
+        vector.vstart = read_u64(&mut reader)?;
+        vector.vxrm = read_u64(&mut reader)?;
+        reader.read_exact(vector.registers_mut())?;
+
+        let models = Models {
+            timing: read_optional(&mut reader)?,
+            caches: read_optional(&mut reader)?,
+            branch_prediction: read_optional(&mut reader)?,
+            profiler: read_optional(&mut reader)?,
+        };
+
+        let ram_size = read_u64(&mut reader)?;
+        check_ram_size(ram_size)?;
+        let ram_size = ram_size as usize;
+        let page_count = read_u32(&mut reader)?;
+
+        let mut pages = vec![];
+        for _ in 0..page_count {
+            let index = read_u32(&mut reader)?;
+
+            let mut encoding = [0u8];
+            reader.read_exact(&mut encoding)?;
+
+            let length = read_u32(&mut reader)? as usize;
+            if length > 2 * PAGE_SIZE {
+                return Err(invalid("oversized page"));
+            }
+
+            let mut data = vec![0u8; length];
+            reader.read_exact(&mut data)?;
+
+            let page = match encoding[0] {
+                RAW => Page::Raw(data),
+                RLE => Page::Rle(data),
+
+                _ => return Err(invalid("malformed page")),
+            };
+            check_page(index, &page, ram_size)?;
+
+            pages.push((index, page));
+        }
+
+        Ok(Self {
+            xlen,
+            compressed,
+            xregs,
+            pc,
+            instret,
+            cycle,
+            tval,
+
+            privilege,
+            mstatus,
+            mepc,
+            pmp,
+            misaligned,
+
+            reservation,
+            waiting,
+
+            msip,
+
+            vector,
+
+            models,
+
+            ram_size,
+            pages,
+        })
+    }
+}
+
+impl Models {
+    fn save(cpu: &CPU) -> Self {
+        Self {
+            timing: cpu
+                .timing
+                .as_ref()
+                .map(|model| saved(|out| model.save_state(out))),
+            caches: cpu
+                .caches
+                .as_ref()
+                .map(|model| saved(|out| model.save_state(out))),
+            branch_prediction: cpu
+                .branch_prediction
+                .as_ref()
+                .map(|model| saved(|out| model.save_state(out))),
+            profiler: cpu
+                .profiler
+                .as_ref()
+                .map(|model| saved(|out| model.save_state(out))),
+        }
+    }
+
+    fn load(&self, cpu: &mut CPU) -> io::Result<()> {
+        if let Some(timing) = &mut cpu.timing {
+            load("timing model", &self.timing, |input| {
+                timing.load_state(input)
+            })?;
+        }
+        if let Some(caches) = &mut cpu.caches {
+            load("caches", &self.caches, |input| caches.load_state(input))?;
+        }
+        if let Some(prediction) = &mut cpu.branch_prediction {
+            load("branch predictor", &self.branch_prediction, |input| {
+                prediction.load_state(input)
+            })?;
+        }
+        if let Some(profiler) = &mut cpu.profiler {
+            load("profiler", &self.profiler, |input| {
+                profiler.load_state(input)
+            })?;
+        }
+
+        Ok(())
+    }
+}
+
+// RAM has to fit in the address space above RAM_BASE, and in the host's
+fn check_ram_size(size: u64) -> io::Result<()> {
+    match size <= RAM_BASE.wrapping_neg() && usize::try_from(size).is_ok() {
+        true => Ok(()),
+        false => Err(invalid("RAM too large")),
+    }
+}
+
+// a page has to decode to exactly the part of RAM it covers
+fn check_page(index: u32, page: &Page, ram_size: usize) -> io::Result<()> {
+    let start = index as usize * PAGE_SIZE;
+    if start >= ram_size {
+        return Err(invalid("page outside of RAM"));
+    }
+    let expected = (ram_size - start).min(PAGE_SIZE);
+
+    match page {
+        Page::Raw(data) if data.len() == expected => Ok(()),
+        Page::Rle(data) if rle_length(data) == Some(expected) => Ok(()),
+
+        _ => Err(invalid("malformed page")),
+    }
+}
+
+pub(crate) fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+// models save their state as little-endian words
+pub(crate) fn put(out: &mut Vec<u8>, value: u64) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+pub(crate) fn get(input: &mut &[u8]) -> io::Result<u64> {
+    read_u64(input)
+}
+
+// the length of a table, which only loads into a model of the same size
+pub(crate) fn get_length(input: &mut &[u8], expected: usize) -> io::Result<()> {
+    match get(input)? == expected as u64 {
+        true => Ok(()),
+        false => Err(invalid("model configured differently")),
+    }
+}
+
+fn saved(save: impl FnOnce(&mut Vec<u8>)) -> Vec<u8> {
+    let mut out = vec![];
+    save(&mut out);
+    out
+}
+
+// a model's state has to be used up exactly by the model it is restored into
+fn load(
+    model: &str,
+    state: &Option<Vec<u8>>,
+    load: impl FnOnce(&mut &[u8]) -> io::Result<()>,
+) -> io::Result<()> {
+    let Some(state) = state else {
+        return Ok(());
+    };
+
+    let mut input = state.as_slice();
+    load(&mut input)
+        .and_then(|()| match input.is_empty() {
+            true => Ok(()),
+            false => Err(invalid("model configured differently")),
+        })
+        .map_err(|error| invalid(&format!("cannot restore the {model}: {error}")))
+}
+
+fn write_optional<W: Write>(writer: &mut W, data: Option<&[u8]>) -> io::Result<()> {
+    match data {
+        Some(data) => {
+            writer.write_all(&[1])?;
+            writer.write_all(&(data.len() as u64).to_le_bytes())?;
+            writer.write_all(data)
+        }
+        None => writer.write_all(&[0]),
+    }
+}
+
+fn read_optional<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    match read_u8(reader)? {
+        0 => Ok(None),
+        1 => {
+            let length = read_u64(reader)?;
+
+            // never trusting the length with an allocation
+            let mut data = vec![];
+            reader.take(length).read_to_end(&mut data)?;
+            if data.len() as u64 != length {
+                return Err(io::ErrorKind::UnexpectedEof.into());
+            }
+
+            Ok(Some(data))
+        }
+
+        _ => Err(invalid("malformed optional state")),
+    }
+}
+
+fn read_u8<R: Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+// PackBits: a control byte n < 128 is followed by n + 1 literal bytes, while
+// n >= 128 repeats the following byte 257 - n times
+fn rle_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![];
+
+    let mut i = 0;
+    while i < data.len() {
+        let run = data[i..]
+            .iter()
+            .take(128)
+            .take_while(|&&byte| byte == data[i])
+            .count();
+
+        if run >= 2 {
+            encoded.push((257 - run) as u8);
+            encoded.push(data[i]);
+            i += run;
+            continue;
+        }
+
+        // gather literals until the next run of at least two bytes
+        let start = i;
+        while i < data.len() && i - start < 128 {
+            if i + 1 < data.len() && data[i] == data[i + 1] {
+                break;
+            }
+            i += 1;
+        }
+
+        encoded.push((i - start - 1) as u8);
+        encoded.extend_from_slice(&data[start..i]);
+    }
+
+    encoded
+}
+
+fn rle_decode(data: &[u8], output: &mut [u8]) {
+    let mut i = 0;
+    let mut o = 0;
+    while i < data.len() {
+        let control = data[i] as usize;
+        i += 1;
+
+        if control < 128 {
+            output[o..o + control + 1].copy_from_slice(&data[i..i + control + 1]);
+            i += control + 1;
+            o += control + 1;
+        } else {
+            output[o..o + 257 - control].fill(data[i]);
+            i += 1;
+            o += 257 - control;
+        }
+    }
+}
+
+// decoded length of well-formed data, so that restoring never has to fail
+fn rle_length(data: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    let mut length = 0;
+    while i < data.len() {
+        let control = data[i] as usize;
+        i += 1;
+
+        if control < 128 {
+            i += control + 1;
+            length += control + 1;
+        } else if control > 128 {
+            i += 1;
+            length += 257 - control;
+        } else {
+            return None;
+        }
+    }
+
+    (i == data.len()).then_some(length)
+}
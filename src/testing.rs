@@ -0,0 +1,256 @@
+use std::fmt::Write;
+
+use crate::{
+    asm::Assembler,
+    bus::{Address, RAM_BASE},
+    cpu::Xlen,
+    emulator::{Emulator, StopReason},
+    exception::RVException,
+};
+
+// enough for any test program, while keeping a runaway loop from hanging the suite
+const DEFAULT_LIMIT: u64 = 1_000_000;
+
+#[derive(Clone, Debug)]
+enum Program {
+    Code(Vec<u8>),
+    Assembly(String),
+}
+
+/// A program to run in a fresh emulator, with the state to start from and
+/// the state expected once it stops.
+///
+/// The program is followed by an ECALL, and is expected to stop there
+/// unless told otherwise. On failure, every difference from the expected
+/// state is reported along with the final registers.
+#[derive(Clone, Debug)]
+pub struct TestCase {
+    program: Program,
+    xlen: Xlen,
+    ram_size: usize,
+    limit: u64,
+
+    registers: Vec<(usize, u64)>,
+    memory: Vec<(Address, Vec<u8>)>,
+    csrs: Vec<(u16, u64)>,
+
+    expected_registers: Vec<(usize, u64)>,
+    expected_memory: Vec<(Address, Vec<u8>)>,
+    expected_csrs: Vec<(u16, u64)>,
+    expected_pc: Option<Address>,
+
+    // None when the instruction limit should be reached instead
+    expected_exception: Option<RVException>,
+}
+
+impl TestCase {
+    pub fn new(code: Vec<u8>) -> Self {
+        Self::with_program(Program::Code(code))
+    }
+
+    // assembled for the width of the test, panicking on errors in the source
+    pub fn asm(source: &str) -> Self {
+        Self::with_program(Program::Assembly(source.to_string()))
+    }
+
+    fn with_program(program: Program) -> Self {
+        Self {
+            program,
+            xlen: Xlen::Rv64,
+            ram_size: 0x10000,
+            limit: DEFAULT_LIMIT,
+            registers: vec![],
+            memory: vec![],
+            csrs: vec![],
+            expected_registers: vec![],
+            expected_memory: vec![],
+            expected_csrs: vec![],
+            expected_pc: None,
+            expected_exception: Some(RVException::EnvironmentCall),
+        }
+    }
+
+    pub fn xlen(mut self, xlen: Xlen) -> Self {
+        self.xlen = xlen;
+        self
+    }
+
+    pub fn ram_size(mut self, ram_size: usize) -> Self {
+        self.ram_size = ram_size;
+        self
+    }
+
+    // the number of instructions to run at most
+    pub fn limit(mut self, limit: u64) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    pub fn register(mut self, index: usize, value: u64) -> Self {
+        self.registers.push((index, value));
+        self
+    }
+
+    pub fn memory(mut self, address: Address, data: &[u8]) -> Self {
+        self.memory.push((address, data.to_vec()));
+        self
+    }
+
+    pub fn csr(mut self, csr: u16, value: u64) -> Self {
+        self.csrs.push((csr, value));
+        self
+    }
+
+    pub fn expect_register(mut self, index: usize, value: u64) -> Self {
+        self.expected_registers.push((index, value));
+        self
+    }
+
+    pub fn expect_memory(mut self, address: Address, data: &[u8]) -> Self {
+        self.expected_memory.push((address, data.to_vec()));
+        self
+    }
+
+    pub fn expect_csr(mut self, csr: u16, value: u64) -> Self {
+        self.expected_csrs.push((csr, value));
+        self
+    }
+
+    // the pc of the instruction execution stopped at
+    pub fn expect_pc(mut self, pc: Address) -> Self {
+        self.expected_pc = Some(pc);
+        self
+    }
+
+    pub fn expect_exception(mut self, exception: RVException) -> Self {
+        self.expected_exception = Some(exception);
+        self
+    }
+
+    pub fn expect_limit(mut self) -> Self {
+        self.expected_exception = None;
+        self
+    }
+
+    // runs the program and checks every expectation, panicking with the differences if any fails
+    pub fn run(self) -> Emulator {
+        self.check().unwrap_or_else(|report| panic!("{report}"))
+    }
+
+    // runs the program, returning the emulator when it matches the expectations and a report otherwise
+    pub fn check(self) -> Result<Emulator, String> {
+        let mut emu = Emulator::with_xlen(self.ram_size, self.xlen);
+
+        // compile every block on first sight, so tests exercise the JIT
+        #[cfg(feature = "jit")]
+        emu.cpu.set_jit_threshold(0);
+
+        let mut code = match self.program {
+            Program::Code(code) => code,
+            Program::Assembly(source) => Assembler::new(RAM_BASE, self.xlen)
+                .assemble(&source)
+                .unwrap_or_else(|error| panic!("{error}")),
+        };
+        code.extend([0x73, 0x00, 0x00, 0x00]); // ECALL
+        emu.init_ram(code);
+
+        for &(index, value) in &self.registers {
+            emu.cpu.xregs[index] = self.xlen.sext(value);
+        }
+        for (address, data) in &self.memory {
+            if emu.write_memory(*address, data).is_err() {
+                panic!("cannot write {} bytes at {address:#x}", data.len());
+            }
+        }
+        for &(csr, value) in &self.csrs {
+            assert!(emu.cpu.write_csr(csr, value), "cannot write CSR {csr:#x}");
+        }
+
+        let reason = emu.run_for(self.limit);
+        let exception = match reason {
+            StopReason::LimitReached => None,
+            StopReason::Exited { .. } => Some(RVException::EnvironmentCall),
+            StopReason::Fault { exception, .. } => Some(exception),
+            StopReason::Breakpoint { .. } => Some(RVException::Breakpoint),
+
+            _ => panic!("unexpected stop: {reason:?}"),
+        };
+
+        let mut differences = vec![];
+        if exception != self.expected_exception {
+            let describe = |exception: Option<RVException>| match exception {
+                Some(exception) => format!("{exception:?}"),
+                None => format!("reaching the limit of {} instructions", self.limit),
+            };
+
+            differences.push(format!(
+                "stop: expected {}, found {}",
+                describe(self.expected_exception),
+                describe(exception)
+            ));
+        }
+
+        if let Some(pc) = self.expected_pc.filter(|&pc| pc != emu.cpu.pc) {
+            differences.push(format!("pc: expected {pc:#x}, found {:#x}", emu.cpu.pc));
+        }
+
+        for &(index, value) in &self.expected_registers {
+            let found = emu.cpu.xregs[index];
+            if found != value {
+                differences.push(format!(
+                    "x{index}: expected {value:#018x}, found {found:#018x}"
+                ));
+            }
+        }
+
+        for (address, data) in &self.expected_memory {
+            let mut found = vec![0; data.len()];
+            match emu.read_memory(*address, &mut found) {
+                Ok(()) if found == *data => {}
+                Ok(()) => differences.push(format!(
+                    "memory at {address:#x}: expected {data:02x?}, found {found:02x?}"
+                )),
+                Err(_) => differences.push(format!(
+                    "memory at {address:#x}: expected {data:02x?}, but it cannot be read"
+                )),
+            }
+        }
+
+        for &(csr, value) in &self.expected_csrs {
+            match emu.cpu.read_csr(csr) {
+                Some(found) if found == value => {}
+                Some(found) => differences.push(format!(
+                    "CSR {csr:#x}: expected {value:#x}, found {found:#x}"
+                )),
+                None => differences.push(format!(
+                    "CSR {csr:#x}: expected {value:#x}, but it does not exist"
+                )),
+            }
+        }
+
+        if differences.is_empty() {
+            return Ok(emu);
+        }
+
+        let mut report = String::from("machine state differs from the expectation:\n");
+        for difference in differences {
+            let _ = writeln!(report, "  {difference}");
+        }
+
+        let _ = writeln!(
+            report,
+            "stopped with {reason:?} after {} instructions, registers:",
+            emu.cpu.instret
+        );
+        for (index, row) in emu.cpu.xregs.chunks(4).enumerate() {
+            let row: Vec<String> = row
+                .iter()
+                .enumerate()
+                .map(|(column, value)| format!("x{:<2} {value:#018x}", 4 * index + column))
+                .collect();
+            let _ = writeln!(report, "  {}", row.join("  "));
+        }
+
+        Err(report)
+    }
+}
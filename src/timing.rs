@@ -0,0 +1,295 @@
+use std::{fmt, io};
+
+use crate::{
+    decoder::{AluOp, Instruction, VectorAddressing, VectorOp, VectorOperand},
+    snapshot::{get, put},
+};
+
+/// Cycles an instruction takes from entering execute until its result can be
+/// forwarded, and the cost of taken control flow.
+///
+/// Multiplies and divides hold execute for their whole latency. Loads hold
+/// it for all but their last cycle, so with the default of two an
+/// instruction using the result right away stalls for one cycle.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Latencies {
+    pub mul: u64,
+    pub div: u64,
+    pub load: u64,
+
+    // cycles lost refetching after a taken branch or jump, resolved in execute
+    pub branch_penalty: u64,
+}
+
+impl Default for Latencies {
+    fn default() -> Self {
+        Self {
+            mul: 3,
+            div: 20,
+            load: 2,
+            branch_penalty: 2,
+        }
+    }
+}
+
+/// Cycles lost to each kind of hazard.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Stalls {
+    // waiting for the result of the preceding load
+    pub load_use: u64,
+
+    // waiting for a multi-cycle instruction to leave execute
+    pub execute: u64,
+
+    // refetching after taken branches and jumps
+    pub branch: u64,
+
+    // waiting for the caches, when a cache simulation feeds the model
+    pub memory: u64,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Alu,
+    Mul,
+    Div,
+    Load,
+}
+
+/// Timing model of a classic five-stage in-order pipeline with full
+/// forwarding, fed with every retired instruction.
+///
+/// Instructions enter execute in program order, one cycle apart unless a
+/// source register is not ready yet, execute is still busy, or a taken
+/// branch flushed the stages behind it. The first instruction enters
+/// execute on cycle 2 and every instruction takes two more cycles to leave
+/// the pipeline, so a run of independent instructions takes four cycles
+/// more than it has instructions.
+#[derive(Clone, Debug)]
+pub struct Pipeline {
+    latencies: Latencies,
+
+    // first cycle the next instruction can enter execute
+    next: u64,
+
+    // cycle the last instruction left execute
+    end: u64,
+
+    // cycle from which each register can be forwarded, and whether a load produces it
+    ready: [u64; 32],
+    loaded: [bool; 32],
+
+    instructions: u64,
+    stalls: Stalls,
+}
+
+impl Pipeline {
+    pub fn new(latencies: Latencies) -> Self {
+        Self {
+            latencies,
+            next: 2,
+            end: 0,
+            ready: [0; 32],
+            loaded: [false; 32],
+            instructions: 0,
+            stalls: Stalls::default(),
+        }
+    }
+
+    pub fn latencies(&self) -> Latencies {
+        self.latencies
+    }
+
+    // cycles until the last instruction so far left the pipeline
+    pub fn cycles(&self) -> u64 {
+        match self.instructions {
+            0 => 0,
+            _ => self.end + 2,
+        }
+    }
+
+    pub fn instructions(&self) -> u64 {
+        self.instructions
+    }
+
+    // cycles per instruction, zero before the first one
+    pub fn cpi(&self) -> f64 {
+        match self.instructions {
+            0 => 0.0,
+            instructions => self.cycles() as f64 / instructions as f64,
+        }
+    }
+
+    pub fn stalls(&self) -> Stalls {
+        self.stalls
+    }
+
+    // accounts for a retired instruction that waited `memory` cycles for the caches, returning the cycles it added
+    pub(crate) fn retire(&mut self, instruction: &Instruction, taken: bool, memory: u64) -> u64 {
+        let before = self.cycles();
+        let (sources, destination, unit) = operands(instruction);
+
+        let mut issue = self.next;
+        for source in sources.into_iter().filter(|&source| source != 0) {
+            let ready = self.ready[source as usize];
+            if ready > issue {
+                // only loads produce results later than execute frees up
+                if self.loaded[source as usize] {
+                    self.stalls.load_use += ready - issue;
+                }
+                issue = ready;
+            }
+        }
+
+        let latencies = &self.latencies;
+        let (latency, occupancy) = match unit {
+            Unit::Alu => (1, 1),
+            Unit::Mul => (latencies.mul.max(1), latencies.mul.max(1)),
+            Unit::Div => (latencies.div.max(1), latencies.div.max(1)),
+            Unit::Load => (latencies.load.max(1), latencies.load.max(2) - 1),
+        };
+
+        // cache misses hold the whole pipeline
+        let (latency, occupancy) = (latency + memory, occupancy + memory);
+        self.stalls.memory += memory;
+
+        if destination != 0 {
+            self.ready[destination as usize] = issue + latency;
+            self.loaded[destination as usize] = unit == Unit::Load;
+        }
+
+        self.end = issue + occupancy;
+        self.next = self.end;
+        self.stalls.execute += occupancy - memory - 1;
+
+        if taken {
+            self.next += latencies.branch_penalty;
+            self.stalls.branch += latencies.branch_penalty;
+        }
+
+        self.instructions += 1;
+        self.cycles() - before
+    }
+
+    pub(crate) fn save_state(&self, out: &mut Vec<u8>) {
+        let stalls = &self.stalls;
+        for value in [self.next, self.end, self.instructions] {
+            put(out, value);
+        }
+        for register in 0..32 {
+            put(out, self.ready[register]);
+            put(out, self.loaded[register] as u64);
+        }
+        for value in [
+            stalls.load_use,
+            stalls.execute,
+            stalls.branch,
+            stalls.memory,
+        ] {
+            put(out, value);
+        }
+    }
+
+    pub(crate) fn load_state(&mut self, input: &mut &[u8]) -> io::Result<()> {
+        self.next = get(input)?;
+        self.end = get(input)?;
+        self.instructions = get(input)?;
+        for register in 0..32 {
+            self.ready[register] = get(input)?;
+            self.loaded[register] = get(input)? != 0;
+        }
+        self.stalls = Stalls {
+            load_use: get(input)?,
+            execute: get(input)?,
+            branch: get(input)?,
+            memory: get(input)?,
+        };
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Pipeline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let stalls = &self.stalls;
+
+        writeln!(
+            f,
+            "{} cycles, {} instructions, CPI {:.2}",
+            self.cycles(),
+            self.instructions,
+            self.cpi()
+        )?;
+        write!(
+            f,
+            "stalls: {} load-use, {} execute, {} branch, {} memory",
+            stalls.load_use, stalls.execute, stalls.branch, stalls.memory
+        )
+    }
+}
+
+// the integer registers an instruction reads and writes, and the unit executing it
+fn operands(instruction: &Instruction) -> ([u8; 2], u8, Unit) {
+    let scalar = |operand: &VectorOperand| match *operand {
+        VectorOperand::Scalar(register) => register,
+        _ => 0,
+    };
+    let stride = |addressing: &VectorAddressing| match *addressing {
+        VectorAddressing::Strided { rs2 } => rs2,
+        _ => 0,
+    };
+
+    match instruction {
+        Instruction::Lui { rd, .. }
+        | Instruction::Auipc { rd, .. }
+        | Instruction::Jal { rd, .. } => ([0, 0], *rd, Unit::Alu),
+        Instruction::Jalr { rd, rs1, .. } => ([*rs1, 0], *rd, Unit::Alu),
+        Instruction::Branch { rs1, rs2, .. } | Instruction::Store { rs1, rs2, .. } => {
+            ([*rs1, *rs2], 0, Unit::Alu)
+        }
+        Instruction::Load { rd, rs1, .. } => ([*rs1, 0], *rd, Unit::Load),
+
+        Instruction::OpImm { op, rd, rs1, .. } | Instruction::OpImm32 { op, rd, rs1, .. } => {
+            ([*rs1, 0], *rd, unit(*op))
+        }
+        Instruction::Op { op, rd, rs1, rs2 } | Instruction::Op32 { op, rd, rs1, rs2 } => {
+            ([*rs1, *rs2], *rd, unit(*op))
+        }
+
+        Instruction::Amo { rd, rs1, rs2, .. } => ([*rs1, *rs2], *rd, Unit::Load),
+        Instruction::Csr { rd, rs1, .. } => ([*rs1, 0], *rd, Unit::Alu),
+        Instruction::CsrImm { rd, .. } => ([0, 0], *rd, Unit::Alu),
+
+        Instruction::Vsetvl { rd, avl, vtype } => ([scalar(avl), scalar(vtype)], *rd, Unit::Alu),
+        Instruction::VectorLoad {
+            rs1, addressing, ..
+        } => ([*rs1, stride(addressing)], 0, Unit::Load),
+        Instruction::VectorStore {
+            rs1, addressing, ..
+        } => ([*rs1, stride(addressing)], 0, Unit::Alu),
+        Instruction::Vector {
+            op, vd, operand, ..
+        } => {
+            let destination = match op {
+                VectorOp::MvXS | VectorOp::Cpop | VectorOp::First => *vd,
+                _ => 0,
+            };
+            ([scalar(operand), 0], destination, Unit::Alu)
+        }
+
+        Instruction::Fence
+        | Instruction::FenceI
+        | Instruction::Ecall
+        | Instruction::Ebreak
+        | Instruction::Mret
+        | Instruction::Wfi => ([0, 0], 0, Unit::Alu),
+    }
+}
+
+fn unit(op: AluOp) -> Unit {
+    match op {
+        AluOp::Mul | AluOp::Mulh | AluOp::Mulhsu | AluOp::Mulhu => Unit::Mul,
+        AluOp::Div | AluOp::Divu | AluOp::Rem | AluOp::Remu => Unit::Div,
+        _ => Unit::Alu,
+    }
+}
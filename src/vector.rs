@@ -0,0 +1,1241 @@
+use std::ops::Range;
+
+use crate::{
+    cpu::CPU,
+    decoder::{Instruction, VectorAddressing, VectorOp, VectorOperand},
+    exception::RVException,
+};
+
+const VILL: u64 = 1 << 63;
+
+/// What tail and masked-off elements become when vtype marks them agnostic.
+///
+/// Hardware is free to do either, so software has to cope with both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AgnosticPolicy {
+    #[default]
+    Undisturbed,
+    Ones,
+}
+
+/// The vector register file along with vl, vtype and the other vector CSRs.
+///
+/// Registers are stored back to back, so a register group is simply a longer
+/// run of bytes starting at its first register.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VectorUnit {
+    // in bits
+    vlen: usize,
+    elen: usize,
+
+    registers: Vec<u8>,
+
+    pub agnostic: AgnosticPolicy,
+
+    pub vl: u64,
+    pub vtype: u64,
+    pub vstart: u64,
+    pub vxrm: u64,
+    pub vxsat: bool,
+}
+
+// the vtype fields an instruction operates under
+#[derive(Clone, Copy)]
+struct Shape {
+    // in bits
+    sew: usize,
+
+    // LMUL in eighths, so that fractional values stay integers
+    lmul8: usize,
+
+    vl: usize,
+    vstart: usize,
+    vlmax: usize,
+    ta: bool,
+    ma: bool,
+}
+
+// the resolved vs1/rs1/imm operand
+#[derive(Clone, Copy)]
+enum Source {
+    Vector(u8),
+    Scalar(u64),
+}
+
+impl Default for VectorUnit {
+    fn default() -> Self {
+        Self::new(128, 64)
+    }
+}
+
+impl VectorUnit {
+    // both in bits, with ELEN being either 32 or 64
+    pub fn new(vlen: usize, elen: usize) -> Self {
+        assert!(
+            Self::supports(vlen, elen),
+            "unsupported VLEN {vlen} / ELEN {elen}"
+        );
+
+        Self {
+            vlen,
+            elen,
+            registers: vec![0x00; 32 * vlen / 8],
+
+            agnostic: AgnosticPolicy::default(),
+
+            vl: 0,
+            vtype: VILL,
+            vstart: 0,
+            vxrm: 0,
+            vxsat: false,
+        }
+    }
+
+    pub(crate) fn supports(vlen: usize, elen: usize) -> bool {
+        matches!(elen, 32 | 64) && vlen.is_power_of_two() && (elen..=65536).contains(&vlen)
+    }
+
+    pub fn vlen(&self) -> usize {
+        self.vlen
+    }
+
+    pub fn elen(&self) -> usize {
+        self.elen
+    }
+
+    pub fn vlenb(&self) -> usize {
+        self.vlen / 8
+    }
+
+    pub fn register(&self, index: u8) -> &[u8] {
+        let start = index as usize * self.vlenb();
+        &self.registers[start..start + self.vlenb()]
+    }
+
+    pub fn register_mut(&mut self, index: u8) -> &mut [u8] {
+        let start = index as usize * self.vlenb();
+        let end = start + self.vlenb();
+        &mut self.registers[start..end]
+    }
+
+    pub(crate) fn registers(&self) -> &[u8] {
+        &self.registers
+    }
+
+    pub(crate) fn registers_mut(&mut self) -> &mut [u8] {
+        &mut self.registers
+    }
+
+    // element `index` of the group starting at `register`, `width` bits wide and zero-extended
+    pub fn element(&self, register: u8, index: usize, width: usize) -> u64 {
+        let bytes = width / 8;
+        let start = register as usize * self.vlenb() + index * bytes;
+
+        let mut value = [0u8; 8];
+        value[..bytes].copy_from_slice(&self.registers[start..start + bytes]);
+        u64::from_le_bytes(value)
+    }
+
+    pub fn set_element(&mut self, register: u8, index: usize, width: usize, value: u64) {
+        let bytes = width / 8;
+        let start = register as usize * self.vlenb() + index * bytes;
+
+        self.registers[start..start + bytes].copy_from_slice(&value.to_le_bytes()[..bytes]);
+    }
+
+    pub fn mask_bit(&self, register: u8, index: usize) -> bool {
+        let byte = self.registers[register as usize * self.vlenb() + index / 8];
+        (byte >> (index % 8)) & 0x01 != 0
+    }
+
+    pub fn set_mask_bit(&mut self, register: u8, index: usize, value: bool) {
+        let offset = register as usize * self.vlenb() + index / 8;
+        let byte = &mut self.registers[offset];
+        if value {
+            *byte |= 1 << (index % 8);
+        } else {
+            *byte &= !(1 << (index % 8));
+        }
+    }
+
+    // vsetvl{i}, where a missing AVL keeps the current vl; returns the new vl
+    pub(crate) fn configure(&mut self, avl: Option<u64>, vtype: u64) -> u64 {
+        let sew = 8usize << ((vtype >> 3) & 0x07);
+        let lmul8 = match vtype & 0x07 {
+            0b000 => Some(8),
+            0b001 => Some(16),
+            0b010 => Some(32),
+            0b011 => Some(64),
+            0b101 => Some(1),
+            0b110 => Some(2),
+            0b111 => Some(4),
+
+            _ => None,
+        };
+
+        // fractional LMUL must still fit one SEW element in ELEN
+        match lmul8 {
+            Some(lmul8) if vtype >> 8 == 0 && sew <= self.elen && sew * 8 <= lmul8 * self.elen => {
+                let vlmax = (self.vlen * lmul8 / 8 / sew) as u64;
+
+                self.vtype = vtype;
+                self.vl = avl.unwrap_or(self.vl).min(vlmax);
+            }
+
+            _ => {
+                self.vtype = VILL;
+                self.vl = 0;
+            }
+        }
+
+        self.vstart = 0;
+        self.vl
+    }
+
+    fn shape(&self) -> Result<Shape, RVException> {
+        if self.vtype & VILL != 0 {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let sew = 8 << ((self.vtype >> 3) & 0x07);
+        let lmul8 = match self.vtype & 0x07 {
+            0b101 => 1,
+            0b110 => 2,
+            0b111 => 4,
+            vlmul => 8 << vlmul,
+        };
+
+        Ok(Shape {
+            sew,
+            lmul8,
+            vl: self.vl as usize,
+            vstart: self.vstart as usize,
+            vlmax: self.vlen * lmul8 / 8 / sew,
+            ta: self.vtype & (1 << 6) != 0,
+            ma: self.vtype & (1 << 7) != 0,
+        })
+    }
+
+    // registers spanned by a group of `width`-bit elements, which must be legal and aligned
+    fn group(&self, shape: &Shape, register: u8, width: usize) -> Result<usize, RVException> {
+        let emul8 = shape.lmul8 * width / shape.sew;
+        if !(8..=self.elen).contains(&width) || !(1..=64).contains(&emul8) {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let registers = (emul8 / 8).max(1);
+        if !(register as usize).is_multiple_of(registers) {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        Ok(registers)
+    }
+
+    fn active(&self, masked: bool, index: usize) -> bool {
+        !masked || self.mask_bit(0, index)
+    }
+
+    fn operand(&self, source: Source, index: usize, width: usize) -> u64 {
+        match source {
+            Source::Vector(vs1) => self.element(vs1, index, width),
+            Source::Scalar(value) => value & mask(width),
+        }
+    }
+
+    // overwrites agnostic elements, if the policy says so
+    fn agnostic(&mut self, register: u8, width: usize, range: Range<usize>) {
+        if self.agnostic == AgnosticPolicy::Ones {
+            for index in range {
+                self.set_element(register, index, width, u64::MAX);
+            }
+        }
+    }
+
+    // writes the results for elements `start..vl` of a group of `width`-bit elements, where
+    // `None` marks a masked-off element, then deals with the tail
+    fn write_back(
+        &mut self,
+        vd: u8,
+        width: usize,
+        shape: &Shape,
+        start: usize,
+        results: Vec<Option<u64>>,
+    ) {
+        for (index, result) in (start..).zip(results) {
+            match result {
+                Some(value) => self.set_element(vd, index, width, value),
+                None if shape.ma => self.agnostic(vd, width, index..index + 1),
+                None => {}
+            }
+        }
+
+        if shape.ta {
+            let registers = (shape.lmul8 * width / shape.sew / 8).max(1);
+            self.agnostic(vd, width, shape.vl..registers * self.vlen / width);
+        }
+    }
+
+    // mask destinations always have an agnostic tail
+    fn write_mask(&mut self, vd: u8, shape: &Shape, start: usize, results: Vec<Option<bool>>) {
+        let ones = self.agnostic == AgnosticPolicy::Ones;
+
+        for (index, result) in (start..).zip(results) {
+            match result {
+                Some(value) => self.set_mask_bit(vd, index, value),
+                None if shape.ma && ones => self.set_mask_bit(vd, index, true),
+                None => {}
+            }
+        }
+
+        if ones {
+            for index in shape.vl..self.vlen {
+                self.set_mask_bit(vd, index, true);
+            }
+        }
+    }
+
+    // executes an OP-V instruction, returning the value of a scalar destination if it has one
+    pub(crate) fn execute(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        operand: VectorOperand,
+        masked: bool,
+        xregs: &[u64; 32],
+    ) -> Result<Option<u64>, RVException> {
+        let source = match operand {
+            VectorOperand::Vector(vs1) => Source::Vector(vs1),
+            VectorOperand::Scalar(rs1) => Source::Scalar(xregs[rs1 as usize]),
+
+            // shift amounts, slide offsets and gather indices are unsigned
+            VectorOperand::Immediate(imm) => match op {
+                VectorOp::Sll
+                | VectorOp::Srl
+                | VectorOp::Sra
+                | VectorOp::Ssrl
+                | VectorOp::Ssra
+                | VectorOp::Nsrl
+                | VectorOp::Nsra
+                | VectorOp::Nclipu
+                | VectorOp::Nclip
+                | VectorOp::Slideup
+                | VectorOp::Slidedown
+                | VectorOp::Rgather => Source::Scalar(imm & 0x1F),
+
+                _ => Source::Scalar(imm),
+            },
+        };
+
+        let result = self.dispatch(op, vd, vs2, source, masked)?;
+        self.vstart = 0;
+
+        Ok(result)
+    }
+
+    fn dispatch(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        source: Source,
+        masked: bool,
+    ) -> Result<Option<u64>, RVException> {
+        // whole register moves do not depend on vtype
+        if let VectorOp::MvNr(count) = op {
+            self.move_registers(vd, vs2, count as usize)?;
+            return Ok(None);
+        }
+
+        let shape = self.shape()?;
+        let sew = shape.sew;
+
+        // scalar destinations are written even without any body element
+        match op {
+            VectorOp::MvXS => return Ok(Some(sext(self.element(vs2, 0, sew), sew) as u64)),
+            VectorOp::Cpop | VectorOp::First => return self.mask_scalar(op, vs2, masked, &shape),
+
+            _ => {}
+        }
+
+        if shape.vstart >= shape.vl {
+            return Ok(None);
+        }
+
+        // only mask and scalar results may overwrite the mask they were computed under
+        if masked && vd == 0 && !is_compare(op) && !is_reduction(op) {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let widths = match op {
+            VectorOp::Waddu
+            | VectorOp::Wadd
+            | VectorOp::Wsubu
+            | VectorOp::Wsub
+            | VectorOp::Wmulu
+            | VectorOp::Wmulsu
+            | VectorOp::Wmul
+            | VectorOp::Wmaccu
+            | VectorOp::Wmacc
+            | VectorOp::Wmaccus
+            | VectorOp::Wmaccsu => [2 * sew, sew, sew],
+
+            VectorOp::WadduW | VectorOp::WaddW | VectorOp::WsubuW | VectorOp::WsubW => {
+                [2 * sew, 2 * sew, sew]
+            }
+
+            VectorOp::Nsrl | VectorOp::Nsra | VectorOp::Nclipu | VectorOp::Nclip => {
+                [sew, 2 * sew, sew]
+            }
+
+            VectorOp::Zext2 | VectorOp::Sext2 => [sew, sew / 2, sew],
+            VectorOp::Zext4 | VectorOp::Sext4 => [sew, sew / 4, sew],
+            VectorOp::Zext8 | VectorOp::Sext8 => [sew, sew / 8, sew],
+
+            _ => [sew, sew, sew],
+        };
+
+        match op {
+            _ if is_compare(op) => self.compare(op, vd, vs2, source, masked, &shape)?,
+
+            _ if is_reduction(op) => self.reduce(op, vd, vs2, source, masked, &shape)?,
+
+            VectorOp::Mandn
+            | VectorOp::Mand
+            | VectorOp::Mor
+            | VectorOp::Mxor
+            | VectorOp::Morn
+            | VectorOp::Mnand
+            | VectorOp::Mnor
+            | VectorOp::Mxnor => self.mask_logical(op, vd, vs2, source, &shape),
+
+            VectorOp::Msbf | VectorOp::Msif | VectorOp::Msof => {
+                self.set_first(op, vd, vs2, masked, &shape)?
+            }
+
+            VectorOp::Iota | VectorOp::Id => self.iota(op, vd, vs2, masked, &shape)?,
+
+            VectorOp::MvSX => {
+                self.set_element(vd, 0, sew, self.operand(source, 0, sew));
+                if shape.ta {
+                    self.agnostic(vd, sew, 1..self.vlen / sew);
+                }
+            }
+
+            VectorOp::Slideup
+            | VectorOp::Slidedown
+            | VectorOp::Slide1up
+            | VectorOp::Slide1down
+            | VectorOp::Rgather
+            | VectorOp::Rgatherei16 => self.permute(op, vd, vs2, source, masked, &shape)?,
+
+            VectorOp::Compress => self.compress(vd, vs2, source, &shape)?,
+
+            // zero- and sign-extensions have no vs1 operand
+            VectorOp::Zext2
+            | VectorOp::Sext2
+            | VectorOp::Zext4
+            | VectorOp::Sext4
+            | VectorOp::Zext8
+            | VectorOp::Sext8 => {
+                self.elementwise(op, vd, vs2, Source::Scalar(0), masked, widths, &shape)?
+            }
+
+            _ => self.elementwise(op, vd, vs2, source, masked, widths, &shape)?,
+        }
+
+        Ok(None)
+    }
+
+    // runs an operation over every body element, with `widths` holding the width of the
+    // vd, vs2 and vs1/rs1 elements
+    #[allow(clippy::too_many_arguments)]
+    fn elementwise(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        source: Source,
+        masked: bool,
+        widths: [usize; 3],
+        shape: &Shape,
+    ) -> Result<(), RVException> {
+        let [dw, aw, bw] = widths;
+
+        self.group(shape, vd, dw)?;
+        self.group(shape, vs2, aw)?;
+        if let Source::Vector(vs1) = source {
+            self.group(shape, vs1, bw)?;
+        }
+
+        // for these, v0 is an operand rather than a mask
+        let carry = masked && matches!(op, VectorOp::Adc | VectorOp::Sbc | VectorOp::Merge);
+        let masked = masked && !carry;
+
+        let inputs: Vec<_> = (shape.vstart..shape.vl)
+            .map(|index| {
+                self.active(masked, index).then(|| {
+                    (
+                        self.element(vs2, index, aw),
+                        self.operand(source, index, bw),
+                        self.element(vd, index, dw),
+                        carry && self.mask_bit(0, index),
+                    )
+                })
+            })
+            .collect();
+
+        let results = inputs
+            .into_iter()
+            .map(|input| input.map(|(a, b, d, c)| self.compute(op, shape.sew, a, b, d, c)))
+            .collect();
+
+        self.write_back(vd, dw, shape, shape.vstart, results);
+        Ok(())
+    }
+
+    // a is the vs2 element, b the vs1/rs1/imm one and d the old vd one, with n being SEW
+    fn compute(&mut self, op: VectorOp, n: usize, a: u64, b: u64, d: u64, carry: bool) -> u64 {
+        let (sa, sb) = (sext(a, n), sext(b, n));
+        let shift = b & (n as u64 - 1);
+        let wide_shift = b & (2 * n as u64 - 1);
+
+        match op {
+            VectorOp::Add => a.wrapping_add(b),
+            VectorOp::Sub => a.wrapping_sub(b),
+            VectorOp::Rsub => b.wrapping_sub(a),
+            VectorOp::Minu => a.min(b),
+            VectorOp::Min => sa.min(sb) as u64,
+            VectorOp::Maxu => a.max(b),
+            VectorOp::Max => sa.max(sb) as u64,
+            VectorOp::And => a & b,
+            VectorOp::Or => a | b,
+            VectorOp::Xor => a ^ b,
+            VectorOp::Sll => a << shift,
+            VectorOp::Srl => a >> shift,
+            VectorOp::Sra => (sa >> shift) as u64,
+
+            VectorOp::Mul => a.wrapping_mul(b),
+            VectorOp::Mulh => ((sa as i128 * sb as i128) >> n) as u64,
+            VectorOp::Mulhu => ((a as u128 * b as u128) >> n) as u64,
+            VectorOp::Mulhsu => ((sa as i128 * b as i128) >> n) as u64,
+
+            // overflowing divisions wrap to the right result once truncated to SEW
+            VectorOp::Divu => a.checked_div(b).unwrap_or(u64::MAX),
+            VectorOp::Div => match sb {
+                0 => u64::MAX,
+                _ => sa.wrapping_div(sb) as u64,
+            },
+            VectorOp::Remu => a.checked_rem(b).unwrap_or(a),
+            VectorOp::Rem => match sb {
+                0 => a,
+                _ => sa.wrapping_rem(sb) as u64,
+            },
+
+            VectorOp::Madd => b.wrapping_mul(d).wrapping_add(a),
+            VectorOp::Nmsub => a.wrapping_sub(b.wrapping_mul(d)),
+            VectorOp::Macc => b.wrapping_mul(a).wrapping_add(d),
+            VectorOp::Nmsac => d.wrapping_sub(b.wrapping_mul(a)),
+
+            VectorOp::Adc => a.wrapping_add(b).wrapping_add(carry as u64),
+            VectorOp::Sbc => a.wrapping_sub(b).wrapping_sub(carry as u64),
+            VectorOp::Merge => match carry {
+                true => b,
+                false => a,
+            },
+            VectorOp::Mv => b,
+
+            VectorOp::Zext2 | VectorOp::Zext4 | VectorOp::Zext8 => a,
+            VectorOp::Sext2 => sext(a, n / 2) as u64,
+            VectorOp::Sext4 => sext(a, n / 4) as u64,
+            VectorOp::Sext8 => sext(a, n / 8) as u64,
+
+            // widening, where SEW is at most 32 bits so that products fit
+            VectorOp::Waddu => a + b,
+            VectorOp::Wadd => (sa + sb) as u64,
+            VectorOp::Wsubu => a.wrapping_sub(b),
+            VectorOp::Wsub => (sa - sb) as u64,
+            VectorOp::WadduW => a.wrapping_add(b),
+            VectorOp::WaddW => (sext(a, 2 * n) + sb) as u64,
+            VectorOp::WsubuW => a.wrapping_sub(b),
+            VectorOp::WsubW => (sext(a, 2 * n) - sb) as u64,
+            VectorOp::Wmulu => a * b,
+            VectorOp::Wmulsu => (sa * b as i64) as u64,
+            VectorOp::Wmul => (sa * sb) as u64,
+            VectorOp::Wmaccu => d.wrapping_add(b * a),
+            VectorOp::Wmacc => d.wrapping_add((sb * sa) as u64),
+            VectorOp::Wmaccus => d.wrapping_add((b as i64 * sa) as u64),
+            VectorOp::Wmaccsu => d.wrapping_add((sb * a as i64) as u64),
+
+            // narrowing, where a is 2 * SEW bits wide
+            VectorOp::Nsrl => a >> wide_shift,
+            VectorOp::Nsra => (sext(a, 2 * n) >> wide_shift) as u64,
+            VectorOp::Nclipu => {
+                let value = self.round(a as i128, wide_shift);
+                self.saturate_unsigned(value, n)
+            }
+            VectorOp::Nclip => {
+                let value = self.round(sext(a, 2 * n) as i128, wide_shift);
+                self.saturate_signed(value, n)
+            }
+
+            // fixed-point
+            VectorOp::Saddu => self.saturate_unsigned(a as i128 + b as i128, n),
+            VectorOp::Sadd => self.saturate_signed(sa as i128 + sb as i128, n),
+            VectorOp::Ssubu => self.saturate_unsigned(a as i128 - b as i128, n),
+            VectorOp::Ssub => self.saturate_signed(sa as i128 - sb as i128, n),
+            VectorOp::Aaddu => self.round(a as i128 + b as i128, 1) as u64,
+            VectorOp::Aadd => self.round(sa as i128 + sb as i128, 1) as u64,
+            VectorOp::Asubu => self.round(a as i128 - b as i128, 1) as u64,
+            VectorOp::Asub => self.round(sa as i128 - sb as i128, 1) as u64,
+            VectorOp::Smul => {
+                let value = self.round(sa as i128 * sb as i128, n as u64 - 1);
+                self.saturate_signed(value, n)
+            }
+            VectorOp::Ssrl => self.round(a as i128, shift) as u64,
+            VectorOp::Ssra => self.round(sa as i128, shift) as u64,
+
+            _ => unreachable!("{op:?} is not an element-wise operation"),
+        }
+    }
+
+    // shifts right by `shift` bits, rounding as vxrm says
+    fn round(&self, value: i128, shift: u64) -> i128 {
+        if shift == 0 {
+            return value;
+        }
+
+        let bit = |index: u64| (value >> index) & 0x01;
+        let below = |bits: u64| value & ((1u128 << bits) - 1) as i128 != 0;
+
+        let increment = match self.vxrm {
+            // round to nearest, ties up
+            0b00 => bit(shift - 1),
+            // round to nearest, ties to even
+            0b01 => bit(shift - 1) & (below(shift - 1) as i128 | bit(shift)),
+            // round down
+            0b10 => 0,
+            // round to odd
+            _ => (bit(shift) ^ 0x01) & below(shift) as i128,
+        };
+
+        (value >> shift) + increment
+    }
+
+    fn saturate_unsigned(&mut self, value: i128, n: usize) -> u64 {
+        let max = mask(n) as i128;
+        if value < 0 || value > max {
+            self.vxsat = true;
+        }
+
+        value.clamp(0, max) as u64
+    }
+
+    fn saturate_signed(&mut self, value: i128, n: usize) -> u64 {
+        let (min, max) = (-(1i128 << (n - 1)), (1i128 << (n - 1)) - 1);
+        if value < min || value > max {
+            self.vxsat = true;
+        }
+
+        value.clamp(min, max) as u64
+    }
+
+    fn compare(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        source: Source,
+        masked: bool,
+        shape: &Shape,
+    ) -> Result<(), RVException> {
+        let n = shape.sew;
+
+        self.group(shape, vs2, n)?;
+        if let Source::Vector(vs1) = source {
+            self.group(shape, vs1, n)?;
+        }
+
+        // for carries, v0 is the carry-in rather than a mask
+        let carry = masked && matches!(op, VectorOp::Madc | VectorOp::Msbc);
+        let masked = masked && !carry;
+
+        let results = (shape.vstart..shape.vl)
+            .map(|index| {
+                if !self.active(masked, index) {
+                    return None;
+                }
+
+                let a = self.element(vs2, index, n);
+                let b = self.operand(source, index, n);
+                let c = (carry && self.mask_bit(0, index)) as u128;
+                let (sa, sb) = (sext(a, n), sext(b, n));
+
+                Some(match op {
+                    VectorOp::Mseq => a == b,
+                    VectorOp::Msne => a != b,
+                    VectorOp::Msltu => a < b,
+                    VectorOp::Mslt => sa < sb,
+                    VectorOp::Msleu => a <= b,
+                    VectorOp::Msle => sa <= sb,
+                    VectorOp::Msgtu => a > b,
+                    VectorOp::Msgt => sa > sb,
+                    VectorOp::Madc => (a as u128 + b as u128 + c) >> n != 0,
+                    VectorOp::Msbc => (a as u128) < b as u128 + c,
+
+                    _ => unreachable!("{op:?} is not a comparison"),
+                })
+            })
+            .collect();
+
+        self.write_mask(vd, shape, shape.vstart, results);
+        Ok(())
+    }
+
+    fn reduce(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        source: Source,
+        masked: bool,
+        shape: &Shape,
+    ) -> Result<(), RVException> {
+        let n = shape.sew;
+        let width = match op {
+            VectorOp::Wredsumu | VectorOp::Wredsum => 2 * n,
+            _ => n,
+        };
+
+        self.group(shape, vs2, n)?;
+        if shape.vstart != 0 || width > self.elen {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let Source::Vector(vs1) = source else {
+            unreachable!("reductions always take a vector operand");
+        };
+
+        let mut accumulator = self.element(vs1, 0, width);
+        for index in 0..shape.vl {
+            if !self.active(masked, index) {
+                continue;
+            }
+
+            let element = self.element(vs2, index, n);
+            let (sa, se) = (sext(accumulator, n), sext(element, n));
+
+            accumulator = match op {
+                VectorOp::Redsum | VectorOp::Wredsumu => accumulator.wrapping_add(element),
+                VectorOp::Wredsum => accumulator.wrapping_add(se as u64),
+                VectorOp::Redand => accumulator & element,
+                VectorOp::Redor => accumulator | element,
+                VectorOp::Redxor => accumulator ^ element,
+                VectorOp::Redminu => accumulator.min(element),
+                VectorOp::Redmin => sa.min(se) as u64 & mask(n),
+                VectorOp::Redmaxu => accumulator.max(element),
+                VectorOp::Redmax => sa.max(se) as u64 & mask(n),
+
+                _ => unreachable!("{op:?} is not a reduction"),
+            };
+        }
+
+        self.set_element(vd, 0, width, accumulator);
+        if shape.ta {
+            self.agnostic(vd, width, 1..self.vlen / width);
+        }
+
+        Ok(())
+    }
+
+    fn mask_logical(&mut self, op: VectorOp, vd: u8, vs2: u8, source: Source, shape: &Shape) {
+        let Source::Vector(vs1) = source else {
+            unreachable!("mask instructions always take a vector operand");
+        };
+
+        let results = (shape.vstart..shape.vl)
+            .map(|index| {
+                let (a, b) = (self.mask_bit(vs2, index), self.mask_bit(vs1, index));
+
+                Some(match op {
+                    VectorOp::Mandn => a & !b,
+                    VectorOp::Mand => a & b,
+                    VectorOp::Mor => a | b,
+                    VectorOp::Mxor => a ^ b,
+                    VectorOp::Morn => a | !b,
+                    VectorOp::Mnand => !(a & b),
+                    VectorOp::Mnor => !(a | b),
+                    VectorOp::Mxnor => !(a ^ b),
+
+                    _ => unreachable!("{op:?} is not a mask instruction"),
+                })
+            })
+            .collect();
+
+        self.write_mask(vd, shape, shape.vstart, results);
+    }
+
+    // vcpop.m and vfirst.m
+    fn mask_scalar(
+        &self,
+        op: VectorOp,
+        vs2: u8,
+        masked: bool,
+        shape: &Shape,
+    ) -> Result<Option<u64>, RVException> {
+        if shape.vstart != 0 {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let mut set =
+            (0..shape.vl).filter(|&index| self.active(masked, index) && self.mask_bit(vs2, index));
+
+        Ok(Some(match op {
+            VectorOp::Cpop => set.count() as u64,
+            _ => set.next().map_or(u64::MAX, |index| index as u64),
+        }))
+    }
+
+    // vmsbf.m, vmsif.m and vmsof.m
+    fn set_first(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        masked: bool,
+        shape: &Shape,
+    ) -> Result<(), RVException> {
+        if shape.vstart != 0 || vd == vs2 {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let mut found = false;
+        let results = (0..shape.vl)
+            .map(|index| {
+                if !self.active(masked, index) {
+                    return None;
+                }
+
+                let bit = self.mask_bit(vs2, index);
+                let result = match op {
+                    VectorOp::Msbf => !found && !bit,
+                    VectorOp::Msif => !found,
+                    _ => !found && bit,
+                };
+
+                found |= bit;
+                Some(result)
+            })
+            .collect();
+
+        self.write_mask(vd, shape, 0, results);
+        Ok(())
+    }
+
+    // viota.m and vid.v
+    fn iota(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        masked: bool,
+        shape: &Shape,
+    ) -> Result<(), RVException> {
+        self.group(shape, vd, shape.sew)?;
+        if op == VectorOp::Iota && shape.vstart != 0 {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let mut count = 0;
+        let results = (shape.vstart..shape.vl)
+            .map(|index| {
+                if !self.active(masked, index) {
+                    return None;
+                }
+
+                if op == VectorOp::Id {
+                    return Some(index as u64);
+                }
+
+                let result = count;
+                count += self.mask_bit(vs2, index) as u64;
+                Some(result)
+            })
+            .collect();
+
+        self.write_back(vd, shape.sew, shape, shape.vstart, results);
+        Ok(())
+    }
+
+    // slides and gathers, whose destination must not overlap their sources
+    fn permute(
+        &mut self,
+        op: VectorOp,
+        vd: u8,
+        vs2: u8,
+        source: Source,
+        masked: bool,
+        shape: &Shape,
+    ) -> Result<(), RVException> {
+        let n = shape.sew;
+
+        let registers = self.group(shape, vd, n)?;
+        self.group(shape, vs2, n)?;
+        let mut overlaps = overlap(vd, vs2, registers, registers);
+
+        if let Source::Vector(vs1) = source {
+            let width = match op {
+                VectorOp::Rgatherei16 => 16,
+                _ => n,
+            };
+
+            let sources = self.group(shape, vs1, width)?;
+            overlaps |= overlap(vd, vs1, registers, sources);
+        }
+
+        if overlaps && op != VectorOp::Slidedown && op != VectorOp::Slide1down {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let offset = match source {
+            Source::Scalar(value) => value,
+            Source::Vector(_) => 0,
+        };
+
+        // slideup leaves the elements below the offset untouched
+        let start = match op {
+            VectorOp::Slideup => shape.vstart.max(offset.min(shape.vl as u64) as usize),
+            _ => shape.vstart,
+        };
+
+        let results = (start..shape.vl)
+            .map(|index| {
+                if !self.active(masked, index) {
+                    return None;
+                }
+
+                let from = |index: u64| match index < shape.vlmax as u64 {
+                    true => self.element(vs2, index as usize, n),
+                    false => 0,
+                };
+
+                Some(match op {
+                    VectorOp::Slideup => from(index as u64 - offset),
+                    VectorOp::Slidedown => from((index as u64).saturating_add(offset)),
+                    VectorOp::Slide1up if index == 0 => offset & mask(n),
+                    VectorOp::Slide1up => from(index as u64 - 1),
+                    VectorOp::Slide1down if index + 1 == shape.vl => offset & mask(n),
+                    VectorOp::Slide1down => from(index as u64 + 1),
+                    VectorOp::Rgather => match source {
+                        Source::Vector(vs1) => from(self.element(vs1, index, n)),
+                        Source::Scalar(value) => from(value),
+                    },
+                    VectorOp::Rgatherei16 => match source {
+                        Source::Vector(vs1) => from(self.element(vs1, index, 16)),
+                        Source::Scalar(_) => unreachable!("vrgatherei16 only has a .vv form"),
+                    },
+
+                    _ => unreachable!("{op:?} is not a permutation"),
+                })
+            })
+            .collect();
+
+        self.write_back(vd, n, shape, start, results);
+        Ok(())
+    }
+
+    fn compress(
+        &mut self,
+        vd: u8,
+        vs2: u8,
+        source: Source,
+        shape: &Shape,
+    ) -> Result<(), RVException> {
+        let n = shape.sew;
+        let Source::Vector(vs1) = source else {
+            unreachable!("vcompress only has a .vm form");
+        };
+
+        let registers = self.group(shape, vd, n)?;
+        self.group(shape, vs2, n)?;
+        if shape.vstart != 0
+            || overlap(vd, vs2, registers, registers)
+            || overlap(vd, vs1, registers, 1)
+        {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let packed: Vec<_> = (0..shape.vl)
+            .filter(|&index| self.mask_bit(vs1, index))
+            .map(|index| self.element(vs2, index, n))
+            .collect();
+
+        let count = packed.len();
+        for (index, value) in packed.into_iter().enumerate() {
+            self.set_element(vd, index, n, value);
+        }
+
+        // everything past the packed elements is tail
+        if shape.ta {
+            self.agnostic(vd, n, count..registers * self.vlen / n);
+        }
+
+        Ok(())
+    }
+
+    fn move_registers(&mut self, vd: u8, vs2: u8, count: usize) -> Result<(), RVException> {
+        if !(vd as usize).is_multiple_of(count) || !(vs2 as usize).is_multiple_of(count) {
+            return Err(RVException::IllegalInstruction);
+        }
+
+        let sew = self.shape().map_or(8, |shape| shape.sew);
+        let vlenb = self.vlenb();
+        let start = (self.vstart as usize * sew / 8).min(count * vlenb);
+
+        let from = vs2 as usize * vlenb;
+        self.registers.copy_within(
+            from + start..from + count * vlenb,
+            vd as usize * vlenb + start,
+        );
+
+        Ok(())
+    }
+}
+
+impl CPU {
+    pub(crate) fn vsetvl(&mut self, rd: u8, avl: VectorOperand, vtype: VectorOperand) {
+        let vtype = match vtype {
+            VectorOperand::Scalar(rs2) => self.xregs[rs2 as usize],
+            VectorOperand::Immediate(imm) => imm,
+            VectorOperand::Vector(_) => unreachable!("vtype never comes from a vector register"),
+        };
+
+        // with rs1 = x0, vl is either set to VLMAX or kept as it is
+        let avl = match avl {
+            VectorOperand::Scalar(0) if rd == 0 => None,
+            VectorOperand::Scalar(0) => Some(u64::MAX),
+            VectorOperand::Scalar(rs1) => Some(self.xregs[rs1 as usize]),
+            VectorOperand::Immediate(imm) => Some(imm),
+            VectorOperand::Vector(_) => unreachable!("AVL never comes from a vector register"),
+        };
+
+        self.xregs[rd as usize] = self.vector.configure(avl, vtype);
+    }
+
+    // executes a vector load or store
+    pub(crate) fn vector_transfer(&mut self, instruction: Instruction) -> Result<(), RVException> {
+        let (store, register, rs1, addressing, eew, nf, masked) = match instruction {
+            Instruction::VectorLoad {
+                vd,
+                rs1,
+                addressing,
+                eew,
+                nf,
+                masked,
+            } => (false, vd, rs1, addressing, eew, nf, masked),
+
+            Instruction::VectorStore {
+                vs3,
+                rs1,
+                addressing,
+                eew,
+                nf,
+                masked,
+            } => (true, vs3, rs1, addressing, eew, nf, masked),
+
+            _ => unreachable!("{instruction:?} is not a vector load or store"),
+        };
+
+        let base = self.xregs[rs1 as usize];
+        let (eew, nf) = (eew as usize, nf as usize);
+
+        // whole register transfers ignore vtype and vl altogether
+        if addressing == VectorAddressing::WholeRegister {
+            if !(register as usize).is_multiple_of(nf) {
+                return Err(self.fault(RVException::IllegalInstruction, 0));
+            }
+
+            let count = nf * self.vector.vlen() / eew;
+            for index in self.vector.vstart as usize..count {
+                let address = self
+                    .xlen()
+                    .zext(base.wrapping_add((index * eew / 8) as u64));
+                if let Err(ex) = self.transfer_element(store, register, index, eew, address) {
+                    self.vector.vstart = index as u64;
+                    return Err(ex);
+                }
+            }
+
+            self.vector.vstart = 0;
+            return Ok(());
+        }
+
+        let shape = self.vector.shape().map_err(|ex| self.fault(ex, 0))?;
+
+        // mask transfers move ceil(vl / 8) bytes, while indexed ones use SEW for the data
+        let (width, vl) = match addressing {
+            VectorAddressing::Mask => (8, shape.vl.div_ceil(8)),
+            VectorAddressing::Indexed { .. } => (shape.sew, shape.vl),
+            _ => (eew, shape.vl),
+        };
+
+        let registers = match addressing {
+            VectorAddressing::Mask => Ok(1),
+            _ => self.vector.group(&shape, register, width),
+        };
+        let indices = match addressing {
+            VectorAddressing::Indexed { vs2, .. } => {
+                self.vector.group(&shape, vs2, eew).map(|_| ())
+            }
+            _ => Ok(()),
+        };
+
+        let registers = match (registers, indices) {
+            (Ok(registers), Ok(()))
+                if nf * registers <= 8 && register as usize + nf * registers <= 32 =>
+            {
+                registers
+            }
+            _ => return Err(self.fault(RVException::IllegalInstruction, 0)),
+        };
+
+        if masked && register == 0 && !store {
+            return Err(self.fault(RVException::IllegalInstruction, 0));
+        }
+
+        if shape.vstart >= vl {
+            self.vector.vstart = 0;
+            return Ok(());
+        }
+
+        let stride = match addressing {
+            VectorAddressing::Strided { rs2 } => self.xregs[rs2 as usize],
+            _ => (nf * width / 8) as u64,
+        };
+
+        let mut vl = vl;
+        'elements: for index in shape.vstart..vl {
+            let fields = (0..nf).map(|field| register + (field * registers) as u8);
+
+            if !self.vector.active(masked, index) {
+                if !store && shape.ma {
+                    for field in fields {
+                        self.vector.agnostic(field, width, index..index + 1);
+                    }
+                }
+                continue;
+            }
+
+            for (field, destination) in fields.enumerate() {
+                let offset = match addressing {
+                    VectorAddressing::Indexed { vs2, .. } => self.vector.element(vs2, index, eew),
+                    _ => stride.wrapping_mul(index as u64),
+                };
+                let address = self.xlen().zext(
+                    base.wrapping_add(offset)
+                        .wrapping_add((field * width / 8) as u64),
+                );
+
+                if let Err(ex) = self.transfer_element(store, destination, index, width, address) {
+                    // only the first element of a fault-only-first load traps
+                    if addressing == VectorAddressing::FaultOnlyFirst && index > 0 {
+                        self.vector.vl = index as u64;
+                        vl = index;
+                        break 'elements;
+                    }
+
+                    self.vector.vstart = index as u64;
+                    return Err(ex);
+                }
+            }
+        }
+
+        // mask loads always have an agnostic tail
+        if !store && (shape.ta || addressing == VectorAddressing::Mask) {
+            for field in 0..nf {
+                let destination = register + (field * registers) as u8;
+                let end = registers * self.vector.vlen() / width;
+                self.vector.agnostic(destination, width, vl..end);
+            }
+        }
+
+        self.vector.vstart = 0;
+        Ok(())
+    }
+
+    fn transfer_element(
+        &mut self,
+        store: bool,
+        register: u8,
+        index: usize,
+        width: usize,
+        address: u64,
+    ) -> Result<(), RVException> {
+        if store {
+            let value = self.vector.element(register, index, width);
+            return match width {
+                8 => self.write::<u8>(address, value as u8),
+                16 => self.write::<u16>(address, value as u16),
+                32 => self.write::<u32>(address, value as u32),
+                _ => self.write::<u64>(address, value),
+            };
+        }
+
+        let value = match width {
+            8 => self.read::<u8>(address)? as u64,
+            16 => self.read::<u16>(address)? as u64,
+            32 => self.read::<u32>(address)? as u64,
+            _ => self.read::<u64>(address)?,
+        };
+
+        self.vector.set_element(register, index, width, value);
+        Ok(())
+    }
+}
+
+fn is_compare(op: VectorOp) -> bool {
+    matches!(
+        op,
+        VectorOp::Mseq
+            | VectorOp::Msne
+            | VectorOp::Msltu
+            | VectorOp::Mslt
+            | VectorOp::Msleu
+            | VectorOp::Msle
+            | VectorOp::Msgtu
+            | VectorOp::Msgt
+            | VectorOp::Madc
+            | VectorOp::Msbc
+    )
+}
+
+fn is_reduction(op: VectorOp) -> bool {
+    matches!(
+        op,
+        VectorOp::Redsum
+            | VectorOp::Redand
+            | VectorOp::Redor
+            | VectorOp::Redxor
+            | VectorOp::Redminu
+            | VectorOp::Redmin
+            | VectorOp::Redmaxu
+            | VectorOp::Redmax
+            | VectorOp::Wredsumu
+            | VectorOp::Wredsum
+    )
+}
+
+// whether two register groups share a register
+fn overlap(a: u8, b: u8, a_registers: usize, b_registers: usize) -> bool {
+    let (a, b) = (a as usize, b as usize);
+    a < b + b_registers && b < a + a_registers
+}
+
+fn mask(width: usize) -> u64 {
+    u64::MAX >> (64 - width)
+}
+
+// interprets the low `width` bits of `value` as signed
+fn sext(value: u64, width: usize) -> i64 {
+    ((value << (64 - width)) as i64) >> (64 - width)
+}
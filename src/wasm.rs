@@ -0,0 +1,198 @@
+//! JavaScript bindings, built with
+//! `cargo build --release --target wasm32-unknown-unknown --features wasm`
+//! and then run through `wasm-bindgen`. 64-bit values are BigInts on the
+//! JavaScript side.
+
+use std::sync::Arc;
+
+use js_sys::Function;
+use wasm_bindgen::prelude::*;
+
+use crate::{
+    bus::Device,
+    console::{Console, CONSOLE_BASE, CONSOLE_SIZE},
+    cpu::Xlen,
+    emulator::Emulator,
+    ffi::{self, stop},
+};
+
+#[wasm_bindgen(js_name = Emulator)]
+pub struct WasmEmulator {
+    emu: Emulator,
+}
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StopKind {
+    LimitReached,
+    Breakpoint,
+    Watchpoint,
+    Condition,
+    Exited,
+    Fault,
+    StartOfHistory,
+    Waiting,
+    Diverged,
+}
+
+impl From<ffi::StopKind> for StopKind {
+    fn from(kind: ffi::StopKind) -> Self {
+        match kind {
+            ffi::StopKind::LimitReached => Self::LimitReached,
+            ffi::StopKind::Breakpoint => Self::Breakpoint,
+            ffi::StopKind::Watchpoint => Self::Watchpoint,
+            ffi::StopKind::Condition => Self::Condition,
+            ffi::StopKind::Exited => Self::Exited,
+            ffi::StopKind::Fault => Self::Fault,
+            ffi::StopKind::StartOfHistory => Self::StartOfHistory,
+            ffi::StopKind::Waiting => Self::Waiting,
+            ffi::StopKind::Diverged => Self::Diverged,
+        }
+    }
+}
+
+/// Why execution stopped, with the fields of the C `Stop`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Stop {
+    pub kind: StopKind,
+    pub pc: u64,
+    pub value: u64,
+    pub cause: u32,
+}
+
+impl From<ffi::Stop> for Stop {
+    fn from(stop: ffi::Stop) -> Self {
+        Self {
+            kind: stop.kind.into(),
+            pc: stop.pc,
+            value: stop.value,
+            cause: stop.cause,
+        }
+    }
+}
+
+// wasm32-unknown-unknown has a single thread, so the function never leaves it
+struct Callback(Function);
+
+unsafe impl Send for Callback {}
+unsafe impl Sync for Callback {}
+
+#[wasm_bindgen(js_class = Emulator)]
+impl WasmEmulator {
+    #[wasm_bindgen(constructor)]
+    pub fn new(ram_size: usize, xlen: u32) -> Result<WasmEmulator, JsError> {
+        let xlen = match xlen {
+            32 => Xlen::Rv32,
+            64 => Xlen::Rv64,
+
+            _ => return Err(JsError::new("the register width must be 32 or 64")),
+        };
+
+        Ok(Self {
+            emu: Emulator::with_xlen(ram_size, xlen),
+        })
+    }
+
+    // copies a raw image to the start of RAM and points pc at it
+    #[wasm_bindgen(js_name = loadImage)]
+    pub fn load_image(&mut self, data: &[u8]) -> Result<(), JsError> {
+        if data.len() > self.emu.cpu.bus.ram.size() {
+            return Err(JsError::new("the image does not fit in RAM"));
+        }
+
+        self.emu.init_ram(data.to_vec());
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = loadElf)]
+    pub fn load_elf(&mut self, data: &[u8]) -> Result<(), JsError> {
+        self.emu
+            .load_elf(data)
+            .map_err(|error| JsError::new(&error.to_string()))
+    }
+
+    pub fn step(&mut self) -> Stop {
+        let reason = self.emu.step();
+        stop(&mut self.emu, reason).into()
+    }
+
+    #[wasm_bindgen(js_name = runFor)]
+    pub fn run_for(&mut self, count: u64) -> Stop {
+        let reason = self.emu.run_for(count);
+        stop(&mut self.emu, reason).into()
+    }
+
+    pub fn register(&self, index: u32) -> Result<u64, JsError> {
+        let cpu = &self.emu.cpu;
+        cpu.xregs
+            .get(index as usize)
+            .map(|&value| cpu.xlen().zext(value))
+            .ok_or_else(|| JsError::new("no such register"))
+    }
+
+    // writes to x0 are ignored
+    #[wasm_bindgen(js_name = setRegister)]
+    pub fn set_register(&mut self, index: u32, value: u64) -> Result<(), JsError> {
+        let cpu = &mut self.emu.cpu;
+        if index >= 32 {
+            return Err(JsError::new("no such register"));
+        }
+
+        if index != 0 {
+            cpu.xregs[index as usize] = cpu.xlen().sext(value);
+        }
+        Ok(())
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn pc(&self) -> u64 {
+        self.emu.cpu.pc
+    }
+
+    #[wasm_bindgen(setter)]
+    pub fn set_pc(&mut self, pc: u64) {
+        let cpu = &mut self.emu.cpu;
+        cpu.pc = cpu.xlen().zext(pc);
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn instret(&self) -> u64 {
+        self.emu.cpu.instret
+    }
+
+    #[wasm_bindgen(js_name = readMemory)]
+    pub fn read_memory(&mut self, address: u64, length: usize) -> Result<Vec<u8>, JsError> {
+        let mut data = vec![0; length];
+        self.emu
+            .read_memory(address, &mut data)
+            .map_err(|_| JsError::new("unmapped memory"))?;
+
+        Ok(data)
+    }
+
+    #[wasm_bindgen(js_name = writeMemory)]
+    pub fn write_memory(&mut self, address: u64, data: &[u8]) -> Result<(), JsError> {
+        self.emu
+            .write_memory(address, data)
+            .map_err(|_| JsError::new("unmapped memory"))
+    }
+
+    // maps a 16550 console at 0x10000000, calling `callback` with every byte written to it
+    #[wasm_bindgen(js_name = attachConsole)]
+    pub fn attach_console(&mut self, callback: Function) -> Result<(), JsError> {
+        let callback = Callback(callback);
+        let console = Console::new(move |byte| {
+            // exceptions thrown by the callback are dropped, the guest cannot handle them
+            let _ = callback.0.call1(&JsValue::NULL, &JsValue::from(byte));
+        });
+
+        match self
+            .emu
+            .map_mmio(CONSOLE_BASE, CONSOLE_SIZE, Arc::new(console))
+        {
+            true => Ok(()),
+            false => Err(JsError::new("a console is already attached")),
+        }
+    }
+}
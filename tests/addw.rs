@@ -1,6 +1,3 @@
-use risemu::emulator::Emulator;
-use risemu::exception::RVException;
-
 mod macros;
 
 #[test]
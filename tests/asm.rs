@@ -0,0 +1,370 @@
+use risemu::asm::{assemble, AsmError, Assembler};
+use risemu::bus::RAM_BASE;
+use risemu::cpu::Xlen;
+use risemu::emulator::{Emulator, StopReason};
+
+mod macros;
+
+fn check(xlen: Xlen, encodings: &[(&str, u32)]) {
+    for &(source, word) in encodings {
+        let bytes = Assembler::new(RAM_BASE, xlen).assemble(source);
+        assert_eq!(bytes, Ok(word.to_le_bytes().to_vec()), "{source}");
+    }
+}
+
+fn run(source: &str) -> Emulator {
+    let mut code = assemble(source).unwrap();
+    code.extend(assemble("li a7, 93\necall").unwrap());
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(code);
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    emu
+}
+
+fn error(source: &str) -> AsmError {
+    assemble(source).unwrap_err()
+}
+
+// the encodings below come from llvm-mc
+#[test]
+fn instructions() {
+    check(
+        Xlen::Rv64,
+        &[
+            ("lui a0, 0xfffff", 0xfffff537),
+            ("auipc t0, 1", 0x00001297),
+            ("jal ra, 2048", 0x001000ef),
+            ("jalr t1, -4(a2)", 0xffc60367),
+            ("beq a0, a1, -8", 0xfeb50ce3),
+            ("bne s0, s1, 4094", 0x7e941fe3),
+            ("blt t0, t1, -4096", 0x8062c063),
+            ("bge a2, a3, 16", 0x00d65863),
+            ("bltu a4, a5, 12", 0x00f76663),
+            ("bgeu s10, s11, 8", 0x01bd7463),
+            ("lb a0, -1(sp)", 0xfff10503),
+            ("lh a1, 2(s0)", 0x00241583),
+            ("lw a2, 2047(t0)", 0x7ff2a603),
+            ("ld a3, -2048(gp)", 0x8001b683),
+            ("lbu a4, 0(tp)", 0x00024703),
+            ("lhu a5, 6(a0)", 0x00655783),
+            ("lwu a6, 12(a1)", 0x00c5e803),
+            ("sb a0, -1(sp)", 0xfea10fa3),
+            ("sh a1, 2(s0)", 0x00b41123),
+            ("sw a2, 2047(t0)", 0x7ec2afa3),
+            ("sd a3, -2048(gp)", 0x80d1b023),
+            ("addi x31, x30, -1", 0xffff0f93),
+            ("slti a0, a1, 5", 0x0055a513),
+            ("sltiu a0, a1, 5", 0x0055b513),
+            ("xori a0, a1, -1", 0xfff5c513),
+            ("ori a0, a1, 0x7ff", 0x7ff5e513),
+            ("andi a0, a1, 255", 0x0ff5f513),
+            ("slli a0, a1, 63", 0x03f59513),
+            ("srli a0, a1, 1", 0x0015d513),
+            ("srai a0, a1, 40", 0x4285d513),
+            ("addiw a0, a1, -5", 0xffb5851b),
+            ("slliw a0, a1, 31", 0x01f5951b),
+            ("srliw a0, a1, 3", 0x0035d51b),
+            ("sraiw a0, a1, 7", 0x4075d51b),
+            ("add a0, a1, a2", 0x00c58533),
+            ("sub a0, a1, a2", 0x40c58533),
+            ("sll a0, a1, a2", 0x00c59533),
+            ("slt a0, a1, a2", 0x00c5a533),
+            ("sltu a0, a1, a2", 0x00c5b533),
+            ("xor a0, a1, a2", 0x00c5c533),
+            ("srl a0, a1, a2", 0x00c5d533),
+            ("sra a0, a1, a2", 0x40c5d533),
+            ("or a0, a1, a2", 0x00c5e533),
+            ("and a0, a1, a2", 0x00c5f533),
+            ("addw a0, a1, a2", 0x00c5853b),
+            ("subw a0, a1, a2", 0x40c5853b),
+            ("sllw a0, a1, a2", 0x00c5953b),
+            ("srlw a0, a1, a2", 0x00c5d53b),
+            ("sraw a0, a1, a2", 0x40c5d53b),
+            ("mul a0, a1, a2", 0x02c58533),
+            ("mulh a0, a1, a2", 0x02c59533),
+            ("mulhsu a0, a1, a2", 0x02c5a533),
+            ("mulhu a0, a1, a2", 0x02c5b533),
+            ("div a0, a1, a2", 0x02c5c533),
+            ("divu a0, a1, a2", 0x02c5d533),
+            ("rem a0, a1, a2", 0x02c5e533),
+            ("remu a0, a1, a2", 0x02c5f533),
+            ("mulw a0, a1, a2", 0x02c5853b),
+            ("divw a0, a1, a2", 0x02c5c53b),
+            ("divuw a0, a1, a2", 0x02c5d53b),
+            ("remw a0, a1, a2", 0x02c5e53b),
+            ("remuw a0, a1, a2", 0x02c5f53b),
+            ("lr.w a0, (a1)", 0x1005a52f),
+            ("lr.d.aq a0, (a1)", 0x1405b52f),
+            ("sc.w.rl a0, a2, (a1)", 0x1ac5a52f),
+            ("sc.d.aqrl a0, a2, (a1)", 0x1ec5b52f),
+            ("amoswap.w a0, a2, (a1)", 0x08c5a52f),
+            ("amoadd.d a0, a2, (a1)", 0x00c5b52f),
+            ("amoxor.w a0, a2, (a1)", 0x20c5a52f),
+            ("amoand.d a0, a2, (a1)", 0x60c5b52f),
+            ("amoor.w a0, a2, (a1)", 0x40c5a52f),
+            ("amomin.d a0, a2, (a1)", 0x80c5b52f),
+            ("amomax.w a0, a2, (a1)", 0xa0c5a52f),
+            ("amominu.d a0, a2, (a1)", 0xc0c5b52f),
+            ("amomaxu.w.aq a0, a2, (a1)", 0xe4c5a52f),
+            ("fence", 0x0ff0000f),
+            ("fence rw, w", 0x0310000f),
+            ("fence.tso", 0x8330000f),
+            ("fence.i", 0x0000100f),
+            ("ecall", 0x00000073),
+            ("ebreak", 0x00100073),
+            ("mret", 0x30200073),
+            ("wfi", 0x10500073),
+            ("csrrw a0, mstatus, a1", 0x30059573),
+            ("csrrs a0, mepc, zero", 0x34102573),
+            ("csrrc a0, 0x7c0, a1", 0x7c05b573),
+            ("csrrwi a0, pmpcfg0, 31", 0x3a0fd573),
+            ("csrrsi a0, pmpaddr63, 1", 0x3ef0e573),
+            ("csrrci a0, vlenb, 0", 0xc2207573),
+            ("sh1add a0, a1, a2", 0x20c5a533),
+            ("sh2add a0, a1, a2", 0x20c5c533),
+            ("sh3add a0, a1, a2", 0x20c5e533),
+            ("add.uw a0, a1, a2", 0x08c5853b),
+            ("sh1add.uw a0, a1, a2", 0x20c5a53b),
+            ("sh2add.uw a0, a1, a2", 0x20c5c53b),
+            ("sh3add.uw a0, a1, a2", 0x20c5e53b),
+            ("slli.uw a0, a1, 40", 0x0a85951b),
+            ("andn a0, a1, a2", 0x40c5f533),
+            ("orn a0, a1, a2", 0x40c5e533),
+            ("xnor a0, a1, a2", 0x40c5c533),
+            ("clz a0, a1", 0x60059513),
+            ("ctz a0, a1", 0x60159513),
+            ("cpop a0, a1", 0x60259513),
+            ("clzw a0, a1", 0x6005951b),
+            ("ctzw a0, a1", 0x6015951b),
+            ("cpopw a0, a1", 0x6025951b),
+            ("max a0, a1, a2", 0x0ac5e533),
+            ("maxu a0, a1, a2", 0x0ac5f533),
+            ("min a0, a1, a2", 0x0ac5c533),
+            ("minu a0, a1, a2", 0x0ac5d533),
+            ("sext.b a0, a1", 0x60459513),
+            ("sext.h a0, a1", 0x60559513),
+            ("zext.h a0, a1", 0x0805c53b),
+            ("rol a0, a1, a2", 0x60c59533),
+            ("ror a0, a1, a2", 0x60c5d533),
+            ("rolw a0, a1, a2", 0x60c5953b),
+            ("rorw a0, a1, a2", 0x60c5d53b),
+            ("rori a0, a1, 60", 0x63c5d513),
+            ("roriw a0, a1, 30", 0x61e5d51b),
+            ("orc.b a0, a1", 0x2875d513),
+            ("rev8 a0, a1", 0x6b85d513),
+            ("clmul a0, a1, a2", 0x0ac59533),
+            ("clmulh a0, a1, a2", 0x0ac5b533),
+            ("clmulr a0, a1, a2", 0x0ac5a533),
+            ("bclr a0, a1, a2", 0x48c59533),
+            ("bclri a0, a1, 63", 0x4bf59513),
+            ("bext a0, a1, a2", 0x48c5d533),
+            ("bexti a0, a1, 0", 0x4805d513),
+            ("binv a0, a1, a2", 0x68c59533),
+            ("binvi a0, a1, 33", 0x6a159513),
+            ("bset a0, a1, a2", 0x28c59533),
+            ("bseti a0, a1, 5", 0x28559513),
+        ],
+    );
+}
+
+#[test]
+fn pseudo_instructions() {
+    check(
+        Xlen::Rv64,
+        &[
+            ("nop", 0x00000013),
+            ("mv a0, a1", 0x00058513),
+            ("not a0, a1", 0xfff5c513),
+            ("neg a0, a1", 0x40b00533),
+            ("negw a0, a1", 0x40b0053b),
+            ("sext.w a0, a1", 0x0005851b),
+            ("zext.b a0, a1", 0x0ff5f513),
+            ("zext.w a0, a1", 0x0805853b),
+            ("seqz a0, a1", 0x0015b513),
+            ("snez a0, a1", 0x00b03533),
+            ("sltz a0, a1", 0x0005a533),
+            ("sgtz a0, a1", 0x00b02533),
+            ("beqz a0, 8", 0x00050463),
+            ("bnez a0, -8", 0xfe051ce3),
+            ("blez a0, 8", 0x00a05463),
+            ("bgez a0, 8", 0x00055463),
+            ("bltz a0, 8", 0x00054463),
+            ("bgtz a0, 8", 0x00a04463),
+            ("bgt a0, a1, 8", 0x00a5c463),
+            ("ble a0, a1, 8", 0x00a5d463),
+            ("bgtu a0, a1, 8", 0x00a5e463),
+            ("bleu a0, a1, 8", 0x00a5f463),
+            ("j -16", 0xff1ff06f),
+            ("jal 16", 0x010000ef),
+            ("jr a0", 0x00050067),
+            ("jalr a0", 0x000500e7),
+            ("ret", 0x00008067),
+            ("csrr a0, misa", 0x30102573),
+            ("csrw mscratch, a1", 0x34059073),
+            ("csrs mie, a1", 0x3045a073),
+            ("csrc mip, a1", 0x3445b073),
+            ("csrwi mtvec, 4", 0x30525073),
+            ("csrsi mstatus, 8", 0x30046073),
+            ("csrci mstatus, 8", 0x30047073),
+        ],
+    );
+}
+
+#[test]
+fn rv32() {
+    check(
+        Xlen::Rv32,
+        &[
+            ("slli a0, a1, 31", 0x01f59513),
+            ("srai a0, a1, 31", 0x41f5d513),
+            ("rev8 a0, a1", 0x6985d513),
+            ("zext.h a0, a1", 0x0805c533),
+            ("rori a0, a1, 31", 0x61f5d513),
+            ("bseti a0, a1, 31", 0x29f59513),
+        ],
+    );
+
+    let rv32 = Assembler::new(RAM_BASE, Xlen::Rv32);
+    for source in [
+        "ld a0, 0(a1)",
+        "addiw a0, a0, 1",
+        "slli a0, a0, 32",
+        "sext.w a0, a0",
+    ] {
+        assert!(rv32.assemble(source).is_err(), "{source}");
+    }
+}
+
+#[test]
+fn loops() {
+    asm_test_case!(
+        10,
+        55,
+        "
+            li a0, 0
+            li t0, 10
+        loop:
+            add a0, a0, t0
+            addi t0, t0, -1
+            bnez t0, loop
+        "
+    );
+
+    // numeric labels can be redefined, and are referred to by direction
+    asm_test_case!(
+        11,
+        6,
+        "
+            li a1, 0
+            li t0, 3
+        1:  addi a1, a1, 1
+            addi t0, t0, -1
+            beqz t0, 1f
+            j 1b
+        1:  slli a1, a1, 1
+        "
+    );
+}
+
+#[test]
+fn load_immediate() {
+    let values: [u64; 10] = [
+        0,
+        0x7ff,
+        0xfffffffffffff800,
+        0x12345000,
+        0x7ffff800,
+        0xffffffff80000000,
+        0x80000000,
+        0x123456789abcdef0,
+        0x8000000000000000,
+        0xffffffff,
+    ];
+
+    let source: String = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| format!("li x{}, {value:#x}\n", index + 5))
+        .collect();
+    let emu = run(&source);
+
+    for (index, &value) in values.iter().enumerate() {
+        assert_eq!(emu.cpu.xregs[index + 5], value, "{value:#x}");
+    }
+}
+
+#[test]
+fn data_and_relocations() {
+    let emu = run("
+        .equ CONSTANT, 0x12345fff
+            la a0, numbers
+            ld a1, 0(a0)
+            lw a2, 8(a0)
+            lbu a3, 12(a0)
+        1:  auipc a4, %pcrel_hi(message)
+            lbu a4, %pcrel_lo(1b)(a4)
+            lui a5, %hi(CONSTANT)
+            addi a5, a5, %lo(CONSTANT)
+            j end
+
+        .data
+        .align 3
+        numbers:
+            .dword 0x1122334455667788
+            .word -2
+            .byte 'z', 0
+        message:
+            .asciz \"hi\\n\"
+        .balign 4
+        end:
+    ");
+
+    assert_eq!(emu.cpu.xregs[11], 0x1122334455667788);
+    assert_eq!(emu.cpu.xregs[12], 0xfffffffffffffffe);
+    assert_eq!(emu.cpu.xregs[13], b'z' as u64);
+    assert_eq!(emu.cpu.xregs[14], b'h' as u64);
+    assert_eq!(emu.cpu.xregs[15], 0x12345fff);
+}
+
+#[test]
+fn calls() {
+    let emu = run("
+            li a0, 5
+            call double
+            call twice
+            j end
+        twice:
+            mv s0, ra
+            call double
+            mv ra, s0
+            tail double
+        double:
+            add a0, a0, a0
+            ret
+        end:
+    ");
+
+    assert_eq!(emu.cpu.xregs[10], 40);
+}
+
+#[test]
+fn errors() {
+    assert_eq!(
+        error("nop\n\nfrob a0"),
+        AsmError {
+            line: 3,
+            message: "unknown instruction `frob`".into(),
+        }
+    );
+
+    assert_eq!(error("nop\nj nowhere").line, 2);
+    assert_eq!(error("x: nop\nx: nop").line, 2);
+    assert_eq!(error("addi a0, a0, 2048").line, 1);
+    assert_eq!(error("add a0, a1").line, 1);
+    assert_eq!(error("add a0, a1, x32").line, 1);
+    assert_eq!(error("li a0, later\n.equ later, 1").line, 1);
+    assert_eq!(error("beq a0, a1, 3").line, 1);
+    assert_eq!(error(".byte 256").line, 1);
+    assert_eq!(error(".frob").line, 1);
+}
@@ -0,0 +1,155 @@
+use risemu::bus::RAM_BASE;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+
+fn emulator(code: Vec<u8>) -> Emulator {
+    let mut code = code;
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(code);
+    emu
+}
+
+fn run(code: Vec<u8>) -> Emulator {
+    let mut emu = emulator(code);
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    emu
+}
+
+#[test]
+fn amo_word() {
+    let emu = run(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x02, 0xb0, 0xff, // li t0, -5
+        0x23, 0x20, 0x55, 0x00, // sw t0, 0(a0)
+        0x13, 0x03, 0x70, 0x00, // li t1, 7
+        0x93, 0x03, 0xf0, 0xff, // li t2, -1
+        0xaf, 0x25, 0x65, 0x00, // amoadd.w a1, t1, (a0)
+        0x2f, 0x26, 0x65, 0x08, // amoswap.w a2, t1, (a0)
+        0xaf, 0x26, 0x75, 0x80, // amomin.w a3, t2, (a0)
+        0x2f, 0x27, 0x65, 0xc0, // amominu.w a4, t1, (a0)
+        0xaf, 0x27, 0x75, 0xa0, // amomax.w a5, t2, (a0)
+        0x2f, 0x28, 0x75, 0xe0, // amomaxu.w a6, t2, (a0)
+        0x2f, 0x29, 0x65, 0x60, // amoand.w s2, t1, (a0)
+        0xaf, 0x29, 0x65, 0x20, // amoxor.w s3, t1, (a0)
+        0x2f, 0x2a, 0x75, 0x40, // amoor.w s4, t2, (a0)
+    ]);
+
+    assert_eq!(emu.cpu.xregs[11], -5i64 as u64);
+    assert_eq!(emu.cpu.xregs[12], 2);
+    assert_eq!(emu.cpu.xregs[13], 7);
+    assert_eq!(emu.cpu.xregs[14], u64::MAX);
+    assert_eq!(emu.cpu.xregs[15], 7);
+    assert_eq!(emu.cpu.xregs[16], 7);
+    assert_eq!(emu.cpu.xregs[18], u64::MAX);
+    assert_eq!(emu.cpu.xregs[19], 7);
+    assert_eq!(emu.cpu.xregs[20], 0);
+    assert_eq!(
+        emu.cpu.bus.ram().contents()[0x1000..0x1008],
+        [0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn amo_double() {
+    let emu = run(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x02, 0x10, 0x00, // li t0, 1
+        0x93, 0x92, 0xf2, 0x03, // slli t0, t0, 63
+        0x13, 0x03, 0xf0, 0xff, // li t1, -1
+        0xaf, 0x35, 0x55, 0x40, // amoor.d a1, t0, (a0)
+        0x2f, 0x36, 0x05, 0x80, // amomin.d a2, zero, (a0)
+        0xaf, 0x36, 0x65, 0xe0, // amomaxu.d a3, t1, (a0)
+        0x2f, 0x37, 0x65, 0x00, // amoadd.d a4, t1, (a0)
+    ]);
+
+    assert_eq!(emu.cpu.xregs[11], 0);
+    assert_eq!(emu.cpu.xregs[12], 1 << 63);
+    assert_eq!(emu.cpu.xregs[13], 1 << 63);
+    assert_eq!(emu.cpu.xregs[14], u64::MAX);
+    assert_eq!(
+        emu.cpu.bus.ram().contents()[0x1000..0x1008],
+        [0xfe, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]
+    );
+}
+
+#[test]
+fn load_reserved_store_conditional() {
+    let emu = run(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x02, 0x50, 0x00, // li t0, 5
+        0x23, 0x20, 0x55, 0x00, // sw t0, 0(a0)
+        0xaf, 0x25, 0x05, 0x10, // lr.w a1, (a0)
+        0x93, 0x85, 0x15, 0x00, // addi a1, a1, 1
+        0x2f, 0x26, 0xb5, 0x18, // sc.w a2, a1, (a0)
+        0xaf, 0x26, 0xb5, 0x18, // sc.w a3, a1, (a0)
+        0x2f, 0x27, 0x05, 0x10, // lr.w a4, (a0)
+        0x23, 0x20, 0x05, 0x00, // sw zero, 0(a0)
+        0xaf, 0x27, 0xb5, 0x18, // sc.w a5, a1, (a0)
+        0x2f, 0x33, 0x05, 0x10, // lr.d t1, (a0)
+        0x2f, 0x28, 0xb5, 0x18, // sc.w a6, a1, (a0)
+        0x2f, 0x33, 0x05, 0x10, // lr.d t1, (a0)
+        0x2f, 0x39, 0xb5, 0x18, // sc.d s2, a1, (a0)
+    ]);
+
+    assert_eq!(emu.cpu.xregs[12], 0);
+    assert_eq!(emu.cpu.xregs[13], 1);
+    assert_eq!(emu.cpu.xregs[14], 6);
+    assert_eq!(emu.cpu.xregs[15], 1);
+    assert_eq!(emu.cpu.xregs[16], 1);
+    assert_eq!(emu.cpu.xregs[18], 0);
+    assert_eq!(emu.cpu.bus.ram().contents()[0x1000..0x1004], [6, 0, 0, 0]);
+}
+
+#[test]
+fn misaligned() {
+    let mut emu = emulator(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x13, 0x05, 0x25, 0x00, // addi a0, a0, 2
+        0xaf, 0x25, 0x05, 0x10, // lr.w a1, (a0)
+    ]);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::LoadAddressMisaligned,
+            pc: RAM_BASE + 8,
+            tval: RAM_BASE + 0x1002,
+        }
+    );
+
+    let mut emu = emulator(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x13, 0x05, 0x45, 0x00, // addi a0, a0, 4
+        0xaf, 0x35, 0xb5, 0x00, // amoadd.d a1, a1, (a0)
+    ]);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::StoreAddressMisaligned,
+            pc: RAM_BASE + 8,
+            tval: RAM_BASE + 0x1004,
+        }
+    );
+}
+
+#[test]
+fn outside_ram() {
+    let mut emu = emulator(vec![
+        0xaf, 0x25, 0xb0, 0x08, // amoswap.w a1, a1, (zero)
+    ]);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::StoreAccessFault,
+            pc: RAM_BASE,
+            tval: 0,
+        }
+    );
+}
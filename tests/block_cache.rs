@@ -0,0 +1,208 @@
+use std::sync::{Arc, Mutex};
+
+use risemu::asm::assemble;
+use risemu::bus::{Address, RAM_BASE};
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::mmio::Mmio;
+
+mod macros;
+
+// a device holding code, which the host may rewrite
+struct Rom(Mutex<Vec<u8>>);
+
+impl Mmio for Rom {
+    fn read(&self, offset: Address, size: usize) -> Option<u64> {
+        let code = self.0.lock().unwrap();
+        let bytes = code.get(offset as usize..offset as usize + size)?;
+
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| (value << 8) | byte as u64),
+        )
+    }
+
+    fn write(&self, _offset: Address, _size: usize, _value: u64) -> bool {
+        false
+    }
+}
+
+// where nothing but a device can be, far past RAM
+const HIGH: Address = 0xffff_ffff_0000_0000;
+
+fn emulator(block_cache: bool, code: Vec<u8>) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.cpu.set_block_cache(block_cache);
+    emu.init_ram(code);
+    emu
+}
+
+#[test]
+fn self_modifying_same_block() {
+    test_case!(
+        14,
+        0x00000002,
+        vec![
+            0x17, 0x05, 0x00, 0x00, // auipc x10, 0
+            0x83, 0x25, 0x45, 0x01, // lw x11, 20(x10)
+            0x23, 0x26, 0xb5, 0x00, // sw x11, 12(x10)
+            0x13, 0x07, 0x10, 0x00, // li x14, 1
+            0x73, 0x00, 0x00, 0x00, // ecall
+            0x13, 0x07, 0x20, 0x00, // .word (li x14, 2)
+        ]
+    );
+}
+
+#[test]
+fn precise_fault_mid_block() {
+    for block_cache in [false, true] {
+        let mut emu = emulator(
+            block_cache,
+            vec![
+                0x13, 0x07, 0x10, 0x00, // li x14, 1
+                0x93, 0x07, 0x20, 0x00, // li x15, 2
+                0x03, 0x38, 0x00, 0x00, // ld x16, 0(x0)
+                0x13, 0x07, 0x30, 0x00, // li x14, 3
+            ],
+        );
+
+        assert!(matches!(emu.run(), Err(RVException::LoadAccessFault)));
+        assert_eq!(emu.cpu.pc, RAM_BASE + 8);
+        assert_eq!(emu.cpu.instret, 2);
+        assert_eq!(emu.cpu.xregs[14], 1);
+        assert_eq!(emu.cpu.xregs[15], 2);
+    }
+}
+
+#[test]
+fn run_for_stops_mid_block() {
+    for block_cache in [false, true] {
+        let mut emu = emulator(
+            block_cache,
+            vec![
+                0x93, 0x02, 0x00, 0x00, // li x5, 0
+                0x13, 0x03, 0x40, 0x06, // li x6, 100
+                0x93, 0x82, 0x12, 0x00, // addi x5, x5, 1
+                0xe3, 0x9e, 0x62, 0xfe, // bne x5, x6, -4
+                0x73, 0x00, 0x00, 0x00, // ecall
+            ],
+        );
+
+        emu.cpu.run_for(2 + 2 * 10 + 1).unwrap();
+        assert_eq!(emu.cpu.xregs[5], 11);
+        assert_eq!(emu.cpu.pc, RAM_BASE + 12);
+
+        assert!(matches!(emu.run(), Err(RVException::EnvironmentCall)));
+        assert_eq!(emu.cpu.xregs[5], 100);
+        assert_eq!(emu.cpu.instret, 2 + 2 * 100);
+    }
+}
+
+#[test]
+fn jump_to_the_last_page() {
+    for block_cache in [false, true] {
+        let mut emu = emulator(
+            block_cache,
+            vec![
+                0x93, 0x02, 0x80, 0xff, // li x5, -8
+                0x67, 0x80, 0x02, 0x00, // jr x5
+            ],
+        );
+
+        assert!(matches!(
+            emu.run(),
+            Err(RVException::InstructionAccessFault)
+        ));
+        assert_eq!(emu.cpu.pc, 0xfffffffffffffff8);
+    }
+}
+
+// every instruction with a handler of its own, against the interpreter
+const BOUND: &str = "
+        li t0, -5
+        li t1, 3
+        lui t2, 0x12345
+        auipc t3, 0
+        la s0, data
+        sd t0, 0(s0)
+        sw t1, 8(s0)
+        sh t2, 12(s0)
+        sb t0, 14(s0)
+        ld a0, 0(s0)
+        lw a1, 0(s0)
+        lwu a2, 0(s0)
+        lh a3, 12(s0)
+        lhu a4, 12(s0)
+        lb a5, 14(s0)
+        lbu a6, 14(s0)
+        addi s1, t0, 7
+        slti s2, t0, 1
+        sltiu s3, t0, 1
+        xori s4, t0, 0x55
+        ori s5, t0, 0x70
+        andi s6, t0, 0x70
+        slli s7, t0, 40
+        srli s8, t0, 3
+        srai s9, t0, 3
+        add s10, t0, t1
+        sub s11, t0, t1
+        slt t4, t0, t1
+        sltu t5, t0, t1
+        xor t6, t0, t1
+        or a7, t0, t1
+        and gp, t0, t1
+        sll tp, t0, t1
+        srl ra, t0, t1
+        sra sp, t0, t1
+        mul t3, t0, t2
+        addiw t1, t2, -1
+        addw t2, t0, t2
+        subw t0, t0, t2
+        li a0, 4
+    1:
+        addi a0, a0, -1
+        beq a0, zero, 2f
+        bne a0, zero, 1b
+    2:
+        blt t0, t1, 3f
+        nop
+    3:
+        bge t0, t1, 4f
+        bltu t0, t1, 4f
+        bgeu t0, t1, 4f
+    4:
+        ecall
+    data:
+        .dword 0
+        .dword 0
+";
+
+#[test]
+fn bound_handlers() {
+    let run = |block_cache| {
+        let mut emu = emulator(block_cache, assemble(BOUND).unwrap());
+        assert!(matches!(emu.run(), Err(RVException::EnvironmentCall)));
+        emu
+    };
+
+    let interpreted = run(false);
+    let translated = run(true);
+    assert_eq!(translated.cpu.xregs, interpreted.cpu.xregs);
+    assert_eq!(translated.cpu.pc, interpreted.cpu.pc);
+    assert_eq!(translated.cpu.instret, interpreted.cpu.instret);
+}
+
+#[test]
+fn runs_from_high_mmio() {
+    for block_cache in [false, true] {
+        let mut emu = emulator(block_cache, vec![]);
+        emu.cpu.set_decode_cache(false);
+        let code = assemble("li a0, 5\nli a7, 93\necall").unwrap();
+        assert!(emu.map_mmio(HIGH, 0x1000, Arc::new(Rom(Mutex::new(code)))));
+        emu.cpu.pc = HIGH;
+
+        assert_eq!(emu.run_for(100), StopReason::Exited { code: 5 });
+    }
+}
@@ -0,0 +1,173 @@
+use risemu::asm::assemble;
+use risemu::branch::{
+    Bimodal, BranchKind, BranchPrediction, BranchStats, Gshare, Predictor, ReturnStack, Tage,
+};
+use risemu::bus::{Address, RAM_BASE};
+use risemu::decoder::Instruction;
+use risemu::emulator::{Emulator, StopReason};
+
+fn simulate(source: &str, predictor: impl Predictor + 'static) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble(source).unwrap());
+
+    // compiled blocks would otherwise go unseen
+    #[cfg(feature = "jit")]
+    emu.cpu.set_jit_threshold(0);
+
+    emu.cpu.branch_prediction = Some(BranchPrediction::new(predictor));
+    assert_eq!(emu.run_for(1_000_000), StopReason::Exited { code: 0 });
+    emu
+}
+
+fn stats(emu: &Emulator, pc: Address) -> BranchStats {
+    let prediction = emu.cpu.branch_prediction.as_ref().unwrap();
+    prediction.branches()[&pc]
+}
+
+// a loop of 100 iterations, with its back edge at RAM_BASE + 8
+const LOOP: &str = "
+        li a1, 100
+    1:
+        addi a1, a1, -1
+        bnez a1, 1b
+        li a7, 93
+        ecall
+";
+
+// a branch taken every `period` iterations of a loop, at RAM_BASE + 0x10
+fn periodic(period: u32) -> String {
+    format!(
+        "
+            li a1, 1000
+            li a2, 0
+        1:
+            addi a2, a2, 1
+            li a3, {period}
+            blt a2, a3, 2f
+            li a2, 0
+        2:
+            addi a1, a1, -1
+            bnez a1, 1b
+            li a7, 93
+            ecall
+        "
+    )
+}
+
+#[test]
+fn kinds() {
+    let jal = |rd| Instruction::Jal { rd, offset: 8 };
+    let jalr = |rd, rs1| Instruction::Jalr { rd, rs1, offset: 0 };
+
+    assert_eq!(BranchKind::of(&jal(0)), Some(BranchKind::Jump));
+    assert_eq!(BranchKind::of(&jal(1)), Some(BranchKind::Call));
+    assert_eq!(BranchKind::of(&jal(5)), Some(BranchKind::Call));
+    assert_eq!(BranchKind::of(&jalr(0, 1)), Some(BranchKind::Return));
+    assert_eq!(BranchKind::of(&jalr(1, 6)), Some(BranchKind::IndirectCall));
+    assert_eq!(BranchKind::of(&jalr(0, 6)), Some(BranchKind::IndirectJump));
+    assert_eq!(BranchKind::of(&Instruction::Fence), None);
+}
+
+#[test]
+fn bimodal_loop() {
+    let emu = simulate(LOOP, Bimodal::new(1024));
+
+    // warming up from weakly not taken, then leaving the loop
+    assert_eq!(
+        stats(&emu, RAM_BASE + 8),
+        BranchStats {
+            kind: BranchKind::Conditional,
+            executions: 100,
+            taken: 99,
+            mispredictions: 2,
+        }
+    );
+
+    let prediction = emu.cpu.branch_prediction.as_ref().unwrap();
+    assert_eq!(prediction.branches().len(), 1);
+    assert_eq!(prediction.accuracy(), 0.98);
+}
+
+#[test]
+fn global_history() {
+    let source = periodic(2);
+
+    let bimodal = stats(&simulate(&source, Bimodal::new(1024)), RAM_BASE + 0x10);
+    assert_eq!(bimodal.executions, 1000);
+    assert!(bimodal.accuracy() <= 0.5, "{bimodal:?}");
+
+    let gshare = stats(&simulate(&source, Gshare::new(1024)), RAM_BASE + 0x10);
+    assert!(gshare.accuracy() > 0.98, "{gshare:?}");
+
+    let tage = stats(&simulate(&source, Tage::new()), RAM_BASE + 0x10);
+    assert!(tage.accuracy() > 0.98, "{tage:?}");
+}
+
+#[test]
+fn tage_long_patterns() {
+    let source = periodic(5);
+
+    let bimodal = stats(&simulate(&source, Bimodal::new(1024)), RAM_BASE + 0x10);
+    assert!((0.75..0.85).contains(&bimodal.accuracy()), "{bimodal:?}");
+
+    let tage = stats(&simulate(&source, Tage::new()), RAM_BASE + 0x10);
+    assert!(tage.accuracy() > 0.95, "{tage:?}");
+}
+
+// calls f directly and through g, 50 times each
+const CALLS: &str = "
+        li s0, 50
+    1:
+        jal f
+        jal g
+        addi s0, s0, -1
+        bnez s0, 1b
+        li a0, 0
+        li a7, 93
+        ecall
+    f:
+        ret
+    g:
+        mv s1, ra
+        jal f
+        mv ra, s1
+        ret
+";
+
+const F_RETURN: Address = RAM_BASE + 0x20;
+const G_RETURN: Address = RAM_BASE + 0x30;
+
+#[test]
+fn return_stack() {
+    let emu = simulate(CALLS, ReturnStack::new(8, Bimodal::new(1024)));
+    assert_eq!(stats(&emu, F_RETURN).kind, BranchKind::Return);
+    assert_eq!(stats(&emu, F_RETURN).executions, 100);
+    assert_eq!(stats(&emu, F_RETURN).mispredictions, 0);
+    assert_eq!(stats(&emu, G_RETURN).mispredictions, 0);
+
+    // direct calls are always right
+    assert_eq!(stats(&emu, RAM_BASE + 4).kind, BranchKind::Call);
+    assert_eq!(stats(&emu, RAM_BASE + 4).mispredictions, 0);
+
+    // without one there is nothing to predict returns with
+    let emu = simulate(CALLS, Bimodal::new(1024));
+    assert_eq!(stats(&emu, F_RETURN).mispredictions, 100);
+
+    // the return address of g is pushed out by the call to f
+    let emu = simulate(CALLS, ReturnStack::new(1, Bimodal::new(1024)));
+    assert_eq!(stats(&emu, F_RETURN).mispredictions, 0);
+    assert_eq!(stats(&emu, G_RETURN).mispredictions, 50);
+}
+
+#[test]
+fn report() {
+    let emu = simulate(CALLS, ReturnStack::new(8, Gshare::new(256)));
+    let report = emu.cpu.branch_prediction.as_ref().unwrap().to_string();
+
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(lines.len(), 1 + 6);
+    assert!(lines[0].starts_with("350 branches, "), "{report}");
+    assert!(lines.iter().any(|line| line.starts_with(&format!(
+        "  {F_RETURN:#010x} return               100 executed  100.00% taken  100.00% accuracy"
+    ))));
+}
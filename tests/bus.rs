@@ -0,0 +1,261 @@
+use std::sync::{Arc, Mutex};
+
+use risemu::asm::assemble;
+use risemu::bus::{Address, Bus, RAM_BASE};
+use risemu::clint::{Clint, CLINT_BASE, CLINT_SIZE};
+use risemu::cpu::MisalignedPolicy;
+use risemu::dram::DRAM;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::mmio::Mmio;
+
+const RAM_SIZE: Address = 0x1000;
+const DEVICE_BASE: Address = 0x1000_0000;
+const DEVICE_SIZE: Address = 0x100;
+
+// accepts everything, remembering the furthest byte it was asked for
+#[derive(Default)]
+struct Device {
+    end: Mutex<Address>,
+}
+
+impl Device {
+    fn access(&self, offset: Address, size: usize) {
+        let mut end = self.end.lock().unwrap();
+        *end = (*end).max(offset + size as Address);
+    }
+}
+
+impl Mmio for Device {
+    fn read(&self, offset: Address, size: usize) -> Option<u64> {
+        self.access(offset, size);
+        Some(0)
+    }
+
+    fn write(&self, offset: Address, size: usize, _value: u64) -> bool {
+        self.access(offset, size);
+        true
+    }
+}
+
+fn bus() -> (Bus, Arc<Device>) {
+    let device = Arc::new(Device::default());
+    let mut bus = Bus::new(DRAM::new(RAM_SIZE as usize), Some(Arc::new(Clint::new(2))));
+    assert!(bus.map(DEVICE_BASE, DEVICE_SIZE, device.clone()));
+    (bus, device)
+}
+
+fn read(bus: &Bus, address: Address, size: usize) -> Result<u64, RVException> {
+    match size {
+        1 => bus.read::<u8>(address).map(u64::from),
+        2 => bus.read::<u16>(address).map(u64::from),
+        4 => bus.read::<u32>(address).map(u64::from),
+        _ => bus.read::<u64>(address),
+    }
+}
+
+fn write(bus: &mut Bus, address: Address, size: usize) -> Result<(), RVException> {
+    match size {
+        1 => bus.write::<u8>(address, 0),
+        2 => bus.write::<u16>(address, 0),
+        4 => bus.write::<u32>(address, 0),
+        _ => bus.write::<u64>(address, 0),
+    }
+}
+
+// every access of every width within a few bytes of either edge of a region
+fn boundaries(base: Address, length: Address) -> impl Iterator<Item = (Address, usize)> {
+    [base, base + length]
+        .into_iter()
+        .flat_map(|edge| edge - 8..edge + 8)
+        .flat_map(|address| [1, 2, 4, 8].map(|size| (address, size)))
+}
+
+fn inside(address: Address, size: usize, base: Address, length: Address) -> bool {
+    address >= base && address + size as Address <= base + length
+}
+
+#[test]
+fn ram_boundaries() {
+    let (mut bus, _) = bus();
+    for (address, size) in boundaries(RAM_BASE, RAM_SIZE) {
+        let expected = inside(address, size, RAM_BASE, RAM_SIZE);
+        assert_eq!(
+            read(&bus, address, size).is_ok(),
+            expected,
+            "{size}-byte load at {address:#x}"
+        );
+        assert_eq!(
+            write(&mut bus, address, size).is_ok(),
+            expected,
+            "{size}-byte store at {address:#x}"
+        );
+    }
+
+    assert_eq!(
+        read(&bus, RAM_BASE + RAM_SIZE - 4, 8),
+        Err(RVException::LoadAccessFault)
+    );
+    assert_eq!(
+        write(&mut bus, RAM_BASE + RAM_SIZE - 4, 8),
+        Err(RVException::StoreAccessFault)
+    );
+}
+
+#[test]
+fn mmio_boundaries() {
+    let (mut bus, device) = bus();
+    for (address, size) in boundaries(DEVICE_BASE, DEVICE_SIZE) {
+        let expected = inside(address, size, DEVICE_BASE, DEVICE_SIZE);
+        assert_eq!(
+            read(&bus, address, size).is_ok(),
+            expected,
+            "{size}-byte load at {address:#x}"
+        );
+        assert_eq!(
+            write(&mut bus, address, size).is_ok(),
+            expected,
+            "{size}-byte store at {address:#x}"
+        );
+    }
+
+    // the device never sees an access running past its end
+    assert_eq!(*device.end.lock().unwrap(), DEVICE_SIZE);
+}
+
+#[test]
+fn clint_boundaries() {
+    let (mut bus, _) = bus();
+    for (address, size) in boundaries(CLINT_BASE, CLINT_SIZE) {
+        let load = read(&bus, address, size);
+        let store = write(&mut bus, address, size);
+
+        // only the msip words of the two harts exist
+        let msip = size == 4
+            && (CLINT_BASE..CLINT_BASE + 8)
+                .step_by(4)
+                .any(|at| at == address);
+        assert_eq!(load.is_ok(), msip, "{size}-byte load at {address:#x}");
+        assert_eq!(store.is_ok(), msip, "{size}-byte store at {address:#x}");
+    }
+}
+
+#[test]
+fn wrapping_accesses() {
+    let (mut bus, _) = bus();
+    for size in [2, 4, 8] {
+        let address = Address::MAX - size as Address + 2;
+        assert_eq!(read(&bus, address, size), Err(RVException::LoadAccessFault));
+        assert_eq!(
+            write(&mut bus, address, size),
+            Err(RVException::StoreAccessFault)
+        );
+    }
+}
+
+fn straddle(source: &str, policy: MisalignedPolicy) -> (StopReason, Emulator) {
+    let mut emu = Emulator::new(RAM_SIZE as usize);
+    emu.cpu.misaligned = policy;
+    emu.init_ram(assemble(source).unwrap());
+    emu.cpu.xregs[10] = RAM_BASE + RAM_SIZE;
+    emu.cpu.xregs[11] = 0x1122334455667788;
+    (emu.run_for(100), emu)
+}
+
+#[test]
+fn loads_past_the_end_of_ram() {
+    for (offset, policy, exception) in [
+        (-8, MisalignedPolicy::Trap, None),
+        (
+            0,
+            MisalignedPolicy::Trap,
+            Some(RVException::LoadAccessFault),
+        ),
+        (
+            -6,
+            MisalignedPolicy::Trap,
+            Some(RVException::LoadAddressMisaligned),
+        ),
+        (
+            -6,
+            MisalignedPolicy::Emulate,
+            Some(RVException::LoadAccessFault),
+        ),
+        (
+            -1,
+            MisalignedPolicy::Emulate,
+            Some(RVException::LoadAccessFault),
+        ),
+    ] {
+        let source = format!("ld a2, {offset}(a0)\nli a7, 93\necall");
+        let (stop, _) = straddle(&source, policy);
+        let address = (RAM_BASE + RAM_SIZE).wrapping_add_signed(offset);
+
+        match exception {
+            None => assert!(matches!(stop, StopReason::Exited { .. }), "{stop:?}"),
+            Some(exception) => assert_eq!(
+                stop,
+                StopReason::Fault {
+                    exception,
+                    pc: RAM_BASE,
+                    tval: address,
+                }
+            ),
+        }
+    }
+}
+
+#[test]
+fn stores_past_the_end_of_ram() {
+    for (offset, policy, exception) in [
+        (0, MisalignedPolicy::Trap, RVException::StoreAccessFault),
+        (
+            -6,
+            MisalignedPolicy::Trap,
+            RVException::StoreAddressMisaligned,
+        ),
+        (-6, MisalignedPolicy::Emulate, RVException::StoreAccessFault),
+        (-1, MisalignedPolicy::Emulate, RVException::StoreAccessFault),
+    ] {
+        let source = format!("sd a1, {offset}(a0)\nli a7, 93\necall");
+        let (stop, emu) = straddle(&source, policy);
+        let address = (RAM_BASE + RAM_SIZE).wrapping_add_signed(offset);
+
+        assert_eq!(
+            stop,
+            StopReason::Fault {
+                exception,
+                pc: RAM_BASE,
+                tval: address,
+            }
+        );
+
+        // the bytes that were in RAM are left untouched
+        let memory = emu.cpu.bus.ram().contents();
+        assert_eq!(memory[memory.len() - 8..], [0; 8]);
+    }
+}
+
+#[test]
+fn host_copies() {
+    let ram = DRAM::new(0x40);
+    let data: Vec<u8> = (1..=0x13).collect();
+
+    // unaligned at both ends, with whole words in between
+    ram.write_bytes(0x05, &data);
+    let mut copy = [0; 0x13];
+    ram.read_bytes(0x05, &mut copy);
+    assert_eq!(copy, data[..]);
+
+    let mut word = [0; 8];
+    ram.read_bytes(0x08, &mut word);
+    assert_eq!(u64::from_le_bytes(word), 0x0b0a_0908_0706_0504);
+    assert_eq!(ram.contents()[..0x06], [0, 0, 0, 0, 0, 1]);
+    assert_eq!(ram.contents()[0x18..], [0; 0x28]);
+}
+
+#[test]
+#[should_panic(expected = "outside of RAM")]
+fn host_copies_past_the_end() {
+    DRAM::new(0x40).write_bytes(0x3c, &[0; 8]);
+}
@@ -0,0 +1,277 @@
+use risemu::asm::assemble;
+use risemu::bus::{Address, RAM_BASE};
+use risemu::cache::{
+    CacheConfig, CacheHierarchy, CacheLevel, CacheStats, Replacement, WritePolicy,
+};
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::timing::{Latencies, Pipeline};
+
+// 2 ways of 8 sets of 64 bytes, so addresses 512 bytes apart share a set
+const SET_STRIDE: Address = 512;
+
+fn l1(replacement: Replacement, ways: usize) -> CacheConfig {
+    CacheConfig {
+        replacement,
+        ..CacheConfig::new(ways * 512, ways, 64, 1)
+    }
+}
+
+fn data_cache(config: CacheConfig) -> CacheHierarchy {
+    CacheHierarchy::new(None, Some(config), None, 100)
+}
+
+fn line(index: u64) -> Address {
+    RAM_BASE + index * SET_STRIDE
+}
+
+#[test]
+fn geometry() {
+    assert!(CacheConfig::new(32 * 1024, 8, 64, 1).supports());
+    assert_eq!(CacheConfig::new(32 * 1024, 8, 64, 1).sets(), 64);
+
+    assert!(!CacheConfig::new(32 * 1024, 8, 48, 1).supports());
+    assert!(!CacheConfig::new(3 * 1024, 1, 64, 1).supports());
+    assert!(!CacheConfig::new(1024, 0, 64, 1).supports());
+    assert!(CacheConfig::new(3 * 512, 3, 64, 1).supports());
+    assert!(!l1(Replacement::Plru, 3).supports());
+}
+
+#[test]
+fn lru() {
+    let mut caches = data_cache(l1(Replacement::Lru, 2));
+    for index in [0, 1, 0, 2] {
+        caches.load(line(index), 8);
+    }
+
+    let cache = caches.cache(CacheLevel::L1d).unwrap();
+    assert!(cache.contains(line(0)));
+    assert!(!cache.contains(line(1)));
+    assert!(cache.contains(line(2)));
+    assert_eq!(
+        cache.stats(),
+        CacheStats {
+            hits: 1,
+            misses: 3,
+            evictions: 1,
+            writebacks: 0,
+        }
+    );
+}
+
+#[test]
+fn pseudo_lru() {
+    // the tree only remembers which half was used last, so it gives up C where LRU gives up B
+    let sequence = [0, 1, 2, 3, 0, 4];
+
+    let mut caches = data_cache(l1(Replacement::Plru, 4));
+    for index in sequence {
+        caches.load(line(index), 8);
+    }
+    let cache = caches.cache(CacheLevel::L1d).unwrap();
+    assert!(cache.contains(line(1)));
+    assert!(!cache.contains(line(2)));
+
+    let mut caches = data_cache(l1(Replacement::Lru, 4));
+    for index in sequence {
+        caches.load(line(index), 8);
+    }
+    let cache = caches.cache(CacheLevel::L1d).unwrap();
+    assert!(!cache.contains(line(1)));
+    assert!(cache.contains(line(2)));
+}
+
+#[test]
+fn random_replacement() {
+    let run = || {
+        let mut caches = data_cache(l1(Replacement::Random, 4));
+        for index in 0..64 {
+            caches.load(line(index % 7), 8);
+        }
+
+        let cache = caches.cache(CacheLevel::L1d).unwrap();
+        let present: Vec<bool> = (0..7).map(|index| cache.contains(line(index))).collect();
+        (present, cache.stats())
+    };
+
+    let (present, stats) = run();
+    assert_eq!(present.iter().filter(|&&present| present).count(), 4);
+    assert_eq!(stats.accesses(), 64);
+    assert_eq!(stats.evictions, stats.misses - 4);
+
+    // the same seed every time
+    assert_eq!(run(), (present, stats));
+}
+
+#[test]
+fn write_back() {
+    let l2 = CacheConfig::new(64 * 1024, 4, 64, 10);
+    let mut caches = CacheHierarchy::new(None, Some(l1(Replacement::Lru, 2)), Some(l2), 100);
+
+    caches.store(line(0), 8);
+    let l1d = caches.cache(CacheLevel::L1d).unwrap();
+    assert!(l1d.is_dirty(line(0)));
+
+    // evicting the dirty line writes it to the second level
+    caches.load(line(1), 8);
+    caches.load(line(2), 8);
+
+    let l1d = caches.cache(CacheLevel::L1d).unwrap();
+    assert!(!l1d.contains(line(0)));
+    assert_eq!(l1d.stats().writebacks, 1);
+    assert!(caches.cache(CacheLevel::L2).unwrap().is_dirty(line(0)));
+}
+
+#[test]
+fn write_through() {
+    let l1d = CacheConfig {
+        write: WritePolicy::WriteThrough,
+        ..l1(Replacement::Lru, 2)
+    };
+    let l2 = CacheConfig::new(64 * 1024, 4, 64, 10);
+    let mut caches = CacheHierarchy::new(None, Some(l1d), Some(l2), 100);
+
+    // stores neither allocate nor stall
+    assert_eq!(caches.store(line(0), 8), 0);
+    assert!(!caches.cache(CacheLevel::L1d).unwrap().contains(line(0)));
+
+    caches.load(line(0), 8);
+    caches.store(line(0), 8);
+
+    let l1d = caches.cache(CacheLevel::L1d).unwrap();
+    assert!(!l1d.is_dirty(line(0)));
+    assert_eq!(l1d.stats().hits, 1);
+    assert_eq!(l1d.stats().misses, 2);
+
+    // every store reached the second level
+    let l2 = caches.cache(CacheLevel::L2).unwrap();
+    assert!(l2.is_dirty(line(0)));
+    assert_eq!(l2.stats().accesses(), 3);
+}
+
+#[test]
+fn latencies() {
+    let l1i = CacheConfig::new(1024, 2, 64, 1);
+    let l2 = CacheConfig::new(4096, 4, 64, 10);
+    let mut caches = CacheHierarchy::new(Some(l1i), None, Some(l2), 100);
+
+    // beyond a first-level hit
+    assert_eq!(caches.fetch(RAM_BASE, 4), 10 + 100);
+    assert_eq!(caches.fetch(RAM_BASE + 4, 4), 0);
+
+    // without a first-level data cache everything goes to the second level
+    assert_eq!(caches.load(RAM_BASE, 8), 10);
+    assert_eq!(caches.load(RAM_BASE + 0x100, 8), 10 + 100);
+
+    // a line evicted from the first level is still in the second
+    caches.fetch(RAM_BASE + SET_STRIDE, 4);
+    caches.fetch(RAM_BASE + 2 * SET_STRIDE, 4);
+    assert_eq!(caches.fetch(RAM_BASE, 4), 10);
+}
+
+#[test]
+fn accesses_straddling_lines() {
+    let mut caches = data_cache(l1(Replacement::Lru, 2));
+    assert_eq!(caches.load(RAM_BASE + 60, 8), 2 * 100);
+
+    let l1d = caches.cache(CacheLevel::L1d).unwrap();
+    assert_eq!(l1d.stats().misses, 2);
+    assert!(l1d.contains(RAM_BASE + 64));
+}
+
+#[test]
+fn regions() {
+    let mut caches = data_cache(l1(Replacement::Lru, 2));
+    caches.add_region("stack", RAM_BASE + 0x8000..RAM_BASE + 0x10000);
+    caches.add_region("heap", RAM_BASE..RAM_BASE + 0x8000);
+
+    caches.load(RAM_BASE + 0x8000, 8);
+    caches.load(RAM_BASE + 0x8008, 8);
+    caches.store(RAM_BASE, 8);
+
+    let stack = caches.region_stats("stack", CacheLevel::L1d).unwrap();
+    assert_eq!((stack.hits, stack.misses), (1, 1));
+
+    let heap = caches.region_stats("heap", CacheLevel::L1d).unwrap();
+    assert_eq!((heap.hits, heap.misses), (0, 1));
+
+    assert_eq!(
+        caches.region_stats("heap", CacheLevel::L2),
+        Some(CacheStats::default())
+    );
+    assert_eq!(caches.region_stats("code", CacheLevel::L1d), None);
+}
+
+fn hierarchy() -> CacheHierarchy {
+    CacheHierarchy::new(
+        Some(CacheConfig::new(1024, 2, 64, 1)),
+        Some(CacheConfig::new(1024, 2, 64, 1)),
+        None,
+        20,
+    )
+}
+
+#[test]
+fn every_access_is_seen() {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(
+        assemble(
+            "
+                li a0, 0x80001000
+                li a1, 64
+            1:
+                ld a2, 0(a0)
+                sd a2, 8(a0)
+                amoadd.d a3, a2, (a0)
+                addi a0, a0, 16
+                addi a1, a1, -1
+                bnez a1, 1b
+                li a0, 0
+                li a7, 93
+                ecall
+            ",
+        )
+        .unwrap(),
+    );
+
+    // compiled blocks would otherwise go unseen
+    #[cfg(feature = "jit")]
+    emu.cpu.set_jit_threshold(0);
+
+    emu.cpu.caches = Some(hierarchy());
+    assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 0 });
+
+    // the ECALL is fetched but does not retire
+    let caches = emu.cpu.caches.as_ref().unwrap();
+    let l1i = caches.cache(CacheLevel::L1i).unwrap().stats();
+    assert_eq!(l1i.accesses(), emu.cpu.instret + 1);
+    assert_eq!(l1i.misses, 1);
+
+    // 1 KiB of data in 16-byte steps, three accesses each
+    let l1d = caches.cache(CacheLevel::L1d).unwrap().stats();
+    assert_eq!(l1d.accesses(), 3 * 64);
+    assert_eq!(l1d.misses, 1024 / 64);
+}
+
+#[test]
+fn stalls_feed_the_timing_model() {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble("ld a1, 0x100(a0)\necall").unwrap());
+    emu.cpu.xregs[10] = RAM_BASE;
+    emu.cpu.caches = Some(hierarchy());
+    emu.cpu.timing = Some(Pipeline::new(Latencies::default()));
+
+    assert!(matches!(
+        emu.run_for(10),
+        StopReason::Fault {
+            exception: RVException::EnvironmentCall,
+            ..
+        }
+    ));
+
+    // both the fetch and the load missed
+    let pipeline = emu.cpu.timing.as_ref().unwrap();
+    assert_eq!(pipeline.stalls().memory, 2 * 20);
+    assert_eq!(pipeline.cycles(), 5 + 2 * 20);
+    assert_eq!(emu.cpu.cycle, 5 + 2 * 20);
+}
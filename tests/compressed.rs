@@ -0,0 +1,338 @@
+use risemu::asm::assemble;
+use risemu::bus::RAM_BASE;
+use risemu::cpu::Xlen;
+use risemu::csr::{MEPC, MISA};
+use risemu::decoder::{decode_compressed, AluOp, BranchOp, Instruction, LoadOp, StoreOp};
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+
+fn emulator(source: &str, decode_cache: bool, block_cache: bool) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.cpu.set_decode_cache(decode_cache);
+    emu.cpu.set_block_cache(block_cache);
+    emu.init_ram(assemble(source).unwrap());
+    emu
+}
+
+fn op_imm(op: AluOp, rd: u8, rs1: u8, imm: i64) -> Instruction {
+    Instruction::OpImm {
+        op,
+        rd,
+        rs1,
+        imm: imm as u64,
+    }
+}
+
+#[test]
+fn expansion() {
+    let cases = [
+        (0x0808, op_imm(AluOp::Add, 10, 2, 16)), // c.addi4spn a0, sp, 16
+        (
+            0x414c, // c.lw a1, 4(a0)
+            Instruction::Load {
+                op: LoadOp::Lw,
+                rd: 11,
+                rs1: 10,
+                offset: 4,
+            },
+        ),
+        (
+            0x6510, // c.ld a2, 8(a0)
+            Instruction::Load {
+                op: LoadOp::Ld,
+                rd: 12,
+                rs1: 10,
+                offset: 8,
+            },
+        ),
+        (
+            0xc12c, // c.sw a1, 64(a0)
+            Instruction::Store {
+                op: StoreOp::Sw,
+                rs1: 10,
+                rs2: 11,
+                offset: 64,
+            },
+        ),
+        (
+            0xfd70, // c.sd a2, 248(a0)
+            Instruction::Store {
+                op: StoreOp::Sd,
+                rs1: 10,
+                rs2: 12,
+                offset: 248,
+            },
+        ),
+        (0x0001, op_imm(AluOp::Add, 0, 0, 0)),    // c.nop
+        (0x1575, op_imm(AluOp::Add, 10, 10, -3)), // c.addi a0, -3
+        (
+            0x25fd, // c.addiw a1, 31
+            Instruction::OpImm32 {
+                op: AluOp::Add,
+                rd: 11,
+                rs1: 11,
+                imm: 31,
+            },
+        ),
+        (0x5601, op_imm(AluOp::Add, 12, 0, -32)), // c.li a2, -32
+        (0x7101, op_imm(AluOp::Add, 2, 2, -512)), // c.addi16sp sp, -512
+        (
+            0x76fd, // c.lui a3, 0xfffff
+            Instruction::Lui {
+                rd: 13,
+                imm: -4096i64 as u64,
+            },
+        ),
+        (0x818d, op_imm(AluOp::Srl, 11, 11, 3)), // c.srli a1, 3
+        (0x95fd, op_imm(AluOp::Sra, 11, 11, 63)), // c.srai a1, 63
+        (0x99fd, op_imm(AluOp::And, 11, 11, -1)), // c.andi a1, -1
+        (
+            0x8d0d, // c.sub a0, a1
+            Instruction::Op {
+                op: AluOp::Sub,
+                rd: 10,
+                rs1: 10,
+                rs2: 11,
+            },
+        ),
+        (
+            0x8d6d, // c.and a0, a1
+            Instruction::Op {
+                op: AluOp::And,
+                rd: 10,
+                rs1: 10,
+                rs2: 11,
+            },
+        ),
+        (
+            0x9d2d, // c.addw a0, a1
+            Instruction::Op32 {
+                op: AluOp::Add,
+                rd: 10,
+                rs1: 10,
+                rs2: 11,
+            },
+        ),
+        (
+            0xb001, // c.j -2048
+            Instruction::Jal {
+                rd: 0,
+                offset: -2048i64 as u64,
+            },
+        ),
+        (
+            0xd101, // c.beqz a0, -256
+            Instruction::Branch {
+                op: BranchOp::Beq,
+                rs1: 10,
+                rs2: 0,
+                offset: -256i64 as u64,
+            },
+        ),
+        (
+            0xedfd, // c.bnez a1, 254
+            Instruction::Branch {
+                op: BranchOp::Bne,
+                rs1: 11,
+                rs2: 0,
+                offset: 254,
+            },
+        ),
+        (0x157e, op_imm(AluOp::Sll, 10, 10, 63)), // c.slli a0, 63
+        (
+            0x557e, // c.lwsp a0, 252(sp)
+            Instruction::Load {
+                op: LoadOp::Lw,
+                rd: 10,
+                rs1: 2,
+                offset: 252,
+            },
+        ),
+        (
+            0x757e, // c.ldsp a0, 504(sp)
+            Instruction::Load {
+                op: LoadOp::Ld,
+                rd: 10,
+                rs1: 2,
+                offset: 504,
+            },
+        ),
+        (
+            0x8082, // c.jr ra
+            Instruction::Jalr {
+                rd: 0,
+                rs1: 1,
+                offset: 0,
+            },
+        ),
+        (
+            0x852e, // c.mv a0, a1
+            Instruction::Op {
+                op: AluOp::Add,
+                rd: 10,
+                rs1: 0,
+                rs2: 11,
+            },
+        ),
+        (0x9002, Instruction::Ebreak), // c.ebreak
+        (
+            0x9502, // c.jalr a0
+            Instruction::Jalr {
+                rd: 1,
+                rs1: 10,
+                offset: 0,
+            },
+        ),
+        (
+            0xdfaa, // c.swsp a0, 252(sp)
+            Instruction::Store {
+                op: StoreOp::Sw,
+                rs1: 2,
+                rs2: 10,
+                offset: 252,
+            },
+        ),
+        (
+            0xffaa, // c.sdsp a0, 504(sp)
+            Instruction::Store {
+                op: StoreOp::Sd,
+                rs1: 2,
+                rs2: 10,
+                offset: 504,
+            },
+        ),
+    ];
+
+    for (half, expected) in cases {
+        assert_eq!(
+            decode_compressed(half, Xlen::Rv64),
+            Ok(expected),
+            "{half:#06x}"
+        );
+    }
+
+    // c.jal 2046, which RV64 replaces with c.addiw
+    assert_eq!(
+        decode_compressed(0x2ffd, Xlen::Rv32),
+        Ok(Instruction::Jal {
+            rd: 1,
+            offset: 2046
+        })
+    );
+}
+
+#[test]
+fn reserved() {
+    for (half, xlen) in [
+        (0x0000, Xlen::Rv64), // all zeros
+        (0x6101, Xlen::Rv64), // c.addi16sp sp, 0
+        (0x6681, Xlen::Rv64), // c.lui a3, 0
+        (0x2001, Xlen::Rv64), // c.addiw zero, 0
+        (0x8002, Xlen::Rv64), // c.jr zero
+        (0x4002, Xlen::Rv64), // c.lwsp zero, 0(sp)
+        (0x2008, Xlen::Rv64), // c.fld
+        (0x6510, Xlen::Rv32), // c.flw, c.ld on RV64
+        (0x157e, Xlen::Rv32), // c.slli a0, 63
+        (0x95fd, Xlen::Rv32), // c.srai a1, 63
+        (0x9d2d, Xlen::Rv32), // c.addw a0, a1
+    ] {
+        assert_eq!(
+            decode_compressed(half, xlen),
+            Err(RVException::IllegalInstruction),
+            "{half:#06x} {xlen:?}"
+        );
+    }
+}
+
+// calls a function through c.jalr, which links to the instruction two bytes later
+const CALL: &str = "
+        li a0, 5
+        .half 0x459d    # c.li a1, 7
+        auipc t0, 0
+        .half 0x02c9    # c.addi t0, 18
+        .half 0x9282    # c.jalr t0
+        .half 0x952e    # c.add a0, a1
+        li a7, 93
+        ecall
+        .half 0x0586    # c.slli a1, 1
+        .half 0x8082    # c.jr ra
+";
+
+#[test]
+fn mixed_lengths() {
+    for (decode_cache, block_cache) in [(false, false), (true, false), (true, true)] {
+        let mut emu = emulator(CALL, decode_cache, block_cache);
+
+        assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 19 });
+        assert_eq!(emu.cpu.xregs[1], RAM_BASE + 14);
+        assert_eq!(emu.cpu.pc, RAM_BASE + 20);
+        assert_eq!(emu.cpu.instret, 9);
+    }
+}
+
+// rewrites the half of an instruction lying in the next page, then runs it again
+const CROSSING: &str = "
+        auipc s0, 1
+        li s1, 0x2b0
+        j 1f
+        .zero 4082
+    1:
+        addi a0, zero, 42
+        li t0, 42
+        bne a0, t0, 2f
+        sh s1, 0(s0)
+        j 1b
+    2:
+        li a7, 93
+        ecall
+";
+
+#[test]
+fn crossing_a_page() {
+    for (decode_cache, block_cache) in [(false, false), (true, false), (true, true)] {
+        let mut emu = emulator(CROSSING, decode_cache, block_cache);
+
+        assert_eq!(emu.run_for(100), StopReason::Exited { code: 43 });
+    }
+}
+
+#[test]
+fn turned_off() {
+    let mut emu = emulator(
+        "
+            csrr t0, misa
+            andi t0, t0, -5
+            csrw misa, t0
+            .half 0x0001    # c.nop
+            .half 0x0001
+        ",
+        true,
+        true,
+    );
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::IllegalInstruction,
+            pc: RAM_BASE + 12,
+            tval: 0x00010001,
+        }
+    );
+    assert!(!emu.cpu.compressed());
+    assert_eq!(emu.cpu.read_csr(MISA).unwrap() & (1 << 2), 0);
+}
+
+#[test]
+fn mepc_alignment() {
+    let mut emu = emulator("", true, true);
+
+    assert!(emu.cpu.write_csr(MEPC, RAM_BASE + 3));
+    assert_eq!(emu.cpu.read_csr(MEPC), Some(RAM_BASE + 2));
+
+    // bit 1 is hidden without C, and comes back along with it
+    let misa = emu.cpu.read_csr(MISA).unwrap();
+    assert!(emu.cpu.write_csr(MISA, misa & !(1 << 2)));
+    assert_eq!(emu.cpu.read_csr(MEPC), Some(RAM_BASE));
+    assert!(emu.cpu.write_csr(MISA, misa));
+    assert_eq!(emu.cpu.read_csr(MEPC), Some(RAM_BASE + 2));
+}
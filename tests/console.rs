@@ -0,0 +1,65 @@
+use std::sync::{Arc, Mutex};
+
+use risemu::bus::RAM_BASE;
+use risemu::console::{Console, CONSOLE_BASE, CONSOLE_SIZE};
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+
+fn emulator(code: Vec<u8>) -> (Emulator, Arc<Mutex<Vec<u8>>>) {
+    let mut code = code;
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let output = Arc::new(Mutex::new(vec![]));
+    let sink = output.clone();
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(code);
+    assert!(emu.map_mmio(
+        CONSOLE_BASE,
+        CONSOLE_SIZE,
+        Arc::new(Console::new(move |byte| sink.lock().unwrap().push(byte))),
+    ));
+
+    (emu, output)
+}
+
+#[test]
+fn prints_after_polling() {
+    let (mut emu, output) = emulator(vec![
+        0x37, 0x05, 0x00, 0x10, // lui a0, 0x10000
+        0x83, 0x42, 0x55, 0x00, // lbu t0, 5(a0)
+        0x93, 0xf2, 0x02, 0x02, // andi t0, t0, 0x20
+        0xe3, 0x8c, 0x02, 0xfe, // beqz t0, -8
+        0x13, 0x03, 0x80, 0x06, // li t1, 104
+        0x23, 0x00, 0x65, 0x00, // sb t1, 0(a0)
+        0x13, 0x03, 0x90, 0x06, // li t1, 105
+        0x23, 0x00, 0x65, 0x00, // sb t1, 0(a0)
+        0x83, 0x45, 0x15, 0x00, // lbu a1, 1(a0)
+    ]);
+
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    assert_eq!(*output.lock().unwrap(), b"hi");
+    assert_eq!(emu.cpu.xregs[11], 0);
+}
+
+#[test]
+fn wide_accesses_fault() {
+    let (mut emu, output) = emulator(vec![
+        0x37, 0x05, 0x00, 0x10, // lui a0, 0x10000
+        0x13, 0x03, 0x80, 0x06, // li t1, 104
+        0x23, 0x20, 0x65, 0x00, // sw t1, 0(a0)
+    ]);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::StoreAccessFault,
+            pc: RAM_BASE + 8,
+            tval: CONSOLE_BASE,
+        }
+    );
+    assert!(output.lock().unwrap().is_empty());
+}
@@ -0,0 +1,134 @@
+use risemu::bus::RAM_BASE;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+
+fn emulator(code: Vec<u8>) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(code);
+    emu
+}
+
+// counts x5 up to 100, then exits with it
+fn counter() -> Vec<u8> {
+    vec![
+        0x93, 0x02, 0x00, 0x00, // li x5, 0
+        0x13, 0x03, 0x40, 0x06, // li x6, 100
+        0x93, 0x82, 0x12, 0x00, // addi x5, x5, 1
+        0xe3, 0x9e, 0x62, 0xfe, // bne x5, x6, -4
+        0x13, 0x85, 0x02, 0x00, // mv a0, x5
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]
+}
+
+#[test]
+fn step() {
+    let mut emu = emulator(counter());
+
+    assert_eq!(emu.step(), StopReason::LimitReached);
+    assert_eq!(emu.cpu.pc, RAM_BASE + 4);
+    assert_eq!(emu.step(), StopReason::LimitReached);
+    assert_eq!(emu.cpu.pc, RAM_BASE + 8);
+    assert_eq!(emu.cpu.xregs[6], 100);
+}
+
+#[test]
+fn run_for() {
+    let mut emu = emulator(counter());
+
+    assert_eq!(emu.run_for(2 + 2 * 5), StopReason::LimitReached);
+    assert_eq!(emu.cpu.xregs[5], 5);
+    assert_eq!(emu.cpu.instret, 12);
+}
+
+#[test]
+fn run_until_pc() {
+    let mut emu = emulator(counter());
+
+    assert_eq!(
+        emu.run_until(RAM_BASE + 16),
+        StopReason::Breakpoint { pc: RAM_BASE + 16 }
+    );
+    assert_eq!(emu.cpu.pc, RAM_BASE + 16);
+    assert_eq!(emu.cpu.xregs[5], 100);
+}
+
+#[test]
+fn run_until_predicate() {
+    let mut emu = emulator(counter());
+
+    assert_eq!(
+        emu.run_until_with(|cpu| cpu.xregs[5] == 42),
+        StopReason::Condition
+    );
+    assert_eq!(emu.cpu.xregs[5], 42);
+}
+
+#[test]
+fn guest_exit() {
+    let mut emu = emulator(counter());
+
+    assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 100 });
+    assert_eq!(emu.cpu.pc, RAM_BASE + 24);
+}
+
+#[test]
+fn load_fault() {
+    let mut emu = emulator(vec![
+        0x03, 0x38, 0x00, 0x01, // ld x16, 16(x0)
+    ]);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::LoadAccessFault,
+            pc: RAM_BASE,
+            tval: 0x10,
+        }
+    );
+}
+
+#[test]
+fn illegal_instruction() {
+    let mut emu = emulator(vec![
+        0xff, 0xff, 0xff, 0xff, // .word 0xffffffff
+    ]);
+
+    assert_eq!(
+        emu.step(),
+        StopReason::Fault {
+            exception: RVException::IllegalInstruction,
+            pc: RAM_BASE,
+            tval: 0xffffffff,
+        }
+    );
+}
+
+#[test]
+fn fetch_fault() {
+    let mut emu = emulator(vec![
+        0x67, 0x00, 0x00, 0x00, // jr x0
+    ]);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::InstructionAccessFault,
+            pc: 0,
+            tval: 0,
+        }
+    );
+}
+
+#[test]
+fn ebreak() {
+    let mut emu = emulator(vec![
+        0x13, 0x00, 0x00, 0x00, // nop
+        0x73, 0x00, 0x10, 0x00, // ebreak
+    ]);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Breakpoint { pc: RAM_BASE + 4 }
+    );
+}
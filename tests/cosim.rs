@@ -0,0 +1,202 @@
+use risemu::asm::assemble;
+use risemu::cosim::{Event, Store, Trace};
+use risemu::emulator::Emulator;
+use risemu::exception::RVException;
+
+// as logged by spike -l --log-commits, boot ROM included
+const TRACE: &str = "\
+core   0: 0x0000000000001000 (0x00000297) auipc   t0, 0x0
+core   0: 3 0x0000000000001000 (0x00000297) x5  0x0000000000001000
+core   0: 0x0000000000001004 (0x02028593) addi    a1, t0, 32
+core   0: 3 0x0000000000001004 (0x02028593) x11 0x0000000000001020
+core   0: 3 0x0000000000001008 (0xf1402573) x10 0x0000000000000000
+core   0: 3 0x000000000000100c (0x0182b283) x5  0x0000000080000000 mem 0x0000000000001018
+core   0: 3 0x0000000000001010 (0x00028067)
+core   0: 3 0x0000000080000000 (0x00001297) x5  0x0000000080001000
+core   0: 3 0x0000000080000004 (0x00500513) x10 0x0000000000000005
+core   0: 3 0x0000000080000008 (0x00700593) x11 0x0000000000000007
+core   0: 3 0x000000008000000c (0x40b5063b) x12 0xfffffffffffffffe
+core   0: 3 0x0000000080000010 (0x00c2b023) mem 0x0000000080001000 0xfffffffffffffffe
+core   0: 3 0x0000000080000014 (0x05d00893) x17 0x000000000000005d
+core   0: exception trap_machine_ecall, epc 0x0000000080000018
+core   0: 3 0x0000000000000000 (0x00000013)
+";
+
+fn emulator() -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(
+        assemble(
+            "
+                auipc t0, 1
+                li a0, 5
+                li a1, 7
+                subw a2, a0, a1
+                sd a2, 0(t0)
+                li a7, 93
+                ecall
+            ",
+        )
+        .unwrap(),
+    );
+    emu
+}
+
+fn cosimulate(trace: &str) -> Result<u64, String> {
+    let trace = Trace::parse(trace).unwrap();
+    trace
+        .cosimulate(&mut emulator())
+        .map_err(|divergence| divergence.to_string())
+}
+
+#[test]
+fn parses_spike_logs() {
+    let trace = Trace::parse(TRACE).unwrap();
+    assert_eq!(trace.events.len(), 13);
+
+    let Event::Commit(store) = &trace.events[9] else {
+        panic!("{:?}", trace.events[9]);
+    };
+    assert_eq!(store.pc, 0x80000010);
+    assert_eq!(store.instruction, 0x00c2b023);
+    assert!(store.registers.is_empty());
+    assert_eq!(
+        store.stores,
+        [Store {
+            address: 0x80001000,
+            size: 8,
+            value: 0xfffffffffffffffe,
+        }]
+    );
+
+    let Event::Trap(trap) = &trace.events[11] else {
+        panic!("{:?}", trace.events[11]);
+    };
+    assert_eq!(trap.exception, Some(RVException::EnvironmentCall));
+    assert_eq!(trap.epc, 0x80000018);
+
+    let trace = Trace::parse(
+        "core   0: 3 0x0000000080000000 (0x30529073) c773_mtvec 0x0000000080000004 f1 0x0000000000000000
+core   0: exception trap_load_access_fault, epc 0x0000000080000004
+core   0:           tval 0x0000000000000000",
+    )
+    .unwrap();
+    let Event::Commit(commit) = &trace.events[0] else {
+        panic!();
+    };
+    assert_eq!(commit.csrs, [(0x305, 0x80000004)]);
+    let Event::Trap(trap) = &trace.events[1] else {
+        panic!();
+    };
+    assert_eq!(trap.tval, Some(0));
+
+    assert!(Trace::parse("core   0: 3 0x80000000 (0x13) x1").is_err());
+    assert!(Trace::parse("core   0:           tval 0x0").is_err());
+}
+
+#[test]
+fn matching_trace() {
+    assert_eq!(cosimulate(TRACE), Ok(6));
+}
+
+#[test]
+fn boot_rom_writes_carry_over() {
+    let trace = Trace::parse(&TRACE.lines().take(8).collect::<Vec<_>>().join("\n")).unwrap();
+
+    let mut emu = emulator();
+    assert_eq!(trace.cosimulate(&mut emu), Ok(1));
+    assert_eq!(emu.cpu.xregs[11], 0x1020);
+}
+
+#[test]
+fn register_divergence() {
+    // a SUBW result that was not sign extended
+    let report =
+        cosimulate(&TRACE.replace("x12 0xfffffffffffffffe", "x12 0x00000000fffffffe")).unwrap_err();
+
+    let lines: Vec<&str> = report.lines().collect();
+    assert_eq!(
+        lines[..4],
+        [
+            "divergence at event 8: core   0: 3 0x000000008000000c (0x40b5063b) x12 0x00000000fffffffe",
+            "  x12: expected 0xfffffffe, found 0xfffffffffffffffe",
+            "preceded by:",
+            "  core   0: 3 0x0000000000001000 (0x00000297) x5  0x0000000000001000",
+        ]
+    );
+    assert!(report.contains("x12 0xfffffffffffffffe"));
+}
+
+#[test]
+fn unexpected_writes() {
+    let report = cosimulate(&TRACE.replace(" x11 0x0000000000000007", "")).unwrap_err();
+    assert!(report.contains("x11: expected 0x1020 to be left alone, found 0x7"));
+}
+
+#[test]
+fn memory_divergence() {
+    let report = cosimulate(&TRACE.replace(
+        "mem 0x0000000080001000 0xfffffffffffffffe",
+        "mem 0x0000000080001000 0x00000000fffffffe",
+    ))
+    .unwrap_err();
+
+    assert!(report.starts_with("divergence at event 9"));
+    assert!(report.contains(
+        "memory at 0x80001000: expected [fe, ff, ff, ff, 00, 00, 00, 00], found [fe, ff, ff, ff, ff, ff, ff, ff]"
+    ));
+
+    // a narrower store only covers the low bytes
+    let trace = TRACE.replace(
+        "mem 0x0000000080001000 0xfffffffffffffffe",
+        "mem 0x0000000080001004 0xffff",
+    );
+    assert_eq!(cosimulate(&trace), Ok(6));
+}
+
+#[test]
+fn control_flow_divergence() {
+    let report = cosimulate(&TRACE.replace(
+        "0x0000000080000008 (0x00700593)",
+        "0x000000008000000c (0x00700593)",
+    ))
+    .unwrap_err();
+    assert!(report.contains("pc: expected 0x8000000c, found 0x80000008"));
+
+    let report = cosimulate(&TRACE.replace("(0x00700593)", "(0x00800593)")).unwrap_err();
+    assert!(report.contains("instruction: expected 0x00800593, found 0x00700593"));
+}
+
+#[test]
+fn trap_divergence() {
+    let report =
+        cosimulate(&TRACE.replace("trap_machine_ecall", "trap_illegal_instruction")).unwrap_err();
+    assert!(report.contains("exception: expected IllegalInstruction, found EnvironmentCall"));
+
+    let report =
+        cosimulate(&TRACE.replace("epc 0x0000000080000018", "epc 0x0000000080000014")).unwrap_err();
+    assert!(report.contains("epc: expected 0x80000014, found 0x80000018"));
+}
+
+#[test]
+fn compressed_instructions() {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(
+        assemble(
+            "
+                .half 0x4515    # c.li a0, 5
+                li a7, 93
+                ecall
+            ",
+        )
+        .unwrap(),
+    );
+
+    // spike logs only the 16 bits of a compressed instruction
+    let trace = Trace::parse(
+        "core   0: 3 0x0000000080000000 (0x4515) x10 0x0000000000000005
+core   0: 3 0x0000000080000002 (0x05d00893) x17 0x000000000000005d
+core   0: exception trap_machine_ecall, epc 0x0000000080000006",
+    )
+    .unwrap();
+    assert_eq!(trace.cosimulate(&mut emu), Ok(2));
+}
@@ -0,0 +1,201 @@
+use risemu::asm::assemble;
+use risemu::bus::RAM_BASE;
+use risemu::debug::WatchKind;
+use risemu::emulator::{Emulator, StopReason};
+
+const STACK_TOP: u64 = RAM_BASE + 0x10000;
+
+fn emulator(block_cache: bool, code: Vec<u8>) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.cpu.set_block_cache(block_cache);
+    emu.init_ram(code);
+    emu
+}
+
+// counts x5 up to 100, then exits with it
+fn counter() -> Vec<u8> {
+    vec![
+        0x93, 0x02, 0x00, 0x00, // li x5, 0
+        0x13, 0x03, 0x40, 0x06, // li x6, 100
+        0x93, 0x82, 0x12, 0x00, // addi x5, x5, 1
+        0xe3, 0x9e, 0x62, 0xfe, // bne x5, x6, -4
+        0x13, 0x85, 0x02, 0x00, // mv a0, x5
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]
+}
+
+// stores 7 to the top of the stack, loads it back and exits with it
+fn store_load() -> Vec<u8> {
+    vec![
+        0x93, 0x02, 0x70, 0x00, // li x5, 7
+        0x23, 0x3c, 0x51, 0xfe, // sd x5, -8(sp)
+        0x03, 0x33, 0x81, 0xff, // ld x6, -8(sp)
+        0x13, 0x05, 0x03, 0x00, // mv a0, x6
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]
+}
+
+#[test]
+fn breakpoint() {
+    for block_cache in [false, true] {
+        let mut emu = emulator(block_cache, counter());
+        emu.add_breakpoint(RAM_BASE + 8);
+
+        for count in 0..3 {
+            assert_eq!(
+                emu.run_for(u64::MAX),
+                StopReason::Breakpoint { pc: RAM_BASE + 8 }
+            );
+            assert_eq!(emu.cpu.xregs[5], count);
+        }
+
+        assert!(emu.remove_breakpoint(RAM_BASE + 8));
+        assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 100 });
+    }
+}
+
+#[test]
+fn breakpoint_stops_predicate() {
+    let mut emu = emulator(true, counter());
+    emu.add_breakpoint(RAM_BASE + 16);
+
+    assert_eq!(
+        emu.run_until_with(|cpu| cpu.xregs[5] == 1000),
+        StopReason::Breakpoint { pc: RAM_BASE + 16 }
+    );
+    assert_eq!(emu.cpu.xregs[5], 100);
+}
+
+#[test]
+fn run_until_keeps_breakpoints() {
+    let mut emu = emulator(true, counter());
+    emu.add_breakpoint(RAM_BASE + 8);
+
+    assert_eq!(
+        emu.run_until(RAM_BASE + 8),
+        StopReason::Breakpoint { pc: RAM_BASE + 8 }
+    );
+    assert_eq!(emu.cpu.debugger.breakpoints().count(), 1);
+
+    emu.remove_breakpoint(RAM_BASE + 8);
+    emu.run_until(RAM_BASE + 16);
+    assert_eq!(emu.cpu.debugger.breakpoints().count(), 0);
+}
+
+#[test]
+fn write_watchpoint() {
+    for block_cache in [false, true] {
+        let mut emu = emulator(block_cache, store_load());
+        emu.add_watchpoint(STACK_TOP - 8..STACK_TOP, WatchKind::Write);
+
+        assert_eq!(
+            emu.run_for(u64::MAX),
+            StopReason::Watchpoint {
+                pc: RAM_BASE + 8,
+                address: STACK_TOP - 8,
+                kind: WatchKind::Write,
+            }
+        );
+        assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 7 });
+    }
+}
+
+#[test]
+fn read_watchpoint() {
+    let mut emu = emulator(true, store_load());
+    emu.add_watchpoint(STACK_TOP - 4..STACK_TOP - 3, WatchKind::Read);
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Watchpoint {
+            pc: RAM_BASE + 12,
+            address: STACK_TOP - 8,
+            kind: WatchKind::Read,
+        }
+    );
+    assert_eq!(emu.cpu.xregs[6], 7);
+}
+
+#[test]
+fn access_watchpoint() {
+    let mut emu = emulator(true, store_load());
+    emu.add_watchpoint(STACK_TOP - 8..STACK_TOP, WatchKind::Access);
+
+    assert!(matches!(
+        emu.run_for(u64::MAX),
+        StopReason::Watchpoint {
+            kind: WatchKind::Write,
+            ..
+        }
+    ));
+    assert!(matches!(
+        emu.run_for(u64::MAX),
+        StopReason::Watchpoint {
+            kind: WatchKind::Read,
+            ..
+        }
+    ));
+    assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 7 });
+}
+
+#[test]
+fn disjoint_watchpoint() {
+    let mut emu = emulator(true, store_load());
+    emu.add_watchpoint(STACK_TOP - 16..STACK_TOP - 8, WatchKind::Access);
+
+    assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 7 });
+}
+
+#[test]
+fn amo_watchpoints() {
+    // amoadd.d reads and writes the word, lr.d only reads it
+    let code = assemble(
+        "
+            li t0, 0x8000fff8
+            li t1, 5
+            amoadd.d t2, t1, (t0)
+            lr.d t3, (t0)
+            li a7, 93
+            ecall
+        ",
+    )
+    .unwrap();
+
+    for kind in [WatchKind::Read, WatchKind::Write, WatchKind::Access] {
+        let mut emu = emulator(true, code.clone());
+        emu.add_watchpoint(STACK_TOP - 8..STACK_TOP, kind);
+
+        let StopReason::Watchpoint {
+            pc,
+            address,
+            kind: hit,
+        } = emu.run_for(u64::MAX)
+        else {
+            panic!("the amoadd.d did not stop at the {kind:?} watchpoint");
+        };
+        assert_eq!(address, STACK_TOP - 8);
+        assert_eq!(emu.cpu.xregs[7], 0);
+        assert_eq!(
+            hit,
+            match kind {
+                WatchKind::Write => WatchKind::Write,
+                _ => WatchKind::Read,
+            }
+        );
+
+        let stop = emu.run_for(u64::MAX);
+        match kind {
+            WatchKind::Write => assert!(matches!(stop, StopReason::Exited { .. })),
+            _ => assert_eq!(
+                stop,
+                StopReason::Watchpoint {
+                    pc: pc + 4,
+                    address: STACK_TOP - 8,
+                    kind: WatchKind::Read,
+                }
+            ),
+        }
+    }
+}
@@ -0,0 +1,114 @@
+use std::sync::{Arc, Mutex};
+
+use risemu::asm::assemble;
+use risemu::bus::Address;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::mmio::Mmio;
+
+mod macros;
+
+// a device holding code, which the host may rewrite
+struct Rom(Mutex<Vec<u8>>);
+
+impl Mmio for Rom {
+    fn read(&self, offset: Address, size: usize) -> Option<u64> {
+        let code = self.0.lock().unwrap();
+        let bytes = code.get(offset as usize..offset as usize + size)?;
+
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| (value << 8) | byte as u64),
+        )
+    }
+
+    fn write(&self, _offset: Address, _size: usize, _value: u64) -> bool {
+        false
+    }
+}
+
+// where nothing but a device can be, far past RAM
+const HIGH: Address = 0xffff_ffff_0000_0000;
+
+#[test]
+fn self_modifying_fence_i() {
+    test_case!(
+        14,
+        0x00000002,
+        vec![
+            0x93, 0x02, 0x00, 0x00, // li x5, 0
+            0x17, 0x05, 0x00, 0x00, // auipc x10, 0
+            0x13, 0x07, 0x10, 0x00, // li x14, 1
+            0x63, 0x9c, 0x02, 0x00, // bne x5, x0, 24
+            0x93, 0x02, 0x10, 0x00, // li x5, 1
+            0x83, 0x25, 0x45, 0x02, // lw x11, 36(x10)
+            0x23, 0x22, 0xb5, 0x00, // sw x11, 4(x10)
+            0x0f, 0x10, 0x00, 0x00, // fence.i
+            0x6f, 0xf0, 0x9f, 0xfe, // j -24
+            0x73, 0x00, 0x00, 0x00, // ecall
+            0x13, 0x07, 0x20, 0x00, // .word (li x14, 2)
+        ]
+    );
+}
+
+#[test]
+fn self_modifying_store() {
+    test_case!(
+        14,
+        0x00000002,
+        vec![
+            0x93, 0x02, 0x00, 0x00, // li x5, 0
+            0x17, 0x05, 0x00, 0x00, // auipc x10, 0
+            0x13, 0x07, 0x10, 0x00, // li x14, 1
+            0x63, 0x9c, 0x02, 0x00, // bne x5, x0, 24
+            0x93, 0x02, 0x10, 0x00, // li x5, 1
+            0x83, 0x25, 0x45, 0x02, // lw x11, 36(x10)
+            0x23, 0x22, 0xb5, 0x00, // sw x11, 4(x10)
+            0x13, 0x00, 0x00, 0x00, // nop
+            0x6f, 0xf0, 0x9f, 0xfe, // j -24
+            0x73, 0x00, 0x00, 0x00, // ecall
+            0x13, 0x07, 0x20, 0x00, // .word (li x14, 2)
+        ]
+    );
+}
+
+#[test]
+fn reload_flushes_cache() {
+    let mut emu = Emulator::new(0x10000);
+
+    emu.init_ram(vec![
+        0x13, 0x07, 0x10, 0x00, // li x14, 1
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+    assert!(matches!(emu.run(), Err(RVException::EnvironmentCall)));
+    assert_eq!(emu.cpu.xregs[14], 1);
+
+    emu.init_ram(vec![
+        0x13, 0x07, 0x20, 0x00, // li x14, 2
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+    assert!(matches!(emu.run(), Err(RVException::EnvironmentCall)));
+    assert_eq!(emu.cpu.xregs[14], 2);
+}
+
+#[test]
+fn device_fetches_are_not_cached() {
+    for block_cache in [false, true] {
+        let mut emu = Emulator::new(0x10000);
+        emu.cpu.set_decode_cache(true);
+        emu.cpu.set_block_cache(block_cache);
+
+        let rom = Arc::new(Rom(Mutex::new(vec![])));
+        assert!(emu.map_mmio(HIGH, 0x1000, rom.clone()));
+
+        for code in [5, 6] {
+            let source = format!("li a0, {code}\nli a7, 93\necall");
+            *rom.0.lock().unwrap() = assemble(&source).unwrap();
+            emu.cpu.pc = HIGH;
+
+            assert_eq!(emu.run_for(100), StopReason::Exited { code });
+        }
+    }
+}
@@ -0,0 +1,266 @@
+use risemu::bus::RAM_BASE;
+use risemu::cpu::Xlen;
+use risemu::elf::{Elf, Symbol};
+use risemu::emulator::{Emulator, StopReason};
+
+// an executable with a single loadable segment holding `code`, followed by `bss` zeroed bytes
+fn executable(xlen: Xlen, entry: u64, address: u64, code: &[u8], bss: u64) -> Vec<u8> {
+    let wide = xlen == Xlen::Rv64;
+    let word = |value: u64| match wide {
+        true => value.to_le_bytes().to_vec(),
+        false => (value as u32).to_le_bytes().to_vec(),
+    };
+    let (ehsize, phentsize) = if wide { (64u16, 56u16) } else { (52, 32) };
+    let offset = (ehsize + phentsize) as u64;
+
+    let mut elf = vec![0x7f, b'E', b'L', b'F', if wide { 2 } else { 1 }, 1, 1];
+    elf.resize(16, 0);
+    elf.extend(2u16.to_le_bytes()); // ET_EXEC
+    elf.extend(0xf3u16.to_le_bytes()); // EM_RISCV
+    elf.extend(1u32.to_le_bytes());
+    elf.extend(word(entry));
+    elf.extend(word(ehsize as u64)); // e_phoff
+    elf.extend(word(0)); // e_shoff
+    elf.extend(0u32.to_le_bytes());
+    elf.extend(ehsize.to_le_bytes());
+    elf.extend(phentsize.to_le_bytes());
+    elf.extend(1u16.to_le_bytes());
+    elf.extend([0; 6]);
+    assert_eq!(elf.len(), ehsize as usize);
+
+    let size = code.len() as u64;
+    elf.extend(1u32.to_le_bytes()); // PT_LOAD
+    if wide {
+        elf.extend(5u32.to_le_bytes());
+    }
+    elf.extend(word(offset));
+    elf.extend(word(address));
+    elf.extend(word(address));
+    elf.extend(word(size));
+    elf.extend(word(size + bss));
+    if !wide {
+        elf.extend(5u32.to_le_bytes());
+    }
+    elf.extend(word(4));
+    assert_eq!(elf.len(), offset as usize);
+
+    elf.extend(code);
+    elf
+}
+
+// appends a symbol table of (name, value, size, st_info, st_shndx) and its string table
+fn with_symbols(mut elf: Vec<u8>, xlen: Xlen, symbols: &[(&str, u64, u64, u8, u16)]) -> Vec<u8> {
+    let wide = xlen == Xlen::Rv64;
+    let word = |value: u64| match wide {
+        true => value.to_le_bytes().to_vec(),
+        false => (value as u32).to_le_bytes().to_vec(),
+    };
+
+    let strtab = elf.len() as u64;
+    let mut names = vec![];
+    elf.push(0);
+    for (name, ..) in symbols {
+        names.push(elf.len() as u64 - strtab);
+        elf.extend(name.as_bytes());
+        elf.push(0);
+    }
+
+    let symtab = elf.len() as u64;
+    let entry = if wide { 24 } else { 16 };
+    elf.resize(elf.len() + entry, 0);
+    for (&(_, value, size, info, shndx), name) in symbols.iter().zip(names) {
+        elf.extend((name as u32).to_le_bytes());
+        if wide {
+            elf.extend([info, 0]);
+            elf.extend(shndx.to_le_bytes());
+        }
+        elf.extend(word(value));
+        elf.extend(word(size));
+        if !wide {
+            elf.extend([info, 0]);
+            elf.extend(shndx.to_le_bytes());
+        }
+    }
+    let symtab_size = elf.len() as u64 - symtab;
+
+    // a null section, the string table, then the symbol table linking to it
+    let shoff = elf.len() as u64;
+    let sections = [
+        (0, 0, 0, 0),
+        (3, strtab, symtab - strtab, 0),
+        (2, symtab, symtab_size, 1),
+    ];
+    for (kind, offset, size, link) in sections {
+        elf.extend(0u32.to_le_bytes());
+        elf.extend((kind as u32).to_le_bytes());
+        elf.extend(word(0));
+        elf.extend(word(0));
+        elf.extend(word(offset));
+        elf.extend(word(size));
+        elf.extend((link as u32).to_le_bytes());
+        elf.extend(0u32.to_le_bytes());
+        elf.extend(word(0));
+        elf.extend(word(entry as u64 * (kind == 2) as u64));
+    }
+
+    let (word_size, shentsize) = if wide { (8, 64u16) } else { (4, 40) };
+    let shoff_field = 0x18 + 2 * word_size;
+    elf.splice(shoff_field..shoff_field + word_size, word(shoff));
+    elf.splice(
+        0x22 + 3 * word_size..0x26 + 3 * word_size,
+        [shentsize.to_le_bytes(), 3u16.to_le_bytes()].concat(),
+    );
+    elf
+}
+
+#[test]
+fn parse_segments() {
+    let code = [0x13, 0x00, 0x00, 0x00]; // nop
+    let elf = Elf::parse(&executable(
+        Xlen::Rv64,
+        RAM_BASE + 0x100,
+        RAM_BASE,
+        &code,
+        12,
+    ))
+    .unwrap();
+
+    assert_eq!(elf.xlen, Xlen::Rv64);
+    assert_eq!(elf.entry, RAM_BASE + 0x100);
+    assert_eq!(elf.segments.len(), 1);
+    assert_eq!(elf.segments[0].address, RAM_BASE);
+    assert_eq!(elf.segments[0].size, 16);
+    assert_eq!(elf.segments[0].data, code);
+    assert!(elf.symbols.is_empty());
+}
+
+#[test]
+fn parse_symbols() {
+    let code = [0x13, 0x00, 0x00, 0x00]; // nop
+
+    for xlen in [Xlen::Rv32, Xlen::Rv64] {
+        let elf = with_symbols(
+            executable(xlen, RAM_BASE, RAM_BASE, &code, 0),
+            xlen,
+            &[
+                ("main", RAM_BASE + 0x10, 0x20, 0x12, 1), // global function
+                ("_start", RAM_BASE, 0, 0x10, 1),         // global label
+                ("buffer", RAM_BASE + 0x100, 0x40, 0x11, 1), // object
+                ("$x", RAM_BASE, 0, 0x00, 1),             // mapping symbol
+                ("printf", 0, 0, 0x12, 0),                // undefined
+                ("answer", 42, 0, 0x00, 0xfff1),          // absolute
+            ],
+        );
+        let elf = Elf::parse(&elf).unwrap();
+
+        let names: Vec<&str> = elf
+            .symbols
+            .iter()
+            .map(|symbol| symbol.name.as_str())
+            .collect();
+        assert_eq!(names, ["_start", "main"]);
+        assert_eq!(
+            elf.symbols[1],
+            Symbol {
+                name: "main".to_string(),
+                address: RAM_BASE + 0x10,
+                size: 0x20,
+            }
+        );
+
+        // a symbol without a size runs up to the next one
+        let name = |address| elf.symbol(address).map(|symbol| symbol.name.as_str());
+        assert_eq!(name(RAM_BASE - 4), None);
+        assert_eq!(name(RAM_BASE + 0xc), Some("_start"));
+        assert_eq!(name(RAM_BASE + 0x2c), Some("main"));
+        assert_eq!(name(RAM_BASE + 0x30), None);
+    }
+}
+
+#[test]
+fn load_and_run() {
+    let code = vec![
+        0x17, 0x05, 0x00, 0x00, // auipc a0, 0
+        0x03, 0x35, 0x85, 0x01, // ld a0, 24(a0)
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ];
+
+    let mut emu = Emulator::new(0x10000);
+    emu.cpu.bus.ram().write_bytes(0, &[0xff; 0x10000]);
+    emu.load_elf(&executable(
+        Xlen::Rv64,
+        RAM_BASE + 0x2000,
+        RAM_BASE + 0x2000,
+        &code,
+        16,
+    ))
+    .unwrap();
+
+    // the ld reads the zeroed memory past the end of the file contents
+    assert_eq!(emu.cpu.pc, RAM_BASE + 0x2000);
+    assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 0 });
+}
+
+#[test]
+fn class_selects_xlen() {
+    let code = vec![
+        0x37, 0x05, 0x00, 0x80, // lui a0, 0x80000
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ];
+
+    let mut emu = Emulator::new(0x10000);
+    emu.load_elf(&executable(Xlen::Rv32, RAM_BASE, RAM_BASE, &code, 0))
+        .unwrap();
+
+    assert_eq!(emu.cpu.xlen(), Xlen::Rv32);
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    assert_eq!(emu.cpu.xregs[10], 0xffffffff80000000);
+}
+
+#[test]
+fn rejects_bad_files() {
+    let code = [0x13, 0x00, 0x00, 0x00]; // nop
+    let mut emu = Emulator::new(0x10000);
+
+    assert!(emu.load_elf(b"not an elf").is_err());
+
+    // outside of RAM
+    let elf = executable(Xlen::Rv64, 0x1000, 0x1000, &code, 0);
+    assert!(emu.load_elf(&elf).is_err());
+
+    // past the end of RAM
+    let elf = executable(Xlen::Rv64, RAM_BASE, RAM_BASE + 0xfffc, &code, 4);
+    assert!(emu.load_elf(&elf).is_err());
+
+    // truncated
+    let elf = executable(Xlen::Rv64, RAM_BASE, RAM_BASE, &code, 0);
+    assert!(emu.load_elf(&elf[..elf.len() - 1]).is_err());
+
+    // another architecture
+    let mut elf = executable(Xlen::Rv64, RAM_BASE, RAM_BASE, &code, 0);
+    elf[0x12] = 0x3e;
+    assert!(emu.load_elf(&elf).is_err());
+}
+
+#[test]
+fn huge_section_offset() {
+    let code = [0x13, 0x00, 0x00, 0x00]; // nop
+    let mut elf = with_symbols(
+        executable(Xlen::Rv64, RAM_BASE, RAM_BASE, &code, 0),
+        Xlen::Rv64,
+        &[("main", RAM_BASE, 4, 0x12, 1)],
+    );
+
+    // the sh_offset of the string table, where symbol names are looked up
+    let shoff = u64::from_le_bytes(elf[0x28..0x30].try_into().unwrap()) as usize;
+    let field = shoff + 64 + 24;
+    elf[field..field + 8].copy_from_slice(&u64::MAX.to_le_bytes());
+
+    let error = Elf::parse(&elf).unwrap_err();
+    assert_eq!(error.to_string(), "truncated ELF file");
+
+    let mut emu = Emulator::new(0x10000);
+    assert!(emu.load_elf(&elf).is_err());
+}
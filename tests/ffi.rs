@@ -0,0 +1,217 @@
+use std::{ffi::c_void, ptr};
+
+use risemu::bus::RAM_BASE;
+use risemu::ffi::*;
+
+const DEVICE: u64 = 0x1000_0000;
+
+unsafe extern "C" fn device_read(
+    context: *mut c_void,
+    offset: u64,
+    size: u32,
+    value: *mut u64,
+) -> bool {
+    let registers = &*(context as *const [u64; 2]);
+    *value = registers[(offset / 8) as usize] + size as u64;
+    true
+}
+
+unsafe extern "C" fn device_write(
+    context: *mut c_void,
+    offset: u64,
+    _size: u32,
+    value: u64,
+) -> bool {
+    let registers = &mut *(context as *mut [u64; 2]);
+    match registers.get_mut((offset / 8) as usize) {
+        Some(register) => *register = value,
+        None => return false,
+    }
+    true
+}
+
+fn load(code: &[u8]) -> *mut risemu::emulator::Emulator {
+    let mut code = code.to_vec();
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let emu = risemu_create(0x10000, 64);
+    assert!(unsafe { risemu_load_image(emu, code.as_ptr(), code.len()) });
+    emu
+}
+
+#[test]
+fn create_and_run() {
+    assert!(risemu_create(0x10000, 16).is_null());
+
+    let emu = load(&[
+        0x13, 0x05, 0xa0, 0x02, // li a0, 42
+    ]);
+
+    unsafe {
+        assert_eq!(risemu_get_pc(emu), RAM_BASE);
+
+        let stop = risemu_step(emu);
+        assert_eq!(stop.kind, StopKind::LimitReached);
+        assert_eq!(stop.pc, RAM_BASE + 4);
+        assert_eq!(risemu_get_register(emu, 10), 42);
+
+        let stop = risemu_run(emu, 100);
+        assert_eq!(stop.kind, StopKind::Exited);
+        assert_eq!(stop.value, 42);
+        assert_eq!(risemu_get_instret(emu), 2);
+
+        risemu_destroy(emu);
+    }
+}
+
+#[test]
+fn registers_and_pc() {
+    let emu = load(&[
+        0x33, 0x05, 0xb5, 0x00, // add a0, a0, a1
+    ]);
+
+    unsafe {
+        assert!(risemu_set_register(emu, 10, 40));
+        assert!(risemu_set_register(emu, 11, 2));
+        assert!(risemu_set_register(emu, 0, 1));
+        assert!(!risemu_set_register(emu, 32, 1));
+
+        assert_eq!(risemu_run(emu, 100).value, 42);
+        assert_eq!(risemu_get_register(emu, 0), 0);
+        assert_eq!(risemu_get_register(emu, 32), 0);
+
+        // skip the add
+        risemu_set_pc(emu, RAM_BASE + 4);
+        assert!(risemu_set_register(emu, 10, 7));
+        assert_eq!(risemu_run(emu, 100).value, 7);
+
+        risemu_destroy(emu);
+    }
+}
+
+#[test]
+fn memory() {
+    let emu = load(&[
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x03, 0x35, 0x05, 0x00, // ld a0, 0(a0)
+    ]);
+
+    unsafe {
+        let value = 0x1122334455667788u64.to_le_bytes();
+        assert!(risemu_write_memory(
+            emu,
+            RAM_BASE + 0x1000,
+            value.as_ptr(),
+            8
+        ));
+        assert_eq!(risemu_run(emu, 100).value, 0x1122334455667788);
+
+        let mut data = [0u8; 4];
+        assert!(risemu_read_memory(
+            emu,
+            RAM_BASE + 0x1002,
+            data.as_mut_ptr(),
+            4
+        ));
+        assert_eq!(data, [0x66, 0x55, 0x44, 0x33]);
+
+        assert!(!risemu_read_memory(
+            emu,
+            RAM_BASE + 0xfffe,
+            data.as_mut_ptr(),
+            4
+        ));
+        assert!(!risemu_write_memory(emu, 0x1000, data.as_ptr(), 4));
+        assert!(risemu_read_memory(emu, 0, ptr::null_mut(), 0));
+
+        risemu_destroy(emu);
+    }
+}
+
+#[test]
+fn writing_code_invalidates_translations() {
+    let emu = load(&[
+        0x13, 0x05, 0x10, 0x00, // li a0, 1
+    ]);
+
+    unsafe {
+        assert_eq!(risemu_run(emu, 100).value, 1);
+
+        let li = [0x13, 0x05, 0x20, 0x00]; // li a0, 2
+        assert!(risemu_write_memory(emu, RAM_BASE, li.as_ptr(), 4));
+        risemu_set_pc(emu, RAM_BASE);
+        assert_eq!(risemu_run(emu, 100).value, 2);
+
+        risemu_destroy(emu);
+    }
+}
+
+#[test]
+fn mmio_callbacks() {
+    let emu = load(&[
+        0xb7, 0x05, 0x00, 0x10, // lui a1, 0x10000
+        0x93, 0x02, 0x50, 0x00, // li t0, 5
+        0x23, 0xb4, 0x55, 0x00, // sd t0, 8(a1)
+        0x03, 0xa5, 0x85, 0x00, // lw a0, 8(a1)
+    ]);
+    let mut registers = [0u64; 2];
+    let context = registers.as_mut_ptr() as *mut c_void;
+
+    unsafe {
+        let (read, write) = (Some(device_read as _), Some(device_write as _));
+        assert!(risemu_map_mmio(emu, DEVICE, 0x1000, read, write, context));
+
+        // overlapping RAM or another device
+        assert!(!risemu_map_mmio(emu, RAM_BASE - 4, 8, read, write, context));
+        assert!(!risemu_map_mmio(
+            emu,
+            DEVICE + 0xff0,
+            0x20,
+            read,
+            write,
+            context
+        ));
+        assert!(!risemu_map_mmio(emu, 0, 0, read, write, context));
+
+        let stop = risemu_run(emu, 100);
+        assert_eq!(stop.kind, StopKind::Exited);
+        assert_eq!(stop.value, 9);
+        assert_eq!(registers, [0, 5]);
+
+        // the device rejects writes past its registers
+        let data = [0u8; 1];
+        assert!(!risemu_write_memory(emu, DEVICE + 16, data.as_ptr(), 1));
+
+        risemu_destroy(emu);
+    }
+}
+
+#[test]
+fn missing_callbacks_fault() {
+    let emu = load(&[
+        0xb7, 0x05, 0x00, 0x10, // lui a1, 0x10000
+        0x03, 0xa5, 0x05, 0x00, // lw a0, 0(a1)
+    ]);
+
+    unsafe {
+        assert!(risemu_map_mmio(
+            emu,
+            DEVICE,
+            0x1000,
+            None,
+            None,
+            ptr::null_mut()
+        ));
+
+        let stop = risemu_run(emu, 100);
+        assert_eq!(stop.kind, StopKind::Fault);
+        assert_eq!(stop.pc, RAM_BASE + 4);
+        assert_eq!(stop.value, DEVICE);
+        assert_eq!(stop.cause, 5);
+
+        risemu_destroy(emu);
+    }
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "fuzzing")]
+
+use arbitrary::{Arbitrary, Unstructured};
+use risemu::cpu::Xlen;
+use risemu::fuzz::{check_decode, Program, Structured};
+
+// what libFuzzer would feed the targets, from a fixed seed
+fn inputs(count: usize) -> impl Iterator<Item = Vec<u8>> {
+    let mut seed = 0x2545f4914f6cdd1du64;
+    (0..count).map(move |index| {
+        (0..64 + 16 * (index % 64))
+            .map(|_| {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                (seed >> 56) as u8
+            })
+            .collect()
+    })
+}
+
+#[test]
+fn decode() {
+    for input in inputs(64) {
+        for word in input.chunks_exact(4) {
+            check_decode(u32::from_le_bytes(word.try_into().unwrap()));
+        }
+    }
+}
+
+#[test]
+fn random_programs() {
+    for input in inputs(200) {
+        Program::arbitrary(&mut Unstructured::new(&input))
+            .unwrap()
+            .check();
+    }
+}
+
+#[test]
+fn structured_programs() {
+    let mut widths = [0; 2];
+    for input in inputs(400) {
+        let Structured(program) = Structured::arbitrary(&mut Unstructured::new(&input)).unwrap();
+        widths[(program.xlen == Xlen::Rv64) as usize] += 1;
+        program.check();
+    }
+
+    assert!(widths.iter().all(|&count| count > 0));
+}
+
+#[test]
+fn end_of_ram() {
+    // a doubleword load straddling the end of RAM, which must fault rather than panic
+    let mut xregs = [0; 32];
+    xregs[8] = risemu::bus::RAM_BASE + risemu::fuzz::RAM_SIZE as u64 - 4;
+
+    Program {
+        xlen: Xlen::Rv64,
+        xregs,
+        instructions: vec![
+            0x00043503, // ld a0, 0(s0)
+            0x00a43023, // sd a0, 0(s0)
+        ],
+    }
+    .check();
+}
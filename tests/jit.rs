@@ -0,0 +1,414 @@
+#![cfg(feature = "jit")]
+
+use std::sync::{Arc, Mutex};
+
+use risemu::asm::assemble;
+use risemu::bus::{Address, RAM_BASE};
+use risemu::emulator::Emulator;
+use risemu::exception::RVException;
+use risemu::mmio::Mmio;
+
+fn r_type(funct7: u32, rs2: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    (funct7 << 25) | (rs2 << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+fn i_type(imm: u32, rs1: u32, funct3: u32, rd: u32, opcode: u32) -> u32 {
+    ((imm & 0xFFF) << 20) | (rs1 << 15) | (funct3 << 12) | (rd << 7) | opcode
+}
+
+// every instruction the JIT compiles, with random operands
+fn random_instruction(seed: &mut u64) -> u32 {
+    let mut next = || {
+        *seed = seed
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        (*seed >> 33) as u32
+    };
+
+    let (rd, rs1, rs2, imm) = (next() % 32, next() % 32, next() % 32, next());
+    match next() % 24 {
+        0 => r_type(0x00, rs2, rs1, 0b000, rd, 0x33),   // add
+        1 => r_type(0x20, rs2, rs1, 0b000, rd, 0x33),   // sub
+        2 => r_type(0x00, rs2, rs1, 0b001, rd, 0x33),   // sll
+        3 => r_type(0x00, rs2, rs1, 0b010, rd, 0x33),   // slt
+        4 => r_type(0x00, rs2, rs1, 0b011, rd, 0x33),   // sltu
+        5 => r_type(0x00, rs2, rs1, 0b100, rd, 0x33),   // xor
+        6 => r_type(0x00, rs2, rs1, 0b101, rd, 0x33),   // srl
+        7 => r_type(0x20, rs2, rs1, 0b101, rd, 0x33),   // sra
+        8 => r_type(0x00, rs2, rs1, 0b110, rd, 0x33),   // or
+        9 => r_type(0x00, rs2, rs1, 0b111, rd, 0x33),   // and
+        10 => r_type(0x01, rs2, rs1, 0b000, rd, 0x33),  // mul
+        11 => i_type(imm, rs1, 0b000, rd, 0x13),        // addi
+        12 => i_type(imm, rs1, 0b010, rd, 0x13),        // slti
+        13 => i_type(imm, rs1, 0b011, rd, 0x13),        // sltiu
+        14 => i_type(imm, rs1, 0b100, rd, 0x13),        // xori
+        15 => i_type(imm, rs1, 0b110, rd, 0x13),        // ori
+        16 => i_type(imm, rs1, 0b111, rd, 0x13),        // andi
+        17 => i_type(imm & 0x3F, rs1, 0b001, rd, 0x13), // slli
+        18 => i_type(imm & 0x3F, rs1, 0b101, rd, 0x13), // srli
+        19 => i_type(0x400 | (imm & 0x3F), rs1, 0b101, rd, 0x13), // srai
+        20 => i_type(imm, rs1, 0b000, rd, 0x1B),        // addiw
+        21 => r_type(0x00, rs2, rs1, 0b000, rd, 0x3B),  // addw
+        22 => r_type(0x20, rs2, rs1, 0b000, rd, 0x3B),  // subw
+        _ => (imm & 0xFFFFF000) | (rd << 7) | 0x37,     // lui
+    }
+}
+
+fn run(code: &[u32], jit: bool) -> Emulator {
+    let (emu, stop) = run_image(
+        code.iter().flat_map(|word| word.to_le_bytes()).collect(),
+        jit,
+    );
+    assert_eq!(stop, RVException::EnvironmentCall);
+    emu
+}
+
+fn run_image(image: Vec<u8>, jit: bool) -> (Emulator, RVException) {
+    let mut emu = emulator(image, jit);
+    let stop = emu.run().unwrap_err();
+    (emu, stop)
+}
+
+fn emulator(image: Vec<u8>, jit: bool) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.cpu.set_block_cache(jit);
+    emu.cpu.set_jit_threshold(0);
+    emu.init_ram(image);
+    emu
+}
+
+// runs a program both ways, expecting the same stop in the same state
+fn compare(source: &str) -> Emulator {
+    let image = assemble(source).unwrap();
+    let (interpreted, expected) = run_image(image.clone(), false);
+    let (compiled, stop) = run_image(image, true);
+
+    assert_eq!(stop, expected);
+    assert_eq!(interpreted.cpu.xregs, compiled.cpu.xregs);
+    assert_eq!(interpreted.cpu.pc, compiled.cpu.pc);
+    assert_eq!(interpreted.cpu.instret, compiled.cpu.instret);
+    assert_eq!(interpreted.cpu.tval, compiled.cpu.tval);
+    assert_eq!(
+        interpreted.cpu.bus.ram().contents(),
+        compiled.cpu.bus.ram().contents()
+    );
+    compiled
+}
+
+// scratch memory standing in for a device
+struct Scratch(Mutex<[u8; 64]>);
+
+impl Mmio for Scratch {
+    fn read(&self, offset: Address, size: usize) -> Option<u64> {
+        let bytes = self.0.lock().unwrap();
+        let bytes = bytes.get(offset as usize..offset as usize + size)?;
+
+        Some(
+            bytes
+                .iter()
+                .rev()
+                .fold(0, |value, &byte| (value << 8) | byte as u64),
+        )
+    }
+
+    fn write(&self, offset: Address, size: usize, value: u64) -> bool {
+        let mut bytes = self.0.lock().unwrap();
+        let Some(bytes) = bytes.get_mut(offset as usize..offset as usize + size) else {
+            return false;
+        };
+
+        for (index, byte) in bytes.iter_mut().enumerate() {
+            *byte = (value >> (8 * index)) as u8;
+        }
+        true
+    }
+}
+
+#[test]
+fn random_alu_streams() {
+    let mut seed = 0x5EED;
+
+    for _ in 0..200 {
+        let mut code: Vec<u32> = (0..48).map(|_| random_instruction(&mut seed)).collect();
+        code.push(0x00000073); // ecall
+
+        let interpreted = run(&code, false);
+        let compiled = run(&code, true);
+
+        assert_eq!(interpreted.cpu.xregs, compiled.cpu.xregs, "{code:08x?}");
+        assert_eq!(interpreted.cpu.pc, compiled.cpu.pc);
+        assert_eq!(interpreted.cpu.instret, compiled.cpu.instret);
+    }
+}
+
+#[test]
+fn compiled_loop() {
+    let code = [
+        0x00000293, // li x5, 0
+        0x06400313, // li x6, 100
+        0x00000393, // li x7, 0
+        0x00128293, // addi x5, x5, 1
+        0x005383b3, // add x7, x7, x5
+        0xfe629ce3, // bne x5, x6, -8
+        0x00000073, // ecall
+    ];
+
+    let interpreted = run(&code, false);
+    let compiled = run(&code, true);
+
+    assert_eq!(compiled.cpu.xregs[7], 5050);
+    assert_eq!(interpreted.cpu.xregs, compiled.cpu.xregs);
+    assert_eq!(interpreted.cpu.instret, compiled.cpu.instret);
+}
+
+#[test]
+fn uncompilable_after_operands() {
+    // divw gets as far as loading its operands before the JIT gives up on it
+    let code = [
+        0x00700293,                  // li x5, 7
+        0xffe00313,                  // li x6, -2
+        r_type(1, 6, 5, 4, 7, 0x3b), // divw x7, x5, x6
+        0x00138393,                  // addi x7, x7, 1
+        0x00000073,                  // ecall
+    ];
+
+    let interpreted = run(&code, false);
+    let compiled = run(&code, true);
+
+    assert_eq!(compiled.cpu.xregs[7], -2i64 as u64);
+    assert_eq!(interpreted.cpu.xregs, compiled.cpu.xregs);
+    assert_eq!(interpreted.cpu.instret, compiled.cpu.instret);
+}
+
+#[test]
+fn loads_and_stores() {
+    // every width, both signs, and loads right after the store they read
+    let emu = compare(
+        "
+            li s0, 0x80008000
+            li s1, 0
+            li s2, 200
+        loop:
+            slli t0, s1, 3
+            add t0, t0, s0
+            ld t1, -8(t0)
+            sub t1, t1, s1
+            sd t1, 0(t0)
+            lb t2, 7(t0)
+            lbu t3, 7(t0)
+            lh t4, 6(t0)
+            lhu t5, 6(t0)
+            lw t6, 4(t0)
+            lwu a1, 4(t0)
+            add a2, a2, t2
+            add a2, a2, t3
+            add a2, a2, t4
+            add a2, a2, t5
+            add a2, a2, t6
+            add a2, a2, a1
+            sb s1, 0(t0)
+            sh s1, 2(t0)
+            sw s1, 4(t0)
+            ld a3, 0(t0)
+            add a2, a2, a3
+            addi s1, s1, 1
+            bne s1, s2, loop
+            ecall
+        ",
+    );
+    assert_eq!(emu.cpu.xregs[9], 200);
+}
+
+#[test]
+fn calls() {
+    let emu = compare(
+        "
+            li s1, 0
+            li s2, 100
+            li s3, 0
+        loop:
+            mv a0, s1
+            call square
+            add s3, s3, a0
+            addi s1, s1, 1
+            bne s1, s2, loop
+            ecall
+        square:
+            mul a0, a0, a0
+            ret
+        ",
+    );
+    assert_eq!(emu.cpu.xregs[19], 328350);
+}
+
+#[test]
+fn misaligned_jump() {
+    // the interpreter raises the exception for the JALR that jumps to a misaligned target
+    compare(
+        "
+            li s1, 0
+            li s2, 20
+            la s3, loop
+        loop:
+            addi s1, s1, 1
+            slt t0, s1, s2
+            xori t0, t0, 1
+            slli t0, t0, 1
+            add t0, t0, s3
+            jr t0
+        ",
+    );
+}
+
+#[test]
+fn faulting_load() {
+    // compiled code reads RAM directly until it reaches the end, where it faults
+    let emu = compare(
+        "
+            li t0, 0x8000ff00
+        loop:
+            ld t1, 0(t0)
+            addi t0, t0, 8
+            j loop
+        ",
+    );
+    assert_eq!(emu.cpu.tval, RAM_BASE + 0x10000);
+}
+
+#[test]
+fn misaligned_load() {
+    compare(
+        "
+            li t0, 0x80008000
+            li s1, 0
+            li s2, 20
+        loop:
+            add t1, t0, s1
+            lw t2, 0(t1)
+            addi s1, s1, 1
+            bne s1, s2, loop
+            ecall
+        ",
+    );
+}
+
+#[test]
+fn device_accesses() {
+    let image = assemble(
+        "
+            li s0, 0x10001000
+            li s1, 0
+            li s2, 50
+        loop:
+            lw t0, 0(s0)
+            add t0, t0, s1
+            sw t0, 0(s0)
+            lb t1, 8(s0)
+            addi t1, t1, -3
+            sb t1, 8(s0)
+            addi s1, s1, 1
+            bne s1, s2, loop
+            lw a0, 0(s0)
+            lb a1, 8(s0)
+            ecall
+        ",
+    )
+    .unwrap();
+
+    for jit in [false, true] {
+        let mut emu = emulator(image.clone(), jit);
+        let scratch = Arc::new(Scratch(Mutex::new([0; 64])));
+        assert!(emu.map_mmio(0x1000_1000, 64, scratch));
+
+        assert_eq!(emu.run(), Err(RVException::EnvironmentCall));
+        assert_eq!(emu.cpu.xregs[10], 1225);
+        assert_eq!(emu.cpu.xregs[11], -150i64 as i8 as u64);
+    }
+}
+
+#[test]
+fn stores_drop_other_blocks() {
+    // the loop keeps rewriting the function it calls, which lives in another page
+    let emu = compare(
+        "
+            li s1, 0
+            li s2, 50
+            li s3, 0
+            la s4, function
+            la t0, alternative
+            lw t1, 0(t0)
+            lw t2, 0(s4)
+            xor t2, t2, t1
+        loop:
+            call function
+            add s3, s3, a0
+            sw t1, 0(s4)
+            xor t1, t1, t2
+            addi s1, s1, 1
+            bne s1, s2, loop
+            ecall
+            .balign 4096
+        function:
+            li a0, 1
+            ret
+        alternative:
+            li a0, 2
+        ",
+    );
+    assert_eq!(emu.cpu.xregs[19], 75);
+}
+
+#[test]
+fn stores_drop_their_own_block() {
+    // compiled by the time its store rewrites the instruction right after it
+    let emu = compare(
+        "
+            li s1, 0
+            li s2, 40
+            li s5, 30
+            la s6, patch
+            li s7, 0x80008000
+            la t0, alternative
+            lw t1, 0(t0)
+            li a0, 0
+        loop:
+            mv s4, s7
+            bne s1, s5, store
+            mv s4, s6
+        store:
+            sw t1, 0(s4)
+        patch:
+            addi a0, a0, 1
+            addi s1, s1, 1
+            bne s1, s2, loop
+            ecall
+        alternative:
+            addi a0, a0, 100
+        ",
+    );
+    assert_eq!(emu.cpu.xregs[10], 30 + 10 * 100);
+}
+
+#[test]
+fn pmp_denied_load() {
+    // a locked entry takes read permission from machine mode, one page into the loop
+    let emu = compare(
+        "
+            li t0, 0x80008000
+            srli t0, t0, 2
+            csrw pmpaddr0, t0
+            li t0, 0x80009000
+            srli t0, t0, 2
+            csrw pmpaddr1, t0
+            li t0, 0x8800
+            csrw pmpcfg0, t0
+
+            li t0, 0x80007f00
+        loop:
+            ld t1, 0(t0)
+            addi t0, t0, 8
+            j loop
+        ",
+    );
+    assert_eq!(emu.cpu.tval, RAM_BASE + 0x8000);
+}
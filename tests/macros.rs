@@ -1,25 +1,18 @@
 #[macro_export]
 macro_rules! test_case {
     ($register:expr, $result:expr, $code:expr) => {
-        let mut emu = Emulator::new(0x10000);
-
-        let mut code = vec![];
-        code.extend($code);
-        code.extend([0x73, 0x00, 0x00, 0x00]); // ECALL
-
-        emu.init_ram(code);
-        match emu.run() {
-            Ok(_) => {}
-            Err(ex) => {
-                println!("{ex:#?} @ {:#x}", emu.cpu.pc);
-
-                match ex {
-                    RVException::EnvironmentCall => {}
-                    _ => panic!(),
-                }
-            }
-        }
+        risemu::testing::TestCase::new($code)
+            .expect_register($register, $result)
+            .run();
+    };
+}
 
-        assert_eq!(emu.cpu.xregs[$register], $result);
+// like test_case!, with the code given as assembly
+#[macro_export]
+macro_rules! asm_test_case {
+    ($register:expr, $result:expr, $source:expr) => {
+        risemu::testing::TestCase::asm($source)
+            .expect_register($register, $result)
+            .run();
     };
 }
@@ -0,0 +1,197 @@
+use risemu::bus::RAM_BASE;
+use risemu::cpu::MisalignedPolicy;
+use risemu::csr::MISA;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+
+fn emulator(code: Vec<u8>, policy: MisalignedPolicy) -> Emulator {
+    let mut code = code;
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let mut emu = Emulator::new(0x10000);
+    emu.cpu.misaligned = policy;
+    emu.init_ram(code);
+    emu
+}
+
+// control transfers can only be misaligned without C
+fn without_compressed(emu: &mut Emulator) {
+    let misa = emu.cpu.read_csr(MISA).unwrap();
+    assert!(emu.cpu.write_csr(MISA, misa & !(1 << 2)));
+}
+
+fn fault(emu: &mut Emulator) -> (RVException, u64, u64) {
+    match emu.run_for(u64::MAX) {
+        StopReason::Fault {
+            exception,
+            pc,
+            tval,
+        } => (exception, pc, tval),
+        stop => panic!("unexpected stop {stop:?}"),
+    }
+}
+
+#[test]
+fn emulated_by_default() {
+    let mut emu = emulator(
+        vec![
+            0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+            0xb7, 0x92, 0x44, 0x00, // lui t0, 1097
+            0x9b, 0x82, 0xd2, 0x8c, // addiw t0, t0, -1843
+            0x93, 0x92, 0xe2, 0x00, // slli t0, t0, 14
+            0x93, 0x82, 0x52, 0x45, // addi t0, t0, 1109
+            0x93, 0x92, 0xc2, 0x00, // slli t0, t0, 12
+            0x93, 0x82, 0x72, 0x66, // addi t0, t0, 1639
+            0x93, 0x92, 0xc2, 0x00, // slli t0, t0, 12
+            0x93, 0x82, 0x82, 0x78, // addi t0, t0, 1928
+            0xa3, 0x31, 0x55, 0x00, // sd t0, 3(a0)
+            0x83, 0x35, 0x35, 0x00, // ld a1, 3(a0)
+            0x03, 0x26, 0x55, 0x00, // lw a2, 5(a0)
+            0x83, 0x56, 0x15, 0x00, // lhu a3, 1(a0)
+        ],
+        MisalignedPolicy::default(),
+    );
+
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    assert_eq!(emu.cpu.xregs[11], 0x1122334455667788);
+    assert_eq!(emu.cpu.xregs[12], 0x33445566);
+    assert_eq!(emu.cpu.xregs[13], 0x0000);
+    assert_eq!(
+        emu.cpu.bus.ram().contents()[0x1000..0x100c],
+        [0, 0, 0, 0x88, 0x77, 0x66, 0x55, 0x44, 0x33, 0x22, 0x11, 0]
+    );
+}
+
+#[test]
+fn trapped_loads() {
+    let mut emu = emulator(
+        vec![
+            0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+            0x83, 0x25, 0x45, 0x00, // lw a1, 4(a0)
+            0x03, 0x16, 0x15, 0x00, // lh a2, 1(a0)
+        ],
+        MisalignedPolicy::Trap,
+    );
+
+    assert_eq!(
+        fault(&mut emu),
+        (
+            RVException::LoadAddressMisaligned,
+            RAM_BASE + 8,
+            RAM_BASE + 0x1001
+        )
+    );
+}
+
+#[test]
+fn trapped_stores() {
+    let mut emu = emulator(
+        vec![
+            0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+            0x23, 0x11, 0x05, 0x00, // sh zero, 2(a0)
+            0x23, 0x32, 0x05, 0x00, // sd zero, 4(a0)
+        ],
+        MisalignedPolicy::Trap,
+    );
+
+    assert_eq!(
+        fault(&mut emu),
+        (
+            RVException::StoreAddressMisaligned,
+            RAM_BASE + 8,
+            RAM_BASE + 0x1004
+        )
+    );
+}
+
+#[test]
+fn emulated_access_past_the_end_of_ram() {
+    let mut emu = emulator(
+        vec![
+            0x17, 0x05, 0x01, 0x00, // auipc a0, 0x10
+            0x83, 0x35, 0xc5, 0xff, // ld a1, -4(a0)
+        ],
+        MisalignedPolicy::Emulate,
+    );
+
+    assert_eq!(
+        fault(&mut emu),
+        (
+            RVException::LoadAccessFault,
+            RAM_BASE + 4,
+            RAM_BASE + 0xfffc
+        )
+    );
+}
+
+#[test]
+fn misaligned_jal() {
+    let mut emu = emulator(
+        vec![
+            0x13, 0x00, 0x00, 0x00, // nop
+            0xef, 0x00, 0x60, 0x00, // jal ra, 6
+        ],
+        MisalignedPolicy::Emulate,
+    );
+
+    without_compressed(&mut emu);
+
+    assert_eq!(
+        fault(&mut emu),
+        (
+            RVException::InstructionAddressMisaligned,
+            RAM_BASE + 4,
+            RAM_BASE + 10
+        )
+    );
+    assert_eq!(emu.cpu.xregs[1], 0);
+}
+
+#[test]
+fn misaligned_jalr() {
+    let mut emu = emulator(
+        vec![
+            0x17, 0x05, 0x00, 0x00, // auipc a0, 0
+            0xe7, 0x00, 0x75, 0x00, // jalr ra, 7(a0)
+        ],
+        MisalignedPolicy::Emulate,
+    );
+
+    without_compressed(&mut emu);
+
+    assert_eq!(
+        fault(&mut emu),
+        (
+            RVException::InstructionAddressMisaligned,
+            RAM_BASE + 4,
+            RAM_BASE + 6
+        )
+    );
+    assert_eq!(emu.cpu.xregs[1], 0);
+}
+
+#[test]
+fn misaligned_branches() {
+    let mut emu = emulator(
+        vec![
+            0x13, 0x05, 0x10, 0x00, // li a0, 1
+            0x63, 0x03, 0x05, 0x00, // beq a0, zero, 6
+            0x63, 0x15, 0x05, 0x00, // bne a0, zero, 10
+        ],
+        MisalignedPolicy::Emulate,
+    );
+
+    without_compressed(&mut emu);
+
+    assert_eq!(
+        fault(&mut emu),
+        (
+            RVException::InstructionAddressMisaligned,
+            RAM_BASE + 8,
+            RAM_BASE + 18
+        )
+    );
+}
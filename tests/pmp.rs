@@ -0,0 +1,298 @@
+use risemu::bus::RAM_BASE;
+use risemu::cpu::Privilege;
+use risemu::csr::{MISA, MSTATUS, MSTATUS_MPP, PMPADDR0, PMPCFG0};
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::snapshot::Snapshot;
+
+fn emulator(code: Vec<u8>) -> Emulator {
+    let mut code = code;
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(code);
+    emu
+}
+
+fn run(code: Vec<u8>) -> Emulator {
+    let mut emu = emulator(code);
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    emu
+}
+
+fn fault(code: Vec<u8>) -> (Emulator, RVException, u64, u64) {
+    let mut emu = emulator(code);
+    match emu.run_for(u64::MAX) {
+        StopReason::Fault {
+            exception,
+            pc,
+            tval,
+        } => (emu, exception, pc, tval),
+        stop => panic!("unexpected stop {stop:?}"),
+    }
+}
+
+#[test]
+fn user_mode_needs_an_entry() {
+    let (emu, exception, pc, tval) = fault(vec![
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+        0x13, 0x00, 0x00, 0x00, // nop
+    ]);
+
+    assert_eq!(emu.cpu.privilege(), Privilege::User);
+    assert_eq!(exception, RVException::InstructionAccessFault);
+    assert_eq!(pc, RAM_BASE + 16);
+    assert_eq!(tval, RAM_BASE + 16);
+}
+
+#[test]
+fn napot_grants_user_access() {
+    let emu = run(vec![
+        0xb7, 0x22, 0x00, 0x20, // lui t0, 131074
+        0x9b, 0x82, 0xf2, 0xff, // addiw t0, t0, -1
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0x93, 0x02, 0xf0, 0x01, // li t0, 0x1f
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x02, 0xa0, 0x02, // li t0, 42
+        0x23, 0x30, 0x55, 0x00, // sd t0, 0(a0)
+        0x83, 0x35, 0x05, 0x00, // ld a1, 0(a0)
+    ]);
+
+    assert_eq!(emu.cpu.privilege(), Privilege::User);
+    assert_eq!(emu.cpu.xregs[11], 42);
+}
+
+#[test]
+fn lowest_numbered_entry_wins() {
+    let (emu, exception, pc, tval) = fault(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x52, 0x25, 0x00, // srli t0, a0, 2
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0xb7, 0x22, 0x00, 0x20, // lui t0, 131074
+        0x9b, 0x82, 0xf2, 0xff, // addiw t0, t0, -1
+        0x73, 0x90, 0x12, 0x3b, // csrw pmpaddr1, t0
+        0xb7, 0x22, 0x00, 0x00, // lui t0, 2
+        0x9b, 0x82, 0x12, 0xf1, // addiw t0, t0, -239
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+        0x83, 0x25, 0x05, 0x00, // lw a1, 0(a0)
+        0x23, 0x20, 0xb5, 0x00, // sw a1, 0(a0)
+    ]);
+
+    assert_eq!(emu.cpu.privilege(), Privilege::User);
+    assert_eq!(exception, RVException::StoreAccessFault);
+    assert_eq!(pc, RAM_BASE + 0x38);
+    assert_eq!(tval, RAM_BASE + 0x1000);
+}
+
+#[test]
+fn partial_match_fails() {
+    let (_, exception, _, tval) = fault(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x52, 0x25, 0x00, // srli t0, a0, 2
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0xb7, 0x22, 0x00, 0x20, // lui t0, 131074
+        0x9b, 0x82, 0xf2, 0xff, // addiw t0, t0, -1
+        0x73, 0x90, 0x12, 0x3b, // csrw pmpaddr1, t0
+        0xb7, 0x22, 0x00, 0x00, // lui t0, 2
+        0x9b, 0x82, 0x32, 0xf1, // addiw t0, t0, -237
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+        0x83, 0x25, 0x05, 0x00, // lw a1, 0(a0)
+        0x83, 0x35, 0xc5, 0xff, // ld a1, -4(a0)
+    ]);
+
+    assert_eq!(exception, RVException::LoadAccessFault);
+    assert_eq!(tval, RAM_BASE + 0xffc);
+}
+
+#[test]
+fn partial_match_binds_machine_mode() {
+    let (emu, exception, pc, tval) = fault(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x52, 0x25, 0x00, // srli t0, a0, 2
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0x93, 0x02, 0x70, 0x01, // li t0, 0x17
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x83, 0x25, 0x05, 0x00, // lw a1, 0(a0)
+        0x03, 0x36, 0xc5, 0xff, // ld a2, -4(a0)
+    ]);
+
+    assert_eq!(emu.cpu.privilege(), Privilege::Machine);
+    assert_eq!(exception, RVException::LoadAccessFault);
+    assert_eq!(pc, RAM_BASE + 0x18);
+    assert_eq!(tval, RAM_BASE + 0xffc);
+}
+
+#[test]
+fn top_of_range() {
+    let (emu, exception, pc, tval) = fault(vec![
+        0xb7, 0x02, 0x00, 0x20, // li t0, 0x20000000
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0xb7, 0x02, 0x00, 0x20, // lui t0, 131072
+        0x9b, 0x82, 0x02, 0x40, // addiw t0, t0, 1024
+        0x73, 0x90, 0x12, 0x3b, // csrw pmpaddr1, t0
+        0xb7, 0x12, 0x00, 0x00, // lui t0, 1
+        0x9b, 0x82, 0x02, 0xd0, // addiw t0, t0, -768
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+        0x17, 0x05, 0x00, 0x00, // auipc a0, 0
+        0x83, 0x25, 0x05, 0x00, // lw a1, 0(a0)
+        0x03, 0x26, 0xc5, 0x7f, // lw a2, 0x7fc(a0)
+        0x93, 0x06, 0x10, 0x00, // li a3, 1
+        0x93, 0x96, 0xc6, 0x00, // slli a3, a3, 12
+        0xb3, 0x86, 0xa6, 0x00, // add a3, a3, a0
+        0x03, 0xa7, 0x06, 0x00, // lw a4, 0(a3)
+    ]);
+
+    assert_eq!(emu.cpu.xregs[11], 0x00000517);
+    assert_eq!(exception, RVException::LoadAccessFault);
+    assert_eq!(pc, RAM_BASE + 0x48);
+    assert_eq!(tval, RAM_BASE + 0x1030);
+}
+
+#[test]
+fn locked_entries_bind_machine_mode() {
+    let (emu, exception, pc, tval) = fault(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x23, 0x20, 0x05, 0x00, // sw zero, 0(a0)
+        0x93, 0x52, 0x25, 0x00, // srli t0, a0, 2
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0x93, 0x02, 0x10, 0x09, // li t0, 0x91
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x73, 0x10, 0x00, 0x3a, // csrw pmpcfg0, zero
+        0x73, 0x10, 0x00, 0x3b, // csrw pmpaddr0, zero
+        0xf3, 0x25, 0x00, 0x3a, // csrr a1, pmpcfg0
+        0x73, 0x26, 0x00, 0x3b, // csrr a2, pmpaddr0
+        0x83, 0x26, 0x05, 0x00, // lw a3, 0(a0)
+        0x23, 0x20, 0xd5, 0x00, // sw a3, 0(a0)
+    ]);
+
+    assert_eq!(emu.cpu.privilege(), Privilege::Machine);
+    assert_eq!(emu.cpu.xregs[11], 0x91);
+    assert_eq!(emu.cpu.xregs[12], (RAM_BASE + 0x1000) >> 2);
+    assert_eq!(exception, RVException::StoreAccessFault);
+    assert_eq!(pc, RAM_BASE + 0x2c);
+    assert_eq!(tval, RAM_BASE + 0x1000);
+}
+
+#[test]
+fn mprv_checks_data_at_mpp() {
+    let (emu, exception, pc, tval) = fault(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x83, 0x25, 0x05, 0x00, // lw a1, 0(a0)
+        0xb7, 0x02, 0x02, 0x00, // li t0, 0x20000
+        0x73, 0xa0, 0x02, 0x30, // csrs mstatus, t0
+        0x83, 0x25, 0x05, 0x00, // lw a1, 0(a0)
+    ]);
+
+    assert_eq!(emu.cpu.privilege(), Privilege::Machine);
+    assert_eq!(exception, RVException::LoadAccessFault);
+    assert_eq!(pc, RAM_BASE + 0x10);
+    assert_eq!(tval, RAM_BASE + 0x1000);
+}
+
+#[test]
+fn machine_csrs_are_privileged() {
+    let (_, exception, pc, _) = fault(vec![
+        0xb7, 0x22, 0x00, 0x20, // lui t0, 131074
+        0x9b, 0x82, 0xf2, 0xff, // addiw t0, t0, -1
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0x93, 0x02, 0xf0, 0x01, // li t0, 0x1f
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+        0x73, 0x25, 0x00, 0x30, // csrr a0, mstatus
+    ]);
+
+    assert_eq!(exception, RVException::IllegalInstruction);
+    assert_eq!(pc, RAM_BASE + 0x24);
+
+    let (_, exception, _, _) = fault(vec![
+        0xb7, 0x22, 0x00, 0x20, // lui t0, 131074
+        0x9b, 0x82, 0xf2, 0xff, // addiw t0, t0, -1
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0x93, 0x02, 0xf0, 0x01, // li t0, 0x1f
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+        0x73, 0x00, 0x20, 0x30, // mret
+    ]);
+
+    assert_eq!(exception, RVException::IllegalInstruction);
+}
+
+#[test]
+fn odd_pmpcfg_does_not_exist_on_rv64() {
+    let emu = emulator(vec![]);
+
+    assert_eq!(emu.cpu.read_csr(PMPCFG0), Some(0));
+    assert_eq!(emu.cpu.read_csr(PMPCFG0 + 1), None);
+    assert_eq!(emu.cpu.read_csr(PMPADDR0 + 63), Some(0));
+}
+
+#[test]
+fn snapshot_keeps_protection() {
+    let emu = run(vec![
+        0xb7, 0x22, 0x00, 0x20, // lui t0, 131074
+        0x9b, 0x82, 0xf2, 0xff, // addiw t0, t0, -1
+        0x73, 0x90, 0x02, 0x3b, // csrw pmpaddr0, t0
+        0x93, 0x02, 0xf0, 0x09, // li t0, 0x9f
+        0x73, 0x90, 0x02, 0x3a, // csrw pmpcfg0, t0
+        0x97, 0x02, 0x00, 0x00, // auipc t0, 0
+        0x93, 0x82, 0x02, 0x01, // addi t0, t0, 16
+        0x73, 0x90, 0x12, 0x34, // csrw mepc, t0
+        0x73, 0x00, 0x20, 0x30, // mret
+    ]);
+
+    let snapshot = emu.snapshot();
+    let mut bytes = vec![];
+    snapshot.write_to(&mut bytes).unwrap();
+    let decoded = Snapshot::read_from(&bytes[..]).unwrap();
+    assert_eq!(decoded, snapshot);
+
+    let mut restored = Emulator::new(0x10000);
+    restored.restore(&decoded).unwrap();
+    assert_eq!(restored.cpu.privilege(), Privilege::User);
+    assert_eq!(restored.cpu.pmp(), emu.cpu.pmp());
+    assert_eq!(restored.cpu.pmp().cfg(0), 0x9f);
+}
+
+#[test]
+fn no_supervisor_mode() {
+    let mut emu = emulator(vec![]);
+
+    // A is implemented, S is not
+    let misa = emu.cpu.read_csr(MISA).unwrap();
+    assert_eq!(misa & 1, 1);
+    assert_eq!(misa & (1 << 18), 0);
+
+    // so MPP cannot hold it
+    assert!(emu.cpu.write_csr(MSTATUS, 3 << 11));
+    assert!(emu.cpu.write_csr(MSTATUS, 1 << 11));
+    assert_eq!(emu.cpu.read_csr(MSTATUS).unwrap() & MSTATUS_MPP, 3 << 11);
+}
@@ -0,0 +1,172 @@
+use risemu::asm::assemble;
+use risemu::bus::{Address, RAM_BASE};
+use risemu::elf::Symbol;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::profile::{FunctionProfile, Profiler, Sampling};
+
+fn profiled(source: &str, profiler: Profiler) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble(source).unwrap());
+
+    // compiled blocks would otherwise go unseen
+    #[cfg(feature = "jit")]
+    emu.cpu.set_jit_threshold(0);
+
+    emu.cpu.profiler = Some(profiler);
+    assert_eq!(emu.run_for(1_000_000), StopReason::Exited { code: 0 });
+    emu
+}
+
+fn symbol(name: &str, offset: Address, size: u64) -> Symbol {
+    Symbol {
+        name: name.to_string(),
+        address: RAM_BASE + offset,
+        size,
+    }
+}
+
+fn folded(profiler: &Profiler) -> String {
+    let mut out = vec![];
+    profiler.write_folded(&mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+// ten calls to work, which calls leaf twice
+const CALLS: &str = "
+        li s0, 10
+    1:
+        jal work
+        addi s0, s0, -1
+        bnez s0, 1b
+        li a0, 0
+        li a7, 93
+        ecall
+    work:
+        mv s1, ra
+        jal leaf
+        jal leaf
+        mv ra, s1
+        ret
+    leaf:
+        addi a1, a1, 1
+        ret
+";
+
+fn symbols() -> Vec<Symbol> {
+    vec![
+        symbol("leaf", 0x30, 8),
+        symbol("work", 0x1c, 0x14),
+        symbol("_start", 0, 0),
+    ]
+}
+
+fn function(name: &str, exclusive: u64, inclusive: u64) -> FunctionProfile {
+    FunctionProfile {
+        name: name.to_string(),
+        exclusive,
+        inclusive,
+    }
+}
+
+#[test]
+fn exact() {
+    let emu = profiled(CALLS, Profiler::new(Sampling::Exact, symbols()));
+    let profiler = emu.cpu.profiler.as_ref().unwrap();
+
+    assert_eq!(profiler.samples(), emu.cpu.instret);
+    assert_eq!(profiler.counts()[&(RAM_BASE + 4)], 10);
+    assert_eq!(profiler.counts()[&(RAM_BASE + 0x30)], 20);
+    assert!(profiler.stack().is_empty());
+
+    assert_eq!(
+        profiler.functions(),
+        [
+            function("work", 50, 90),
+            function("leaf", 40, 40),
+            function("_start", 33, 123),
+        ]
+    );
+    assert_eq!(
+        folded(profiler),
+        "_start 33\n_start;work 50\n_start;work;leaf 40\n"
+    );
+}
+
+#[test]
+fn sampled() {
+    let emu = profiled(CALLS, Profiler::new(Sampling::Every(3), symbols()));
+    let profiler = emu.cpu.profiler.as_ref().unwrap();
+
+    assert_eq!(profiler.samples(), 123 / 3);
+    assert_eq!(profiler.counts().values().sum::<u64>(), 123 / 3);
+    assert_eq!(
+        profiler
+            .functions()
+            .iter()
+            .map(|function| function.exclusive)
+            .sum::<u64>(),
+        123 / 3
+    );
+}
+
+#[test]
+fn unknown_addresses() {
+    let emu = profiled(CALLS, Profiler::new(Sampling::Exact, vec![]));
+    let profiler = emu.cpu.profiler.as_ref().unwrap();
+
+    let leaf = profiler
+        .functions()
+        .into_iter()
+        .find(|function| function.name == "0x80000030")
+        .unwrap();
+    assert_eq!(leaf, function("0x80000030", 20, 20));
+    assert!(folded(profiler).contains("\n0x80000004;0x80000020;0x80000030 10\n"));
+}
+
+#[test]
+fn tail_calls_and_unwinding() {
+    // outer tail calls leaf, whose return skips outer, then unwind returns past a call it never came back from
+    let source = "
+            jal outer
+            jal unwind
+            li a0, 0
+            li a7, 93
+            ecall
+        outer:
+            j leaf
+        leaf:
+            ret
+        unwind:
+            mv s1, ra
+            jal 1f
+        1:
+            mv ra, s1
+            ret
+    ";
+    let symbols = vec![
+        symbol("_start", 0, 0x14),
+        symbol("outer", 0x14, 4),
+        symbol("leaf", 0x18, 4),
+        symbol("unwind", 0x1c, 0x10),
+    ];
+
+    let emu = profiled(source, Profiler::new(Sampling::Exact, symbols));
+    let profiler = emu.cpu.profiler.as_ref().unwrap();
+    assert!(profiler.stack().is_empty());
+    assert_eq!(
+        folded(profiler),
+        "_start 4\n_start;leaf 1\n_start;outer 1\n_start;unwind 2\n_start;unwind;unwind 2\n"
+    );
+}
+
+#[test]
+fn report() {
+    let emu = profiled(CALLS, Profiler::new(Sampling::Exact, symbols()));
+    assert_eq!(
+        emu.cpu.profiler.as_ref().unwrap().to_string(),
+        "123 samples
+          50   40.65% self         90   73.17% total  work
+          40   32.52% self         40   32.52% total  leaf
+          33   26.83% self        123  100.00% total  _start"
+    );
+}
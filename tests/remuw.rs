@@ -0,0 +1,79 @@
+mod macros;
+
+#[test]
+fn rr_op_remuw1() {
+    test_case!(
+        14,
+        0x00000002,
+        vec![
+            0x93, 0x00, 0x40, 0x01, // li x1, 20
+            0x13, 0x01, 0x60, 0x00, // li x2, 6
+            0x3b, 0xf7, 0x20, 0x02, // remuw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_remuw2() {
+    test_case!(
+        14,
+        0x00000003,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x70, 0x00, // li x2, 7
+            0x3b, 0xf7, 0x20, 0x02, // remuw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_remuw3() {
+    test_case!(
+        14,
+        0x00000002,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x13, 0x01, 0x30, 0x00, // li x2, 3
+            0x3b, 0xf7, 0x20, 0x02, // remuw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_remuw4() {
+    test_case!(
+        14,
+        0x7fffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x37, 0x01, 0x00, 0x80, // lui x2, 0x80000
+            0x3b, 0xf7, 0x20, 0x02, // remuw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_remuw5() {
+    test_case!(
+        14,
+        0xfffffffffffffffe,
+        vec![
+            0x93, 0x00, 0xe0, 0xff, // li x1, -2
+            0x13, 0x01, 0xf0, 0xff, // li x2, -1
+            0x3b, 0xf7, 0x20, 0x02, // remuw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_remuw6() {
+    test_case!(
+        14,
+        0xffffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x3b, 0xf7, 0x20, 0x02, // remuw x14, x1, x2
+        ]
+    );
+}
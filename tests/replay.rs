@@ -0,0 +1,235 @@
+use std::collections::hash_map::RandomState;
+use std::hash::BuildHasher;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use risemu::asm::assemble;
+use risemu::bus::{Address, RAM_BASE};
+use risemu::clint::Clint;
+use risemu::console::{Console, CONSOLE_BASE, CONSOLE_SIZE};
+use risemu::emulator::{Emulator, StopReason};
+use risemu::mmio::Mmio;
+use risemu::replay::{Entry, Event, Recording};
+use risemu::smp::Machine;
+
+const DEVICE_BASE: Address = 0x2000_0000;
+
+// answers every read with a different random number
+struct Noise(RandomState);
+
+impl Mmio for Noise {
+    fn read(&self, offset: Address, _size: usize) -> Option<u64> {
+        Some(self.0.hash_one(offset) as u32 as u64)
+    }
+
+    fn write(&self, _offset: Address, _size: usize, _value: u64) -> bool {
+        false
+    }
+}
+
+// sums 20 reads of the device, printing the low byte of each, and exits with the sum
+const SUM: &str = "
+        li s0, 0x20000000
+        li s1, 0x10000000
+        li s2, 20
+        li a0, 0
+    1:
+        lw t0, 0(s0)
+        add a0, a0, t0
+        sb t0, 0(s1)
+        addi s2, s2, -1
+        bnez s2, 1b
+        li a7, 93
+        ecall
+";
+
+fn console(emu: &mut Emulator) -> Arc<Mutex<Vec<u8>>> {
+    let output = Arc::new(Mutex::new(vec![]));
+    let sink = output.clone();
+    let console = Console::new(move |byte| sink.lock().unwrap().push(byte));
+    assert!(emu.map_mmio(CONSOLE_BASE, CONSOLE_SIZE, Arc::new(console)));
+    output
+}
+
+fn reload(recording: &Recording) -> Recording {
+    let mut bytes = vec![];
+    recording.write_to(&mut bytes).unwrap();
+    Recording::read_from(bytes.as_slice()).unwrap()
+}
+
+#[test]
+fn device_reads() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(assemble(SUM).unwrap());
+    let noise = Arc::new(Noise(RandomState::new()));
+    assert!(original.map_mmio(DEVICE_BASE, 0x1000, noise));
+    let printed = console(&mut original);
+
+    let start = original.snapshot();
+    original.start_recording();
+    let stop = original.run_for(u64::MAX);
+    let recording = reload(&original.finish_recording().unwrap());
+    assert!(matches!(stop, StopReason::Exited { .. }));
+
+    // 20 reads, and 20 writes to the console
+    assert_eq!(recording.events[0].len(), 40);
+    assert!(matches!(
+        recording.events[0][0],
+        Entry {
+            instret: 4,
+            event: Event::Read {
+                address: DEVICE_BASE,
+                size: 4,
+                value: Some(_),
+            },
+        }
+    ));
+
+    // without the device, but printing the same again
+    let mut replayed = Emulator::new(0x100);
+    replayed.restore(&start).unwrap();
+    let reprinted = console(&mut replayed);
+
+    replayed.start_replay(&recording);
+    assert_eq!(replayed.run_for(u64::MAX), stop);
+    assert!(replayed.finish_replay());
+
+    assert_eq!(replayed.snapshot(), original.snapshot());
+    assert_eq!(*reprinted.lock().unwrap(), *printed.lock().unwrap());
+}
+
+#[test]
+fn divergence() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(assemble(SUM).unwrap());
+    assert!(original.map_mmio(DEVICE_BASE, 0x1000, Arc::new(Noise(RandomState::new()))));
+    console(&mut original);
+
+    original.start_recording();
+    original.run_for(u64::MAX);
+    let recording = original.finish_recording().unwrap();
+
+    // reading another register of the device
+    let mut replayed = Emulator::new(0x10000);
+    replayed.init_ram(assemble(&SUM.replace("lw t0, 0(s0)", "lw t0, 4(s0)")).unwrap());
+
+    replayed.start_replay(&recording);
+    assert_eq!(
+        replayed.run_for(u64::MAX),
+        StopReason::Diverged { pc: RAM_BASE + 16 }
+    );
+    assert!(!replayed.finish_replay());
+}
+
+#[test]
+fn host_interrupt() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(
+        assemble(
+            "
+                li a0, 0
+            1:
+                addi a0, a0, 1
+                csrr t0, mip
+                beqz t0, 1b
+                li a7, 93
+                ecall
+            ",
+        )
+        .unwrap(),
+    );
+
+    let clint = Arc::new(Clint::new(1));
+    original.cpu.bus.clint = Some(clint.clone());
+    let start = original.snapshot();
+
+    // raised from another thread at whatever point the guest happens to be
+    original.start_recording();
+    let host = thread::spawn(move || {
+        thread::sleep(Duration::from_millis(1));
+        clint.set_software_pending(0, true);
+    });
+    let stop = original.run_for(u64::MAX);
+    host.join().unwrap();
+    let recording = reload(&original.finish_recording().unwrap());
+
+    let mut replayed = Emulator::new(0x100);
+    replayed.restore(&start).unwrap();
+    replayed.start_replay(&recording);
+    assert_eq!(replayed.run_for(u64::MAX), stop);
+    assert!(replayed.finish_replay());
+
+    // the CLINT of the replay never saw the host raise the interrupt, only the hart did
+    assert_eq!(replayed.cpu.xregs, original.cpu.xregs);
+    assert_eq!(replayed.cpu.pc, original.cpu.pc);
+    assert_eq!(replayed.cpu.instret, original.cpu.instret);
+}
+
+// every hart appends its id to a shared array 200 times, then hart 0 wakes hart 1 up
+const ORDER: &str = "
+        csrr a0, mhartid
+        la s0, next
+        la s1, order
+        li s2, 200
+    1:
+        li t0, 1
+        amoadd.w t1, t0, (s0)
+        add t1, s1, t1
+        sb a0, 0(t1)
+        addi s2, s2, -1
+        bnez s2, 1b
+        bnez a0, 2f
+        li t2, 0x2000000
+        li t3, 1
+        sw t3, 4(t2)
+        li a7, 93
+        ecall
+    2:
+        wfi
+        csrr t3, mip
+        andi t3, t3, 8
+        beqz t3, 2b
+        li a7, 93
+        ecall
+        .align 3
+    next:
+        .dword 0
+    order:
+        .zero 400
+";
+
+fn machine() -> Machine {
+    let mut machine = Machine::new(2, 0x10000);
+    machine.init_ram(assemble(ORDER).unwrap());
+    machine.set_quantum(3);
+    machine
+}
+
+#[test]
+fn threaded_schedule() {
+    let mut original = machine();
+    original.start_recording();
+    let stops = original.run_threaded(u64::MAX);
+    let recording = reload(&original.finish_recording().unwrap());
+    assert_eq!(
+        stops,
+        [
+            StopReason::Exited { code: 0 },
+            StopReason::Exited { code: 1 }
+        ]
+    );
+
+    let mut replayed = machine();
+    replayed.start_replay(&recording);
+    assert_eq!(replayed.run_threaded(u64::MAX), stops);
+    assert!(replayed.finish_replay());
+
+    let ram = |machine: &Machine| machine.harts()[0].bus.ram().contents();
+    assert_eq!(ram(&replayed), ram(&original));
+    for (replayed, original) in replayed.harts().iter().zip(original.harts()) {
+        assert_eq!(replayed.xregs, original.xregs);
+        assert_eq!(replayed.pc, original.pc);
+        assert_eq!(replayed.instret, original.instret);
+    }
+}
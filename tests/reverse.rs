@@ -0,0 +1,153 @@
+use risemu::bus::RAM_BASE;
+use risemu::debug::WatchKind;
+use risemu::emulator::{Emulator, StopReason};
+
+const STACK_TOP: u64 = RAM_BASE + 0x10000;
+
+fn emulator(code: Vec<u8>) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(code);
+    emu.enable_reverse(16);
+    emu
+}
+
+// counts x5 up to 100, then exits with it
+fn counter() -> Vec<u8> {
+    vec![
+        0x93, 0x02, 0x00, 0x00, // li x5, 0
+        0x13, 0x03, 0x40, 0x06, // li x6, 100
+        0x93, 0x82, 0x12, 0x00, // addi x5, x5, 1
+        0xe3, 0x9e, 0x62, 0xfe, // bne x5, x6, -4
+        0x13, 0x85, 0x02, 0x00, // mv a0, x5
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]
+}
+
+// stores 7 to the top of the stack, loads it back and exits with it
+fn store_load() -> Vec<u8> {
+    vec![
+        0x93, 0x02, 0x70, 0x00, // li x5, 7
+        0x23, 0x3c, 0x51, 0xfe, // sd x5, -8(sp)
+        0x03, 0x33, 0x81, 0xff, // ld x6, -8(sp)
+        0x13, 0x05, 0x03, 0x00, // mv a0, x6
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]
+}
+
+#[test]
+fn reverse_step_matches_forward() {
+    let mut emu = emulator(counter());
+    assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 100 });
+
+    let end = emu.cpu.instret;
+    for target in (end - 40..end).rev() {
+        assert_eq!(emu.reverse_step(), StopReason::LimitReached);
+        assert_eq!(emu.cpu.instret, target);
+
+        let mut forward = Emulator::new(0x10000);
+        forward.init_ram(counter());
+        forward.run_for(target);
+        assert_eq!(emu.snapshot(), forward.snapshot());
+    }
+}
+
+#[test]
+fn reverse_step_to_start() {
+    let mut emu = emulator(counter());
+    emu.run_for(3);
+
+    for _ in 0..3 {
+        assert_eq!(emu.reverse_step(), StopReason::LimitReached);
+    }
+    assert_eq!(emu.reverse_step(), StopReason::StartOfHistory);
+    assert_eq!(emu.cpu.instret, 0);
+    assert_eq!(emu.cpu.pc, RAM_BASE);
+}
+
+#[test]
+fn reverse_continue_to_breakpoint() {
+    let mut emu = emulator(counter());
+    emu.run_for(u64::MAX);
+
+    emu.add_breakpoint(RAM_BASE + 8);
+    for count in (95..100).rev() {
+        assert_eq!(
+            emu.reverse_continue(),
+            StopReason::Breakpoint { pc: RAM_BASE + 8 }
+        );
+        assert_eq!(emu.cpu.xregs[5], count);
+    }
+
+    // and forward again from the past
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Breakpoint { pc: RAM_BASE + 8 }
+    );
+    assert_eq!(emu.cpu.xregs[5], 96);
+}
+
+#[test]
+fn reverse_continue_to_watchpoint() {
+    let mut emu = emulator(store_load());
+    emu.run_for(u64::MAX);
+
+    emu.add_watchpoint(STACK_TOP - 8..STACK_TOP, WatchKind::Write);
+    assert_eq!(
+        emu.reverse_continue(),
+        StopReason::Watchpoint {
+            pc: RAM_BASE + 8,
+            address: STACK_TOP - 8,
+            kind: WatchKind::Write,
+        }
+    );
+    assert_eq!(emu.cpu.xregs[6], 0);
+
+    assert_eq!(emu.reverse_continue(), StopReason::StartOfHistory);
+    assert_eq!(emu.cpu.instret, 0);
+}
+
+#[test]
+fn host_edits_are_history() {
+    let mut emu = emulator(vec![
+        0x93, 0x08, 0x00, 0x04, // li a7, 64
+        0x73, 0x00, 0x00, 0x00, // ecall
+        0x93, 0x02, 0x05, 0x00, // mv x5, a0
+        0x93, 0x82, 0x12, 0x00, // addi x5, x5, 1
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Fault { .. }));
+
+    // the host handles the call and skips over it
+    emu.cpu.xregs[10] = 42;
+    emu.cpu.pc += 4;
+    assert_eq!(emu.run_for(2), StopReason::LimitReached);
+    assert_eq!(emu.cpu.instret, 3);
+
+    assert_eq!(emu.reverse_step(), StopReason::LimitReached);
+    assert_eq!(emu.cpu.instret, 2);
+    assert_eq!(emu.cpu.pc, RAM_BASE + 12);
+    assert_eq!(emu.cpu.xregs[5], 42);
+
+    // back to the state the host left, not to the call it handled
+    assert_eq!(emu.reverse_step(), StopReason::LimitReached);
+    assert_eq!(emu.cpu.instret, 1);
+    assert_eq!(emu.cpu.pc, RAM_BASE + 8);
+    assert_eq!(emu.cpu.xregs[10], 42);
+
+    assert_eq!(emu.run_for(u64::MAX), StopReason::Exited { code: 42 });
+    assert_eq!(emu.cpu.xregs[5], 43);
+}
+
+#[test]
+fn disabled() {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(counter());
+    emu.run_for(10);
+
+    assert_eq!(emu.reverse_step(), StopReason::StartOfHistory);
+    assert_eq!(emu.reverse_continue(), StopReason::StartOfHistory);
+    assert_eq!(emu.cpu.instret, 10);
+}
@@ -0,0 +1,229 @@
+use risemu::bus::RAM_BASE;
+use risemu::cpu::Xlen;
+use risemu::csr::MISA;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::snapshot::Snapshot;
+
+fn emulator(code: Vec<u8>, xlen: Xlen) -> Emulator {
+    let mut code = code;
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let mut emu = Emulator::with_xlen(0x10000, xlen);
+    emu.init_ram(code);
+    emu
+}
+
+fn run(code: Vec<u8>) -> Emulator {
+    let mut emu = emulator(code, Xlen::Rv32);
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    emu
+}
+
+#[test]
+fn registers_are_sign_extended() {
+    let emu = run(vec![
+        0x37, 0x05, 0x00, 0x80, // lui a0, 524288
+        0x13, 0x05, 0xf5, 0xff, // addi a0, a0, -1
+        0x93, 0x05, 0x15, 0x00, // addi a1, a0, 1
+        0x33, 0x86, 0xb5, 0x00, // add a2, a1, a1
+        0xb3, 0x06, 0xb0, 0x40, // sub a3, zero, a1
+        0x33, 0x37, 0xb5, 0x00, // sltu a4, a0, a1
+        0xb3, 0x27, 0xb5, 0x00, // slt a5, a0, a1
+    ]);
+
+    assert_eq!(emu.cpu.xregs[10], 0x7fffffff);
+    assert_eq!(emu.cpu.xregs[11], 0xffffffff80000000);
+    assert_eq!(emu.cpu.xregs[12], 0);
+    assert_eq!(emu.cpu.xregs[13], 0xffffffff80000000);
+    assert_eq!(emu.cpu.xregs[14], 1);
+    assert_eq!(emu.cpu.xregs[15], 0);
+
+    // the stack pointer starts at the end of RAM too
+    assert_eq!(emu.cpu.xregs[2] as u32 as u64, RAM_BASE + 0x10000);
+}
+
+#[test]
+fn shifts() {
+    let emu = run(vec![
+        0x13, 0x05, 0x90, 0xff, // li a0, -7
+        0x93, 0x02, 0x10, 0x02, // li t0, 33
+        0xb3, 0x15, 0x55, 0x00, // sll a1, a0, t0
+        0x33, 0x56, 0x55, 0x00, // srl a2, a0, t0
+        0xb3, 0x56, 0x55, 0x40, // sra a3, a0, t0
+        0x13, 0x57, 0xc5, 0x01, // srli a4, a0, 28
+        0x93, 0x57, 0xf5, 0x41, // srai a5, a0, 31
+        0x13, 0x18, 0xf5, 0x01, // slli a6, a0, 31
+    ]);
+
+    assert_eq!(emu.cpu.xregs[11] as u32, 0xfffffff2);
+    assert_eq!(emu.cpu.xregs[12] as u32, 0x7ffffffc);
+    assert_eq!(emu.cpu.xregs[13] as u32, 0xfffffffc);
+    assert_eq!(emu.cpu.xregs[14] as u32, 0xf);
+    assert_eq!(emu.cpu.xregs[15] as u32, 0xffffffff);
+    assert_eq!(emu.cpu.xregs[16] as u32, 0x80000000);
+}
+
+#[test]
+fn multiply_divide() {
+    let emu = run(vec![
+        0x13, 0x05, 0xd0, 0xff, // li a0, -3
+        0xb7, 0x02, 0x00, 0x80, // lui t0, 0x80000
+        0x13, 0x03, 0xf0, 0xff, // li t1, -1
+        0x33, 0x16, 0xa5, 0x02, // mulh a2, a0, a0
+        0xb3, 0xb6, 0x52, 0x02, // mulhu a3, t0, t0
+        0x33, 0x97, 0xa2, 0x02, // mulh a4, t0, a0
+        0xb3, 0x27, 0x55, 0x02, // mulhsu a5, a0, t0
+        0x33, 0xc8, 0x62, 0x02, // div a6, t0, t1
+        0x33, 0xe9, 0x62, 0x02, // rem s2, t0, t1
+        0xb3, 0xd9, 0x02, 0x02, // divu s3, t0, zero
+        0x33, 0xfa, 0xa2, 0x02, // remu s4, t0, a0
+        0xb3, 0x8a, 0xa2, 0x02, // mul s5, t0, a0
+    ]);
+
+    assert_eq!(emu.cpu.xregs[12] as u32, 0);
+    assert_eq!(emu.cpu.xregs[13] as u32, 0x40000000);
+    assert_eq!(emu.cpu.xregs[14] as u32, 1);
+    assert_eq!(emu.cpu.xregs[15] as u32, 0xfffffffe);
+    assert_eq!(emu.cpu.xregs[16] as u32, 0x80000000);
+    assert_eq!(emu.cpu.xregs[18] as u32, 0);
+    assert_eq!(emu.cpu.xregs[19] as u32, 0xffffffff);
+    assert_eq!(emu.cpu.xregs[20] as u32, 0x80000000);
+    assert_eq!(emu.cpu.xregs[21] as u32, 0x80000000);
+}
+
+#[test]
+fn memory_and_jumps() {
+    let emu = run(vec![
+        0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+        0x93, 0x02, 0xe0, 0xff, // li t0, -2
+        0x23, 0x20, 0x55, 0x00, // sw t0, 0(a0)
+        0x83, 0x25, 0x05, 0x00, // lw a1, 0(a0)
+        0x03, 0x56, 0x05, 0x00, // lhu a2, 0(a0)
+        0x83, 0x06, 0x35, 0x00, // lb a3, 3(a0)
+        0xef, 0x00, 0x80, 0x00, // jal ra, 8
+        0x13, 0x07, 0x10, 0x00, // li a4, 1
+        0xe7, 0x83, 0x80, 0x00, // jalr t2, 8(ra)
+    ]);
+
+    assert_eq!(emu.cpu.xregs[10], (RAM_BASE + 0x1000) as i32 as u64);
+    assert_eq!(emu.cpu.xregs[11] as u32, 0xfffffffe);
+    assert_eq!(emu.cpu.xregs[12], 0xfffe);
+    assert_eq!(emu.cpu.xregs[13] as u32, 0xffffffff);
+    assert_eq!(emu.cpu.xregs[1] as u32 as u64, RAM_BASE + 28);
+    assert_eq!(emu.cpu.xregs[14], 0);
+    assert_eq!(emu.cpu.xregs[7] as u32 as u64, RAM_BASE + 36);
+    assert_eq!(
+        emu.cpu.bus.ram().contents()[0x1000..0x1004],
+        [0xfe, 0xff, 0xff, 0xff]
+    );
+}
+
+#[test]
+fn bit_manipulation() {
+    let emu = run(vec![
+        0x37, 0x55, 0x34, 0x12, // lui a0, 74565
+        0x13, 0x05, 0x85, 0x67, // addi a0, a0, 1656
+        0x93, 0x02, 0xf0, 0xff, // li t0, -1
+        0x93, 0x55, 0x85, 0x69, // rev8 a1, a0
+        0x33, 0x46, 0x05, 0x08, // zext.h a2, a0
+        0x93, 0x16, 0x05, 0x60, // clz a3, a0
+        0x13, 0x97, 0x22, 0x60, // cpop a4, t0
+        0x93, 0x97, 0xf2, 0x49, // bclri a5, t0, 31
+        0x13, 0x58, 0x45, 0x60, // rori a6, a0, 4
+        0x33, 0xb9, 0x52, 0x0a, // clmulh s2, t0, t0
+    ]);
+
+    assert_eq!(emu.cpu.xregs[11] as u32, 0x78563412);
+    assert_eq!(emu.cpu.xregs[12] as u32, 0x5678);
+    assert_eq!(emu.cpu.xregs[13] as u32, 3);
+    assert_eq!(emu.cpu.xregs[14] as u32, 32);
+    assert_eq!(emu.cpu.xregs[15] as u32, 0x7fffffff);
+    assert_eq!(emu.cpu.xregs[16] as u32, 0x81234567);
+    assert_eq!(emu.cpu.xregs[18] as u32, 0x55555555);
+}
+
+#[test]
+fn rv64_only_encodings() {
+    let code = vec![
+        0x03, 0x35, 0x01, 0x00, // ld a0, 0(sp)
+        0x23, 0x30, 0xa1, 0x00, // sd a0, 0(sp)
+        0x03, 0x65, 0x01, 0x00, // lwu a0, 0(sp)
+        0x1b, 0x05, 0x15, 0x00, // addiw a0, a0, 1
+        0x3b, 0x05, 0xa5, 0x00, // addw a0, a0, a0
+        0x13, 0x15, 0x05, 0x02, // slli a0, a0, 32
+        0x13, 0x55, 0xf5, 0x43, // srai a0, a0, 63
+        0x13, 0x55, 0x85, 0x6b, // rev8 a0, a0
+        0x3b, 0x05, 0xa5, 0x08, // add.uw a0, a0, a0
+        0x1b, 0x15, 0x05, 0x60, // clzw a0, a0
+    ];
+
+    for word in code.chunks(4) {
+        let mut emu = emulator(word.to_vec(), Xlen::Rv32);
+        assert_eq!(
+            emu.run_for(1),
+            StopReason::Fault {
+                exception: RVException::IllegalInstruction,
+                pc: RAM_BASE,
+                tval: u32::from_le_bytes(word.try_into().unwrap()) as u64,
+            },
+            "{word:02x?}"
+        );
+    }
+}
+
+#[test]
+fn misa_selects_xlen() {
+    let mut emu = emulator(
+        vec![
+            0x73, 0x25, 0x10, 0x30, // csrr a0, misa
+            0x93, 0x02, 0x10, 0x00, // li t0, 1
+            0x93, 0x92, 0xe2, 0x03, // slli t0, t0, 62
+            0x73, 0x90, 0x12, 0x30, // csrw misa, t0
+            0xb7, 0x05, 0x00, 0x80, // lui a1, 0x80000
+            0x13, 0x86, 0xf5, 0xff, // addi a2, a1, -1
+            0xf3, 0x26, 0x10, 0x30, // csrr a3, misa
+        ],
+        Xlen::Rv64,
+    );
+    assert_eq!(emu.cpu.read_csr(MISA), Some(0x8000000000301105));
+
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    assert_eq!(emu.cpu.xlen(), Xlen::Rv32);
+    // registers are truncated to the new width, leaving only the extension bits of the RV64 misa
+    assert_eq!(emu.cpu.xregs[10], 0x301105);
+    assert_eq!(emu.cpu.xregs[12], 0x7fffffff);
+    // the written value also had C cleared
+    assert_eq!(emu.cpu.xregs[13], 0x40301101);
+    assert!(!emu.cpu.compressed());
+}
+
+#[test]
+fn vill_is_the_top_bit() {
+    let emu = run(vec![
+        0xd7, 0x72, 0xd0, 0x0d, // vsetvli t0, zero, e64, mf8, ta, ma
+        0x73, 0x25, 0x10, 0xc2, // csrr a0, vtype
+    ]);
+
+    assert_eq!(emu.cpu.xregs[10] as u32, 0x80000000);
+}
+
+#[test]
+fn snapshot() {
+    let emu = run(vec![
+        0x13, 0x05, 0xf0, 0xff, // li a0, -1
+    ]);
+
+    let mut bytes = vec![];
+    emu.snapshot().write_to(&mut bytes).unwrap();
+
+    let mut restored = Emulator::new(0x10000);
+    restored
+        .restore(&Snapshot::read_from(bytes.as_slice()).unwrap())
+        .unwrap();
+    assert_eq!(restored.cpu.xlen(), Xlen::Rv32);
+    assert_eq!(restored.cpu.xregs, emu.cpu.xregs);
+}
@@ -0,0 +1,66 @@
+mod macros;
+
+#[test]
+fn rr_op_sllw1() {
+    test_case!(
+        14,
+        0x00000002,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0x97, 0x20, 0x00, // sllw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sllw2() {
+    test_case!(
+        14,
+        0x00000002,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x10, 0x02, // li x2, 33
+            0x3b, 0x97, 0x20, 0x00, // sllw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sllw3() {
+    test_case!(
+        14,
+        0xffffffff80000000,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0x03, // li x2, 63
+            0x3b, 0x97, 0x20, 0x00, // sllw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sllw4() {
+    test_case!(
+        14,
+        0xffffffff80000000,
+        vec![
+            0xb7, 0x00, 0x00, 0x40, // lui x1, 0x40000
+            0x13, 0x01, 0x10, 0x02, // li x2, 33
+            0x3b, 0x97, 0x20, 0x00, // sllw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sllw5() {
+    test_case!(
+        14,
+        0xffffffff80000000,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x13, 0x01, 0x00, 0x02, // li x2, 32
+            0x3b, 0x97, 0x20, 0x00, // sllw x14, x1, x2
+        ]
+    );
+}
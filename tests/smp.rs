@@ -0,0 +1,186 @@
+use risemu::bus::RAM_BASE;
+use risemu::emulator::StopReason;
+use risemu::smp::Machine;
+
+fn load(harts: usize, code: Vec<u8>) -> Machine {
+    let mut code = code;
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let mut machine = Machine::new(harts, 0x10000);
+    machine.init_ram(code);
+    machine
+}
+
+// every hart adds 1000 to the word after the code with an LR/SC loop, then exits with its id
+fn counter() -> Vec<u8> {
+    vec![
+        0x17, 0x14, 0x00, 0x00, // auipc s0, 1
+        0x93, 0x04, 0x80, 0x3e, // li s1, 1000
+        0xaf, 0x22, 0x04, 0x10, // lr.w t0, (s0)
+        0x93, 0x82, 0x12, 0x00, // addi t0, t0, 1
+        0x2f, 0x23, 0x54, 0x18, // sc.w t1, t0, (s0)
+        0xe3, 0x1a, 0x03, 0xfe, // bnez t1, -12
+        0x93, 0x84, 0xf4, 0xff, // addi s1, s1, -1
+        0xe3, 0x96, 0x04, 0xfe, // bnez s1, -20
+        0x73, 0x25, 0x40, 0xf1, // csrr a0, mhartid
+    ]
+}
+
+fn counted(machine: &Machine) -> u32 {
+    let memory = machine.harts()[0].bus.ram().contents();
+    u32::from_le_bytes(memory[0x1000..0x1004].try_into().unwrap())
+}
+
+fn exits(harts: u64) -> Vec<StopReason> {
+    (0..harts).map(|code| StopReason::Exited { code }).collect()
+}
+
+#[test]
+fn hart_ids() {
+    let mut machine = load(
+        4,
+        vec![
+            0x73, 0x25, 0x40, 0xf1, // csrr a0, mhartid
+        ],
+    );
+
+    assert_eq!(machine.run_for(u64::MAX), exits(4));
+}
+
+#[test]
+fn round_robin() {
+    let mut machine = load(4, counter());
+    machine.set_quantum(7);
+
+    assert_eq!(machine.run_for(u64::MAX), exits(4));
+    assert_eq!(counted(&machine), 4000);
+}
+
+#[test]
+fn round_robin_is_deterministic() {
+    let instret = |quantum| {
+        let mut machine = load(3, counter());
+        machine.set_quantum(quantum);
+        machine.run_for(u64::MAX);
+
+        machine
+            .harts()
+            .iter()
+            .map(|hart| hart.instret)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(instret(3), instret(3));
+    assert_eq!(instret(50), instret(50));
+}
+
+#[test]
+fn threaded() {
+    let mut machine = load(4, counter());
+
+    assert_eq!(machine.run_threaded(u64::MAX), exits(4));
+    assert_eq!(counted(&machine), 4000);
+}
+
+#[test]
+fn amo_threaded() {
+    let mut machine = load(
+        4,
+        vec![
+            0x17, 0x14, 0x00, 0x00, // auipc s0, 1
+            0x93, 0x04, 0x80, 0x3e, // li s1, 1000
+            0x93, 0x02, 0x10, 0x00, // li t0, 1
+            0x2f, 0x20, 0x54, 0x00, // amoadd.w zero, t0, (s0)
+            0x93, 0x84, 0xf4, 0xff, // addi s1, s1, -1
+            0xe3, 0x9c, 0x04, 0xfe, // bnez s1, -8
+            0x73, 0x25, 0x40, 0xf1, // csrr a0, mhartid
+        ],
+    );
+
+    assert_eq!(machine.run_threaded(u64::MAX), exits(4));
+    assert_eq!(counted(&machine), 4000);
+}
+
+#[test]
+fn limit() {
+    let mut machine = load(
+        2,
+        vec![
+            0x6f, 0x00, 0x00, 0x00, // j 0
+        ],
+    );
+
+    assert_eq!(machine.run_for(10), [StopReason::LimitReached; 2]);
+    assert!(machine.harts().iter().all(|hart| hart.instret == 10));
+
+    assert_eq!(machine.run_threaded(10), [StopReason::LimitReached; 2]);
+    assert!(machine.harts().iter().all(|hart| hart.instret == 20));
+}
+
+// hart 0 spins for a while before raising a software interrupt on hart 1, which waits for it
+fn interrupt() -> Vec<u8> {
+    vec![
+        0xf3, 0x22, 0x40, 0xf1, // csrr t0, mhartid
+        0x63, 0x92, 0x02, 0x02, // bnez t0, 36
+        0x13, 0x03, 0x80, 0x0c, // li t1, 200
+        0x13, 0x03, 0xf3, 0xff, // addi t1, t1, -1
+        0xe3, 0x1e, 0x03, 0xfe, // bnez t1, -4
+        0xb7, 0x03, 0x00, 0x02, // lui t2, 0x2000
+        0x13, 0x0e, 0x10, 0x00, // li t3, 1
+        0x23, 0xa2, 0xc3, 0x01, // sw t3, 4(t2)
+        0x13, 0x05, 0x00, 0x00, // li a0, 0
+        0x6f, 0x00, 0x40, 0x02, // j 36
+        0x73, 0x00, 0x50, 0x10, // wfi
+        0x73, 0x2e, 0x40, 0x34, // csrr t3, mip
+        0x13, 0x7e, 0x8e, 0x00, // andi t3, t3, 8
+        0xe3, 0x0a, 0x0e, 0xfe, // beqz t3, -12
+        0xb7, 0x03, 0x00, 0x02, // lui t2, 0x2000
+        0x23, 0xa2, 0x03, 0x00, // sw zero, 4(t2)
+        0x73, 0x25, 0x40, 0x34, // csrr a0, mip
+        0x13, 0x05, 0xa5, 0x02, // addi a0, a0, 42
+    ]
+}
+
+#[test]
+fn inter_processor_interrupt() {
+    let mut machine = load(2, interrupt());
+    machine.set_quantum(10);
+
+    let expected = [
+        StopReason::Exited { code: 0 },
+        StopReason::Exited { code: 42 },
+    ];
+    assert_eq!(machine.run_for(u64::MAX), expected);
+
+    // WFI only retired once, the hart did not spin
+    assert_eq!(machine.harts()[1].instret, 11);
+    assert!(!machine.clint().software_pending(1));
+
+    let mut machine = load(2, interrupt());
+    assert_eq!(machine.run_threaded(u64::MAX), expected);
+    assert_eq!(machine.harts()[1].instret, 11);
+}
+
+#[test]
+fn waiting_forever() {
+    let mut machine = load(
+        2,
+        vec![
+            0x73, 0x00, 0x50, 0x10, // wfi
+        ],
+    );
+
+    let expected = [StopReason::Waiting { pc: RAM_BASE + 4 }; 2];
+    assert_eq!(machine.run_for(u64::MAX), expected);
+
+    let mut machine = load(
+        2,
+        vec![
+            0x73, 0x00, 0x50, 0x10, // wfi
+        ],
+    );
+    assert_eq!(machine.run_threaded(u64::MAX), expected);
+}
@@ -0,0 +1,310 @@
+use std::io::ErrorKind;
+use std::sync::Arc;
+
+use risemu::asm::assemble;
+use risemu::branch::{BranchPrediction, ReturnStack, Tage};
+use risemu::bus::RAM_BASE;
+use risemu::cache::{CacheConfig, CacheHierarchy, CacheLevel};
+use risemu::clint::Clint;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::profile::{Profiler, Sampling};
+use risemu::snapshot::Snapshot;
+use risemu::timing::{Latencies, Pipeline};
+
+// stores the squares of 0..200 in the page after the code, exits with the last one
+fn squares() -> Vec<u8> {
+    vec![
+        0x93, 0x02, 0x00, 0x00, // li x5, 0
+        0x13, 0x03, 0x80, 0x0c, // li x6, 200
+        0x97, 0x13, 0x00, 0x00, // auipc x7, 0x1
+        0x33, 0x84, 0x52, 0x02, // mul x8, x5, x5
+        0x23, 0xb0, 0x83, 0x00, // sd x8, 0(x7)
+        0x93, 0x83, 0x83, 0x00, // addi x7, x7, 8
+        0x93, 0x82, 0x12, 0x00, // addi x5, x5, 1
+        0xe3, 0x98, 0x62, 0xfe, // bne x5, x6, -16
+        0x13, 0x05, 0x04, 0x00, // mv a0, x8
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]
+}
+
+#[test]
+fn round_trip() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(squares());
+    assert_eq!(original.run_for(300), StopReason::LimitReached);
+
+    let mut bytes = vec![];
+    original.snapshot().write_to(&mut bytes).unwrap();
+    let snapshot = Snapshot::read_from(bytes.as_slice()).unwrap();
+    assert_eq!(snapshot, original.snapshot());
+
+    let mut restored = Emulator::new(0x100);
+    restored.restore(&snapshot).unwrap();
+
+    let expected = StopReason::Exited { code: 199 * 199 };
+    assert_eq!(original.run_for(u64::MAX), expected);
+    assert_eq!(restored.run_for(u64::MAX), expected);
+
+    assert_eq!(original.cpu.xregs, restored.cpu.xregs);
+    assert_eq!(original.cpu.pc, restored.cpu.pc);
+    assert_eq!(original.cpu.instret, restored.cpu.instret);
+    assert_eq!(original.cpu.cycle, restored.cpu.cycle);
+    assert_eq!(
+        original.cpu.bus.ram().contents(),
+        restored.cpu.bus.ram().contents()
+    );
+}
+
+#[test]
+fn restore_rewinds() {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(squares());
+
+    let start = emu.snapshot();
+    emu.run_for(u64::MAX);
+    let end = emu.snapshot();
+
+    emu.restore(&start).unwrap();
+    assert_eq!(emu.snapshot(), start);
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Exited { code: 199 * 199 }
+    );
+    assert_eq!(emu.snapshot(), end);
+}
+
+#[test]
+fn sparse_ram() {
+    let mut emu = Emulator::new(16 * 1024 * 1024);
+    emu.init_ram(squares());
+    emu.run_for(u64::MAX);
+
+    let mut bytes = vec![];
+    emu.snapshot().write_to(&mut bytes).unwrap();
+    assert!(bytes.len() < 4096, "{} bytes", bytes.len());
+}
+
+#[test]
+fn rejects_garbage() {
+    let error = Snapshot::read_from(&b"not a snapshot at all"[..]).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(squares());
+
+    let mut bytes = vec![];
+    emu.snapshot().write_to(&mut bytes).unwrap();
+
+    let mut version = bytes.clone();
+    version[8] = 0xff;
+    let error = Snapshot::read_from(version.as_slice()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+
+    let truncated = &bytes[..bytes.len() - 1];
+    assert!(Snapshot::read_from(truncated).is_err());
+}
+
+#[test]
+fn arbitrary_memory() {
+    let emu = Emulator::new(3 * 4096 + 100);
+
+    // runs of every length mixed with noise, plus a partial last page
+    let mut seed = 0x1234_5678u64;
+    let mut memory = emu.cpu.bus.ram().contents();
+    for byte in memory.iter_mut() {
+        seed = seed.wrapping_mul(6364136223846793005).wrapping_add(1);
+        *byte = match (seed >> 60) % 4 {
+            0 => (seed >> 33) as u8,
+            _ => 0xaa,
+        };
+    }
+    emu.cpu.bus.ram().write_bytes(0, &memory);
+
+    let mut bytes = vec![];
+    emu.snapshot().write_to(&mut bytes).unwrap();
+
+    let mut restored = Emulator::new(0x10);
+    restored
+        .restore(&Snapshot::read_from(bytes.as_slice()).unwrap())
+        .unwrap();
+    assert_eq!(
+        emu.cpu.bus.ram().contents(),
+        restored.cpu.bus.ram().contents()
+    );
+}
+
+// through the encoded form, the way a snapshot reaches another process
+fn reload(emu: &Emulator) -> Snapshot {
+    let mut bytes = vec![];
+    emu.snapshot().write_to(&mut bytes).unwrap();
+    Snapshot::read_from(bytes.as_slice()).unwrap()
+}
+
+#[test]
+fn mid_reservation() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(
+        assemble(
+            "
+                la s0, data
+                lr.d t0, (s0)
+                addi t0, t0, 1
+                sc.d a0, t0, (s0)
+                li a7, 93
+                ecall
+                .align 3
+            data:
+                .dword 41
+            ",
+        )
+        .unwrap(),
+    );
+
+    // right after the LR
+    assert_eq!(original.run_for(3), StopReason::LimitReached);
+
+    let mut restored = Emulator::new(0x100);
+    restored.restore(&reload(&original)).unwrap();
+
+    // the SC only succeeds if the reservation survived
+    assert_eq!(original.run_for(u64::MAX), StopReason::Exited { code: 0 });
+    assert_eq!(restored.run_for(u64::MAX), StopReason::Exited { code: 0 });
+
+    assert_eq!(original.cpu.xregs, restored.cpu.xregs);
+    assert_eq!(
+        original.cpu.bus.ram().contents(),
+        restored.cpu.bus.ram().contents()
+    );
+}
+
+#[test]
+fn mid_wfi() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(
+        assemble(
+            "
+                wfi
+                li a0, 7
+                li a7, 93
+                ecall
+            ",
+        )
+        .unwrap(),
+    );
+
+    // the interrupt pending at the other hart does not wake this one
+    let clint = Arc::new(Clint::new(2));
+    clint.set_software_pending(1, true);
+    original.cpu.bus.clint = Some(clint);
+
+    assert_eq!(original.run_for(10), StopReason::LimitReached);
+    assert_eq!(original.cpu.pc, RAM_BASE + 4);
+
+    let mut restored = Emulator::new(0x100);
+    restored.restore(&reload(&original)).unwrap();
+
+    let clint = restored.cpu.bus.clint.as_ref().unwrap();
+    assert_eq!(clint.harts(), 2);
+    assert!(!clint.software_pending(0));
+    assert!(clint.software_pending(1));
+
+    // both keep waiting
+    assert_eq!(original.run_for(10), StopReason::LimitReached);
+    assert_eq!(restored.run_for(10), StopReason::LimitReached);
+
+    assert_eq!(original.cpu.pc, restored.cpu.pc);
+    assert_eq!(original.cpu.instret, restored.cpu.instret);
+    assert_eq!(original.snapshot(), restored.snapshot());
+}
+
+fn with_models(emu: &mut Emulator) {
+    let l1 = CacheConfig::new(1024, 2, 64, 1);
+    let l2 = CacheConfig::new(8192, 4, 64, 10);
+
+    emu.cpu.timing = Some(Pipeline::new(Latencies::default()));
+    emu.cpu.caches = Some(CacheHierarchy::new(Some(l1), Some(l1), Some(l2), 100));
+    emu.cpu.branch_prediction = Some(BranchPrediction::new(ReturnStack::new(8, Tage::new())));
+    emu.cpu.profiler = Some(Profiler::new(Sampling::Exact, vec![]));
+}
+
+#[test]
+fn models() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(squares());
+    with_models(&mut original);
+    assert_eq!(original.run_for(300), StopReason::LimitReached);
+
+    let mut restored = Emulator::new(0x100);
+    with_models(&mut restored);
+    restored.restore(&reload(&original)).unwrap();
+    assert_eq!(restored.snapshot(), original.snapshot());
+
+    let expected = StopReason::Exited { code: 199 * 199 };
+    assert_eq!(original.run_for(u64::MAX), expected);
+    assert_eq!(restored.run_for(u64::MAX), expected);
+
+    let (original, restored) = (&original.cpu, &restored.cpu);
+    assert_eq!(
+        original.timing.as_ref().unwrap().to_string(),
+        restored.timing.as_ref().unwrap().to_string()
+    );
+    assert_eq!(
+        original.branch_prediction.as_ref().unwrap().to_string(),
+        restored.branch_prediction.as_ref().unwrap().to_string()
+    );
+    assert_eq!(
+        original.profiler.as_ref().unwrap().to_string(),
+        restored.profiler.as_ref().unwrap().to_string()
+    );
+
+    let stats =
+        |cpu: &risemu::cpu::CPU, level| cpu.caches.as_ref().unwrap().cache(level).unwrap().stats();
+    for level in [CacheLevel::L1i, CacheLevel::L1d, CacheLevel::L2] {
+        assert_eq!(stats(original, level), stats(restored, level));
+    }
+}
+
+#[test]
+fn models_configured_differently() {
+    let mut original = Emulator::new(0x10000);
+    original.init_ram(squares());
+    with_models(&mut original);
+    original.run_for(300);
+
+    // the timing model takes its state before the caches turn theirs down
+    let mut restored = Emulator::new(0x100);
+    restored.init_ram(squares());
+    restored.cpu.timing = Some(Pipeline::new(Latencies::default()));
+    restored.cpu.caches = Some(CacheHierarchy::new(
+        None,
+        Some(CacheConfig::new(512, 2, 64, 1)),
+        None,
+        100,
+    ));
+    restored.run_for(10);
+
+    let before = restored.snapshot();
+    let error = restored.restore(&original.snapshot()).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert!(error.to_string().starts_with("cannot restore the caches"));
+    assert_eq!(restored.snapshot(), before);
+}
+
+#[test]
+fn huge_ram() {
+    let emu = Emulator::new(0x10000);
+    let mut snapshot = emu.snapshot();
+    snapshot.ram_size = usize::MAX;
+
+    let mut restored = Emulator::new(0x100);
+    let before = restored.snapshot();
+    let error = restored.restore(&snapshot).unwrap_err();
+    assert_eq!(error.kind(), ErrorKind::InvalidData);
+    assert_eq!(restored.snapshot(), before);
+
+    let mut bytes = vec![];
+    snapshot.write_to(&mut bytes).unwrap();
+    let error = Snapshot::read_from(bytes.as_slice()).unwrap_err();
+    assert_eq!(error.to_string(), "RAM too large");
+}
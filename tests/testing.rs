@@ -0,0 +1,102 @@
+use risemu::bus::RAM_BASE;
+use risemu::cpu::Xlen;
+use risemu::csr::{MEPC, MSTATUS};
+use risemu::exception::RVException;
+use risemu::testing::TestCase;
+
+#[test]
+fn initial_state() {
+    TestCase::asm(
+        "
+            ld a2, 0(a0)
+            add a2, a2, a1
+            sd a2, 8(a0)
+            csrr a3, mepc
+        ",
+    )
+    .register(10, RAM_BASE + 0x1000)
+    .register(11, 5)
+    .memory(RAM_BASE + 0x1000, &37u64.to_le_bytes())
+    .csr(MEPC, 0x1234)
+    .expect_register(12, 42)
+    .expect_register(13, 0x1234)
+    .expect_memory(RAM_BASE + 0x1008, &42u64.to_le_bytes())
+    .expect_csr(MEPC, 0x1234)
+    .expect_pc(RAM_BASE + 16)
+    .run();
+}
+
+#[test]
+fn exceptions() {
+    TestCase::asm("ld a0, 0(zero)")
+        .expect_exception(RVException::LoadAccessFault)
+        .expect_pc(RAM_BASE)
+        .run();
+
+    TestCase::new(vec![0xff, 0xff, 0xff, 0xff])
+        .expect_exception(RVException::IllegalInstruction)
+        .run();
+
+    TestCase::asm("ebreak")
+        .expect_exception(RVException::Breakpoint)
+        .run();
+}
+
+#[test]
+fn limit() {
+    TestCase::asm("1: addi a0, a0, 1\nj 1b")
+        .limit(10)
+        .expect_limit()
+        .expect_register(10, 5)
+        .run();
+}
+
+#[test]
+fn rv32() {
+    let emu = TestCase::asm("li a0, -1\nsrli a1, a0, 31")
+        .xlen(Xlen::Rv32)
+        .expect_register(10, u64::MAX)
+        .expect_register(11, 1)
+        .expect_csr(MSTATUS, 0)
+        .run();
+
+    assert_eq!(emu.cpu.xlen(), Xlen::Rv32);
+}
+
+#[test]
+fn reports_every_difference() {
+    let Err(report) = TestCase::asm("li a0, 1\nsw a0, 0(sp)")
+        .register(2, RAM_BASE + 0x100)
+        .expect_register(10, 2)
+        .expect_register(11, 0)
+        .expect_memory(RAM_BASE + 0x100, &[2, 0])
+        .expect_memory(0, &[0])
+        .expect_csr(0x7ff, 0)
+        .expect_exception(RVException::Breakpoint)
+        .expect_pc(RAM_BASE)
+        .check()
+    else {
+        panic!("the expectations are wrong");
+    };
+
+    let differences: Vec<&str> = report.lines().skip(1).take(6).collect();
+    assert_eq!(
+        differences,
+        [
+            "  stop: expected Breakpoint, found EnvironmentCall",
+            "  pc: expected 0x80000000, found 0x80000008",
+            "  x10: expected 0x0000000000000002, found 0x0000000000000001",
+            "  memory at 0x80000100: expected [02, 00], found [01, 00]",
+            "  memory at 0x0: expected [00], but it cannot be read",
+            "  CSR 0x7ff: expected 0x0, but it does not exist",
+        ]
+    );
+    assert!(report.contains("stopped with Fault"));
+    assert!(report.contains("x10 0x0000000000000001"));
+}
+
+#[test]
+#[should_panic(expected = "x10: expected 0x0000000000000003")]
+fn panics_on_mismatch() {
+    TestCase::asm("li a0, 2").expect_register(10, 3).run();
+}
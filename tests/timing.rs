@@ -0,0 +1,271 @@
+use risemu::asm::{assemble, Assembler};
+use risemu::bus::RAM_BASE;
+use risemu::cpu::Xlen;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::timing::{Latencies, Pipeline, Stalls};
+
+// runs a program up to its final ECALL under the timing model
+fn timed(source: &str, latencies: Latencies) -> Emulator {
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble(&format!("{source}\necall")).unwrap());
+    emu.cpu.timing = Some(Pipeline::new(latencies));
+    emu.cpu.xregs[10] = RAM_BASE + 0x1000;
+
+    let stop = emu.run_for(10_000);
+    assert!(
+        matches!(
+            stop,
+            StopReason::Fault {
+                exception: RVException::EnvironmentCall,
+                ..
+            }
+        ),
+        "{stop:?}"
+    );
+    emu
+}
+
+fn pipeline(emu: &Emulator) -> &Pipeline {
+    emu.cpu.timing.as_ref().unwrap()
+}
+
+#[test]
+fn independent_instructions() {
+    let emu = timed(
+        "
+            li a1, 1
+            li a2, 2
+            add a3, a1, a2
+            sub a4, a3, a1
+        ",
+        Latencies::default(),
+    );
+
+    // filling and draining the pipeline costs four cycles
+    let pipeline = pipeline(&emu);
+    assert_eq!(pipeline.instructions(), 4);
+    assert_eq!(pipeline.cycles(), 8);
+    assert_eq!(pipeline.cpi(), 2.0);
+    assert_eq!(pipeline.stalls(), Stalls::default());
+    assert_eq!(emu.cpu.cycle, 8);
+}
+
+#[test]
+fn load_use() {
+    let emu = timed(
+        "
+            ld a1, 0(a0)
+            addi a2, a1, 1
+        ",
+        Latencies::default(),
+    );
+    assert_eq!(pipeline(&emu).cycles(), 7);
+    assert_eq!(pipeline(&emu).stalls().load_use, 1);
+
+    // an independent instruction in between hides the latency
+    let emu = timed(
+        "
+            ld a1, 0(a0)
+            addi a3, a0, 8
+            addi a2, a1, 1
+        ",
+        Latencies::default(),
+    );
+    assert_eq!(pipeline(&emu).cycles(), 7);
+    assert_eq!(pipeline(&emu).stalls().load_use, 0);
+
+    let emu = timed(
+        "
+            ld a1, 0(a0)
+            addi a2, a1, 1
+        ",
+        Latencies {
+            load: 4,
+            ..Latencies::default()
+        },
+    );
+    assert_eq!(pipeline(&emu).cycles(), 9);
+    assert_eq!(
+        pipeline(&emu).stalls(),
+        Stalls {
+            load_use: 1,
+            execute: 2,
+            branch: 0,
+            memory: 0,
+        }
+    );
+}
+
+#[test]
+fn multiply_and_divide() {
+    let source = "
+        mul a2, a0, a1
+        addi a3, a2, 1
+        divu a4, a0, a1
+    ";
+
+    let emu = timed(source, Latencies::default());
+    assert_eq!(pipeline(&emu).cycles(), 3 + 4 + 2 + 19);
+    assert_eq!(pipeline(&emu).stalls().execute, 2 + 19);
+
+    let emu = timed(
+        source,
+        Latencies {
+            mul: 1,
+            div: 4,
+            ..Latencies::default()
+        },
+    );
+    assert_eq!(pipeline(&emu).cycles(), 3 + 4 + 3);
+}
+
+#[test]
+fn taken_branches() {
+    let emu = timed(
+        "
+            li a1, 3
+        1:
+            addi a1, a1, -1
+            bnez a1, 1b
+        ",
+        Latencies::default(),
+    );
+
+    assert_eq!(pipeline(&emu).instructions(), 7);
+    assert_eq!(pipeline(&emu).stalls().branch, 4);
+    assert_eq!(pipeline(&emu).cycles(), 7 + 4 + 4);
+
+    let emu = timed(
+        "
+            j 1f
+            nop
+        1:
+            nop
+        ",
+        Latencies {
+            branch_penalty: 5,
+            ..Latencies::default()
+        },
+    );
+    assert_eq!(pipeline(&emu).cycles(), 2 + 4 + 5);
+}
+
+#[test]
+fn report() {
+    let emu = timed(
+        "
+            ld a1, 0(a0)
+            addi a2, a1, 1
+        ",
+        Latencies::default(),
+    );
+    assert_eq!(
+        pipeline(&emu).to_string(),
+        "7 cycles, 2 instructions, CPI 3.50\nstalls: 1 load-use, 0 execute, 0 branch, 0 memory"
+    );
+}
+
+#[test]
+fn mcycle() {
+    let source = "
+        li a1, 100
+        csrr a2, mcycle
+        csrw mcycle, a1
+        csrr a3, cycle
+        li a7, 93
+        ecall
+    ";
+
+    // without a timing model every instruction takes one cycle
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble(source).unwrap());
+    assert_eq!(emu.run_for(100), StopReason::Exited { code: 0 });
+    assert_eq!(emu.cpu.xregs[12], 1);
+    assert_eq!(emu.cpu.xregs[13], 101);
+    assert_eq!(emu.cpu.cycle, 103);
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble(source).unwrap());
+    emu.cpu.timing = Some(Pipeline::new(Latencies::default()));
+    assert_eq!(emu.run_for(100), StopReason::Exited { code: 0 });
+    assert_eq!(emu.cpu.xregs[12], 5);
+    assert_eq!(emu.cpu.xregs[13], 101);
+    assert_eq!(emu.cpu.cycle, 103);
+    assert_eq!(pipeline(&emu).cycles(), 9);
+
+    // cycle is read-only
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble("csrw cycle, a1").unwrap());
+    assert!(matches!(
+        emu.run_for(1),
+        StopReason::Fault {
+            exception: RVException::IllegalInstruction,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn mcycle_halves_on_rv32() {
+    let source = "
+        csrr a1, mcycleh
+        csrr a2, mcycle
+        li a3, 7
+        csrw mcycleh, a3
+    ";
+
+    let mut emu = Emulator::with_xlen(0x10000, Xlen::Rv32);
+    let code = Assembler::new(RAM_BASE, Xlen::Rv32)
+        .assemble(source)
+        .unwrap();
+    emu.init_ram(code);
+    emu.cpu.cycle = 0x1_ffff_fff0;
+
+    assert_eq!(emu.run_for(4), StopReason::LimitReached);
+    assert_eq!(emu.cpu.xregs[11], 1);
+    assert_eq!(emu.cpu.xregs[12], 0xffff_ffff_ffff_fff1);
+    assert_eq!(emu.cpu.cycle, 0x7_ffff_fff4);
+
+    // the high halves do not exist on RV64
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble("csrr a1, mcycleh").unwrap());
+    assert!(matches!(
+        emu.run_for(1),
+        StopReason::Fault {
+            exception: RVException::IllegalInstruction,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn blocks_are_still_timed() {
+    let source = "
+        li a1, 200
+    1:
+        addi a1, a1, -1
+        bnez a1, 1b
+    ";
+
+    let untimed = {
+        let mut emu = Emulator::new(0x10000);
+        emu.init_ram(assemble(source).unwrap());
+        #[cfg(feature = "jit")]
+        emu.cpu.set_jit_threshold(0);
+        emu.run_for(401);
+        emu.cpu.cycle
+    };
+    assert_eq!(untimed, 401);
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(assemble(source).unwrap());
+    #[cfg(feature = "jit")]
+    emu.cpu.set_jit_threshold(0);
+    emu.cpu.timing = Some(Pipeline::new(Latencies::default()));
+    emu.run_for(401);
+
+    assert_eq!(emu.cpu.instret, 401);
+    assert_eq!(pipeline(&emu).instructions(), 401);
+    assert_eq!(emu.cpu.cycle, 401 + 4 + 2 * 199);
+}
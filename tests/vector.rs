@@ -0,0 +1,604 @@
+use risemu::bus::RAM_BASE;
+use risemu::emulator::{Emulator, StopReason};
+use risemu::exception::RVException;
+use risemu::snapshot::Snapshot;
+use risemu::vector::AgnosticPolicy;
+
+// offset of the test data in RAM, which programs address through an initial `auipc a0, 1`
+const DATA: usize = 0x1000;
+
+fn program(code: Vec<u8>, data: &[u8]) -> Emulator {
+    let mut code = code;
+    code.extend([
+        0x93, 0x08, 0xd0, 0x05, // li a7, 93
+        0x73, 0x00, 0x00, 0x00, // ecall
+    ]);
+
+    let mut emu = Emulator::new(0x10000);
+    emu.init_ram(code);
+    emu.cpu.bus.ram().write_bytes(DATA, data);
+    emu
+}
+
+fn run(mut emu: Emulator) -> Emulator {
+    assert!(matches!(emu.run_for(u64::MAX), StopReason::Exited { .. }));
+    emu
+}
+
+fn elements(emu: &Emulator, register: u8, count: usize, width: usize) -> Vec<u64> {
+    (0..count)
+        .map(|index| emu.cpu.vector.element(register, index, width))
+        .collect()
+}
+
+fn bytes() -> Vec<u8> {
+    (0..64).collect()
+}
+
+#[test]
+fn vsetvl() {
+    let emu = run(program(
+        vec![
+            0xd7, 0x72, 0x00, 0x0d, // vsetvli t0, zero, e32, m1, ta, ma
+            0x57, 0x73, 0x20, 0x0c, // vsetvli t1, zero, e8, m4, ta, ma
+            0x93, 0x05, 0x40, 0x06, // li a1, 100
+            0xd7, 0xf3, 0xf5, 0x0c, // vsetvli t2, a1, e16, mf2, ta, ma
+            0x93, 0x05, 0x30, 0x00, // li a1, 3
+            0x57, 0xfe, 0x95, 0x01, // vsetvli t3, a1, e64, m2, tu, mu
+            0x57, 0x70, 0x10, 0x0d, // vsetvli zero, zero, e32, m2, ta, ma
+            0xf3, 0x2e, 0x00, 0xc2, // csrr t4, vl
+            0x57, 0xff, 0x0f, 0xcc, // vsetivli t5, 31, e8, m1, ta, ma
+            0xf3, 0x2f, 0x20, 0xc2, // csrr t6, vlenb
+            0x73, 0x29, 0x10, 0xc2, // csrr s2, vtype
+        ],
+        &[],
+    ));
+
+    assert_eq!(emu.cpu.xregs[5], 4);
+    assert_eq!(emu.cpu.xregs[6], 64);
+    assert_eq!(emu.cpu.xregs[7], 4);
+    assert_eq!(emu.cpu.xregs[28], 3);
+    assert_eq!(emu.cpu.xregs[29], 3);
+    assert_eq!(emu.cpu.xregs[30], 16);
+    assert_eq!(emu.cpu.xregs[31], 16);
+    assert_eq!(emu.cpu.xregs[18], 0xc0);
+}
+
+#[test]
+fn vill() {
+    let mut emu = program(
+        vec![
+            0xd7, 0x72, 0xd0, 0x0d, // vsetvli t0, zero, e64, mf8, ta, ma
+            0x73, 0x23, 0x10, 0xc2, // csrr t1, vtype
+            0xf3, 0x23, 0x00, 0xc2, // csrr t2, vl
+            0xd7, 0x80, 0x21, 0x02, // vadd.vv v1, v2, v3
+        ],
+        &[],
+    );
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::IllegalInstruction,
+            pc: RAM_BASE + 12,
+            tval: 0,
+        }
+    );
+    assert_eq!(emu.cpu.xregs[5], 0);
+    assert_eq!(emu.cpu.xregs[6], 1 << 63);
+    assert_eq!(emu.cpu.xregs[7], 0);
+}
+
+#[test]
+fn misaligned_group() {
+    let mut emu = program(
+        vec![
+            0x57, 0x70, 0x12, 0xcd, // vsetivli zero, 4, e32, m2, ta, ma
+            0xd7, 0x00, 0x22, 0x02, // vadd.vv v1, v2, v4
+        ],
+        &[],
+    );
+
+    assert!(matches!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::IllegalInstruction,
+            ..
+        }
+    ));
+}
+
+#[test]
+fn integer_arithmetic() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x57, 0x31, 0x15, 0x02, // vadd.vi v2, v1, 10
+            0xd7, 0x01, 0x11, 0x02, // vadd.vv v3, v1, v2
+            0x93, 0x02, 0xf0, 0xff, // li t0, -1
+            0x57, 0xc2, 0x12, 0x02, // vadd.vx v4, v1, t0
+            0xd7, 0xb2, 0x12, 0x0e, // vrsub.vi v5, v1, 5
+            0x57, 0xb3, 0x2f, 0x96, // vsll.vi v6, v2, 31
+            0xd7, 0xb3, 0x40, 0xa6, // vsra.vi v7, v4, 1
+            0x57, 0x24, 0x21, 0x96, // vmul.vv v8, v2, v2
+            0xd7, 0x44, 0x40, 0x1e, // vmax.vx v9, v4, zero
+            0x57, 0x45, 0x40, 0x1a, // vmaxu.vx v10, v4, zero
+        ],
+        &[],
+    ));
+
+    assert_eq!(elements(&emu, 2, 4, 32), [10, 11, 12, 13]);
+    assert_eq!(elements(&emu, 3, 4, 32), [10, 12, 14, 16]);
+    assert_eq!(elements(&emu, 4, 4, 32), [0xffffffff, 0, 1, 2]);
+    assert_eq!(elements(&emu, 5, 4, 32), [5, 4, 3, 2]);
+    assert_eq!(elements(&emu, 6, 4, 32), [0, 0x80000000, 0, 0x80000000]);
+    assert_eq!(elements(&emu, 7, 4, 32), [0xffffffff, 0, 0, 1]);
+    assert_eq!(elements(&emu, 8, 4, 32), [100, 121, 144, 169]);
+    assert_eq!(elements(&emu, 9, 4, 32), [0, 0, 1, 2]);
+    assert_eq!(elements(&emu, 10, 4, 32), [0xffffffff, 0, 1, 2]);
+}
+
+#[test]
+fn division() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x02, 0xcc, // vsetivli zero, 4, e8, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x93, 0x02, 0x00, 0xf8, // li t0, -128
+            0x57, 0xc1, 0x02, 0x5e, // vmv.v.x v2, t0
+            0x13, 0x03, 0xf0, 0xff, // li t1, -1
+            0xd7, 0x61, 0x23, 0x86, // vdiv.vx v3, v2, t1
+            0x57, 0x62, 0x23, 0x8e, // vrem.vx v4, v2, t1
+            0xd7, 0xa2, 0x20, 0x82, // vdivu.vv v5, v2, v1
+            0x57, 0xa3, 0x20, 0x8a, // vremu.vv v6, v2, v1
+            0xd7, 0xa3, 0x20, 0x86, // vdiv.vv v7, v2, v1
+        ],
+        &[],
+    ));
+
+    assert_eq!(elements(&emu, 3, 4, 8), [0x80; 4]);
+    assert_eq!(elements(&emu, 4, 4, 8), [0; 4]);
+    assert_eq!(elements(&emu, 5, 4, 8), [0xff, 128, 64, 42]);
+    assert_eq!(elements(&emu, 6, 4, 8), [128, 0, 0, 2]);
+    assert_eq!(elements(&emu, 7, 4, 8), [0xff, 0x80, 0xc0, 0xd6]);
+}
+
+#[test]
+fn multiply_add() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x82, 0xcc, // vsetivli zero, 4, e16, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x57, 0xb1, 0x02, 0x5e, // vmv.v.i v2, 5
+            0xd7, 0x31, 0x01, 0x5e, // vmv.v.i v3, 2
+            0xd7, 0xa1, 0x20, 0xb6, // vmacc.vv v3, v1, v2
+            0x57, 0xb2, 0x01, 0x5e, // vmv.v.i v4, 3
+            0x57, 0xa2, 0x20, 0xa6, // vmadd.vv v4, v1, v2
+            0xd7, 0xb2, 0x00, 0x5e, // vmv.v.i v5, 1
+            0x93, 0x02, 0x40, 0x00, // li t0, 4
+            0xd7, 0xe2, 0x12, 0xbe, // vnmsac.vx v5, t0, v1
+        ],
+        &[],
+    ));
+
+    assert_eq!(elements(&emu, 3, 4, 16), [2, 7, 12, 17]);
+    assert_eq!(elements(&emu, 4, 4, 16), [5, 8, 11, 14]);
+    assert_eq!(elements(&emu, 5, 4, 16), [1, 0xfffd, 0xfff9, 0xfff5]);
+}
+
+fn policies() -> Vec<u8> {
+    vec![
+        0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+        0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+        0x57, 0xb0, 0x10, 0x72, // vmsleu.vi v0, v1, 1
+        0x57, 0xb1, 0x03, 0x5e, // vmv.v.i v2, 7
+        0xd7, 0xb1, 0x00, 0x5e, // vmv.v.i v3, 1
+        0x57, 0xb2, 0x00, 0x5e, // vmv.v.i v4, 1
+        0xd7, 0xb2, 0x01, 0x5e, // vmv.v.i v5, 3
+        0x57, 0x70, 0x02, 0xc1, // vsetivli zero, 4, e32, m1, tu, mu
+        0x57, 0x31, 0x15, 0x00, // vadd.vi v2, v1, 10, v0.t
+        0x57, 0x70, 0x01, 0xc1, // vsetivli zero, 2, e32, m1, tu, mu
+        0xd7, 0x31, 0x01, 0x5e, // vmv.v.i v3, 2
+        0x57, 0x70, 0x01, 0xcd, // vsetivli zero, 2, e32, m1, ta, ma
+        0x57, 0x32, 0x01, 0x5e, // vmv.v.i v4, 2
+        0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+        0xd7, 0x32, 0x15, 0x00, // vadd.vi v5, v1, 10, v0.t
+    ]
+}
+
+#[test]
+fn undisturbed_policy() {
+    let emu = run(program(policies(), &[]));
+
+    assert_eq!(elements(&emu, 2, 4, 32), [10, 11, 7, 7]);
+    assert_eq!(elements(&emu, 3, 4, 32), [2, 2, 1, 1]);
+    assert_eq!(elements(&emu, 4, 4, 32), [2, 2, 1, 1]);
+    assert_eq!(elements(&emu, 5, 4, 32), [10, 11, 3, 3]);
+}
+
+#[test]
+fn agnostic_policy() {
+    let mut emu = program(policies(), &[]);
+    emu.cpu.vector.agnostic = AgnosticPolicy::Ones;
+    let emu = run(emu);
+
+    // undisturbed elements stay as they are whatever the policy
+    assert_eq!(elements(&emu, 2, 4, 32), [10, 11, 7, 7]);
+    assert_eq!(elements(&emu, 3, 4, 32), [2, 2, 1, 1]);
+    assert_eq!(elements(&emu, 4, 4, 32), [2, 2, 0xffffffff, 0xffffffff]);
+    assert_eq!(elements(&emu, 5, 4, 32), [10, 11, 0xffffffff, 0xffffffff]);
+}
+
+#[test]
+fn compare() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x84, 0xcc, // vsetivli zero, 8, e16, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x57, 0xb1, 0x11, 0x62, // vmseq.vi v2, v1, 3
+            0xd7, 0x31, 0x12, 0x7e, // vmsgt.vi v3, v1, 4
+            0x93, 0x02, 0x20, 0x00, // li t0, 2
+            0x57, 0xc2, 0x12, 0x6e, // vmslt.vx v4, v1, t0
+            0xd7, 0x82, 0x10, 0x66, // vmsne.vv v5, v1, v1
+        ],
+        &[],
+    ));
+
+    assert_eq!(emu.cpu.vector.register(2)[..2], [0x08, 0]);
+    assert_eq!(emu.cpu.vector.register(3)[..2], [0xe0, 0]);
+    assert_eq!(emu.cpu.vector.register(4)[..2], [0x03, 0]);
+    assert_eq!(emu.cpu.vector.register(5)[..2], [0x00, 0]);
+}
+
+#[test]
+fn unit_stride() {
+    let emu = run(program(
+        vec![
+            0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+            0x57, 0x70, 0x14, 0xcd, // vsetivli zero, 8, e32, m2, ta, ma
+            0x07, 0x61, 0x05, 0x02, // vle32.v v2, (a0)
+            0x93, 0x05, 0x05, 0x10, // addi a1, a0, 256
+            0x27, 0xe1, 0x05, 0x02, // vse32.v v2, (a1)
+            0x57, 0xf0, 0x81, 0xcc, // vsetivli zero, 3, e16, m1, ta, ma
+            0x07, 0x52, 0x05, 0x02, // vle16.v v4, (a0)
+        ],
+        &bytes(),
+    ));
+
+    assert_eq!(
+        elements(&emu, 2, 8, 32),
+        [
+            0x03020100, 0x07060504, 0x0b0a0908, 0x0f0e0d0c, 0x13121110, 0x17161514, 0x1b1a1918,
+            0x1f1e1d1c,
+        ]
+    );
+    assert_eq!(emu.cpu.vector.register(3)[0], 16);
+    assert_eq!(elements(&emu, 4, 3, 16), [0x0100, 0x0302, 0x0504]);
+
+    let memory = emu.cpu.bus.ram().contents();
+    assert_eq!(memory[DATA + 256..DATA + 288], bytes()[..32]);
+    assert_eq!(memory[DATA + 288], 0);
+}
+
+#[test]
+fn strided_indexed_and_segment() {
+    let emu = run(program(
+        vec![
+            0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+            0x93, 0x05, 0x05, 0x10, // addi a1, a0, 256
+            0x93, 0x02, 0x80, 0x00, // li t0, 8
+            0x57, 0x70, 0x82, 0xcc, // vsetivli zero, 4, e16, m1, ta, ma
+            0x87, 0x50, 0x55, 0x0a, // vlse16.v v1, (a0), t0
+            0x57, 0xa1, 0x08, 0x52, // vid.v v2
+            0x57, 0x31, 0x21, 0x96, // vsll.vi v2, v2, 2
+            0x87, 0x51, 0x25, 0x06, // vluxei16.v v3, (a0), v2
+            0x07, 0x52, 0x05, 0x22, // vlseg2e16.v v4, (a0)
+            0xa7, 0xd0, 0x55, 0x0a, // vsse16.v v1, (a1), t0
+        ],
+        &bytes(),
+    ));
+
+    assert_eq!(elements(&emu, 1, 4, 16), [0x0100, 0x0908, 0x1110, 0x1918]);
+    assert_eq!(elements(&emu, 3, 4, 16), [0x0100, 0x0504, 0x0908, 0x0d0c]);
+    assert_eq!(elements(&emu, 4, 4, 16), [0x0100, 0x0504, 0x0908, 0x0d0c]);
+    assert_eq!(elements(&emu, 5, 4, 16), [0x0302, 0x0706, 0x0b0a, 0x0f0e]);
+
+    let memory = emu.cpu.bus.ram().contents();
+    assert_eq!(memory[DATA + 256..DATA + 260], [0x00, 0x01, 0x00, 0x00]);
+    assert_eq!(memory[DATA + 264..DATA + 266], [0x08, 0x09]);
+}
+
+#[test]
+fn mask_and_whole_register() {
+    let emu = run(program(
+        vec![
+            0x17, 0x15, 0x00, 0x00, // auipc a0, 1
+            0x93, 0x05, 0x55, 0x00, // addi a1, a0, 5
+            0x13, 0x06, 0x05, 0x10, // addi a2, a0, 256
+            0x57, 0x70, 0x05, 0xcc, // vsetivli zero, 10, e8, m1, ta, ma
+            0x07, 0x80, 0xb5, 0x02, // vlm.v v0, (a1)
+            0x07, 0x61, 0x85, 0x22, // vl2re32.v v2, (a0)
+            0x27, 0x01, 0x86, 0x22, // vs2r.v v2, (a2)
+        ],
+        &bytes(),
+    ));
+
+    assert_eq!(emu.cpu.vector.register(0)[..3], [5, 6, 0]);
+    assert_eq!(emu.cpu.vector.register(2), &bytes()[..16]);
+    assert_eq!(emu.cpu.vector.register(3), &bytes()[16..32]);
+    assert_eq!(
+        emu.cpu.bus.ram().contents()[DATA + 256..DATA + 288],
+        bytes()[..32]
+    );
+}
+
+#[test]
+fn fault_only_first() {
+    let emu = run(program(
+        vec![
+            0x17, 0x05, 0x01, 0x00, // auipc a0, 16
+            0x13, 0x05, 0x85, 0xff, // addi a0, a0, -8
+            0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+            0x87, 0x60, 0x05, 0x03, // vle32ff.v v1, (a0)
+            0xf3, 0x22, 0x00, 0xc2, // csrr t0, vl
+        ],
+        &[],
+    ));
+
+    assert_eq!(emu.cpu.xregs[5], 2);
+}
+
+#[test]
+fn load_fault() {
+    let mut emu = program(
+        vec![
+            0x17, 0x05, 0x01, 0x00, // auipc a0, 16
+            0x13, 0x05, 0x85, 0xff, // addi a0, a0, -8
+            0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+            0x87, 0x60, 0x05, 0x02, // vle32.v v1, (a0)
+        ],
+        &[],
+    );
+
+    assert_eq!(
+        emu.run_for(u64::MAX),
+        StopReason::Fault {
+            exception: RVException::LoadAccessFault,
+            pc: RAM_BASE + 12,
+            tval: RAM_BASE + 0x10000,
+        }
+    );
+    assert_eq!(emu.cpu.vector.vstart, 2);
+}
+
+#[test]
+fn widening_and_narrowing() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x82, 0xcc, // vsetivli zero, 4, e16, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x93, 0x02, 0xe0, 0xff, // li t0, -2
+            0x57, 0xc1, 0x02, 0x5e, // vmv.v.x v2, t0
+            0x57, 0x22, 0x11, 0xc6, // vwadd.vv v4, v1, v2
+            0x57, 0x23, 0x11, 0xc2, // vwaddu.vv v6, v1, v2
+            0x57, 0xe4, 0x12, 0xee, // vwmul.vx v8, v1, t0
+            0x57, 0xa5, 0x40, 0xd6, // vwadd.wv v10, v4, v1
+            0x57, 0x36, 0x64, 0xb2, // vnsrl.wi v12, v6, 8
+            0xd7, 0x36, 0x60, 0xba, // vnclipu.wi v13, v6, 0
+            0x73, 0x23, 0x90, 0x00, // csrr t1, vxsat
+        ],
+        &[],
+    ));
+
+    assert_eq!(elements(&emu, 4, 4, 32), [0xfffffffe, 0xffffffff, 0, 1]);
+    assert_eq!(elements(&emu, 6, 4, 32), [0xfffe, 0xffff, 0x10000, 0x10001]);
+    assert_eq!(
+        elements(&emu, 8, 4, 32),
+        [0, 0xfffffffe, 0xfffffffc, 0xfffffffa]
+    );
+    assert_eq!(elements(&emu, 10, 4, 32), [0xfffffffe, 0, 2, 4]);
+    assert_eq!(elements(&emu, 12, 4, 16), [0xff, 0xff, 0x100, 0x100]);
+    assert_eq!(elements(&emu, 13, 4, 16), [0xfffe, 0xffff, 0xffff, 0xffff]);
+    assert_eq!(emu.cpu.xregs[6], 1);
+}
+
+#[test]
+fn fixed_point() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x02, 0xcc, // vsetivli zero, 4, e8, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x93, 0x02, 0xe0, 0x0f, // li t0, 254
+            0x57, 0xc1, 0x12, 0x82, // vsaddu.vx v2, v1, t0
+            0x73, 0x23, 0x90, 0x00, // csrr t1, vxsat
+            0x73, 0x50, 0x90, 0x00, // csrwi vxsat, 0
+            0x93, 0x03, 0xf0, 0x07, // li t2, 127
+            0xd7, 0xc1, 0x13, 0x86, // vsadd.vx v3, v1, t2
+            0x73, 0x50, 0xa0, 0x00, // csrwi vxrm, 0
+            0x57, 0x62, 0x10, 0x26, // vaadd.vx v4, v1, zero
+            0x73, 0x50, 0xa1, 0x00, // csrwi vxrm, 2
+            0xd7, 0x62, 0x10, 0x26, // vaadd.vx v5, v1, zero
+            0x73, 0xd0, 0xa0, 0x00, // csrwi vxrm, 1
+            0x57, 0x63, 0x10, 0x26, // vaadd.vx v6, v1, zero
+            0x73, 0xd0, 0xa1, 0x00, // csrwi vxrm, 3
+            0xd7, 0x63, 0x10, 0x26, // vaadd.vx v7, v1, zero
+            0x73, 0x50, 0x90, 0x00, // csrwi vxsat, 0
+            0x13, 0x0e, 0x00, 0xf8, // li t3, -128
+            0xd7, 0x44, 0x0e, 0x5e, // vmv.v.x v9, t3
+            0x57, 0x85, 0x94, 0x9e, // vsmul.vv v10, v9, v9
+            0xf3, 0x2e, 0xf0, 0x00, // csrr t4, vcsr
+        ],
+        &[],
+    ));
+
+    assert_eq!(elements(&emu, 2, 4, 8), [254, 255, 255, 255]);
+    assert_eq!(emu.cpu.xregs[6], 1);
+    assert_eq!(elements(&emu, 3, 4, 8), [0x7f; 4]);
+    assert_eq!(elements(&emu, 4, 4, 8), [0, 1, 1, 2]);
+    assert_eq!(elements(&emu, 5, 4, 8), [0, 0, 1, 1]);
+    assert_eq!(elements(&emu, 6, 4, 8), [0, 0, 1, 2]);
+    assert_eq!(elements(&emu, 7, 4, 8), [0, 1, 1, 1]);
+    assert_eq!(elements(&emu, 10, 4, 8), [0x7f; 4]);
+
+    // vxrm in bits 2:1, vxsat in bit 0
+    assert_eq!(emu.cpu.xregs[29], 0b111);
+}
+
+#[test]
+fn reductions() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x84, 0xcc, // vsetivli zero, 8, e16, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x57, 0x31, 0x05, 0x5e, // vmv.v.i v2, 10
+            0xd7, 0xb2, 0x0f, 0x5e, // vmv.v.i v5, -1
+            0xd7, 0x21, 0x11, 0x02, // vredsum.vs v3, v1, v2
+            0x57, 0xa2, 0x12, 0x1e, // vredmax.vs v4, v1, v5
+            0x57, 0xa3, 0x12, 0x1a, // vredmaxu.vs v6, v1, v5
+            0x57, 0xb0, 0x11, 0x72, // vmsleu.vi v0, v1, 3
+            0xd7, 0x23, 0x11, 0x00, // vredsum.vs v7, v1, v2, v0.t
+            0xd7, 0x35, 0x10, 0x0e, // vrsub.vi v11, v1, 0
+            0x57, 0x36, 0x00, 0x5e, // vmv.v.i v12, 0
+            0x57, 0x04, 0xb6, 0xc6, // vwredsum.vs v8, v11, v12
+            0xd7, 0x04, 0xb6, 0xc2, // vwredsumu.vs v9, v11, v12
+        ],
+        &[],
+    ));
+
+    assert_eq!(emu.cpu.vector.element(3, 0, 16), 38);
+    assert_eq!(emu.cpu.vector.element(4, 0, 16), 7);
+    assert_eq!(emu.cpu.vector.element(6, 0, 16), 0xffff);
+    assert_eq!(emu.cpu.vector.element(7, 0, 16), 16);
+    assert_eq!(emu.cpu.vector.element(8, 0, 32), 0xffffffe4);
+    assert_eq!(emu.cpu.vector.element(9, 0, 32), 0x6ffe4);
+}
+
+#[test]
+fn mask_instructions() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x04, 0xcc, // vsetivli zero, 8, e8, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x57, 0x31, 0x11, 0x7a, // vmsgtu.vi v2, v1, 2
+            0xd7, 0x31, 0x12, 0x72, // vmsleu.vi v3, v1, 4
+            0x57, 0xa2, 0x21, 0x66, // vmand.mm v4, v2, v3
+            0xd7, 0xa2, 0x21, 0x6e, // vmxor.mm v5, v2, v3
+            0x57, 0xa3, 0x21, 0x62, // vmandn.mm v6, v2, v3
+            0xd7, 0x22, 0x48, 0x42, // vcpop.m t0, v4
+            0x57, 0xa3, 0x48, 0x42, // vfirst.m t1, v4
+            0xd7, 0xa3, 0x73, 0x6e, // vmclr.m v7
+            0xd7, 0xa3, 0x78, 0x42, // vfirst.m t2, v7
+            0x57, 0xa4, 0x40, 0x52, // vmsbf.m v8, v4
+            0xd7, 0xa4, 0x41, 0x52, // vmsif.m v9, v4
+            0x57, 0x25, 0x41, 0x52, // vmsof.m v10, v4
+            0xd7, 0x25, 0x28, 0x52, // viota.m v11, v2
+        ],
+        &[],
+    ));
+
+    assert_eq!(emu.cpu.vector.register(4)[0], 0x18);
+    assert_eq!(emu.cpu.vector.register(5)[0], 0xe7);
+    assert_eq!(emu.cpu.vector.register(6)[0], 0xe0);
+    assert_eq!(emu.cpu.xregs[5], 2);
+    assert_eq!(emu.cpu.xregs[6], 3);
+    assert_eq!(emu.cpu.xregs[7], u64::MAX);
+    assert_eq!(emu.cpu.vector.register(8)[0], 0x07);
+    assert_eq!(emu.cpu.vector.register(9)[0], 0x0f);
+    assert_eq!(emu.cpu.vector.register(10)[0], 0x08);
+    assert_eq!(elements(&emu, 11, 8, 8), [0, 0, 0, 0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn permutations() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0xd7, 0xb0, 0x10, 0x02, // vadd.vi v1, v1, 1
+            0x57, 0x31, 0x00, 0x5e, // vmv.v.i v2, 0
+            0x57, 0x31, 0x11, 0x3a, // vslideup.vi v2, v1, 2
+            0xd7, 0xb1, 0x10, 0x3e, // vslidedown.vi v3, v1, 1
+            0x93, 0x02, 0x90, 0x00, // li t0, 9
+            0x57, 0xe2, 0x12, 0x3a, // vslide1up.vx v4, v1, t0
+            0xd7, 0xe2, 0x12, 0x3e, // vslide1down.vx v5, v1, t0
+            0x57, 0xa3, 0x08, 0x52, // vid.v v6
+            0x57, 0xb3, 0x61, 0x0e, // vrsub.vi v6, v6, 3
+            0xd7, 0x03, 0x13, 0x32, // vrgather.vv v7, v1, v6
+            0x57, 0x34, 0x11, 0x32, // vrgather.vi v8, v1, 2
+            0xd7, 0xa4, 0x08, 0x52, // vid.v v9
+            0xd7, 0xb4, 0x90, 0x26, // vand.vi v9, v9, 1
+            0x57, 0x30, 0x90, 0x66, // vmsne.vi v0, v9, 0
+            0x57, 0x25, 0x10, 0x5e, // vcompress.vm v10, v1, v0
+            0x57, 0x23, 0x10, 0x42, // vmv.x.s t1, v1
+            0x93, 0x03, 0xb0, 0xff, // li t2, -5
+            0xd7, 0xe5, 0x03, 0x42, // vmv.s.x v11, t2
+            0x57, 0x2e, 0xb0, 0x42, // vmv.x.s t3, v11
+            0x57, 0xb6, 0x60, 0x9e, // vmv2r.v v12, v6
+        ],
+        &[],
+    ));
+
+    assert_eq!(elements(&emu, 2, 4, 32), [0, 0, 1, 2]);
+    assert_eq!(elements(&emu, 3, 4, 32), [2, 3, 4, 0]);
+    assert_eq!(elements(&emu, 4, 4, 32), [9, 1, 2, 3]);
+    assert_eq!(elements(&emu, 5, 4, 32), [2, 3, 4, 9]);
+    assert_eq!(elements(&emu, 7, 4, 32), [4, 3, 2, 1]);
+    assert_eq!(elements(&emu, 8, 4, 32), [3, 3, 3, 3]);
+    assert_eq!(elements(&emu, 10, 4, 32), [2, 4, 0, 0]);
+    assert_eq!(emu.cpu.xregs[6], 1);
+    assert_eq!(emu.cpu.xregs[28], -5i64 as u64);
+    assert_eq!(emu.cpu.vector.register(12), emu.cpu.vector.register(6));
+    assert_eq!(emu.cpu.vector.register(13), emu.cpu.vector.register(7));
+}
+
+#[test]
+fn extension_and_carry() {
+    let emu = run(program(
+        vec![
+            0x93, 0x02, 0x00, 0x08, // li t0, 0x80
+            0x57, 0x70, 0x02, 0xcc, // vsetivli zero, 4, e8, m1, ta, ma
+            0xd7, 0xc0, 0x02, 0x5e, // vmv.v.x v1, t0
+            0x57, 0xa2, 0x08, 0x52, // vid.v v4
+            0x57, 0xb0, 0x40, 0x62, // vmseq.vi v0, v4, 1
+            0x13, 0x03, 0xf0, 0x0f, // li t1, 255
+            0xd7, 0x42, 0x03, 0x5e, // vmv.v.x v5, t1
+            0x57, 0x83, 0x42, 0x40, // vadc.vvm v6, v4, v5, v0
+            0xd7, 0x83, 0x42, 0x44, // vmadc.vvm v7, v4, v5, v0
+            0x57, 0x84, 0x42, 0x4e, // vmsbc.vv v8, v4, v5
+            0xd7, 0x44, 0x43, 0x46, // vmadc.vx v9, v4, t1
+            0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+            0x57, 0x21, 0x12, 0x4a, // vzext.vf4 v2, v1
+            0xd7, 0xa1, 0x12, 0x4a, // vsext.vf4 v3, v1
+        ],
+        &[],
+    ));
+
+    assert_eq!(elements(&emu, 6, 4, 8), [0xff, 0x01, 0x01, 0x02]);
+    assert_eq!(emu.cpu.vector.register(7)[0] & 0x0f, 0b1110);
+    assert_eq!(emu.cpu.vector.register(8)[0] & 0x0f, 0b1111);
+    assert_eq!(emu.cpu.vector.register(9)[0] & 0x0f, 0b1110);
+    assert_eq!(elements(&emu, 2, 4, 32), [0x80; 4]);
+    assert_eq!(elements(&emu, 3, 4, 32), [0xffffff80; 4]);
+}
+
+#[test]
+fn snapshot() {
+    let emu = run(program(
+        vec![
+            0x57, 0x70, 0x02, 0xcd, // vsetivli zero, 4, e32, m1, ta, ma
+            0xd7, 0xa0, 0x08, 0x52, // vid.v v1
+            0x73, 0x50, 0xa1, 0x00, // csrwi vxrm, 2
+        ],
+        &[],
+    ));
+
+    let mut bytes = vec![];
+    emu.snapshot().write_to(&mut bytes).unwrap();
+    let snapshot = Snapshot::read_from(bytes.as_slice()).unwrap();
+
+    let mut restored = Emulator::new(0x10000);
+    restored.restore(&snapshot).unwrap();
+    assert_eq!(restored.cpu.vector, emu.cpu.vector);
+    assert_eq!(elements(&restored, 1, 4, 32), [0, 1, 2, 3]);
+}
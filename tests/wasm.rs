@@ -0,0 +1,272 @@
+use std::{path::PathBuf, process::Command};
+
+use risemu::asm::assemble;
+use risemu::bus::RAM_BASE;
+use wasm_bindgen_cli_support::Bindgen;
+use wasmi::{Caller, Engine, Extern, ExternRef, Linker, Memory, Module, Store, Val};
+
+// StopKind values on the JavaScript side
+const LIMIT_REACHED: i32 = 0;
+const EXITED: i32 = 4;
+
+// prints "hi" on the console and exits with 7
+const HELLO: &str = "
+        li t0, 0x10000000
+        li t1, 104
+        sb t1, 0(t0)
+        li t1, 105
+        sb t1, 0(t0)
+        li a0, 7
+        li a7, 93
+        ecall
+";
+
+// builds the bindings for wasm32 and runs them through wasm-bindgen, unless the target is missing
+fn build() -> Option<Vec<u8>> {
+    let libdir = Command::new("rustc")
+        .args([
+            "--print",
+            "target-libdir",
+            "--target",
+            "wasm32-unknown-unknown",
+        ])
+        .output()
+        .unwrap();
+    let libdir = PathBuf::from(String::from_utf8(libdir.stdout).unwrap().trim());
+    if !libdir.exists() {
+        eprintln!("skipped, the wasm32-unknown-unknown target is not installed");
+        return None;
+    }
+
+    let target = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("wasm");
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--release", "--lib", "--features", "wasm"])
+        .args(["--target", "wasm32-unknown-unknown"])
+        .args([
+            "--manifest-path",
+            concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"),
+        ])
+        .arg("--target-dir")
+        .arg(&target)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let mut output = Bindgen::new()
+        .input_path(target.join("wasm32-unknown-unknown/release/risemu.wasm"))
+        .web(true)
+        .unwrap()
+        .generate_output()
+        .unwrap();
+
+    Some(output.wasm_mut().emit_wasm())
+}
+
+#[derive(Default)]
+struct Host {
+    console: Vec<u8>,
+
+    // __wbindgen_start is exported as the import itself, so its caller has no instance
+    instance: Option<wasmi::Instance>,
+}
+
+fn string(caller: &Caller<'_, Host>, args: &[Val]) -> String {
+    let Some(Extern::Memory(memory)) = caller.get_export("memory") else {
+        panic!("no memory");
+    };
+
+    let start = args[0].i32().unwrap() as usize;
+    let end = start + args[1].i32().unwrap() as usize;
+    String::from_utf8(memory.data(caller)[start..end].to_vec()).unwrap()
+}
+
+// the few JavaScript imports the bindings use
+fn import(
+    mut caller: Caller<'_, Host>,
+    name: &str,
+    args: &[Val],
+    results: &mut [Val],
+) -> Result<(), wasmi::Error> {
+    if name.starts_with("__wbg_Error_") {
+        let message = string(&caller, args);
+        results[0] = Val::ExternRef(ExternRef::new(&mut caller, message));
+    } else if name.starts_with("__wbg___wbindgen_throw_") {
+        return Err(wasmi::Error::new(string(&caller, args)));
+    } else if name.starts_with("__wbg_call_") {
+        // the console callback, with the byte as its argument
+        let byte = args[2]
+            .externref()
+            .and_then(|byte| byte.data(&caller))
+            .and_then(|byte| byte.downcast_ref::<f64>())
+            .copied()
+            .unwrap();
+
+        caller.data_mut().console.push(byte as u8);
+        results[0] = Val::ExternRef(ExternRef::null());
+    } else if name.starts_with("__wbindgen_generic_") {
+        // casts a number to a JavaScript value
+        let number = args[0].f64().unwrap().to_float();
+        results[0] = Val::ExternRef(ExternRef::new(&mut caller, number));
+    } else if name == "__wbindgen_init_externref_table" {
+        let instance = caller.data().instance.unwrap();
+        let table = instance
+            .get_table(&caller, "__wbindgen_externrefs")
+            .unwrap();
+
+        table.grow(&mut caller, 4, Val::ExternRef(ExternRef::null()))?;
+    } else {
+        return Err(wasmi::Error::new(format!("unexpected import {name}")));
+    }
+
+    Ok(())
+}
+
+struct Bindings {
+    store: Store<Host>,
+    instance: wasmi::Instance,
+}
+
+impl Bindings {
+    fn new(wasm: &[u8]) -> Self {
+        let engine = Engine::default();
+        let module = Module::new(&engine, wasm).unwrap();
+        let mut store = Store::new(&engine, Host::default());
+        let mut linker = Linker::new(&engine);
+
+        for entry in module.imports() {
+            let ty = entry.ty().func().unwrap().clone();
+            let name = entry.name().to_string();
+            linker
+                .func_new(
+                    entry.module(),
+                    entry.name(),
+                    ty,
+                    move |caller, args, results| import(caller, &name, args, results),
+                )
+                .unwrap();
+        }
+
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .unwrap()
+            .start(&mut store)
+            .unwrap();
+        store.data_mut().instance = Some(instance);
+
+        let mut bindings = Self { store, instance };
+        bindings.call("__wbindgen_start", &[]);
+        bindings
+    }
+
+    fn call(&mut self, name: &str, args: &[Val]) -> Vec<Val> {
+        let func = self.instance.get_func(&self.store, name).unwrap();
+        let mut results: Vec<_> = func
+            .ty(&self.store)
+            .results()
+            .iter()
+            .map(|&ty| Val::default(ty))
+            .collect();
+
+        func.call(&mut self.store, args, &mut results).unwrap();
+        results
+    }
+
+    fn memory(&self) -> Memory {
+        self.instance.get_memory(&self.store, "memory").unwrap()
+    }
+
+    // copies bytes into the module, as the glue does for a Uint8Array
+    fn pass(&mut self, data: &[u8]) -> [Val; 2] {
+        let length = Val::I32(data.len() as i32);
+        let pointer = self.call("__wbindgen_malloc", &[length.clone(), Val::I32(1)])[0].clone();
+
+        let memory = self.memory();
+        memory
+            .write(&mut self.store, pointer.i32().unwrap() as usize, data)
+            .unwrap();
+
+        [pointer, length]
+    }
+
+    // the message of an error thrown through the externref table
+    fn error(&mut self, index: &Val) -> String {
+        let table = self
+            .instance
+            .get_table(&self.store, "__wbindgen_externrefs")
+            .unwrap();
+        let error = table.get(&self.store, index.i32().unwrap() as u32).unwrap();
+
+        error
+            .externref()
+            .and_then(|error| error.data(&self.store))
+            .and_then(|error| error.downcast_ref::<String>())
+            .cloned()
+            .unwrap()
+    }
+}
+
+#[test]
+fn javascript_api() {
+    let Some(wasm) = build() else {
+        return;
+    };
+    let mut js = Bindings::new(&wasm);
+
+    let result = js.call("emulator_new", &[Val::I32(0x10000), Val::I32(48)]);
+    assert_eq!(result[2].i32(), Some(1));
+    assert_eq!(js.error(&result[1]), "the register width must be 32 or 64");
+
+    let result = js.call("emulator_new", &[Val::I32(0x10000), Val::I32(64)]);
+    assert_eq!(result[2].i32(), Some(0));
+    let emu = result[0].i32().unwrap();
+
+    let image = assemble(HELLO).unwrap();
+    let [pointer, length] = js.pass(&image);
+    let result = js.call("emulator_loadImage", &[Val::I32(emu), pointer, length]);
+    assert_eq!(result.last().unwrap().i32(), Some(0));
+
+    let callback = Val::ExternRef(ExternRef::new(&mut js.store, "callback"));
+    let result = js.call("emulator_attachConsole", &[Val::I32(emu), callback]);
+    assert_eq!(result.last().unwrap().i32(), Some(0));
+
+    let stop = js.call("emulator_step", &[Val::I32(emu)])[0].i32().unwrap();
+    assert_eq!(
+        js.call("__wbg_get_stop_kind", &[Val::I32(stop)])[0].i32(),
+        Some(LIMIT_REACHED)
+    );
+    assert_eq!(
+        js.call("emulator_pc", &[Val::I32(emu)])[0].i64(),
+        Some(RAM_BASE as i64 + 4)
+    );
+
+    let stop = js.call("emulator_runFor", &[Val::I32(emu), Val::I64(100)])[0]
+        .i32()
+        .unwrap();
+    assert_eq!(
+        js.call("__wbg_get_stop_kind", &[Val::I32(stop)])[0].i32(),
+        Some(EXITED)
+    );
+    assert_eq!(
+        js.call("__wbg_get_stop_value", &[Val::I32(stop)])[0].i64(),
+        Some(7)
+    );
+    assert_eq!(js.store.data().console, b"hi");
+
+    let result = js.call("emulator_register", &[Val::I32(emu), Val::I32(10)]);
+    assert_eq!(result[0].i64(), Some(7));
+    let result = js.call("emulator_register", &[Val::I32(emu), Val::I32(32)]);
+    assert_eq!(result[2].i32(), Some(1));
+    assert_eq!(js.error(&result[1]), "no such register");
+
+    let length = Val::I32(image.len() as i32);
+    let result = js.call(
+        "emulator_readMemory",
+        &[Val::I32(emu), Val::I64(RAM_BASE as i64), length.clone()],
+    );
+    assert_eq!(result[3].i32(), Some(0));
+
+    let start = result[0].i32().unwrap() as usize;
+    let data = js.memory().data(&js.store)[start..start + image.len()].to_vec();
+    assert_eq!(data, image);
+    js.call("__wbindgen_free", &[result[0].clone(), length, Val::I32(1)]);
+}
@@ -0,0 +1,401 @@
+mod macros;
+
+#[test]
+fn rr_op_sh1add1() {
+    test_case!(
+        14,
+        0x000000000000000a,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x33, 0xa7, 0x20, 0x20, // sh1add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh1add2() {
+    test_case!(
+        14,
+        0x0123456789abcdee,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xa7, 0x20, 0x20, // sh1add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh1add3() {
+    test_case!(
+        14,
+        0xffffffff00000003,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xa7, 0x20, 0x20, // sh1add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh2add1() {
+    test_case!(
+        14,
+        0x0000000000000010,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x33, 0xc7, 0x20, 0x20, // sh2add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh2add2() {
+    test_case!(
+        14,
+        0x0369d0369d0369cc,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xc7, 0x20, 0x20, // sh2add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh2add3() {
+    test_case!(
+        14,
+        0xfffffffe00000005,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xc7, 0x20, 0x20, // sh2add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh3add1() {
+    test_case!(
+        14,
+        0x000000000000001c,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x33, 0xe7, 0x20, 0x20, // sh3add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh3add2() {
+    test_case!(
+        14,
+        0x07f6e5d4c3b2a188,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xe7, 0x20, 0x20, // sh3add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh3add3() {
+    test_case!(
+        14,
+        0xfffffffc00000009,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xe7, 0x20, 0x20, // sh3add x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_add_uw1() {
+    test_case!(
+        14,
+        0x0000000000000007,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x3b, 0x87, 0x20, 0x08, // add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_add_uw2() {
+    test_case!(
+        14,
+        0x0000000080000002,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0x87, 0x20, 0x08, // add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_add_uw3() {
+    test_case!(
+        14,
+        0x00000000fffffffe,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0xff, // li x2, -1
+            0x3b, 0x87, 0x20, 0x08, // add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh1add_uw1() {
+    test_case!(
+        14,
+        0x000000000000000a,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x3b, 0xa7, 0x20, 0x20, // sh1add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh1add_uw2() {
+    test_case!(
+        14,
+        0x0000000100000003,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0xa7, 0x20, 0x20, // sh1add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh1add_uw3() {
+    test_case!(
+        14,
+        0x0123456989abcded,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x37, 0x21, 0x09, 0x00, // lui x2, 0x92
+            0x1b, 0x01, 0xb1, 0xa2, // addiw x2, x2, -1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x51, 0x3c, // addi x2, x2, 965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0xd1, 0xab, // addi x2, x2, -1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xf1, 0xde, // addi x2, x2, -529
+            0x3b, 0xa7, 0x20, 0x20, // sh1add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh2add_uw1() {
+    test_case!(
+        14,
+        0x0000000000000010,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x3b, 0xc7, 0x20, 0x20, // sh2add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh2add_uw2() {
+    test_case!(
+        14,
+        0x0000000200000005,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0xc7, 0x20, 0x20, // sh2add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh2add_uw3() {
+    test_case!(
+        14,
+        0x0123456b89abcdeb,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x37, 0x21, 0x09, 0x00, // lui x2, 0x92
+            0x1b, 0x01, 0xb1, 0xa2, // addiw x2, x2, -1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x51, 0x3c, // addi x2, x2, 965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0xd1, 0xab, // addi x2, x2, -1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xf1, 0xde, // addi x2, x2, -529
+            0x3b, 0xc7, 0x20, 0x20, // sh2add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh3add_uw1() {
+    test_case!(
+        14,
+        0x000000000000001c,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x3b, 0xe7, 0x20, 0x20, // sh3add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh3add_uw2() {
+    test_case!(
+        14,
+        0x0000000400000009,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0xe7, 0x20, 0x20, // sh3add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_sh3add_uw3() {
+    test_case!(
+        14,
+        0x0123456f89abcde7,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x37, 0x21, 0x09, 0x00, // lui x2, 0x92
+            0x1b, 0x01, 0xb1, 0xa2, // addiw x2, x2, -1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x51, 0x3c, // addi x2, x2, 965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0xd1, 0xab, // addi x2, x2, -1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xf1, 0xde, // addi x2, x2, -529
+            0x3b, 0xe7, 0x20, 0x20, // sh3add.uw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn imm_op_slli_uw1() {
+    test_case!(
+        14,
+        0x0000000080000001,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x1b, 0x97, 0x00, 0x08, // slli.uw x14, x1, 0
+        ]
+    );
+}
+
+#[test]
+fn imm_op_slli_uw2() {
+    test_case!(
+        14,
+        0x0000000800000010,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x1b, 0x97, 0x40, 0x08, // slli.uw x14, x1, 4
+        ]
+    );
+}
+
+#[test]
+fn imm_op_slli_uw3() {
+    test_case!(
+        14,
+        0xffffffff00000000,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x1b, 0x97, 0x00, 0x0a, // slli.uw x14, x1, 32
+        ]
+    );
+}
+
+#[test]
+fn imm_op_slli_uw4() {
+    test_case!(
+        14,
+        0x8000000000000000,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x1b, 0x97, 0xf0, 0x0b, // slli.uw x14, x1, 63
+        ]
+    );
+}
@@ -0,0 +1,1352 @@
+mod macros;
+
+#[test]
+fn rr_op_andn1() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xf7, 0x20, 0x40, // andn x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_andn2() {
+    test_case!(
+        14,
+        0xffffffffffffff00,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0x0f, // li x2, 255
+            0x33, 0xf7, 0x20, 0x40, // andn x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_andn3() {
+    test_case!(
+        14,
+        0x00000000deadbeef,
+        vec![
+            0xb7, 0x80, 0x03, 0x00, // lui x1, 0x38
+            0x9b, 0x80, 0x70, 0xab, // addiw x1, x1, -1353
+            0x93, 0x90, 0xe0, 0x00, // slli x1, x1, 14
+            0x93, 0x80, 0xf0, 0xee, // addi x1, x1, -273
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0xf7, 0x20, 0x40, // andn x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_orn1() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xe7, 0x20, 0x40, // orn x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_orn2() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x01, 0xf0, 0xff, // li x2, -1
+            0x33, 0xe7, 0x20, 0x40, // orn x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_orn3() {
+    test_case!(
+        14,
+        0xffffffffffffffef,
+        vec![
+            0xb7, 0x80, 0x03, 0x00, // lui x1, 0x38
+            0x9b, 0x80, 0x70, 0xab, // addiw x1, x1, -1353
+            0x93, 0x90, 0xe0, 0x00, // slli x1, x1, 14
+            0x93, 0x80, 0xf0, 0xee, // addi x1, x1, -273
+            0x13, 0x01, 0xf0, 0x0f, // li x2, 255
+            0x33, 0xe7, 0x20, 0x40, // orn x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_xnor1() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xc7, 0x20, 0x40, // xnor x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_xnor2() {
+    test_case!(
+        14,
+        0xffffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0xff, // li x2, -1
+            0x33, 0xc7, 0x20, 0x40, // xnor x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_xnor3() {
+    test_case!(
+        14,
+        0xffffffff21524110,
+        vec![
+            0xb7, 0x80, 0x03, 0x00, // lui x1, 0x38
+            0x9b, 0x80, 0x70, 0xab, // addiw x1, x1, -1353
+            0x93, 0x90, 0xe0, 0x00, // slli x1, x1, 14
+            0x93, 0x80, 0xf0, 0xee, // addi x1, x1, -273
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0xc7, 0x20, 0x40, // xnor x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn un_op_clz1() {
+    test_case!(
+        14,
+        0x0000000000000040,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x97, 0x00, 0x60, // clz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_clz2() {
+    test_case!(
+        14,
+        0x000000000000003f,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x97, 0x00, 0x60, // clz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_clz3() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x97, 0x00, 0x60, // clz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_clz4() {
+    test_case!(
+        14,
+        0x0000000000000020,
+        vec![
+            0xb7, 0x80, 0x03, 0x00, // lui x1, 0x38
+            0x9b, 0x80, 0x70, 0xab, // addiw x1, x1, -1353
+            0x93, 0x90, 0xe0, 0x00, // slli x1, x1, 14
+            0x93, 0x80, 0xf0, 0xee, // addi x1, x1, -273
+            0x13, 0x97, 0x00, 0x60, // clz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctz1() {
+    test_case!(
+        14,
+        0x0000000000000040,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x97, 0x10, 0x60, // ctz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctz2() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x97, 0x10, 0x60, // ctz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctz3() {
+    test_case!(
+        14,
+        0x000000000000003f,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0xf0, 0x03, // slli x1, x1, 63
+            0x13, 0x97, 0x10, 0x60, // ctz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctz4() {
+    test_case!(
+        14,
+        0x0000000000000004,
+        vec![
+            0xb7, 0xe0, 0xf6, 0xff, // lui x1, 0xfff6e
+            0x9b, 0x80, 0x50, 0x5d, // addiw x1, x1, 1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xb0, 0xc3, // addi x1, x1, -965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0x30, 0x54, // addi x1, x1, 1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x00, 0x21, // addi x1, x1, 528
+            0x13, 0x97, 0x10, 0x60, // ctz x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_cpop1() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x97, 0x20, 0x60, // cpop x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_cpop2() {
+    test_case!(
+        14,
+        0x0000000000000040,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x97, 0x20, 0x60, // cpop x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_cpop3() {
+    test_case!(
+        14,
+        0x0000000000000020,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x97, 0x20, 0x60, // cpop x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_clzw1() {
+    test_case!(
+        14,
+        0x0000000000000020,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x1b, 0x97, 0x00, 0x60, // clzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_clzw2() {
+    test_case!(
+        14,
+        0x000000000000001f,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x1b, 0x97, 0x00, 0x60, // clzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_clzw3() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x1b, 0x97, 0x00, 0x60, // clzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_clzw4() {
+    test_case!(
+        14,
+        0x0000000000000020,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0x00, 0x02, // slli x1, x1, 32
+            0x1b, 0x97, 0x00, 0x60, // clzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctzw1() {
+    test_case!(
+        14,
+        0x0000000000000020,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x1b, 0x97, 0x10, 0x60, // ctzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctzw2() {
+    test_case!(
+        14,
+        0x0000000000000003,
+        vec![
+            0x93, 0x00, 0x80, 0x00, // li x1, 8
+            0x1b, 0x97, 0x10, 0x60, // ctzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctzw3() {
+    test_case!(
+        14,
+        0x0000000000000020,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0x00, 0x02, // slli x1, x1, 32
+            0x1b, 0x97, 0x10, 0x60, // ctzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_ctzw4() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0xb7, 0x00, 0x00, 0x80, // lui x1, 0x80000
+            0x9b, 0x80, 0x10, 0x00, // addiw x1, x1, 1
+            0x1b, 0x97, 0x10, 0x60, // ctzw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_cpopw1() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x1b, 0x97, 0x20, 0x60, // cpopw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_cpopw2() {
+    test_case!(
+        14,
+        0x0000000000000020,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x1b, 0x97, 0x20, 0x60, // cpopw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_cpopw3() {
+    test_case!(
+        14,
+        0x0000000000000014,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x1b, 0x97, 0x20, 0x60, // cpopw x14, x1
+        ]
+    );
+}
+
+#[test]
+fn rr_op_max1() {
+    test_case!(
+        14,
+        0x0000000000000002,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x20, 0x00, // li x2, 2
+            0x33, 0xe7, 0x20, 0x0a, // max x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_max2() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xe7, 0x20, 0x0a, // max x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_max3() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xe7, 0x20, 0x0a, // max x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_maxu1() {
+    test_case!(
+        14,
+        0x0000000000000002,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x20, 0x00, // li x2, 2
+            0x33, 0xf7, 0x20, 0x0a, // maxu x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_maxu2() {
+    test_case!(
+        14,
+        0xffffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xf7, 0x20, 0x0a, // maxu x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_maxu3() {
+    test_case!(
+        14,
+        0xfedcba9876543210,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xf7, 0x20, 0x0a, // maxu x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_min1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x20, 0x00, // li x2, 2
+            0x33, 0xc7, 0x20, 0x0a, // min x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_min2() {
+    test_case!(
+        14,
+        0xffffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xc7, 0x20, 0x0a, // min x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_min3() {
+    test_case!(
+        14,
+        0xfedcba9876543210,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xc7, 0x20, 0x0a, // min x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_minu1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x20, 0x00, // li x2, 2
+            0x33, 0xd7, 0x20, 0x0a, // minu x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_minu2() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xd7, 0x20, 0x0a, // minu x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_minu3() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xd7, 0x20, 0x0a, // minu x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn un_op_sext_b1() {
+    test_case!(
+        14,
+        0x000000000000007f,
+        vec![
+            0x93, 0x00, 0xf0, 0x07, // li x1, 127
+            0x13, 0x97, 0x40, 0x60, // sext.b x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_sext_b2() {
+    test_case!(
+        14,
+        0xffffffffffffff80,
+        vec![
+            0x93, 0x00, 0x00, 0x08, // li x1, 128
+            0x13, 0x97, 0x40, 0x60, // sext.b x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_sext_b3() {
+    test_case!(
+        14,
+        0xffffffffffffffef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x97, 0x40, 0x60, // sext.b x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_sext_h1() {
+    test_case!(
+        14,
+        0x0000000000007fff,
+        vec![
+            0xb7, 0x80, 0x00, 0x00, // lui x1, 0x8
+            0x9b, 0x80, 0xf0, 0xff, // addiw x1, x1, -1
+            0x13, 0x97, 0x50, 0x60, // sext.h x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_sext_h2() {
+    test_case!(
+        14,
+        0xffffffffffff8000,
+        vec![
+            0xb7, 0x80, 0x00, 0x00, // lui x1, 0x8
+            0x13, 0x97, 0x50, 0x60, // sext.h x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_sext_h3() {
+    test_case!(
+        14,
+        0xffffffffffffcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x97, 0x50, 0x60, // sext.h x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_zext_h1() {
+    test_case!(
+        14,
+        0x0000000000007fff,
+        vec![
+            0xb7, 0x80, 0x00, 0x00, // lui x1, 0x8
+            0x9b, 0x80, 0xf0, 0xff, // addiw x1, x1, -1
+            0x3b, 0xc7, 0x00, 0x08, // zext.h x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_zext_h2() {
+    test_case!(
+        14,
+        0x000000000000ffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x3b, 0xc7, 0x00, 0x08, // zext.h x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_zext_h3() {
+    test_case!(
+        14,
+        0x000000000000cdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x3b, 0xc7, 0x00, 0x08, // zext.h x14, x1
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rol1() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0x97, 0x20, 0x60, // rol x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rol2() {
+    test_case!(
+        14,
+        0x123456789abcdef0,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x33, 0x97, 0x20, 0x60, // rol x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rol3() {
+    test_case!(
+        14,
+        0x123456789abcdef0,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x04, // li x2, 68
+            0x33, 0x97, 0x20, 0x60, // rol x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rol4() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0xf0, 0x03, // slli x1, x1, 63
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0x97, 0x20, 0x60, // rol x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_ror1() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0xd7, 0x20, 0x60, // ror x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_ror2() {
+    test_case!(
+        14,
+        0xf0123456789abcde,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x33, 0xd7, 0x20, 0x60, // ror x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_ror3() {
+    test_case!(
+        14,
+        0xf0123456789abcde,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x04, // li x2, 68
+            0x33, 0xd7, 0x20, 0x60, // ror x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_ror4() {
+    test_case!(
+        14,
+        0x8000000000000000,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xd7, 0x20, 0x60, // ror x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rolw1() {
+    test_case!(
+        14,
+        0xffffffff89abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x3b, 0x97, 0x20, 0x60, // rolw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rolw2() {
+    test_case!(
+        14,
+        0xffffffff9abcdef8,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x3b, 0x97, 0x20, 0x60, // rolw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rolw3() {
+    test_case!(
+        14,
+        0xffffffff9abcdef8,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x02, // li x2, 36
+            0x3b, 0x97, 0x20, 0x60, // rolw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rolw4() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x93, 0x90, 0xf0, 0x01, // slli x1, x1, 31
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0x97, 0x20, 0x60, // rolw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rolw5() {
+    test_case!(
+        14,
+        0xffffffff80000000,
+        vec![
+            0xb7, 0x00, 0x00, 0x40, // lui x1, 0x40000
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0x97, 0x20, 0x60, // rolw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rorw1() {
+    test_case!(
+        14,
+        0xffffffff89abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x3b, 0xd7, 0x20, 0x60, // rorw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rorw2() {
+    test_case!(
+        14,
+        0xfffffffff89abcde,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x00, // li x2, 4
+            0x3b, 0xd7, 0x20, 0x60, // rorw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rorw3() {
+    test_case!(
+        14,
+        0xfffffffff89abcde,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x40, 0x02, // li x2, 36
+            0x3b, 0xd7, 0x20, 0x60, // rorw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rorw4() {
+    test_case!(
+        14,
+        0xffffffff80000000,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0xd7, 0x20, 0x60, // rorw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_rorw5() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x20, 0x00, // li x1, 2
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x3b, 0xd7, 0x20, 0x60, // rorw x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn imm_op_rori1() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0xd7, 0x00, 0x60, // rori x14, x1, 0
+        ]
+    );
+}
+
+#[test]
+fn imm_op_rori2() {
+    test_case!(
+        14,
+        0xf0123456789abcde,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0xd7, 0x40, 0x60, // rori x14, x1, 4
+        ]
+    );
+}
+
+#[test]
+fn imm_op_rori3() {
+    test_case!(
+        14,
+        0x02468acf13579bde,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0xd7, 0xf0, 0x63, // rori x14, x1, 63
+        ]
+    );
+}
+
+#[test]
+fn imm_op_rori4() {
+    test_case!(
+        14,
+        0x8000000000000000,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x13, 0xd7, 0x10, 0x60, // rori x14, x1, 1
+        ]
+    );
+}
+
+#[test]
+fn imm_op_roriw1() {
+    test_case!(
+        14,
+        0xffffffff89abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x1b, 0xd7, 0x00, 0x60, // roriw x14, x1, 0
+        ]
+    );
+}
+
+#[test]
+fn imm_op_roriw2() {
+    test_case!(
+        14,
+        0xfffffffff89abcde,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x1b, 0xd7, 0x40, 0x60, // roriw x14, x1, 4
+        ]
+    );
+}
+
+#[test]
+fn imm_op_roriw3() {
+    test_case!(
+        14,
+        0x0000000013579bdf,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x1b, 0xd7, 0xf0, 0x61, // roriw x14, x1, 31
+        ]
+    );
+}
+
+#[test]
+fn imm_op_roriw4() {
+    test_case!(
+        14,
+        0xffffffff80000000,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x1b, 0xd7, 0x10, 0x60, // roriw x14, x1, 1
+        ]
+    );
+}
+
+#[test]
+fn un_op_orc_b1() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0xd7, 0x70, 0x28, // orc.b x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_orc_b2() {
+    test_case!(
+        14,
+        0xff0000000000ff00,
+        vec![
+            0x93, 0x00, 0x10, 0x00, // li x1, 1
+            0x93, 0x90, 0xb0, 0x02, // slli x1, x1, 43
+            0x93, 0x80, 0x10, 0x00, // addi x1, x1, 1
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x13, 0xd7, 0x70, 0x28, // orc.b x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_orc_b3() {
+    test_case!(
+        14,
+        0xffffffffffffffff,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0xd7, 0x70, 0x28, // orc.b x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_rev81() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0xd7, 0x80, 0x6b, // rev8 x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_rev82() {
+    test_case!(
+        14,
+        0xefcdab8967452301,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0xd7, 0x80, 0x6b, // rev8 x14, x1
+        ]
+    );
+}
+
+#[test]
+fn un_op_rev83() {
+    test_case!(
+        14,
+        0xff00000000000000,
+        vec![
+            0x93, 0x00, 0xf0, 0x0f, // li x1, 255
+            0x13, 0xd7, 0x80, 0x6b, // rev8 x14, x1
+        ]
+    );
+}
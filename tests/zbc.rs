@@ -0,0 +1,202 @@
+mod macros;
+
+#[test]
+fn rr_op_clmul1() {
+    test_case!(
+        14,
+        0x0000000000000005,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x30, 0x00, // li x2, 3
+            0x33, 0x97, 0x20, 0x0a, // clmul x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmul2() {
+    test_case!(
+        14,
+        0x40a0789828c810f0,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0x97, 0x20, 0x0a, // clmul x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmul3() {
+    test_case!(
+        14,
+        0x5555555555555555,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0xff, // li x2, -1
+            0x33, 0x97, 0x20, 0x0a, // clmul x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmul4() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0xf0, 0x03, // slli x1, x1, 63
+            0x13, 0x01, 0x20, 0x00, // li x2, 2
+            0x33, 0x97, 0x20, 0x0a, // clmul x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulh1() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x30, 0x00, // li x2, 3
+            0x33, 0xb7, 0x20, 0x0a, // clmulh x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulh2() {
+    test_case!(
+        14,
+        0x00e038d8688850b0,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xb7, 0x20, 0x0a, // clmulh x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulh3() {
+    test_case!(
+        14,
+        0x5555555555555555,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0xff, // li x2, -1
+            0x33, 0xb7, 0x20, 0x0a, // clmulh x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulh4() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0xf0, 0x03, // slli x1, x1, 63
+            0x13, 0x01, 0x20, 0x00, // li x2, 2
+            0x33, 0xb7, 0x20, 0x0a, // clmulh x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulr1() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x30, 0x00, // li x1, 3
+            0x13, 0x01, 0x30, 0x00, // li x2, 3
+            0x33, 0xa7, 0x20, 0x0a, // clmulr x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulr2() {
+    test_case!(
+        14,
+        0x01c071b0d110a160,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x37, 0xe1, 0xf6, 0xff, // lui x2, 0xfff6e
+            0x1b, 0x01, 0x51, 0x5d, // addiw x2, x2, 1493
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0xb1, 0xc3, // addi x2, x2, -965
+            0x13, 0x11, 0xd1, 0x00, // slli x2, x2, 13
+            0x13, 0x01, 0x31, 0x54, // addi x2, x2, 1347
+            0x13, 0x11, 0xc1, 0x00, // slli x2, x2, 12
+            0x13, 0x01, 0x01, 0x21, // addi x2, x2, 528
+            0x33, 0xa7, 0x20, 0x0a, // clmulr x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulr3() {
+    test_case!(
+        14,
+        0xaaaaaaaaaaaaaaaa,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0xff, // li x2, -1
+            0x33, 0xa7, 0x20, 0x0a, // clmulr x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_clmulr4() {
+    test_case!(
+        14,
+        0x0000000000000002,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0xf0, 0x03, // slli x1, x1, 63
+            0x13, 0x01, 0x20, 0x00, // li x2, 2
+            0x33, 0xa7, 0x20, 0x0a, // clmulr x14, x1, x2
+        ]
+    );
+}
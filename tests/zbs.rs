@@ -0,0 +1,432 @@
+mod macros;
+
+#[test]
+fn rr_op_bclr1() {
+    test_case!(
+        14,
+        0xfffffffffffffffe,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0x97, 0x20, 0x48, // bclr x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bclr2() {
+    test_case!(
+        14,
+        0x7fffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0x03, // li x2, 63
+            0x33, 0x97, 0x20, 0x48, // bclr x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bclr3() {
+    test_case!(
+        14,
+        0x0123456789abcdee,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x04, // li x2, 64
+            0x33, 0x97, 0x20, 0x48, // bclr x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bclr4() {
+    test_case!(
+        14,
+        0x0000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x01, 0x50, 0x00, // li x2, 5
+            0x33, 0x97, 0x20, 0x48, // bclr x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bext1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0xd7, 0x20, 0x48, // bext x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bext2() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x10, 0x00, // li x2, 1
+            0x33, 0xd7, 0x20, 0x48, // bext x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bext3() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0xf0, 0x03, // slli x1, x1, 63
+            0x13, 0x01, 0xf0, 0x03, // li x2, 63
+            0x33, 0xd7, 0x20, 0x48, // bext x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bext4() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x04, // li x2, 64
+            0x33, 0xd7, 0x20, 0x48, // bext x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_binv1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0x97, 0x20, 0x68, // binv x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_binv2() {
+    test_case!(
+        14,
+        0x7fffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0xf0, 0x03, // li x2, 63
+            0x33, 0x97, 0x20, 0x68, // binv x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_binv3() {
+    test_case!(
+        14,
+        0x0123456789abcdee,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x04, // li x2, 64
+            0x33, 0x97, 0x20, 0x68, // binv x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_binv4() {
+    test_case!(
+        14,
+        0x0123456789abcdcf,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x50, 0x00, // li x2, 5
+            0x33, 0x97, 0x20, 0x68, // binv x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bset1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x01, 0x00, 0x00, // li x2, 0
+            0x33, 0x97, 0x20, 0x28, // bset x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bset2() {
+    test_case!(
+        14,
+        0x8000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x01, 0xf0, 0x03, // li x2, 63
+            0x33, 0x97, 0x20, 0x28, // bset x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bset3() {
+    test_case!(
+        14,
+        0x0123456789abcdef,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x01, 0x00, 0x04, // li x2, 64
+            0x33, 0x97, 0x20, 0x28, // bset x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn rr_op_bset4() {
+    test_case!(
+        14,
+        0xffffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x01, 0x50, 0x00, // li x2, 5
+            0x33, 0x97, 0x20, 0x28, // bset x14, x1, x2
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bclri1() {
+    test_case!(
+        14,
+        0xfffffffffffffffe,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x97, 0x00, 0x48, // bclri x14, x1, 0
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bclri2() {
+    test_case!(
+        14,
+        0x7fffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x97, 0xf0, 0x4b, // bclri x14, x1, 63
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bclri3() {
+    test_case!(
+        14,
+        0x0123456789abcdcf,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x97, 0x50, 0x48, // bclri x14, x1, 5
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bexti1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0xd7, 0x00, 0x48, // bexti x14, x1, 0
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bexti2() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0xd7, 0x10, 0x48, // bexti x14, x1, 1
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bexti3() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x93, 0x90, 0xf0, 0x03, // slli x1, x1, 63
+            0x13, 0xd7, 0xf0, 0x4b, // bexti x14, x1, 63
+        ]
+    );
+}
+
+#[test]
+fn imm_op_binvi1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x97, 0x00, 0x68, // binvi x14, x1, 0
+        ]
+    );
+}
+
+#[test]
+fn imm_op_binvi2() {
+    test_case!(
+        14,
+        0x7fffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x97, 0xf0, 0x6b, // binvi x14, x1, 63
+        ]
+    );
+}
+
+#[test]
+fn imm_op_binvi3() {
+    test_case!(
+        14,
+        0x0123456789abcdcf,
+        vec![
+            0xb7, 0x20, 0x09, 0x00, // lui x1, 0x92
+            0x9b, 0x80, 0xb0, 0xa2, // addiw x1, x1, -1493
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0x50, 0x3c, // addi x1, x1, 965
+            0x93, 0x90, 0xd0, 0x00, // slli x1, x1, 13
+            0x93, 0x80, 0xd0, 0xab, // addi x1, x1, -1347
+            0x93, 0x90, 0xc0, 0x00, // slli x1, x1, 12
+            0x93, 0x80, 0xf0, 0xde, // addi x1, x1, -529
+            0x13, 0x97, 0x50, 0x68, // binvi x14, x1, 5
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bseti1() {
+    test_case!(
+        14,
+        0x0000000000000001,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x97, 0x00, 0x28, // bseti x14, x1, 0
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bseti2() {
+    test_case!(
+        14,
+        0x8000000000000000,
+        vec![
+            0x93, 0x00, 0x00, 0x00, // li x1, 0
+            0x13, 0x97, 0xf0, 0x2b, // bseti x14, x1, 63
+        ]
+    );
+}
+
+#[test]
+fn imm_op_bseti3() {
+    test_case!(
+        14,
+        0xffffffffffffffff,
+        vec![
+            0x93, 0x00, 0xf0, 0xff, // li x1, -1
+            0x13, 0x97, 0x50, 0x28, // bseti x14, x1, 5
+        ]
+    );
+}